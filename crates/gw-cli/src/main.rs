@@ -9,6 +9,16 @@ use gw_nl::{AddressManager, BridgeManager};
 #[command(name = "gwarden")]
 #[command(version, about = "Ghost network orchestration", long_about = None)]
 struct Cli {
+    /// Answer yes to every prompt and confirmation (non-interactive mode).
+    /// `net apply --commit` confirms at once, skipping the auto-rollback window.
+    #[arg(
+        short = 'y',
+        long = "yes",
+        global = true,
+        env = "GWARDEN_ASSUME_YES",
+        value_parser = clap::builder::BoolishValueParser::new()
+    )]
+    assume_yes: bool,
     #[command(subcommand)]
     command: Commands,
 }
@@ -22,6 +32,9 @@ enum Commands {
     },
     /// VM operations
     Vm {
+        /// libvirt connection URI for virsh (e.g. qemu:///system)
+        #[arg(long, global = true)]
+        uri: Option<String>,
         #[command(subcommand)]
         action: VmAction,
     },
@@ -36,7 +49,19 @@ enum Commands {
         action: PolicyAction,
     },
     /// Terminal UI
-    Tui,
+    Tui {
+        /// Topology that defines the managed bridges (defaults to ./ghostnet.toml if present)
+        #[arg(short, long)]
+        file: Option<String>,
+        #[command(flatten)]
+        scope: InterfaceScope,
+        /// Seconds between status refreshes ('r' refreshes immediately)
+        #[arg(long, default_value = "2", value_parser = clap::value_parser!(u64).range(1..))]
+        interval: u64,
+        /// Quit on the first 'q' instead of asking to press it again
+        #[arg(long)]
+        no_confirm_quit: bool,
+    },
     /// Metrics server
     Metrics {
         #[command(subcommand)]
@@ -44,9 +69,25 @@ enum Commands {
     },
     /// Troubleshooting and diagnostics
     Doctor {
+        /// Topology used for topology-aware checks (defaults to ./ghostnet.toml if present)
+        #[arg(short, long)]
+        file: Option<String>,
+        #[command(flatten)]
+        scope: InterfaceScope,
+        /// Print the results and an error/warning summary as JSON
+        #[arg(long)]
+        json: bool,
+        /// Exit 2 on warnings too, instead of 1
+        #[arg(long)]
+        strict: bool,
+        /// Offer to run the suggested module load or sysctl for each error (--yes runs them unprompted)
+        #[arg(long, conflicts_with = "json")]
+        fix: bool,
         #[command(subcommand)]
         action: Option<DoctorAction>,
     },
+    /// Verify root, required binaries, kernel modules and the state dir
+    SelfCheck,
     /// Maintainer documentation helpers
     #[command(hide = true)]
     Docs {
@@ -55,39 +96,124 @@ enum Commands {
     },
 }
 
+/// Which interfaces `net status`, the TUI and bridge diagnostics report.
+#[derive(Clone, Copy, Default, clap::ValueEnum)]
+enum InterfaceFilterMode {
+    /// Bridges defined by the topology and the ports attached to them
+    #[default]
+    Managed,
+    /// Every bridge on the host
+    All,
+}
+
+#[derive(clap::Args)]
+struct InterfaceScope {
+    /// Interfaces to report: GhostWarden-managed bridges or all of them
+    #[arg(
+        long,
+        value_enum,
+        env = "GWARDEN_INTERFACE_FILTER",
+        default_value = "managed"
+    )]
+    interface_filter: InterfaceFilterMode,
+    /// Report every interface on the host (same as --interface-filter all)
+    #[arg(long)]
+    all: bool,
+}
+
+impl InterfaceScope {
+    fn resolve(&self, topology: Option<&Topology>) -> gw_core::InterfaceFilter {
+        if self.all || matches!(self.interface_filter, InterfaceFilterMode::All) {
+            return gw_core::InterfaceFilter::All;
+        }
+        match topology {
+            Some(topology) => gw_core::InterfaceFilter::managed(topology),
+            None => {
+                eprintln!("ℹ️  No topology found; showing all interfaces (use --file to scope)");
+                gw_core::InterfaceFilter::All
+            }
+        }
+    }
+}
+
 #[derive(Subcommand)]
 enum NetAction {
     /// Show planned changes without applying
     Plan {
-        #[arg(short, long, default_value = "ghostnet.toml")]
-        file: String,
+        /// Topology file; repeat to layer overrides over a base, later files win
+        #[arg(
+            short,
+            long = "file",
+            value_name = "FILE",
+            default_value = "ghostnet.toml"
+        )]
+        files: Vec<String>,
+        /// Print the effective topology after merging all --file layers
+        #[arg(long)]
+        show_merged: bool,
+        /// Emit validation findings and the plan as JSON; exit nonzero on validation errors
+        #[arg(long, conflicts_with = "show_merged")]
+        json: bool,
     },
     /// Apply network configuration
     Apply {
-        #[arg(short, long, default_value = "ghostnet.toml")]
-        file: String,
+        /// Topology file; repeat to layer overrides over a base, later files win
+        #[arg(
+            short,
+            long = "file",
+            value_name = "FILE",
+            default_value = "ghostnet.toml"
+        )]
+        files: Vec<String>,
+        /// Print the effective topology after merging all --file layers
+        #[arg(long)]
+        show_merged: bool,
         /// Execute changes on the host; without this flag apply is a dry run
         #[arg(long)]
         commit: bool,
         /// Auto-rollback window in seconds; press ENTER to confirm, 0 disables the wait
         #[arg(long, default_value = "30")]
         confirm: u64,
+        /// FIFO or Unix socket to read the confirmation from instead of stdin (any byte confirms)
+        #[arg(long, value_name = "PATH")]
+        confirm_channel: Option<std::path::PathBuf>,
         /// host:port to probe for connectivity; rollback runs if it is unreachable
         #[arg(long)]
         probe: Option<String>,
         /// Timeout in seconds for the connectivity probe
         #[arg(long, default_value = "3")]
         probe_timeout: u64,
+        /// Dry run only: emit validation findings, conflicts and the plan as JSON
+        #[arg(long, conflicts_with_all = ["show_merged", "commit"])]
+        json: bool,
     },
     /// Show current network status
-    Status,
+    Status {
+        /// Topology that defines the managed bridges (defaults to ./ghostnet.toml if present)
+        #[arg(short, long)]
+        file: Option<String>,
+        #[command(flatten)]
+        scope: InterfaceScope,
+    },
     /// Compare desired nftables rules with live system
     Diff {
-        #[arg(short, long, default_value = "ghostnet.toml")]
-        file: String,
+        /// Topology file; repeat to layer overrides over a base, later files win
+        #[arg(
+            short,
+            long = "file",
+            value_name = "FILE",
+            default_value = "ghostnet.toml"
+        )]
+        files: Vec<String>,
+        /// Print the effective topology after merging all --file layers
+        #[arg(long)]
+        show_merged: bool,
         /// Only diff nftables tables (or networks) matching this name
         #[arg(long)]
         table: Option<String>,
+        /// Emit per-table results as JSON and exit nonzero on any drift
+        #[arg(long, conflicts_with = "show_merged")]
+        json: bool,
     },
     /// Roll back the last applied configuration snapshot
     Rollback {
@@ -108,6 +234,28 @@ enum NetAction {
         #[arg(long)]
         confirm: bool,
     },
+    /// Save the host's full nftables ruleset to a JSON file
+    Export {
+        /// Destination file
+        #[arg(long, value_name = "FILE", default_value = "ruleset.json")]
+        out: std::path::PathBuf,
+    },
+    /// Replace the host's nftables ruleset with one saved by `net export`
+    Import {
+        /// Ruleset file written by `net export`
+        file: std::path::PathBuf,
+        /// Load the ruleset; without this flag only a summary is printed
+        #[arg(long)]
+        execute: bool,
+    },
+    /// Remove dnsmasq configs left behind by networks no longer in the topology
+    Clean {
+        #[arg(short, long, default_value = "ghostnet.toml")]
+        file: String,
+        /// Delete the orphaned configs and restart dnsmasq; without this flag only a report is printed
+        #[arg(long)]
+        execute: bool,
+    },
 }
 
 #[derive(Subcommand)]
@@ -120,9 +268,48 @@ enum VmAction {
         net: String,
         #[arg(long)]
         tap: Option<String>,
+        /// Tag the VM's interface onto this VLAN (1-4094)
+        #[arg(long)]
+        vlan: Option<u16>,
+        /// Guest MTU for the interface (at least 576), e.g. 9000 on jumbo-frame bridges
+        #[arg(long)]
+        mtu: Option<u32>,
+        /// Number of virtio queues (multiqueue, at least 1)
+        #[arg(long)]
+        queues: Option<u8>,
+        /// The bridge is an Open vSwitch bridge
+        #[arg(long)]
+        ovs: bool,
+        /// Average inbound (host to VM) bandwidth limit in KiB/s
+        #[arg(long)]
+        inbound: Option<u32>,
+        /// Average outbound (VM to host) bandwidth limit in KiB/s
+        #[arg(long)]
+        outbound: Option<u32>,
+        /// Peak rate in KiB/s for each direction with an average limit
+        #[arg(long)]
+        peak: Option<u32>,
+        /// Burst size in KiB for each direction with an average limit
+        #[arg(long)]
+        burst: Option<u32>,
+    },
+    /// Detach a VM's bridge interface by MAC
+    Detach {
+        #[arg(long)]
+        vm: String,
+        #[arg(long)]
+        mac: String,
+        /// Also hot-unplug the interface from the running VM
+        #[arg(long)]
+        live: bool,
+        /// Wait until libvirt no longer lists the interface
+        #[arg(long)]
+        wait: bool,
     },
     /// List VMs and their network attachments
     List,
+    /// List libvirt networks with their bridge, subnet and state
+    NetList,
 }
 
 #[derive(Subcommand)]
@@ -160,6 +347,13 @@ enum PolicyAction {
     },
     /// List available policy profiles
     List,
+    /// Compare a network's policy profile against its live nftables table
+    Diff {
+        #[arg(long)]
+        net: String,
+        #[arg(long, default_value = "ghostnet.toml")]
+        file: String,
+    },
 }
 
 #[derive(Subcommand)]
@@ -168,6 +362,9 @@ enum MetricsAction {
     Serve {
         #[arg(long, default_value = ":9138")]
         addr: String,
+        /// Topology whose port forwards are reported (defaults to ./ghostnet.toml if present)
+        #[arg(short, long)]
+        file: Option<String>,
     },
 }
 
@@ -198,20 +395,48 @@ fn main() -> anyhow::Result<()> {
     let cli = Cli::parse();
 
     match cli.command {
-        Commands::Net { action } => handle_net_action(action)?,
-        Commands::Vm { action } => handle_vm_action(action)?,
+        Commands::Net { action } => handle_net_action(action, cli.assume_yes)?,
+        Commands::Vm { uri, action } => handle_vm_action(action, uri.as_deref())?,
         Commands::Forward { action } => handle_forward_action(action)?,
         Commands::Policy { action } => handle_policy_action(action)?,
-        Commands::Tui => {
-            tokio::runtime::Runtime::new()?.block_on(async { run_tui().await })?;
+        Commands::Tui {
+            file,
+            scope,
+            interval,
+            no_confirm_quit,
+        } => {
+            let topology = load_optional_topology(file.as_deref())?;
+            let filter = scope.resolve(topology.as_ref().map(|(_, topology)| topology));
+            let interval = std::time::Duration::from_secs(interval);
+            let topology_path = topology.map(|(path, _)| path);
+            tokio::runtime::Runtime::new()?.block_on(async {
+                run_tui(filter, interval, topology_path, !no_confirm_quit).await
+            })?;
         }
         Commands::Metrics { action } => {
             tokio::runtime::Runtime::new()?
                 .block_on(async { handle_metrics_action(action).await })?;
         }
-        Commands::Doctor { action } => {
-            tokio::runtime::Runtime::new()?
-                .block_on(async { handle_doctor_action(action).await })?;
+        Commands::Doctor {
+            file,
+            scope,
+            json,
+            strict,
+            fix,
+            action,
+        } => {
+            let fix = fix.then_some(cli.assume_yes);
+            let summary = tokio::runtime::Runtime::new()?.block_on(async {
+                handle_doctor_action(action, file.as_deref(), &scope, json, fix).await
+            })?;
+            // 2 for errors, 1 for warnings only, so cron health checks can gate on it
+            let code = summary.exit_code(strict);
+            if code != 0 {
+                std::process::exit(code);
+            }
+        }
+        Commands::SelfCheck => {
+            tokio::runtime::Runtime::new()?.block_on(async { run_self_check().await })?;
         }
         Commands::Docs { action } => handle_docs_action(action)?,
     }
@@ -256,6 +481,7 @@ fn generate_command_reference() -> String {
         "gwarden net rollback",
         "gwarden net state",
         "gwarden net state-clear",
+        "gwarden net clean",
         "gwarden vm",
         "gwarden vm attach",
         "gwarden vm list",
@@ -266,6 +492,7 @@ fn generate_command_reference() -> String {
         "gwarden policy",
         "gwarden policy set",
         "gwarden policy list",
+        "gwarden policy diff",
         "gwarden metrics",
         "gwarden metrics serve",
         "gwarden doctor",
@@ -273,6 +500,7 @@ fn generate_command_reference() -> String {
         "gwarden doctor docker",
         "gwarden doctor bridges",
         "gwarden doctor all",
+        "gwarden self-check",
         "gwarden tui",
     ];
 
@@ -290,33 +518,91 @@ fn generate_command_reference() -> String {
     markdown
 }
 
-fn handle_net_action(action: NetAction) -> anyhow::Result<()> {
+/// Resolve an acknowledgement flag, honouring the global `--yes` loudly.
+fn acknowledged(flag: bool, assume_yes: bool, what: &str) -> bool {
+    if !flag && assume_yes {
+        println!(
+            "⚠️  --yes: proceeding to {} without explicit confirmation",
+            what
+        );
+    }
+    flag || assume_yes
+}
+
+fn handle_net_action(action: NetAction, assume_yes: bool) -> anyhow::Result<()> {
     match action {
-        NetAction::Plan { file } => {
-            let topology = Topology::from_file(std::path::Path::new(&file))?;
-            let plan = Plan::from_topology(&topology)?;
-            plan.display();
+        NetAction::Plan {
+            files,
+            show_merged,
+            json,
+        } => {
+            let topology = load_topology(&files, show_merged)?;
+            if json {
+                print_plan_json(&topology, None)?;
+            } else {
+                let plan = Plan::from_topology(&topology)?;
+                plan.display();
+                if gw_nl::has_net_admin() {
+                    tokio::runtime::Runtime::new()?
+                        .block_on(async { check_planned_rulesets(&topology, &plan).await })?;
+                } else {
+                    println!(
+                        "\nℹ️  Running without CAP_NET_ADMIN: generated nftables rulesets were not checked with nft."
+                    );
+                }
+            }
         }
         NetAction::Apply {
-            file,
+            files, json: true, ..
+        } => {
+            let topology = load_topology(&files, false)?;
+            let conflicts = tokio::runtime::Runtime::new()?
+                .block_on(async { gw_core::ConflictDetector::new().detect_all().await })?;
+            print_plan_json(&topology, Some(&conflicts))?;
+        }
+        NetAction::Apply {
+            files,
+            show_merged,
             commit,
             confirm,
+            confirm_channel,
             probe,
             probe_timeout,
+            json: false,
         } => {
             // Run async apply
             tokio::runtime::Runtime::new()?.block_on(async {
-                apply_network_config(&file, commit, confirm, probe, probe_timeout).await
+                apply_network_config(
+                    &files,
+                    show_merged,
+                    commit,
+                    confirm,
+                    confirm_channel.as_deref(),
+                    probe,
+                    probe_timeout,
+                    assume_yes,
+                )
+                .await
             })?;
         }
-        NetAction::Status => {
-            tokio::runtime::Runtime::new()?.block_on(async { show_network_status().await })?;
-        }
-        NetAction::Diff { file, table } => {
+        NetAction::Status { file, scope } => {
+            let topology = load_optional_topology(file.as_deref())?;
+            let filter = scope.resolve(topology.as_ref().map(|(_, topology)| topology));
             tokio::runtime::Runtime::new()?
-                .block_on(async { diff_network_config(&file, table.as_deref()).await })?;
+                .block_on(async { show_network_status(&filter).await })?;
+        }
+        NetAction::Diff {
+            files,
+            show_merged,
+            table,
+            json,
+        } => {
+            tokio::runtime::Runtime::new()?.block_on(async {
+                diff_network_config(&files, show_merged, table.as_deref(), json).await
+            })?;
         }
         NetAction::Rollback { execute } => {
+            let execute = acknowledged(execute, assume_yes, "execute the rollback");
             tokio::runtime::Runtime::new()?
                 .block_on(async { run_snapshot_rollback(execute).await })?;
         }
@@ -324,9 +610,194 @@ fn handle_net_action(action: NetAction) -> anyhow::Result<()> {
             show_apply_state(json)?;
         }
         NetAction::StateClear { confirm } => {
-            clear_apply_state(confirm)?;
+            clear_apply_state(acknowledged(confirm, assume_yes, "clear apply state"))?;
+        }
+        NetAction::Export { out } => {
+            tokio::runtime::Runtime::new()?
+                .block_on(async { nft_manager().dump_ruleset_to_file(&out).await })?;
+        }
+        NetAction::Import { file, execute } => {
+            let execute = acknowledged(execute, assume_yes, "replace the nftables ruleset");
+            tokio::runtime::Runtime::new()?
+                .block_on(async { import_ruleset(&file, execute).await })?;
+        }
+        NetAction::Clean { file, execute } => {
+            let execute = acknowledged(execute, assume_yes, "delete orphaned dnsmasq configs");
+            tokio::runtime::Runtime::new()?
+                .block_on(async { clean_orphaned_dnsmasq_configs(&file, execute).await })?;
+        }
+    }
+    Ok(())
+}
+
+/// Load the `--file` layers in order, printing the merged result on request.
+fn load_topology(files: &[String], show_merged: bool) -> anyhow::Result<Topology> {
+    let topology = Topology::from_files(files)?;
+
+    if show_merged {
+        let format = gw_core::ConfigFormat::from_path(std::path::Path::new(&files[0]))?;
+        println!("📄 Effective topology ({}):\n", files.join(" + "));
+        println!("{}", topology.render(format)?);
+    }
+
+    Ok(topology)
+}
+
+/// Run each planned nftables ruleset through `nft -c`, so a payload this host's
+/// nft/kernel rejects shows up in the plan instead of halfway through apply.
+async fn check_planned_rulesets(topology: &Topology, plan: &Plan) -> anyhow::Result<()> {
+    let nft_mgr = nft_manager();
+    let profiles = gw_core::ProfileLoader::new().load_default_profiles();
+    let mut rejected = 0;
+
+    for action in &plan.actions {
+        let PlanAction::CreateNftRuleset { table, .. } = action else {
+            continue;
+        };
+        let Some(generated) = generate_ruleset(&nft_mgr, topology, table, &profiles)? else {
+            continue;
+        };
+        match nft_mgr.check_ruleset(&generated.ruleset).await {
+            Ok(()) => println!("  ✅ nft accepts the ruleset for {}", table),
+            Err(e) => {
+                println!("  ❌ nft rejects the ruleset for {}: {:#}", table, e);
+                rejected += 1;
+            }
         }
     }
+
+    if rejected > 0 {
+        anyhow::bail!("nft rejected {} generated ruleset(s)", rejected);
+    }
+    Ok(())
+}
+
+/// Structured plan output for CI: validation findings (each with a `code` and
+/// `severity`), optional conflicts, and the planned actions.
+fn print_plan_json(
+    topology: &Topology,
+    conflicts: Option<&gw_core::ConflictReport>,
+) -> anyhow::Result<()> {
+    let profiles = gw_core::ProfileLoader::new().load_default_profiles();
+    let kernel_modules = gw_core::available_kernel_modules();
+    let mut validator = gw_core::TopologyValidator::new(topology).with_profiles(&profiles);
+    if let Some(modules) = &kernel_modules {
+        validator = validator.with_kernel_modules(modules);
+    }
+    let warnings = validator.validate()?;
+    let findings: Vec<_> = warnings.iter().map(|warning| warning.finding()).collect();
+    let plan = Plan::from_topology(topology)?;
+
+    let mut output = serde_json::json!({
+        "validation": findings,
+        "plan": plan,
+    });
+    if let Some(conflicts) = conflicts {
+        output["conflicts"] = serde_json::to_value(conflicts)?;
+    }
+    println!("{}", serde_json::to_string_pretty(&output)?);
+
+    let errors = warnings.iter().filter(|warning| warning.is_error()).count();
+    if errors > 0 {
+        anyhow::bail!("Topology validation failed with {} error(s)", errors);
+    }
+    Ok(())
+}
+
+/// Networks whose dnsmasq config the current topology expects to exist.
+fn dhcp_network_names(topology: &Topology) -> std::collections::HashSet<String> {
+    topology
+        .networks
+        .iter()
+        .filter_map(|(name, network)| match network {
+            gw_core::Network::Routed(routed) if routed.dhcp => Some(name.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+fn find_orphaned_dnsmasq_configs(topology: &Topology) -> anyhow::Result<Vec<std::path::PathBuf>> {
+    DnsmasqManager::new().find_orphaned_configs(
+        std::path::Path::new(gw_dhcpdns::DNSMASQ_CONFIG_DIR),
+        &dhcp_network_names(topology),
+    )
+}
+
+async fn clean_orphaned_dnsmasq_configs(file: &str, execute: bool) -> anyhow::Result<()> {
+    let topology = Topology::from_file(std::path::Path::new(file))?;
+    let orphans = find_orphaned_dnsmasq_configs(&topology)?;
+
+    if orphans.is_empty() {
+        println!(
+            "✅ No orphaned dnsmasq configs in {}",
+            gw_dhcpdns::DNSMASQ_CONFIG_DIR
+        );
+        return Ok(());
+    }
+
+    println!(
+        "⚠️  Found {} dnsmasq config(s) for networks not in {}:",
+        orphans.len(),
+        file
+    );
+    for path in &orphans {
+        println!("  - {}", path.display());
+    }
+
+    if !execute {
+        println!("\nRun with '--execute' to delete them and restart dnsmasq.");
+        return Ok(());
+    }
+
+    let dnsmasq_mgr = DnsmasqManager::new();
+    for path in &orphans {
+        dnsmasq_mgr.delete_config(&path.to_string_lossy())?;
+    }
+    dnsmasq_mgr.restart().await?;
+
+    println!("🧹 Removed {} orphaned dnsmasq config(s)", orphans.len());
+    Ok(())
+}
+
+/// Preview the tables in a saved ruleset, then load it in place of the live one.
+async fn import_ruleset(path: &std::path::Path, execute: bool) -> anyhow::Result<()> {
+    use anyhow::Context;
+
+    let data = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let parsed: serde_json::Value = serde_json::from_str(&data)
+        .with_context(|| format!("{} is not valid JSON", path.display()))?;
+    let tables: Vec<String> = parsed
+        .get("nftables")
+        .and_then(|commands| commands.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|command| {
+            let table = command.get("table")?;
+            Some(format!(
+                "{} {}",
+                table.get("family")?.as_str()?,
+                table.get("name")?.as_str()?
+            ))
+        })
+        .collect();
+
+    println!(
+        "📄 {} holds {} table(s); importing replaces the entire live ruleset:",
+        path.display(),
+        tables.len()
+    );
+    for table in &tables {
+        println!("  - {}", table);
+    }
+
+    if !execute {
+        println!("\nRun with '--execute' to load it.");
+        return Ok(());
+    }
+
+    nft_manager().apply_ruleset_from_file(path).await?;
+    println!("✅ Imported nftables ruleset from {}", path.display());
     Ok(())
 }
 
@@ -370,7 +841,10 @@ fn describe_owned_resource(resource: &gw_core::OwnedResource) -> String {
         OwnedResource::Address { iface, addr } => format!("address {} on {}", addr, iface),
         OwnedResource::NftTable { table } => format!("nftables table {}", table),
         OwnedResource::DnsmasqConfig { path } => format!("dnsmasq config {}", path),
+        OwnedResource::CoreDnsConfig { path } => format!("CoreDNS config {}", path),
         OwnedResource::Vlan { name } => format!("VLAN {}", name),
+        OwnedResource::Vxlan { name } => format!("VXLAN {}", name),
+        OwnedResource::Route { dst, dev } => format!("route {} dev {}", dst, dev),
     }
 }
 
@@ -396,7 +870,7 @@ fn clear_apply_state(confirm: bool) -> anyhow::Result<()> {
     Ok(())
 }
 
-async fn show_network_status() -> anyhow::Result<()> {
+async fn show_network_status(filter: &gw_core::InterfaceFilter) -> anyhow::Result<()> {
     use gw_core::NetworkStatus;
     use gw_dhcpdns::LeaseReader;
     use gw_nft::NftStatusCollector;
@@ -407,6 +881,7 @@ async fn show_network_status() -> anyhow::Result<()> {
     // Collect bridge status
     let bridge_collector = StatusCollector::new().await?;
     status.bridges = bridge_collector.collect_bridge_status().await?;
+    status.retain_interfaces(filter);
 
     // Collect nftables status
     let nft_collector = NftStatusCollector::new();
@@ -419,30 +894,79 @@ async fn show_network_status() -> anyhow::Result<()> {
     // Display status
     status.display();
 
+    if !gw_nl::has_net_admin() {
+        println!(
+            "\nℹ️  Running without CAP_NET_ADMIN: nftables tables are not visible. Re-run with sudo for the full view."
+        );
+    }
+
     Ok(())
 }
 
+/// How long apply waits for a bridge to gain carrier after an uplink joins it
+const LINK_UP_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// Persists how long a committed apply took when dropped, for
+/// `gwarden metrics serve` to report as `ghostwarden_apply_duration_seconds`.
+struct ApplyTimer {
+    topology: String,
+    started: std::time::Instant,
+}
+
+impl ApplyTimer {
+    fn start(topology: String) -> Self {
+        Self {
+            topology,
+            started: std::time::Instant::now(),
+        }
+    }
+}
+
+impl Drop for ApplyTimer {
+    fn drop(&mut self) {
+        let result = gw_core::default_apply_durations_path().and_then(|path| {
+            gw_core::record_apply_duration(&path, &self.topology, self.started.elapsed())
+        });
+        if let Err(err) = result {
+            eprintln!("⚠️  Could not record apply duration: {:#}", err);
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 async fn apply_network_config(
-    file: &str,
+    files: &[String],
+    show_merged: bool,
     commit: bool,
     confirm: u64,
+    confirm_channel: Option<&std::path::Path>,
     probe: Option<String>,
     probe_timeout: u64,
+    assume_yes: bool,
 ) -> anyhow::Result<()> {
     use gw_core::{
-        ConflictDetector, ExecutionContext, Plan, PlanAction, RollbackManager, Topology,
+        ConfirmChannel, ConflictDetector, ExecutionContext, Plan, PlanAction, RollbackManager,
         TopologyValidator,
     };
     use gw_dhcpdns::DnsmasqManager;
-    use gw_nft::NftManager;
     use gw_nl::{AddressManager, BridgeManager};
 
-    println!("🚀 Loading topology from {}", file);
-    let topology = Topology::from_file(std::path::Path::new(file))?;
+    if commit {
+        run_preflight().await?;
+    }
+
+    println!("🚀 Loading topology from {}", files.join(", "));
+    let topology = load_topology(files, show_merged)?;
+
+    let profiles = gw_core::ProfileLoader::new().load_default_profiles();
 
     // Validate topology
     println!("🔍 Validating topology...");
-    let validator = TopologyValidator::new(&topology);
+    let kernel_modules = gw_core::available_kernel_modules();
+    let mut validator = TopologyValidator::new(&topology).with_profiles(&profiles);
+    if let Some(modules) = &kernel_modules {
+        validator = validator.with_kernel_modules(modules);
+    }
     let validation_warnings = validator.validate()?;
 
     if !validation_warnings.is_empty() {
@@ -489,27 +1013,37 @@ async fn apply_network_config(
         return Ok(());
     }
 
+    // Open the confirm channel before touching the host, so a bad path fails
+    // early and the supervisor can connect as soon as the changes are live.
+    let confirm_channel = match confirm_channel {
+        Some(path) if confirm > 0 && !assume_yes => Some(ConfirmChannel::open(path)?),
+        _ => None,
+    };
+
     println!("\n⚡ Applying configuration...");
+    // Recorded when this function returns, whichever way the apply ends
+    let _apply_timer = ApplyTimer::start(files.join(","));
 
     // Create managers
     let bridge_mgr = BridgeManager::new().await?;
     let addr_mgr = AddressManager::new().await?;
-    let nft_mgr = NftManager::new();
+    let nft_mgr = nft_manager();
     let dnsmasq_mgr = DnsmasqManager::new();
+    let coredns_mgr = gw_dhcpdns::CoreDnsManager::new();
     let vlan_mgr = gw_nl::VlanManager::new().await?;
+    let vxlan_mgr = gw_nl::VxlanManager::new().await?;
+    let route_mgr = gw_nl::RouteManager::new().await?;
 
     let mut context = ExecutionContext::new(true);
     context.attach_plan(plan.clone());
 
-    let profiles = gw_core::ProfileLoader::new().load_default_profiles();
-
     // Execute plan actions
     for (i, action) in plan.actions.iter().enumerate() {
         println!("\n[{}/{}] {}", i + 1, plan.actions.len(), action);
 
         match action {
-            PlanAction::CreateBridge { name, cidr } => {
-                bridge_mgr.create_bridge(name).await?;
+            PlanAction::CreateBridge { name, cidr, mtu } => {
+                bridge_mgr.ensure_bridge(name, *mtu).await?;
                 if let Some(cidr_str) = cidr {
                     // Extract gateway IP from CIDR for address assignment
                     let gw_ip = extract_gateway_ip(cidr_str, &topology)?;
@@ -525,6 +1059,10 @@ async fn apply_network_config(
                 bridge_mgr.enable_forwarding(iface).await?;
                 context.record_action(action.clone());
             }
+            PlanAction::AddRoute { dst, gateway, dev } => {
+                route_mgr.add_route(dst, *gateway, dev).await?;
+                context.record_action(action.clone());
+            }
             PlanAction::CreateNftRuleset { table, .. } => {
                 if let Some(generated) = generate_ruleset(&nft_mgr, &topology, table, &profiles)? {
                     if let Some(policy_name) = &generated.policy_loaded {
@@ -537,6 +1075,8 @@ async fn apply_network_config(
                     let snapshot = nft_mgr.apply_ruleset(table, &generated.ruleset).await?;
                     context.record_nft_snapshot(table.clone(), snapshot);
                     context.record_action(action.clone());
+                } else {
+                    eprintln!("   ⚠️  No nftables config for {}; skipped", table);
                 }
             }
             PlanAction::StartDnsmasq { config_path } => {
@@ -545,10 +1085,35 @@ async fn apply_network_config(
                     let config = dnsmasq_mgr.generate_config(
                         &dns_config.bridge,
                         &dns_config.cidr,
+                        dns_config.gateway,
+                        &dns_config.zones,
+                        &dns_config.upstreams,
+                        &dns_config.dhcp_options,
+                        &dns_config.dhcp_pool,
+                        !dns_config.coredns,
+                    )?;
+                    // Recorded first so a rejected file is rolled back
+                    let changed = dnsmasq_mgr.write_config(config_path, &config)?;
+                    context.record_action(action.clone());
+                    // dnsmasq only reads its config directory at startup, and an
+                    // unchanged file needs no restart (nor a cache-flushing SIGHUP)
+                    if changed {
+                        dnsmasq_mgr.validate_config(config_path).await?;
+                        dnsmasq_mgr.restart().await?;
+                    }
+                }
+            }
+            PlanAction::StartCoreDns { config_path } => {
+                if let Some(dns_config) = get_dns_config(&topology, config_path)?
+                    && dns_config.coredns
+                {
+                    let config = coredns_mgr.generate_corefile(
                         &dns_config.zones,
+                        &dns_config.upstreams,
+                        dns_config.gateway,
                     )?;
-                    dnsmasq_mgr.write_config(config_path, &config)?;
-                    dnsmasq_mgr.restart().await?;
+                    coredns_mgr.write_config(config_path, &config)?;
+                    coredns_mgr.reload().await?;
                     context.record_action(action.clone());
                 }
             }
@@ -563,6 +1128,47 @@ async fn apply_network_config(
             PlanAction::AttachVlanToBridge { vlan, bridge } => {
                 vlan_mgr.attach_vlan_to_bridge(vlan, bridge).await?;
                 context.record_action(action.clone());
+                // With the uplink enslaved the bridge gains carrier; let later
+                // steps see it, but a cable that is out is not an apply failure
+                if let Err(e) = bridge_mgr.wait_until_up(bridge, LINK_UP_TIMEOUT).await {
+                    println!("⚠️  {}", e);
+                }
+            }
+            PlanAction::SetBridgeVlanFiltering {
+                bridge,
+                default_pvid,
+            } => {
+                bridge_mgr.set_vlan_filtering(bridge, true).await?;
+                bridge_mgr
+                    .set_vlan_default_pvid(bridge, *default_pvid)
+                    .await?;
+                context.record_action(action.clone());
+            }
+            PlanAction::SetBridgeStp { bridge, enabled } => {
+                bridge_mgr.set_stp(bridge, *enabled).await?;
+                context.record_action(action.clone());
+            }
+            PlanAction::CreateVxlan {
+                name,
+                vni,
+                local,
+                group,
+                peers,
+                dstport,
+            } => {
+                let config = gw_nl::VxlanConfig {
+                    vni: *vni,
+                    local: *local,
+                    group: *group,
+                    peers: peers.clone(),
+                    dstport: *dstport,
+                };
+                vxlan_mgr.create_vxlan(name, &config).await?;
+                context.record_action(action.clone());
+            }
+            PlanAction::AttachVxlanToBridge { vxlan, bridge } => {
+                vxlan_mgr.attach_vxlan_to_bridge(vxlan, bridge).await?;
+                context.record_action(action.clone());
             }
         }
     }
@@ -594,7 +1200,9 @@ async fn apply_network_config(
         state_path.display()
     );
 
-    let rollback_mgr = RollbackManager::new(confirm);
+    let mut rollback_mgr = RollbackManager::new(confirm);
+    rollback_mgr.assume_yes = assume_yes;
+    rollback_mgr.confirm_channel = confirm_channel;
 
     if let Some(target) = probe.as_ref() {
         let timeout_secs = probe_timeout.max(1);
@@ -617,7 +1225,10 @@ async fn apply_network_config(
                 &addr_mgr,
                 &nft_mgr,
                 &dnsmasq_mgr,
+                &coredns_mgr,
                 &vlan_mgr,
+                &vxlan_mgr,
+                &route_mgr,
             )
             .await?;
             rollback::clear_record()?;
@@ -640,7 +1251,10 @@ async fn apply_network_config(
                 &addr_mgr,
                 &nft_mgr,
                 &dnsmasq_mgr,
+                &coredns_mgr,
                 &vlan_mgr,
+                &vxlan_mgr,
+                &route_mgr,
             )
             .await?;
             rollback::clear_record()?;
@@ -670,17 +1284,22 @@ fn print_diff(diff_text: &str) {
     }
 }
 
-async fn diff_network_config(file: &str, table_filter: Option<&str>) -> anyhow::Result<()> {
-    use std::path::Path;
-
-    println!("🔍 Loading topology from {}", file);
-    let topology = Topology::from_file(Path::new(file))?;
+async fn diff_network_config(
+    files: &[String],
+    show_merged: bool,
+    table_filter: Option<&str>,
+    json: bool,
+) -> anyhow::Result<()> {
+    if !json {
+        println!("🔍 Loading topology from {}", files.join(", "));
+    }
+    let topology = load_topology(files, show_merged)?;
     let plan = Plan::from_topology(&topology)?;
-    let nft_mgr = NftManager::new();
+    let nft_mgr = nft_manager();
     let profiles = gw_core::ProfileLoader::new().load_default_profiles();
 
     let filter_owned = table_filter.map(|f| f.to_string());
-    let mut matched_any = false;
+    let mut results = Vec::new();
 
     for action in &plan.actions {
         if let PlanAction::CreateNftRuleset { table, .. } = action {
@@ -698,57 +1317,114 @@ async fn diff_network_config(file: &str, table_filter: Option<&str>) -> anyhow::
             }
 
             if let Some(generated) = generate_ruleset(&nft_mgr, &topology, table, &profiles)? {
-                matched_any = true;
-                println!("\n=== Table {} (network {}) ===", table, generated.network);
-                if let Some(policy_name) = &generated.policy_loaded {
-                    println!("   📜 Policy: {}", policy_name);
-                } else if let Some(missing) = &generated.policy_missing {
-                    println!(
-                        "   ⚠️  Policy '{}' not found; diff computed without policy",
-                        missing
-                    );
-                }
-
+                let check_error = nft_mgr
+                    .check_ruleset(&generated.ruleset)
+                    .await
+                    .err()
+                    .map(|e| format!("{:#}", e));
                 let diff = nft_mgr.diff_ruleset(table, &generated.ruleset).await?;
-                if diff.matches {
-                    println!("✅ Table is in sync with desired ruleset.");
-                } else {
-                    if diff.current_exists {
-                        println!("❌ Drift detected between desired and live rules.");
-                    } else {
-                        println!("❌ Table is missing from the system.");
-                    }
-
-                    if let Some(diff_text) = diff.diff.as_ref() {
-                        print_diff(diff_text);
+                if !json {
+                    print_table_diff(&generated, &diff);
+                    if let Some(error) = &check_error {
+                        println!("❌ nft rejects the desired ruleset: {}", error);
                     }
                 }
+                results.push((generated.network, diff, check_error));
             }
         }
     }
 
-    if !matched_any {
-        if let Some(filter) = table_filter {
-            println!(
+    if results.is_empty() {
+        let message = match table_filter {
+            Some(filter) => format!(
                 "No nftables entries matched filter '{}' in plan {}.",
-                filter, file
-            );
+                filter,
+                files.join(", ")
+            ),
+            None => "No nftables tables found in the generated plan.".to_string(),
+        };
+        if json {
+            eprintln!("{}", message);
         } else {
-            println!("No nftables tables found in the generated plan.");
+            println!("{}", message);
         }
     }
 
+    if json {
+        let entries: Vec<serde_json::Value> = results
+            .iter()
+            .map(|(network, diff, check_error)| {
+                serde_json::json!({
+                    "table": diff.table,
+                    "network": network,
+                    "in_sync": diff.matches,
+                    "table_missing": !diff.current_exists,
+                    "added_lines": diff.added_lines(),
+                    "removed_lines": diff.removed_lines(),
+                    "nft_check_error": check_error,
+                })
+            })
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&entries)?);
+
+        let drifted = results.iter().filter(|(_, diff, _)| !diff.matches).count();
+        if drifted > 0 {
+            anyhow::bail!("Drift detected in {} nftables table(s)", drifted);
+        }
+    }
+
+    let rejected = results
+        .iter()
+        .filter(|(_, _, check_error)| check_error.is_some())
+        .count();
+    if rejected > 0 {
+        anyhow::bail!("nft rejected {} generated ruleset(s)", rejected);
+    }
+
     Ok(())
 }
 
+fn print_table_diff(generated: &GeneratedRuleset, diff: &gw_nft::NftDiff) {
+    println!(
+        "\n=== Table {} (network {}) ===",
+        diff.table, generated.network
+    );
+    if let Some(policy_name) = &generated.policy_loaded {
+        println!("   📜 Policy: {}", policy_name);
+    } else if let Some(missing) = &generated.policy_missing {
+        println!(
+            "   ⚠️  Policy '{}' not found; diff computed without policy",
+            missing
+        );
+    }
+
+    if diff.matches {
+        println!("✅ Table is in sync with desired ruleset.");
+    } else {
+        if diff.current_exists {
+            println!("❌ Drift detected between desired and live rules.");
+        } else {
+            println!("❌ Table is missing from the system.");
+        }
+
+        if let Some(diff_text) = diff.diff.as_ref() {
+            print_diff(diff_text);
+        }
+    }
+}
+
 /// Execute rollback by deleting all created resources in reverse order
+#[allow(clippy::too_many_arguments)]
 async fn execute_rollback(
     context: &ExecutionContext,
     bridge_mgr: &BridgeManager,
     addr_mgr: &AddressManager,
     nft_mgr: &NftManager,
     dnsmasq_mgr: &DnsmasqManager,
+    coredns_mgr: &gw_dhcpdns::CoreDnsManager,
     vlan_mgr: &gw_nl::VlanManager,
+    vxlan_mgr: &gw_nl::VxlanManager,
+    route_mgr: &gw_nl::RouteManager,
 ) -> anyhow::Result<()> {
     use gw_core::RollbackOp;
 
@@ -791,12 +1467,33 @@ async fn execute_rollback(
                     eprintln!("     ⚠️  Failed to restart dnsmasq: {}", e);
                 }
             }
+            RollbackOp::DeleteCoreDnsConfig { path } => {
+                println!("  ⏪ Deleting CoreDNS config: {}", path);
+                if let Err(e) = coredns_mgr.delete_config(&path) {
+                    eprintln!("     ⚠️  Failed to delete config: {}", e);
+                }
+                if let Err(e) = coredns_mgr.reload().await {
+                    eprintln!("     ⚠️  Failed to reload CoreDNS: {}", e);
+                }
+            }
             RollbackOp::DeleteVlan { name } => {
                 println!("  ⏪ Deleting VLAN: {}", name);
                 if let Err(e) = vlan_mgr.delete_vlan(&name).await {
                     eprintln!("     ⚠️  Failed to delete VLAN: {}", e);
                 }
             }
+            RollbackOp::DeleteRoute { dst, gateway, dev } => {
+                println!("  ⏪ Deleting route {} via {} dev {}", dst, gateway, dev);
+                if let Err(e) = route_mgr.delete_route(&dst, gateway, &dev).await {
+                    eprintln!("     ⚠️  Failed to delete route: {}", e);
+                }
+            }
+            RollbackOp::DeleteVxlan { name } => {
+                println!("  ⏪ Deleting VXLAN: {}", name);
+                if let Err(e) = vxlan_mgr.delete_vxlan(&name).await {
+                    eprintln!("     ⚠️  Failed to delete VXLAN: {}", e);
+                }
+            }
         }
     }
 
@@ -829,6 +1526,13 @@ struct GeneratedRuleset {
     policy_missing: Option<String>,
 }
 
+/// Ruleset generator; `GWARDEN_NFT_PER_RULE_CIDRS=1` falls back to one rule per
+/// policy CIDR for nft builds without interval sets.
+fn nft_manager() -> NftManager {
+    let per_rule = std::env::var("GWARDEN_NFT_PER_RULE_CIDRS").is_ok_and(|value| value == "1");
+    NftManager::new().with_cidr_sets(!per_rule)
+}
+
 fn generate_ruleset(
     nft_mgr: &NftManager,
     topology: &Topology,
@@ -858,9 +1562,16 @@ fn generate_ruleset(
         &bridge_name,
         &config.cidr,
         &config.gateway_ip,
+        config
+            .ipv6
+            .as_ref()
+            .map(|(cidr, gw)| (cidr.as_str(), gw.as_str())),
         &config.masq_iface,
         &config.forwards,
+        config.hairpin,
         policy,
+        &config.ct_helpers,
+        config.quota.as_ref(),
     )?;
 
     Ok(Some(GeneratedRuleset {
@@ -875,7 +1586,13 @@ fn generate_ruleset(
 struct DnsConfig {
     bridge: String,
     cidr: String,
+    gateway: std::net::IpAddr,
     zones: Vec<String>,
+    dhcp_options: Vec<gw_core::DhcpOption>,
+    dhcp_pool: gw_core::DhcpPool,
+    upstreams: Vec<String>,
+    /// CoreDNS, not dnsmasq, answers DNS
+    coredns: bool,
 }
 
 fn get_dns_config(topology: &Topology, config_path: &str) -> anyhow::Result<Option<DnsConfig>> {
@@ -884,18 +1601,23 @@ fn get_dns_config(topology: &Topology, config_path: &str) -> anyhow::Result<Opti
     for (name, network) in &topology.networks {
         if config_path.contains(name)
             && let gw_core::Network::Routed(routed) = network
-            && routed.dhcp
+            && (routed.dhcp || routed.dns.as_ref().is_some_and(|dns| dns.uses_coredns()))
         {
-            let zones = if let Some(dns) = &routed.dns {
-                dns.zones.clone()
+            let (zones, upstreams) = if let Some(dns) = &routed.dns {
+                (dns.zones.clone(), dns.upstreams.clone())
             } else {
-                vec![]
+                (vec![], vec![])
             };
 
             return Ok(Some(DnsConfig {
                 bridge: format!("br-{}", name),
                 cidr: routed.cidr.clone(),
+                gateway: routed.gw_ip,
                 zones,
+                dhcp_options: routed.dhcp_options.clone(),
+                dhcp_pool: routed.dhcp_pool(),
+                upstreams,
+                coredns: routed.dns.as_ref().is_some_and(|dns| dns.uses_coredns()),
             }));
         }
     }
@@ -946,8 +1668,16 @@ async fn run_snapshot_rollback(execute: bool) -> anyhow::Result<()> {
     }
 
     println!("\nThe following operations will run, in order:");
-    for op in &operations {
-        println!("  - {}", describe_rollback_op(op));
+    for (i, op) in operations.iter().enumerate() {
+        println!("  {}. {}", i + 1, describe_rollback_op(op));
+    }
+
+    let irreversible = context.irreversible_actions();
+    if !irreversible.is_empty() {
+        println!("\nLeft in place (no rollback operation):");
+        for action in irreversible {
+            println!("  - {}", action);
+        }
     }
 
     if !execute {
@@ -955,13 +1685,18 @@ async fn run_snapshot_rollback(execute: bool) -> anyhow::Result<()> {
         return Ok(());
     }
 
+    run_preflight().await?;
+
     println!("\n🔄 Executing rollback from snapshot...");
 
     let bridge_mgr = BridgeManager::new().await?;
     let addr_mgr = AddressManager::new().await?;
     let nft_mgr = NftManager::new();
     let dnsmasq_mgr = DnsmasqManager::new();
+    let coredns_mgr = gw_dhcpdns::CoreDnsManager::new();
     let vlan_mgr = gw_nl::VlanManager::new().await?;
+    let vxlan_mgr = gw_nl::VxlanManager::new().await?;
+    let route_mgr = gw_nl::RouteManager::new().await?;
 
     execute_rollback(
         &context,
@@ -969,7 +1704,10 @@ async fn run_snapshot_rollback(execute: bool) -> anyhow::Result<()> {
         &addr_mgr,
         &nft_mgr,
         &dnsmasq_mgr,
+        &coredns_mgr,
         &vlan_mgr,
+        &vxlan_mgr,
+        &route_mgr,
     )
     .await?;
     rollback::clear_record()?;
@@ -994,27 +1732,87 @@ fn describe_rollback_op(op: &gw_core::RollbackOp) -> String {
             }
         }
         RollbackOp::DeleteDnsmasqConfig { path } => format!("delete dnsmasq config {}", path),
+        RollbackOp::DeleteCoreDnsConfig { path } => format!("delete CoreDNS config {}", path),
         RollbackOp::DeleteVlan { name } => format!("delete VLAN {}", name),
+        RollbackOp::DeleteVxlan { name } => format!("delete VXLAN {}", name),
+        RollbackOp::DeleteRoute { dst, gateway, dev } => {
+            format!("delete route {} via {} dev {}", dst, gateway, dev)
+        }
     }
 }
 
-fn handle_vm_action(action: VmAction) -> anyhow::Result<()> {
+fn handle_vm_action(action: VmAction, uri: Option<&str>) -> anyhow::Result<()> {
     match action {
-        VmAction::Attach { vm, net, tap } => {
-            tokio::runtime::Runtime::new()?
-                .block_on(async { attach_vm_to_network(&vm, &net, tap.as_deref()).await })?;
+        VmAction::Attach {
+            vm,
+            net,
+            tap,
+            vlan,
+            mtu,
+            queues,
+            ovs,
+            inbound,
+            outbound,
+            peak,
+            burst,
+        } => {
+            if (peak.is_some() || burst.is_some()) && inbound.is_none() && outbound.is_none() {
+                anyhow::bail!("--peak and --burst need an --inbound or --outbound average rate");
+            }
+            let options = gw_libvirt::InterfaceOptions {
+                vlan,
+                mtu,
+                queues,
+                ovs,
+                bandwidth_in_kbps: inbound,
+                bandwidth_in_peak_kbps: inbound.and(peak),
+                bandwidth_in_burst_kb: inbound.and(burst),
+                bandwidth_out_kbps: outbound,
+                bandwidth_out_peak_kbps: outbound.and(peak),
+                bandwidth_out_burst_kb: outbound.and(burst),
+                ..Default::default()
+            };
+            tokio::runtime::Runtime::new()?.block_on(async {
+                attach_vm_to_network(&vm, &net, tap.as_deref(), options, uri).await
+            })?;
+        }
+        VmAction::Detach {
+            vm,
+            mac,
+            live,
+            wait,
+        } => {
+            let options = gw_libvirt::DetachOptions {
+                live,
+                wait_for_detach: wait,
+                ..Default::default()
+            };
+            tokio::runtime::Runtime::new()?.block_on(async {
+                libvirt_manager(uri)
+                    .detach_vm_interface(&vm, &mac, &options)
+                    .await
+            })?;
         }
         VmAction::List => {
-            tokio::runtime::Runtime::new()?.block_on(async { list_vms().await })?;
+            tokio::runtime::Runtime::new()?.block_on(async { list_vms(uri).await })?;
+        }
+        VmAction::NetList => {
+            tokio::runtime::Runtime::new()?.block_on(async { list_libvirt_networks(uri).await })?;
         }
     }
     Ok(())
 }
 
-async fn list_vms() -> anyhow::Result<()> {
-    use gw_libvirt::LibvirtManager;
+fn libvirt_manager(uri: Option<&str>) -> gw_libvirt::LibvirtManager {
+    let mgr = gw_libvirt::LibvirtManager::new();
+    match uri {
+        Some(uri) => mgr.with_uri(uri),
+        None => mgr,
+    }
+}
 
-    let mgr = LibvirtManager::new();
+async fn list_vms(uri: Option<&str>) -> anyhow::Result<()> {
+    let mgr = libvirt_manager(uri);
     let vms = mgr.list_vms().await?;
 
     if vms.is_empty() {
@@ -1030,17 +1828,47 @@ async fn list_vms() -> anyhow::Result<()> {
             .unwrap_or_else(|| "-".to_string());
         println!("  {} {} [{}]", id_str, vm.name, vm.state);
         if !vm.interfaces.is_empty() {
-            println!("    Interfaces: {}", vm.interfaces.join(", "));
+            let interfaces: Vec<String> = vm.interfaces.iter().map(ToString::to_string).collect();
+            println!("    Interfaces: {}", interfaces.join(", "));
         }
     }
 
     Ok(())
 }
 
-async fn attach_vm_to_network(vm: &str, bridge: &str, tap: Option<&str>) -> anyhow::Result<()> {
-    use gw_libvirt::LibvirtManager;
+async fn list_libvirt_networks(uri: Option<&str>) -> anyhow::Result<()> {
+    let mgr = libvirt_manager(uri);
+    let networks = mgr.list_libvirt_networks().await?;
+
+    if networks.is_empty() {
+        println!("No libvirt networks found");
+        return Ok(());
+    }
+
+    println!("Libvirt networks ({}):\n", networks.len());
+    for net in networks {
+        let state = if net.active { "active" } else { "inactive" };
+        let autostart = if net.autostart { ", autostart" } else { "" };
+        println!("  {} [{}{}]", net.name, state, autostart);
+        if let Some(bridge) = &net.bridge {
+            println!("    Bridge: {}", bridge);
+        }
+        if let Some(cidr) = &net.cidr {
+            println!("    Subnet: {}", cidr);
+        }
+    }
 
-    let mgr = LibvirtManager::new();
+    Ok(())
+}
+
+async fn attach_vm_to_network(
+    vm: &str,
+    bridge: &str,
+    tap: Option<&str>,
+    options: gw_libvirt::InterfaceOptions,
+    uri: Option<&str>,
+) -> anyhow::Result<()> {
+    let mgr = libvirt_manager(uri);
 
     // Convert network name to bridge name (e.g., "nat_dev" -> "br-nat_dev")
     let bridge_name = if bridge.starts_with("br-") {
@@ -1049,7 +1877,77 @@ async fn attach_vm_to_network(vm: &str, bridge: &str, tap: Option<&str>) -> anyh
         format!("br-{}", bridge)
     };
 
-    mgr.attach_vm_to_bridge(vm, &bridge_name, tap).await?;
+    let topology_path = default_topology_path();
+    let topology = match Topology::from_file(&topology_path) {
+        Ok(topology) => Some(topology),
+        Err(err)
+            if err
+                .downcast_ref::<std::io::Error>()
+                .is_some_and(|e| e.kind() == std::io::ErrorKind::NotFound) =>
+        {
+            eprintln!(
+                "⚠️  No topology at {}; attaching without port isolation or VLANs",
+                topology_path.display()
+            );
+            None
+        }
+        Err(err) => {
+            return Err(err.context(format!(
+                "Failed to load topology {}",
+                topology_path.display()
+            )));
+        }
+    };
+    let isolated = topology
+        .as_ref()
+        .is_some_and(|topology| topology.bridge_isolates_ports(&bridge_name));
+    let port_vlans = topology
+        .as_ref()
+        .and_then(|topology| topology.bridge_port_vlans(&bridge_name));
+
+    let options = gw_libvirt::InterfaceOptions {
+        isolated,
+        ..options
+    };
+    mgr.attach_vm_to_bridge_advanced(vm, &bridge_name, tap, &options)
+        .await?;
+
+    if isolated {
+        println!("🔒 Port isolation enabled on {}", bridge_name);
+
+        // libvirt applies isolation when the tap comes up; fix up a tap that is already enslaved.
+        if let Some(tap) = tap {
+            let bridge_mgr = BridgeManager::new().await?;
+            let members = bridge_mgr.get_bridge_members(&bridge_name).await?;
+            if members.iter().any(|member| member == tap) {
+                bridge_mgr.set_port_isolated(tap, true).await?;
+            }
+        }
+    }
+
+    if let Some(vlans) = port_vlans {
+        // The tap only joins the bridge once the VM starts; program it now if it already has.
+        let enslaved_tap = match tap {
+            Some(tap) => {
+                let bridge_mgr = BridgeManager::new().await?;
+                let members = bridge_mgr.get_bridge_members(&bridge_name).await?;
+                if members.iter().any(|member| member == tap) {
+                    bridge_mgr.set_port_vlans(tap, &bridge_name, vlans).await?;
+                    true
+                } else {
+                    false
+                }
+            }
+            None => false,
+        };
+
+        if !enslaved_tap {
+            println!(
+                "⚠️  Port VLANs for {} not applied: the VM's tap is not on the bridge yet (pass --tap for a running VM)",
+                bridge_name
+            );
+        }
+    }
 
     Ok(())
 }
@@ -1093,10 +1991,13 @@ fn handle_forward_action(action: ForwardAction) -> anyhow::Result<()> {
                         anyhow::bail!("Forward for '{}' already exists", public);
                     }
 
-                    routed.forwards.push(gw_core::PortForward {
+                    let forward = gw_core::PortForward {
                         public: public.clone(),
                         dst: dst.clone(),
-                    });
+                        priority: None,
+                    };
+                    gw_core::TopologyValidator::validate_forward(&forward)?;
+                    routed.forwards.push(forward);
 
                     topology.write_file(&topology_path)?;
 
@@ -1244,7 +2145,68 @@ fn handle_policy_action(action: PolicyAction) -> anyhow::Result<()> {
         PolicyAction::List => {
             list_policy_profiles()?;
         }
+        PolicyAction::Diff { net, file } => {
+            tokio::runtime::Runtime::new()?
+                .block_on(async { diff_network_policy(&net, &file).await })?;
+        }
+    }
+    Ok(())
+}
+
+async fn diff_network_policy(net: &str, file: &str) -> anyhow::Result<()> {
+    use gw_core::Network;
+
+    let topology = Topology::from_file(std::path::Path::new(file))?;
+    let network = topology
+        .networks
+        .get(net)
+        .ok_or_else(|| anyhow::anyhow!("Network '{}' not found in topology", net))?;
+
+    let profile_name = match network {
+        Network::Routed(routed) => routed.policy_profile.clone(),
+        Network::Bridge(_) | Network::Vxlan(_) => {
+            anyhow::bail!(
+                "Network '{}' is not routed; policy profiles are only rendered to nftables for routed networks",
+                net
+            );
+        }
+    };
+    let Some(profile_name) = profile_name else {
+        println!("ℹ️  Network '{}' has no policy profile set", net);
+        return Ok(());
+    };
+
+    let profiles = gw_core::ProfileLoader::new().load_default_profiles();
+    if !profiles.contains_key(&profile_name) {
+        anyhow::bail!("Policy profile '{}' not found", profile_name);
+    }
+
+    let table = format!("gw-{}", net);
+    let nft_mgr = nft_manager();
+    let Some(generated) = generate_ruleset(&nft_mgr, &topology, &table, &profiles)? else {
+        anyhow::bail!("No nftables table is generated for network '{}'", net);
+    };
+
+    println!(
+        "🔍 Policy '{}' on network {} (table {})",
+        profile_name, net, table
+    );
+
+    let diff = nft_mgr.diff_ruleset(&table, &generated.ruleset).await?;
+    if diff.matches {
+        println!("✅ Live rules match the current profile definition.");
+    } else {
+        if diff.current_exists {
+            println!("❌ Live rules drifted from the current profile definition.");
+        } else {
+            println!("❌ Table {} is missing from the system.", table);
+        }
+
+        if let Some(diff_text) = diff.diff.as_ref() {
+            print_diff(diff_text);
+        }
     }
+
     Ok(())
 }
 
@@ -1270,10 +2232,21 @@ fn list_policy_profiles() -> anyhow::Result<()> {
     Ok(())
 }
 
-async fn run_tui() -> anyhow::Result<()> {
+async fn run_tui(
+    filter: gw_core::InterfaceFilter,
+    interval: std::time::Duration,
+    topology_path: Option<std::path::PathBuf>,
+    confirm_quit: bool,
+) -> anyhow::Result<()> {
     use gw_tui::TuiApp;
 
-    let mut app = TuiApp::new();
+    let mut app = TuiApp::new()
+        .with_interface_filter(filter)
+        .with_refresh_interval(interval)
+        .with_confirm_quit(confirm_quit);
+    if let Some(path) = topology_path {
+        app = app.with_topology_path(path);
+    }
     app.run().await?;
 
     Ok(())
@@ -1281,7 +2254,7 @@ async fn run_tui() -> anyhow::Result<()> {
 
 async fn handle_metrics_action(action: MetricsAction) -> anyhow::Result<()> {
     match action {
-        MetricsAction::Serve { addr } => {
+        MetricsAction::Serve { addr, file } => {
             use gw_metrics::{MetricsCollector, MetricsServer};
 
             // Parse port from address
@@ -1289,50 +2262,450 @@ async fn handle_metrics_action(action: MetricsAction) -> anyhow::Result<()> {
 
             println!("🚀 Starting metrics server on port {}...", port);
 
-            // Create collector
+            // Create collector; applies recorded before startup were reported
+            // by an earlier server, if any
             let collector = MetricsCollector::new()?;
+            collector.skip_recorded_apply_durations()?;
 
             // Create and start server
-            let server = MetricsServer::new(collector, port);
+            // Re-read the topology on every scrape so forward counts follow edits
+            let topology_path = load_optional_topology(file.as_deref())?.map(|(path, _)| path);
+            if let Some(path) = &topology_path {
+                println!("📄 Reporting port forwards from {}", path.display());
+            }
+            let server = MetricsServer::new(collector, port).with_refresh(move |collector| {
+                collector.update_rollback_age()?;
+                collector.update_apply_durations()?;
+                // Scrapes are handled on the runtime, so step off it to query netlink
+                let (bridges, traffic) = tokio::task::block_in_place(|| {
+                    tokio::runtime::Handle::current().block_on(collect_bridge_traffic())
+                })?;
+                collector.update_bridge_metrics(&bridges)?;
+                collector.update_bridge_traffic(&traffic)?;
+                let rule_counters = tokio::task::block_in_place(|| {
+                    tokio::runtime::Handle::current().block_on(collect_rule_counters())
+                });
+                collector.update_rule_counters(&rule_counters)?;
+                if let Some(path) = &topology_path {
+                    let topology = Topology::from_file(path)?;
+                    collector.update_forward_metrics(&topology)?;
+
+                    let leases =
+                        gw_dhcpdns::LeaseReader::new().read_default_leases_by_network(&topology)?;
+                    for (network, network_leases) in &leases {
+                        collector.update_dhcp_metrics(network_leases, network)?;
+                    }
+                    let networks: Vec<&str> = leases.keys().map(String::as_str).collect();
+                    collector.retain_dhcp_networks(&networks);
+                }
+                Ok(())
+            });
+
             server.serve().await?;
         }
     }
     Ok(())
 }
 
-async fn handle_doctor_action(action: Option<DoctorAction>) -> anyhow::Result<()> {
+/// Every bridge with its RX/TX counters; a bridge that vanished between the
+/// two queries is left out of the counters
+async fn collect_bridge_traffic() -> anyhow::Result<(
+    Vec<gw_core::BridgeStatus>,
+    Vec<(String, gw_core::LinkStats)>,
+)> {
+    let collector = gw_nl::StatusCollector::new().await?;
+    let bridges = collector.collect_bridge_status().await?;
+
+    let mut traffic = vec![];
+    for bridge in &bridges {
+        if let Ok(stats) = collector.get_link_stats(&bridge.name).await {
+            traffic.push((bridge.name.clone(), stats));
+        }
+    }
+    Ok((bridges, traffic))
+}
+
+/// Counted rules of every Ghostwarden table (profiles with `counters = true`).
+/// Tables nft cannot list, or a missing nft, just leave the counters empty.
+async fn collect_rule_counters() -> Vec<(String, Vec<gw_core::RuleCounter>)> {
+    let nft_mgr = NftManager::new();
+    let tables = nft_mgr.list_tables().await.unwrap_or_default();
+
+    let mut counters = vec![];
+    for table in tables.into_iter().filter(|table| table.starts_with("gw-")) {
+        if let Ok(rules) = nft_mgr.read_counters(&table).await
+            && !rules.is_empty()
+        {
+            counters.push((table, rules));
+        }
+    }
+    counters
+}
+
+async fn self_check_report() -> anyhow::Result<gw_troubleshoot::DiagnosticReport> {
+    let state_dir = rollback::default_state_dir()?;
+    let mut results = gw_troubleshoot::PreflightDiagnostics::new(state_dir)
+        .diagnose()
+        .await?;
+    results.insert(1, net_admin_diagnostic());
+
+    let mut report = gw_troubleshoot::DiagnosticReport::new();
+    report.add_section("Self-check", results);
+    Ok(report)
+}
+
+/// Root alone is not enough inside unprivileged containers; netlink and nft need CAP_NET_ADMIN.
+fn net_admin_diagnostic() -> gw_troubleshoot::DiagnosticResult {
+    use gw_troubleshoot::{DiagnosticLevel, DiagnosticResult};
+
+    if gw_nl::has_net_admin() {
+        DiagnosticResult::new(
+            DiagnosticLevel::Info,
+            "CAP_NET_ADMIN available",
+            "Links, addresses and nftables rules can be modified",
+        )
+    } else {
+        DiagnosticResult::new(
+            DiagnosticLevel::Error,
+            "CAP_NET_ADMIN missing",
+            "GhostWarden needs CAP_NET_ADMIN / root to modify network configuration",
+        )
+        .with_suggestion(
+            "Run as root; in a container, grant NET_ADMIN (e.g. `--cap-add NET_ADMIN`)",
+        )
+    }
+}
+
+async fn run_self_check() -> anyhow::Result<()> {
+    println!("🔍 Checking GhostWarden prerequisites...");
+    let report = self_check_report().await?;
+    report.display();
+
+    if report.has_errors() {
+        anyhow::bail!("Self-check failed. Fix the errors above before applying changes.");
+    }
+    Ok(())
+}
+
+/// Gate host-mutating commands on the self-check so missing prerequisites fail
+/// up front instead of halfway through an apply.
+async fn run_preflight() -> anyhow::Result<()> {
+    let report = self_check_report().await?;
+    if report.has_errors() {
+        report.display();
+        anyhow::bail!(
+            "Preflight checks failed; nothing was changed. Fix the errors above and re-run (see `gwarden self-check`)."
+        );
+    }
+    Ok(())
+}
+
+async fn handle_doctor_action(
+    action: Option<DoctorAction>,
+    file: Option<&str>,
+    scope: &InterfaceScope,
+    json: bool,
+    fix: Option<bool>,
+) -> anyhow::Result<gw_troubleshoot::ReportSummary> {
     use gw_troubleshoot::Troubleshooter;
 
-    let troubleshooter = Troubleshooter::new();
+    let topology = load_optional_topology(file)?;
+    let mut troubleshooter = Troubleshooter::new();
+    if matches!(
+        action,
+        Some(DoctorAction::Bridges | DoctorAction::All) | None
+    ) && let Some(bridges) = scope
+        .resolve(topology.as_ref().map(|(_, topology)| topology))
+        .bridges()
+    {
+        troubleshooter = troubleshooter.with_bridge_scope(bridges.iter().cloned());
+    }
+    if let Some((_, topology)) = &topology {
+        troubleshooter = troubleshooter.with_ipv6_required(topology.has_ipv6_networks());
+    }
 
-    match action {
+    let mut report = match action {
         Some(DoctorAction::Nftables) => {
-            println!("🔍 Checking nftables/iptables configuration...\n");
-            let results = troubleshooter.check_nftables().await?;
-            for result in results {
-                result.display();
+            if !json {
+                println!("🔍 Checking nftables/iptables configuration...\n");
             }
+            let results = troubleshooter.check_nftables().await?;
+            print_doctor_results("nftables/iptables", results, json)?
         }
         Some(DoctorAction::Docker) => {
-            println!("🔍 Checking Docker networking...\n");
-            let results = troubleshooter.check_docker().await?;
-            for result in results {
-                result.display();
+            if !json {
+                println!("🔍 Checking Docker networking...\n");
             }
+            let results = troubleshooter.check_docker().await?;
+            print_doctor_results("Docker Networking", results, json)?
         }
         Some(DoctorAction::Bridges) => {
-            println!("🔍 Checking bridge configuration...\n");
-            let results = troubleshooter.check_bridges().await?;
-            for result in results {
-                result.display();
+            if !json {
+                println!("🔍 Checking bridge configuration...\n");
             }
+            let results = troubleshooter.check_bridges().await?;
+            print_doctor_results("Bridge Configuration", results, json)?
         }
         Some(DoctorAction::All) | None => {
-            println!("🩺 Running comprehensive network diagnostics...\n");
-            let report = troubleshooter.run_all().await?;
-            report.display();
+            if !json {
+                println!("🩺 Running comprehensive network diagnostics...\n");
+            }
+            let mut report = troubleshooter.run_all().await?;
+            if let Some((path, topology)) = &topology {
+                report.add_section(
+                    "dnsmasq Configs",
+                    dnsmasq_config_diagnostics(path, topology)?,
+                );
+                let helper_results = ct_helper_diagnostics(topology).await?;
+                if !helper_results.is_empty() {
+                    report.add_section("Conntrack Helpers", helper_results);
+                }
+            }
+            if json {
+                println!("{}", report.to_json()?);
+            } else {
+                report.display();
+            }
+            report
+        }
+    };
+
+    if let Some(assume_yes) = fix {
+        run_doctor_fixes(&mut report, &troubleshooter, topology.as_ref(), assume_yes).await?;
+    }
+
+    Ok(report.summary())
+}
+
+/// Results of a single doctor check, as a one-section JSON report or as text
+fn print_doctor_results(
+    section: &str,
+    results: Vec<gw_troubleshoot::DiagnosticResult>,
+    json: bool,
+) -> anyhow::Result<gw_troubleshoot::DiagnosticReport> {
+    let mut report = gw_troubleshoot::DiagnosticReport::new();
+    report.add_section(section, results);
+    if json {
+        println!("{}", report.to_json()?);
+    } else {
+        report.results().for_each(|result| result.display());
+    }
+    Ok(report)
+}
+
+/// Offer each error's suggested command, run the allowlisted ones the user
+/// accepts, and re-run the check's section to confirm the error is gone.
+async fn run_doctor_fixes(
+    report: &mut gw_troubleshoot::DiagnosticReport,
+    troubleshooter: &gw_troubleshoot::Troubleshooter,
+    topology: Option<&(std::path::PathBuf, Topology)>,
+    assume_yes: bool,
+) -> anyhow::Result<()> {
+    use gw_troubleshoot::{DiagnosticLevel, SafeFix};
+
+    let pending: Vec<(String, gw_troubleshoot::DiagnosticResult)> = report
+        .sections()
+        .flat_map(|(section, results)| {
+            results
+                .iter()
+                .filter(|result| result.level >= DiagnosticLevel::Error && result.command.is_some())
+                .map(move |result| (section.to_string(), result.clone()))
+        })
+        .collect();
+
+    println!("\n━━━ Fixes ━━━");
+    if pending.is_empty() {
+        println!("  ✅ No errors with a suggested fix");
+        return Ok(());
+    }
+
+    for (section, result) in pending {
+        let command = result.command.as_deref().unwrap_or_default();
+        let Some(fix) = SafeFix::parse(command) else {
+            println!(
+                "\n⏭️  {}: run `{}` yourself (only module loads and sysctl writes are run automatically)",
+                result.title, command
+            );
+            continue;
+        };
+
+        println!("\n🔧 {}", result.title);
+        let what = format!("run `{}`", fix);
+        if !acknowledged(false, assume_yes, &what) && !prompt_yes_no(&format!("  Run `{}`?", fix))?
+        {
+            println!("  Skipped");
+            continue;
+        }
+
+        if let Err(e) = fix.run(&gw_core::SystemCommandRunner).await {
+            println!("  ❌ {:#}", e);
+            continue;
+        }
+
+        let Some(results) = rerun_doctor_section(&section, troubleshooter, topology).await? else {
+            println!("  ✅ Ran `{}`", fix);
+            continue;
+        };
+        let still_failing = results
+            .iter()
+            .any(|r| r.title == result.title && r.level >= DiagnosticLevel::Error);
+        report.replace_section_results(&section, results);
+        if still_failing {
+            println!("  ❌ Still failing after `{}`", fix);
+        } else {
+            println!("  ✅ Resolved");
         }
     }
 
     Ok(())
 }
+
+/// Fresh results for a report section, or `None` for sections doctor cannot
+/// re-run on its own.
+async fn rerun_doctor_section(
+    section: &str,
+    troubleshooter: &gw_troubleshoot::Troubleshooter,
+    topology: Option<&(std::path::PathBuf, Topology)>,
+) -> anyhow::Result<Option<Vec<gw_troubleshoot::DiagnosticResult>>> {
+    Ok(Some(match (section, topology) {
+        ("nftables/iptables", _) => troubleshooter.check_nftables().await?,
+        ("Docker Networking", _) => troubleshooter.check_docker().await?,
+        ("Bridge Configuration", _) => troubleshooter.check_bridges().await?,
+        ("DHCP/DNS", _) => troubleshooter.check_dnsmasq().await?,
+        ("Conntrack Helpers", Some((_, topology))) => ct_helper_diagnostics(topology).await?,
+        ("dnsmasq Configs", Some((path, topology))) => dnsmasq_config_diagnostics(path, topology)?,
+        _ => return Ok(None),
+    }))
+}
+
+/// Ask a yes/no question on stdin; anything but "y"/"yes" is a no.
+fn prompt_yes_no(question: &str) -> anyhow::Result<bool> {
+    use std::io::Write;
+
+    print!("{} [y/N] ", question);
+    std::io::stdout().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+/// Topology for topology-aware checks; `None` when no file is present.
+fn load_optional_topology(
+    file: Option<&str>,
+) -> anyhow::Result<Option<(std::path::PathBuf, Topology)>> {
+    let path = match file {
+        Some(file) => std::path::PathBuf::from(file),
+        None => default_topology_path(),
+    };
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let topology = Topology::from_file(&path)?;
+    Ok(Some((path, topology)))
+}
+
+/// Module checks for the conntrack helpers requested by routed networks.
+async fn ct_helper_diagnostics(
+    topology: &Topology,
+) -> anyhow::Result<Vec<gw_troubleshoot::DiagnosticResult>> {
+    use gw_core::{CtHelper, Network};
+
+    let mut helpers: Vec<&CtHelper> = topology
+        .networks
+        .values()
+        .filter_map(|network| match network {
+            Network::Routed(routed) => Some(routed),
+            _ => None,
+        })
+        .flat_map(|routed| routed.ct_helpers.iter())
+        .filter_map(|name| CtHelper::lookup(name))
+        .collect();
+    helpers.sort_by_key(|helper| helper.name);
+    helpers.dedup();
+
+    let descriptions: Vec<(String, String)> = helpers
+        .iter()
+        .flat_map(|helper| {
+            [
+                (
+                    helper.module.to_string(),
+                    format!("{} conntrack helper", helper.name),
+                ),
+                (
+                    helper.nat_module.to_string(),
+                    format!("{} NAT helper", helper.name),
+                ),
+            ]
+        })
+        .collect();
+    let modules: Vec<(&str, &str)> = descriptions
+        .iter()
+        .map(|(module, description)| (module.as_str(), description.as_str()))
+        .collect();
+
+    gw_troubleshoot::NftablesDiagnostics::new()
+        .check_modules(&modules)
+        .await
+}
+
+fn dnsmasq_config_diagnostics(
+    path: &std::path::Path,
+    topology: &Topology,
+) -> anyhow::Result<Vec<gw_troubleshoot::DiagnosticResult>> {
+    use gw_troubleshoot::{DiagnosticLevel, DiagnosticResult};
+
+    let orphans = find_orphaned_dnsmasq_configs(topology)?;
+
+    if orphans.is_empty() {
+        return Ok(vec![DiagnosticResult::new(
+            DiagnosticLevel::Info,
+            "dnsmasq configs match topology",
+            format!(
+                "No orphaned gw-*.conf files in {}",
+                gw_dhcpdns::DNSMASQ_CONFIG_DIR
+            ),
+        )]);
+    }
+
+    Ok(orphans
+        .iter()
+        .map(|orphan| {
+            DiagnosticResult::new(
+                DiagnosticLevel::Warning,
+                "Orphaned dnsmasq config",
+                format!(
+                    "{} does not match any DHCP network in {}",
+                    orphan.display(),
+                    path.display()
+                ),
+            )
+            .with_suggestion("dnsmasq may still serve a subnet that no longer exists")
+            .with_command(format!(
+                "sudo gwarden net clean -f {} --execute",
+                path.display()
+            ))
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn assume_yes_env_accepts_boolish_values() {
+        let parse = |value: &str| {
+            // SAFETY: the only test in this crate that touches the environment
+            unsafe { std::env::set_var("GWARDEN_ASSUME_YES", value) };
+            Cli::try_parse_from(["gwarden", "net", "status"])
+                .expect("parses")
+                .assume_yes
+        };
+
+        assert!(parse("1"));
+        assert!(parse("yes"));
+        assert!(!parse("0"));
+        assert!(!parse("false"));
+    }
+}
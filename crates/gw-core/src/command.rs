@@ -0,0 +1,194 @@
+use std::future::Future;
+use std::io;
+use std::os::unix::process::ExitStatusExt;
+use std::pin::Pin;
+use std::process::{ExitStatus, Output, Stdio};
+use std::sync::Mutex;
+
+/// Future returned by [`CommandRunner::run`].
+pub type CommandFuture<'a> = Pin<Box<dyn Future<Output = io::Result<Output>> + Send + 'a>>;
+
+/// Runs external programs (`nft`, `virsh`, `docker`, ...) to completion.
+///
+/// Managers hold an `Arc<dyn CommandRunner>` instead of spawning processes
+/// directly, so their parsing and error handling can be tested against a
+/// [`MockCommandRunner`].
+pub trait CommandRunner: Send + Sync {
+    /// Run `program` with `args`, writing `stdin` to it when given, and
+    /// capture its exit status, stdout and stderr.
+    fn run<'a>(
+        &'a self,
+        program: &'a str,
+        args: &'a [&'a str],
+        stdin: Option<&'a [u8]>,
+    ) -> CommandFuture<'a>;
+}
+
+/// Spawns real processes with `tokio::process`.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemCommandRunner;
+
+impl CommandRunner for SystemCommandRunner {
+    fn run<'a>(
+        &'a self,
+        program: &'a str,
+        args: &'a [&'a str],
+        stdin: Option<&'a [u8]>,
+    ) -> CommandFuture<'a> {
+        Box::pin(async move {
+            use tokio::io::AsyncWriteExt;
+
+            let mut child = tokio::process::Command::new(program)
+                .args(args)
+                .stdin(if stdin.is_some() {
+                    Stdio::piped()
+                } else {
+                    Stdio::null()
+                })
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped())
+                .kill_on_drop(true)
+                .spawn()?;
+
+            if let (Some(data), Some(mut pipe)) = (stdin, child.stdin.take()) {
+                pipe.write_all(data).await?;
+            }
+
+            child.wait_with_output().await
+        })
+    }
+}
+
+/// A command seen by [`MockCommandRunner`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordedCommand {
+    /// Program and arguments joined by spaces, e.g. `nft -j list tables`.
+    pub command: String,
+    pub stdin: Option<String>,
+}
+
+/// Answers commands with canned outputs and records every call.
+///
+/// Responses are matched by command-line prefix in the order they were
+/// added; a command with no match fails as if the program were missing.
+#[derive(Debug, Default)]
+pub struct MockCommandRunner {
+    responses: Vec<(String, Output)>,
+    calls: Mutex<Vec<RecordedCommand>>,
+}
+
+impl MockCommandRunner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Answer commands starting with `prefix` with `stdout` and exit status 0.
+    pub fn ok(self, prefix: impl Into<String>, stdout: &str) -> Self {
+        self.respond(prefix, 0, stdout, "")
+    }
+
+    /// Answer commands starting with `prefix` with `stderr` and `code`.
+    pub fn fail(self, prefix: impl Into<String>, code: i32, stderr: &str) -> Self {
+        self.respond(prefix, code, "", stderr)
+    }
+
+    pub fn respond(
+        mut self,
+        prefix: impl Into<String>,
+        code: i32,
+        stdout: &str,
+        stderr: &str,
+    ) -> Self {
+        self.responses.push((
+            prefix.into(),
+            Output {
+                status: ExitStatus::from_raw(code << 8),
+                stdout: stdout.as_bytes().to_vec(),
+                stderr: stderr.as_bytes().to_vec(),
+            },
+        ));
+        self
+    }
+
+    /// Every command run so far, in order.
+    pub fn calls(&self) -> Vec<RecordedCommand> {
+        self.calls.lock().unwrap().clone()
+    }
+}
+
+impl CommandRunner for MockCommandRunner {
+    fn run<'a>(
+        &'a self,
+        program: &'a str,
+        args: &'a [&'a str],
+        stdin: Option<&'a [u8]>,
+    ) -> CommandFuture<'a> {
+        let command = std::iter::once(program)
+            .chain(args.iter().copied())
+            .collect::<Vec<_>>()
+            .join(" ");
+        let response = self
+            .responses
+            .iter()
+            .find(|(prefix, _)| command.starts_with(prefix.as_str()))
+            .map(|(_, output)| output.clone());
+
+        self.calls.lock().unwrap().push(RecordedCommand {
+            command: command.clone(),
+            stdin: stdin.map(|data| String::from_utf8_lossy(data).into_owned()),
+        });
+
+        Box::pin(async move {
+            response.ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("no canned output for `{}`", command),
+                )
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn mock_matches_prefixes_and_records_calls() {
+        let runner = MockCommandRunner::new()
+            .fail("nft -j list table", 1, "No such file or directory")
+            .ok("nft", "{}");
+
+        let missing = runner
+            .run("nft", &["-j", "list", "table", "inet", "gw-lab"], None)
+            .await
+            .unwrap();
+        assert_eq!(missing.status.code(), Some(1));
+        assert_eq!(missing.stderr, b"No such file or directory");
+
+        let applied = runner
+            .run("nft", &["-j", "-f", "-"], Some(b"{\"nftables\":[]}"))
+            .await
+            .unwrap();
+        assert!(applied.status.success());
+        assert_eq!(applied.stdout, b"{}");
+
+        let err = runner.run("virsh", &["list"], None).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+
+        let calls = runner.calls();
+        assert_eq!(calls.len(), 3);
+        assert_eq!(calls[0].command, "nft -j list table inet gw-lab");
+        assert_eq!(calls[1].stdin.as_deref(), Some("{\"nftables\":[]}"));
+    }
+
+    #[tokio::test]
+    async fn system_runner_captures_output_and_stdin() {
+        let output = SystemCommandRunner
+            .run("cat", &[], Some(b"hello"))
+            .await
+            .unwrap();
+        assert!(output.status.success());
+        assert_eq!(output.stdout, b"hello");
+    }
+}
@@ -1,5 +1,7 @@
+use crate::command::{CommandRunner, SystemCommandRunner};
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
+use std::sync::Arc;
 
 /// Docker network information
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -12,23 +14,28 @@ pub struct DockerNetwork {
 }
 
 /// Docker bridge manager for compatibility with Docker networking
-pub struct DockerBridgeManager;
+pub struct DockerBridgeManager {
+    runner: Arc<dyn CommandRunner>,
+}
 
 impl DockerBridgeManager {
     pub fn new() -> Self {
-        Self
+        Self {
+            runner: Arc::new(SystemCommandRunner),
+        }
+    }
+
+    /// Run `docker` through `runner` instead of spawning it directly.
+    pub fn with_runner(mut self, runner: Arc<dyn CommandRunner>) -> Self {
+        self.runner = runner;
+        self
     }
 
     /// List all Docker networks
     pub async fn list_networks(&self) -> Result<Vec<DockerNetwork>> {
-        use tokio::process::Command;
-
-        let output = Command::new("docker")
-            .arg("network")
-            .arg("ls")
-            .arg("--format")
-            .arg("{{json .}}")
-            .output()
+        let output = self
+            .runner
+            .run("docker", &["network", "ls", "--format", "{{json .}}"], None)
             .await
             .context("Failed to run docker network ls")?;
 
@@ -51,13 +58,9 @@ impl DockerBridgeManager {
 
     /// Get Docker bridge names (docker0, br-*)
     pub async fn get_docker_bridges(&self) -> Result<Vec<String>> {
-        use tokio::process::Command;
-
-        let output = Command::new("docker")
-            .arg("network")
-            .arg("inspect")
-            .arg("bridge")
-            .output()
+        let output = self
+            .runner
+            .run("docker", &["network", "inspect", "bridge"], None)
             .await
             .context("Failed to inspect docker bridge network")?;
 
@@ -84,9 +87,7 @@ impl DockerBridgeManager {
 
     /// Check if Docker is running
     pub async fn is_docker_running(&self) -> bool {
-        use tokio::process::Command;
-
-        let output = Command::new("docker").arg("info").output().await;
+        let output = self.runner.run("docker", &["info"], None).await;
 
         output.map(|o| o.status.success()).unwrap_or(false)
     }
@@ -114,21 +115,25 @@ impl DockerBridgeManager {
         subnet: &str,
         gateway: &str,
     ) -> Result<()> {
-        use tokio::process::Command;
-
         // Create Docker network using macvlan or bridge driver
-        let output = Command::new("docker")
-            .arg("network")
-            .arg("create")
-            .arg("--driver=bridge")
-            .arg(format!("--subnet={}", subnet))
-            .arg(format!("--gateway={}", gateway))
-            .arg(format!(
-                "--opt=com.docker.network.bridge.name={}",
-                bridge_name
-            ))
-            .arg(network_name)
-            .output()
+        let subnet = format!("--subnet={}", subnet);
+        let gateway = format!("--gateway={}", gateway);
+        let bridge_opt = format!("--opt=com.docker.network.bridge.name={}", bridge_name);
+        let output = self
+            .runner
+            .run(
+                "docker",
+                &[
+                    "network",
+                    "create",
+                    "--driver=bridge",
+                    &subnet,
+                    &gateway,
+                    &bridge_opt,
+                    network_name,
+                ],
+                None,
+            )
             .await
             .context("Failed to create Docker network")?;
 
@@ -146,14 +151,9 @@ impl DockerBridgeManager {
 
     /// Attach a Docker container to a Ghostwarden bridge
     pub async fn attach_container_to_bridge(&self, container: &str, network: &str) -> Result<()> {
-        use tokio::process::Command;
-
-        let output = Command::new("docker")
-            .arg("network")
-            .arg("connect")
-            .arg(network)
-            .arg(container)
-            .output()
+        let output = self
+            .runner
+            .run("docker", &["network", "connect", network, container], None)
             .await
             .context("Failed to connect container to network")?;
 
@@ -171,14 +171,18 @@ impl DockerBridgeManager {
 
     /// List containers on a Docker network
     pub async fn list_containers_on_network(&self, network: &str) -> Result<Vec<String>> {
-        use tokio::process::Command;
-
-        let output = Command::new("docker")
-            .arg("network")
-            .arg("inspect")
-            .arg(network)
-            .arg("--format={{range $k, $v := .Containers}}{{$v.Name}} {{end}}")
-            .output()
+        let output = self
+            .runner
+            .run(
+                "docker",
+                &[
+                    "network",
+                    "inspect",
+                    network,
+                    "--format={{range $k, $v := .Containers}}{{$v.Name}} {{end}}",
+                ],
+                None,
+            )
             .await
             .context("Failed to inspect Docker network")?;
 
@@ -206,6 +210,7 @@ impl Default for DockerBridgeManager {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::command::MockCommandRunner;
 
     #[tokio::test]
     async fn test_docker_bridge_manager() {
@@ -218,4 +223,25 @@ mod tests {
             assert!(bridges.contains(&"docker0".to_string()));
         }
     }
+
+    #[tokio::test]
+    async fn lists_containers_and_falls_back_without_docker() {
+        let runner = Arc::new(
+            MockCommandRunner::new()
+                .ok("docker network inspect gw-lab", "web db \n")
+                .fail(
+                    "docker network inspect bridge",
+                    1,
+                    "Cannot connect to the Docker daemon",
+                ),
+        );
+        let mgr = DockerBridgeManager::new().with_runner(runner);
+
+        assert_eq!(
+            mgr.list_containers_on_network("gw-lab").await.unwrap(),
+            vec!["web", "db"]
+        );
+        assert_eq!(mgr.get_docker_bridges().await.unwrap(), vec!["docker0"]);
+        assert!(!mgr.is_docker_running().await);
+    }
 }
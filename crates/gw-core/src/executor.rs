@@ -2,6 +2,7 @@ use crate::planner::{Action, Plan};
 use crate::rollback::RollbackRecord;
 use anyhow::Result;
 use std::collections::HashMap;
+use std::net::IpAddr;
 
 /// Executor trait for applying network changes
 pub struct Executor {
@@ -23,10 +24,16 @@ impl Executor {
             Action::CreateBridge { .. }
             | Action::AddAddress { .. }
             | Action::EnableForwarding { .. }
+            | Action::AddRoute { .. }
             | Action::CreateNftRuleset { .. }
             | Action::StartDnsmasq { .. }
+            | Action::StartCoreDns { .. }
             | Action::CreateVlan { .. }
-            | Action::AttachVlanToBridge { .. } => {
+            | Action::AttachVlanToBridge { .. }
+            | Action::SetBridgeVlanFiltering { .. }
+            | Action::SetBridgeStp { .. }
+            | Action::CreateVxlan { .. }
+            | Action::AttachVxlanToBridge { .. } => {
                 println!("Executing: {}", action);
                 Ok(())
             }
@@ -58,9 +65,20 @@ pub enum RollbackOp {
     DeleteDnsmasqConfig {
         path: String,
     },
+    DeleteCoreDnsConfig {
+        path: String,
+    },
     DeleteVlan {
         name: String,
     },
+    DeleteVxlan {
+        name: String,
+    },
+    DeleteRoute {
+        dst: String,
+        gateway: IpAddr,
+        dev: String,
+    },
 }
 
 impl ExecutionContext {
@@ -108,41 +126,62 @@ impl ExecutionContext {
     }
 
     pub fn rollback_operations(&self) -> Vec<RollbackOp> {
-        let mut ops = Vec::new();
-
-        for action in self.actions_completed.iter().rev() {
-            match action {
-                Action::CreateBridge { name, .. } => {
-                    ops.push(RollbackOp::DeleteBridge { name: name.clone() });
-                }
-                Action::AddAddress { iface, addr } => {
-                    ops.push(RollbackOp::RemoveAddress {
-                        iface: iface.clone(),
-                        addr: addr.clone(),
-                    });
-                }
-                Action::CreateNftRuleset { table, .. } => {
-                    let snapshot = self.nft_snapshot(table).cloned().unwrap_or(None);
-                    ops.push(RollbackOp::RestoreNft {
-                        table: table.clone(),
-                        snapshot,
-                    });
-                }
-                Action::StartDnsmasq { config_path } => {
-                    ops.push(RollbackOp::DeleteDnsmasqConfig {
-                        path: config_path.clone(),
-                    });
-                }
-                Action::CreateVlan { name, .. } => {
-                    ops.push(RollbackOp::DeleteVlan { name: name.clone() });
-                }
-                Action::EnableForwarding { .. } | Action::AttachVlanToBridge { .. } => {
-                    // No direct rollback operation or handled elsewhere
-                }
+        self.actions_completed
+            .iter()
+            .rev()
+            .filter_map(|action| self.rollback_op(action))
+            .collect()
+    }
+
+    /// Completed actions rollback leaves in place, newest first.
+    pub fn irreversible_actions(&self) -> Vec<&Action> {
+        self.actions_completed
+            .iter()
+            .rev()
+            .filter(|action| self.rollback_op(action).is_none())
+            .collect()
+    }
+
+    fn rollback_op(&self, action: &Action) -> Option<RollbackOp> {
+        match action {
+            Action::CreateBridge { name, .. } => {
+                Some(RollbackOp::DeleteBridge { name: name.clone() })
+            }
+            Action::AddAddress { iface, addr } => Some(RollbackOp::RemoveAddress {
+                iface: iface.clone(),
+                addr: addr.clone(),
+            }),
+            Action::AddRoute { dst, gateway, dev } => Some(RollbackOp::DeleteRoute {
+                dst: dst.clone(),
+                gateway: *gateway,
+                dev: dev.clone(),
+            }),
+            Action::CreateNftRuleset { table, .. } => {
+                let snapshot = self.nft_snapshot(table).cloned().unwrap_or(None);
+                Some(RollbackOp::RestoreNft {
+                    table: table.clone(),
+                    snapshot,
+                })
+            }
+            Action::StartDnsmasq { config_path } => Some(RollbackOp::DeleteDnsmasqConfig {
+                path: config_path.clone(),
+            }),
+            Action::StartCoreDns { config_path } => Some(RollbackOp::DeleteCoreDnsConfig {
+                path: config_path.clone(),
+            }),
+            Action::CreateVlan { name, .. } => Some(RollbackOp::DeleteVlan { name: name.clone() }),
+            Action::CreateVxlan { name, .. } => {
+                Some(RollbackOp::DeleteVxlan { name: name.clone() })
+            }
+            Action::EnableForwarding { .. }
+            | Action::AttachVlanToBridge { .. }
+            | Action::SetBridgeVlanFiltering { .. }
+            | Action::SetBridgeStp { .. }
+            | Action::AttachVxlanToBridge { .. } => {
+                // No direct rollback operation or handled elsewhere
+                None
             }
         }
-
-        ops
     }
 }
 
@@ -188,4 +227,40 @@ mod tests {
             other => panic!("unexpected op: {:?}", other),
         }
     }
+
+    #[test]
+    fn rollback_preview_orders_ops_and_lists_irreversible_actions() {
+        let mut ctx = ExecutionContext::new(true);
+        ctx.record_action(Action::CreateBridge {
+            name: "br-nat".into(),
+            cidr: Some("10.33.0.0/24".into()),
+            mtu: None,
+        });
+        ctx.record_action(Action::EnableForwarding {
+            iface: "br-nat".into(),
+        });
+        ctx.record_action(Action::AddAddress {
+            iface: "br-nat".into(),
+            addr: "10.33.0.1/24".into(),
+        });
+
+        assert_eq!(
+            ctx.rollback_operations(),
+            vec![
+                RollbackOp::RemoveAddress {
+                    iface: "br-nat".into(),
+                    addr: "10.33.0.1/24".into(),
+                },
+                RollbackOp::DeleteBridge {
+                    name: "br-nat".into(),
+                },
+            ]
+        );
+        assert_eq!(
+            ctx.irreversible_actions(),
+            vec![&Action::EnableForwarding {
+                iface: "br-nat".into(),
+            }]
+        );
+    }
 }
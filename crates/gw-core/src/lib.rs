@@ -1,3 +1,4 @@
+pub mod command;
 pub mod config_format;
 pub mod conflict;
 pub mod detector;
@@ -12,13 +13,14 @@ pub mod status;
 pub mod topology;
 pub mod validator;
 
+pub use command::*;
 pub use config_format::*;
 pub use conflict::*;
 pub use detector::*;
 pub use docker::*;
 pub use executor::*;
 pub use planner::{Action as PlanAction, NftConfig, Plan, nft_config_for_table};
-pub use policy::{Action as PolicyAction, PolicyProfile, Protocol, Service};
+pub use policy::{Action as PolicyAction, IcmpRateLimit, PolicyProfile, Protocol, Service};
 pub use profile_loader::*;
 pub use rollback::*;
 pub use state::*;
@@ -1,10 +1,14 @@
-use crate::topology::Topology;
+use crate::topology::{PortForward, Topology, TrafficQuota, VXLAN_DEFAULT_PORT};
 use serde::{Deserialize, Serialize};
 use std::fmt;
+use std::net::IpAddr;
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct Plan {
     pub actions: Vec<Action>,
+    /// Topology issues that do not block the plan but change what gets applied.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -12,6 +16,9 @@ pub enum Action {
     CreateBridge {
         name: String,
         cidr: Option<String>,
+        /// MTU to converge the bridge to, existing or not
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        mtu: Option<u32>,
     },
     AddAddress {
         iface: String,
@@ -20,6 +27,11 @@ pub enum Action {
     EnableForwarding {
         iface: String,
     },
+    AddRoute {
+        dst: String,
+        gateway: IpAddr,
+        dev: String,
+    },
     CreateNftRuleset {
         table: String,
         policy_profile: Option<String>,
@@ -27,6 +39,9 @@ pub enum Action {
     StartDnsmasq {
         config_path: String,
     },
+    StartCoreDns {
+        config_path: String,
+    },
     CreateVlan {
         parent: String,
         vlan_id: u16,
@@ -36,6 +51,26 @@ pub enum Action {
         vlan: String,
         bridge: String,
     },
+    SetBridgeVlanFiltering {
+        bridge: String,
+        default_pvid: u16,
+    },
+    SetBridgeStp {
+        bridge: String,
+        enabled: bool,
+    },
+    CreateVxlan {
+        name: String,
+        vni: u32,
+        local: Option<IpAddr>,
+        group: Option<IpAddr>,
+        peers: Vec<IpAddr>,
+        dstport: u16,
+    },
+    AttachVxlanToBridge {
+        vxlan: String,
+        bridge: String,
+    },
 }
 
 #[derive(Debug, Clone)]
@@ -43,19 +78,29 @@ pub struct NftConfig {
     pub network_name: String,
     pub cidr: String,
     pub gateway_ip: String,
+    /// IPv6 prefix and gateway of a dual-stack network.
+    pub ipv6: Option<(String, String)>,
+    /// Empty for policy-only tables (routed network without `masq_out`).
     pub masq_iface: String,
     pub forwards: Vec<(String, String)>,
+    /// DNAT forwards for bridge clients addressing the public IP too.
+    pub hairpin: bool,
     pub policy_profile: Option<String>,
+    pub ct_helpers: Vec<String>,
+    pub quota: Option<TrafficQuota>,
 }
 
 impl fmt::Display for Action {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Action::CreateBridge { name, cidr } => {
+            Action::CreateBridge { name, cidr, mtu } => {
                 write!(f, "Create bridge: {}", name)?;
                 if let Some(cidr) = cidr {
                     write!(f, " ({})", cidr)?;
                 }
+                if let Some(mtu) = mtu {
+                    write!(f, " mtu {}", mtu)?;
+                }
                 Ok(())
             }
             Action::AddAddress { iface, addr } => {
@@ -64,6 +109,9 @@ impl fmt::Display for Action {
             Action::EnableForwarding { iface } => {
                 write!(f, "Enable forwarding on {}", iface)
             }
+            Action::AddRoute { dst, gateway, dev } => {
+                write!(f, "Add route {} via {} dev {}", dst, gateway, dev)
+            }
             Action::CreateNftRuleset {
                 table,
                 policy_profile,
@@ -77,6 +125,9 @@ impl fmt::Display for Action {
             Action::StartDnsmasq { config_path } => {
                 write!(f, "Start dnsmasq with config: {}", config_path)
             }
+            Action::StartCoreDns { config_path } => {
+                write!(f, "Reload CoreDNS with config: {}", config_path)
+            }
             Action::CreateVlan {
                 parent,
                 vlan_id,
@@ -87,6 +138,41 @@ impl fmt::Display for Action {
             Action::AttachVlanToBridge { vlan, bridge } => {
                 write!(f, "Attach VLAN {} to bridge {}", vlan, bridge)
             }
+            Action::SetBridgeVlanFiltering {
+                bridge,
+                default_pvid,
+            } => {
+                write!(
+                    f,
+                    "Enable VLAN filtering on {} (default PVID {})",
+                    bridge, default_pvid
+                )
+            }
+            Action::SetBridgeStp { bridge, enabled } => {
+                let verb = if *enabled { "Enable" } else { "Disable" };
+                write!(f, "{} STP on {}", verb, bridge)
+            }
+            Action::CreateVxlan {
+                name,
+                vni,
+                group,
+                peers,
+                dstport,
+                ..
+            } => {
+                write!(f, "Create VXLAN {} (VNI: {}, port {})", name, vni, dstport)?;
+                if let Some(group) = group {
+                    write!(f, " group {}", group)?;
+                }
+                if !peers.is_empty() {
+                    let peers: Vec<String> = peers.iter().map(ToString::to_string).collect();
+                    write!(f, " peers {}", peers.join(", "))?;
+                }
+                Ok(())
+            }
+            Action::AttachVxlanToBridge { vxlan, bridge } => {
+                write!(f, "Attach VXLAN {} to bridge {}", vxlan, bridge)
+            }
         }
     }
 }
@@ -95,11 +181,14 @@ impl Plan {
     pub fn new() -> Self {
         Self {
             actions: Vec::new(),
+            warnings: Vec::new(),
         }
     }
 
     pub fn from_topology(topology: &Topology) -> anyhow::Result<Self> {
         let mut plan = Plan::new();
+        // VXLAN ports join bridges other networks create, so they go last
+        let mut vxlan_actions = Vec::new();
 
         for (net_name, network) in &topology.networks {
             match network {
@@ -107,29 +196,74 @@ impl Plan {
                     plan.actions.push(Action::CreateBridge {
                         name: format!("br-{}", net_name),
                         cidr: Some(routed.cidr.clone()),
+                        mtu: routed.mtu,
                     });
                     plan.actions.push(Action::AddAddress {
                         iface: format!("br-{}", net_name),
                         addr: routed.cidr.clone(),
                     });
+                    if let (Some(cidr6), Some(gw_ip6)) = (&routed.cidr6, routed.gw_ip6)
+                        && let Some((_, prefix)) = cidr6.split_once('/')
+                    {
+                        plan.actions.push(Action::AddAddress {
+                            iface: format!("br-{}", net_name),
+                            addr: format!("{}/{}", gw_ip6, prefix),
+                        });
+                    }
                     plan.actions.push(Action::EnableForwarding {
                         iface: format!("br-{}", net_name),
                     });
+                    for route in &routed.routes {
+                        plan.actions.push(Action::AddRoute {
+                            dst: route.dst.clone(),
+                            gateway: route.via,
+                            dev: format!("br-{}", net_name),
+                        });
+                    }
 
-                    // Ensure a corresponding nftables ruleset is generated for NAT + policy handling
-                    plan.actions.push(Action::CreateNftRuleset {
-                        table: format!("gw-{}", net_name),
-                        policy_profile: routed.policy_profile.clone(),
-                    });
+                    // NAT needs masq_out; without it a table is only worth creating for policy or a quota.
+                    if routed.masq_out.is_some()
+                        || routed.policy_profile.is_some()
+                        || routed.quota.is_some()
+                    {
+                        plan.actions.push(Action::CreateNftRuleset {
+                            table: format!("gw-{}", net_name),
+                            policy_profile: routed.policy_profile.clone(),
+                        });
+                    } else {
+                        plan.warnings.push(format!(
+                            "network '{}' has no masq_out, policy_profile or quota; no nftables table will be generated",
+                            net_name
+                        ));
+                    }
+
+                    if routed.masq_out.is_none() && !routed.forwards.is_empty() {
+                        plan.warnings.push(format!(
+                            "network '{}' has port forwards but no masq_out; DNAT rules will match on every interface except the bridge",
+                            net_name
+                        ));
+                    }
 
                     if routed.dhcp {
                         plan.actions.push(Action::StartDnsmasq {
                             config_path: format!("/etc/dnsmasq.d/gw-{}.conf", net_name),
                         });
                     }
+
+                    if routed.dns.as_ref().is_some_and(|dns| dns.uses_coredns()) {
+                        plan.actions.push(Action::StartCoreDns {
+                            config_path: format!("/etc/coredns/gw.d/gw-{}.conf", net_name),
+                        });
+                    }
                 }
                 crate::topology::Network::Bridge(bridge) => {
-                    // Create VLAN if specified
+                    plan.actions.push(Action::CreateBridge {
+                        name: bridge.iface.clone(),
+                        cidr: None,
+                        mtu: bridge.mtu,
+                    });
+
+                    // Create VLAN if specified; the bridge must exist to take it
                     if let Some(vlan_id) = bridge.vlan
                         && let Some(uplink) = topology.interfaces.get("uplink")
                     {
@@ -147,17 +281,39 @@ impl Plan {
                         });
                     }
 
-                    plan.actions.push(Action::CreateBridge {
-                        name: bridge.iface.clone(),
-                        cidr: None,
-                    });
+                    if let Some(enabled) = bridge.stp {
+                        plan.actions.push(Action::SetBridgeStp {
+                            bridge: bridge.iface.clone(),
+                            enabled,
+                        });
+                    }
+
+                    if let Some(default_pvid) = bridge.vlan_filtering_pvid() {
+                        plan.actions.push(Action::SetBridgeVlanFiltering {
+                            bridge: bridge.iface.clone(),
+                            default_pvid,
+                        });
+                    }
                 }
-                crate::topology::Network::Vxlan(_vxlan) => {
-                    // TODO: VXLAN support
+                crate::topology::Network::Vxlan(vxlan) => {
+                    let name = vxlan.device_name();
+                    vxlan_actions.push(Action::CreateVxlan {
+                        name: name.clone(),
+                        vni: vxlan.vni,
+                        local: vxlan.local,
+                        group: vxlan.group,
+                        peers: vxlan.peers.clone(),
+                        dstport: vxlan.dstport.unwrap_or(VXLAN_DEFAULT_PORT),
+                    });
+                    vxlan_actions.push(Action::AttachVxlanToBridge {
+                        vxlan: name,
+                        bridge: vxlan.bridge.clone(),
+                    });
                 }
             }
         }
 
+        plan.actions.extend(vxlan_actions);
         Ok(plan)
     }
 
@@ -166,6 +322,9 @@ impl Plan {
         for (i, action) in self.actions.iter().enumerate() {
             println!("  {}. {}", i + 1, action);
         }
+        for warning in &self.warnings {
+            println!("  ⚠️  {}", warning);
+        }
     }
 }
 
@@ -173,18 +332,25 @@ pub fn nft_config_for_table(topology: &Topology, table_name: &str) -> Option<Nft
     topology.networks.iter().find_map(|(name, network)| {
         if let crate::topology::Network::Routed(routed) = network {
             let expected_table = format!("gw-{}", name);
-            if expected_table == table_name {
-                routed.masq_out.as_ref().map(|masq_out| NftConfig {
+            if expected_table == table_name
+                && (routed.masq_out.is_some()
+                    || routed.policy_profile.is_some()
+                    || routed.quota.is_some())
+            {
+                Some(NftConfig {
                     network_name: name.clone(),
                     cidr: routed.cidr.clone(),
                     gateway_ip: routed.gw_ip.to_string(),
-                    masq_iface: masq_out.clone(),
-                    forwards: routed
-                        .forwards
-                        .iter()
-                        .map(|f| (f.public.clone(), f.dst.clone()))
-                        .collect(),
+                    ipv6: routed
+                        .cidr6
+                        .clone()
+                        .zip(routed.gw_ip6.map(|gw| gw.to_string())),
+                    masq_iface: routed.masq_out.clone().unwrap_or_default(),
+                    forwards: ordered_forwards(&routed.forwards),
+                    hairpin: routed.hairpin,
                     policy_profile: routed.policy_profile.clone(),
+                    ct_helpers: routed.ct_helpers.clone(),
+                    quota: routed.quota.clone(),
                 })
             } else {
                 None
@@ -195,8 +361,389 @@ pub fn nft_config_for_table(topology: &Topology, table_name: &str) -> Option<Nft
     })
 }
 
+/// Order forwards for rule emission. nftables evaluates prerouting first-match,
+/// so higher `priority` goes first and, on ties, address-specific forwards
+/// precede wildcards that would otherwise shadow them.
+fn ordered_forwards(forwards: &[PortForward]) -> Vec<(String, String)> {
+    let mut ordered: Vec<&PortForward> = forwards.iter().collect();
+    ordered.sort_by_key(|f| (std::cmp::Reverse(f.priority.unwrap_or(0)), f.is_wildcard()));
+    ordered
+        .into_iter()
+        .map(|f| (f.public.clone(), f.dst.clone()))
+        .collect()
+}
+
 impl Default for Plan {
     fn default() -> Self {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn forward(public: &str, dst: &str, priority: Option<i32>) -> PortForward {
+        PortForward {
+            public: public.into(),
+            dst: dst.into(),
+            priority,
+        }
+    }
+
+    #[test]
+    fn specific_forwards_precede_wildcards() {
+        let ordered = ordered_forwards(&[
+            forward(":8080/tcp", "10.0.0.10:80", None),
+            forward("0.0.0.0:9090/tcp", "10.0.0.11:80", None),
+            forward("203.0.113.5:8080/tcp", "10.0.0.20:80", None),
+        ]);
+
+        let publics: Vec<&str> = ordered.iter().map(|(p, _)| p.as_str()).collect();
+        assert_eq!(
+            publics,
+            vec!["203.0.113.5:8080/tcp", ":8080/tcp", "0.0.0.0:9090/tcp"]
+        );
+    }
+
+    #[test]
+    fn priority_outranks_specificity() {
+        let ordered = ordered_forwards(&[
+            forward("203.0.113.5:8000/tcp", "10.0.0.20:80", None),
+            forward(":8000/tcp", "10.0.0.10:80", Some(10)),
+        ]);
+
+        assert_eq!(ordered[0].0, ":8000/tcp");
+    }
+
+    #[test]
+    fn unspecified_addresses_of_both_families_are_wildcards() {
+        for public in [":80/tcp", "0.0.0.0:80/tcp", "[::]:80/tcp", "[0::0]:80/tcp"] {
+            assert!(
+                forward(public, "10.0.0.10:80", None).is_wildcard(),
+                "{}",
+                public
+            );
+        }
+        for public in ["203.0.113.5:80/tcp", "[2001:db8::5]:80/tcp"] {
+            assert!(
+                !forward(public, "10.0.0.10:80", None).is_wildcard(),
+                "{}",
+                public
+            );
+        }
+    }
+
+    #[test]
+    fn unset_priority_is_not_serialized() {
+        let json = serde_json::to_value(forward(":80/tcp", "10.0.0.10:80", None)).unwrap();
+        assert!(json.get("priority").is_none());
+        let json = serde_json::to_value(forward(":80/tcp", "10.0.0.10:80", Some(5))).unwrap();
+        assert_eq!(json["priority"], 5);
+    }
+
+    fn routed_topology(extra: &str) -> Topology {
+        Topology::from_toml(&format!(
+            r#"
+version = 1
+
+[interfaces]
+
+[networks.lab]
+type = "routed"
+cidr = "10.40.0.0/24"
+gw_ip = "10.40.0.1"
+{}
+"#,
+            extra
+        ))
+        .expect("topology")
+    }
+
+    #[test]
+    fn routed_without_masq_or_policy_warns_instead_of_planning_table() {
+        let topology = routed_topology("");
+        let plan = Plan::from_topology(&topology).unwrap();
+
+        assert!(
+            !plan
+                .actions
+                .iter()
+                .any(|a| matches!(a, Action::CreateNftRuleset { .. }))
+        );
+        assert_eq!(plan.warnings.len(), 1);
+        assert!(plan.warnings[0].contains("no masq_out"));
+        assert!(nft_config_for_table(&topology, "gw-lab").is_none());
+    }
+
+    #[test]
+    fn routed_without_masq_but_with_policy_gets_policy_only_table() {
+        let topology = routed_topology(r#"policy_profile = "routed-tight""#);
+        let plan = Plan::from_topology(&topology).unwrap();
+
+        assert!(plan.actions.contains(&Action::CreateNftRuleset {
+            table: "gw-lab".into(),
+            policy_profile: Some("routed-tight".into()),
+        }));
+        assert!(plan.warnings.is_empty());
+
+        let config = nft_config_for_table(&topology, "gw-lab").expect("policy-only config");
+        assert!(config.masq_iface.is_empty());
+    }
+
+    #[test]
+    fn coredns_backend_plans_a_corefile_after_dnsmasq() {
+        let dns = |backend: &str| {
+            routed_topology(&format!(
+                "dhcp = true\n[networks.lab.dns]\nenabled = true\nzones = [\"lab.lan\"]\n{}",
+                backend
+            ))
+        };
+        let coredns = Action::StartCoreDns {
+            config_path: "/etc/coredns/gw.d/gw-lab.conf".into(),
+        };
+
+        let plan = Plan::from_topology(&dns(r#"backend = "coredns""#)).unwrap();
+        let dnsmasq_index = plan
+            .actions
+            .iter()
+            .position(|a| matches!(a, Action::StartDnsmasq { .. }))
+            .expect("dnsmasq still serves DHCP");
+        let coredns_index = plan
+            .actions
+            .iter()
+            .position(|a| *a == coredns)
+            .expect("corefile");
+        assert!(dnsmasq_index < coredns_index);
+
+        let plan = Plan::from_topology(&dns("")).unwrap();
+        assert!(!plan.actions.contains(&coredns));
+    }
+
+    #[test]
+    fn static_routes_follow_the_bridge_address() {
+        let topology =
+            routed_topology(r#"routes = [{ dst = "192.168.50.0/24", via = "10.40.0.254" }]"#);
+        let plan = Plan::from_topology(&topology).unwrap();
+
+        let route = Action::AddRoute {
+            dst: "192.168.50.0/24".into(),
+            gateway: "10.40.0.254".parse().unwrap(),
+            dev: "br-lab".into(),
+        };
+        let route_index = plan
+            .actions
+            .iter()
+            .position(|a| *a == route)
+            .expect("route");
+        let addr_index = plan
+            .actions
+            .iter()
+            .position(|a| matches!(a, Action::AddAddress { .. }))
+            .expect("address");
+        assert!(addr_index < route_index);
+        assert_eq!(
+            route.to_string(),
+            "Add route 192.168.50.0/24 via 10.40.0.254 dev br-lab"
+        );
+    }
+
+    #[test]
+    fn bridge_stp_is_only_planned_when_set() {
+        let topology = |stp: &str| {
+            Topology::from_toml(&format!(
+                r#"
+version = 1
+
+[interfaces]
+
+[networks.lan]
+type = "bridge"
+iface = "br-lan"
+{}
+"#,
+                stp
+            ))
+            .expect("topology")
+        };
+
+        let plan = Plan::from_topology(&topology("stp = true")).unwrap();
+        assert_eq!(
+            plan.actions,
+            vec![
+                Action::CreateBridge {
+                    name: "br-lan".into(),
+                    cidr: None,
+                    mtu: None,
+                },
+                Action::SetBridgeStp {
+                    bridge: "br-lan".into(),
+                    enabled: true,
+                },
+            ]
+        );
+
+        let plan = Plan::from_topology(&topology("")).unwrap();
+        assert!(
+            !plan
+                .actions
+                .iter()
+                .any(|a| matches!(a, Action::SetBridgeStp { .. }))
+        );
+    }
+
+    #[test]
+    fn bridge_is_created_before_its_uplink_vlan() {
+        let topology = Topology::from_toml(
+            r#"
+version = 1
+
+[interfaces]
+uplink = "enp6s0"
+
+[networks.work]
+type = "bridge"
+iface = "br-work"
+vlan = 20
+"#,
+        )
+        .unwrap();
+
+        let plan = Plan::from_topology(&topology).unwrap();
+        assert_eq!(
+            plan.actions,
+            vec![
+                Action::CreateBridge {
+                    name: "br-work".into(),
+                    cidr: None,
+                    mtu: None,
+                },
+                Action::CreateVlan {
+                    parent: "enp6s0".into(),
+                    vlan_id: 20,
+                    name: "enp6s0.20".into(),
+                },
+                Action::AttachVlanToBridge {
+                    vlan: "enp6s0.20".into(),
+                    bridge: "br-work".into(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn bridge_mtu_is_carried_by_create_bridge() {
+        let topology = Topology::from_toml(
+            r#"
+version = 1
+
+[interfaces]
+
+[networks.jumbo]
+type = "routed"
+cidr = "10.40.0.0/24"
+gw_ip = "10.40.0.1"
+mtu = 9000
+"#,
+        )
+        .unwrap();
+
+        let plan = Plan::from_topology(&topology).unwrap();
+        let create = &plan.actions[0];
+        assert_eq!(
+            create,
+            &Action::CreateBridge {
+                name: "br-jumbo".into(),
+                cidr: Some("10.40.0.0/24".into()),
+                mtu: Some(9000),
+            }
+        );
+        assert_eq!(
+            create.to_string(),
+            "Create bridge: br-jumbo (10.40.0.0/24) mtu 9000"
+        );
+
+        // Actions recorded before `mtu` existed still load
+        let old: Action =
+            serde_json::from_str(r#"{"CreateBridge":{"name":"br-old","cidr":null}}"#).unwrap();
+        assert!(matches!(old, Action::CreateBridge { mtu: None, .. }));
+    }
+
+    #[test]
+    fn vlan_filtering_flag_plans_default_pvid() {
+        let topology = Topology::from_toml(
+            r#"
+version = 1
+
+[interfaces]
+
+[networks.trunk]
+type = "bridge"
+iface = "br-trunk"
+vlan_filtering = true
+"#,
+        )
+        .expect("topology");
+        let plan = Plan::from_topology(&topology).unwrap();
+
+        assert!(plan.actions.contains(&Action::SetBridgeVlanFiltering {
+            bridge: "br-trunk".into(),
+            default_pvid: 1,
+        }));
+    }
+
+    #[test]
+    fn vxlan_is_created_and_attached_after_its_bridge() {
+        let topology = Topology::from_toml(
+            r#"
+version = 1
+
+[interfaces]
+
+[networks.overlay]
+type = "vxlan"
+vni = 1200
+bridge = "br-overlay"
+peers = ["10.0.0.11", "10.0.0.12"]
+local = "10.0.0.10"
+
+[networks.lan]
+type = "bridge"
+iface = "br-overlay"
+"#,
+        )
+        .expect("topology");
+        let plan = Plan::from_topology(&topology).unwrap();
+
+        let create = plan
+            .actions
+            .iter()
+            .position(|a| matches!(a, Action::CreateVxlan { .. }))
+            .expect("vxlan action");
+        assert_eq!(
+            plan.actions[create],
+            Action::CreateVxlan {
+                name: "vxlan1200".into(),
+                vni: 1200,
+                local: Some("10.0.0.10".parse().unwrap()),
+                group: None,
+                peers: vec!["10.0.0.11".parse().unwrap(), "10.0.0.12".parse().unwrap()],
+                dstport: VXLAN_DEFAULT_PORT,
+            }
+        );
+        assert_eq!(
+            plan.actions[create + 1],
+            Action::AttachVxlanToBridge {
+                vxlan: "vxlan1200".into(),
+                bridge: "br-overlay".into(),
+            }
+        );
+
+        let bridge = plan
+            .actions
+            .iter()
+            .position(|a| matches!(a, Action::CreateBridge { name, .. } if name == "br-overlay"))
+            .expect("bridge action");
+        assert!(bridge < create);
+    }
+}
@@ -1,4 +1,6 @@
+use anyhow::{Result, bail};
 use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PolicyProfile {
@@ -10,15 +12,197 @@ pub struct PolicyProfile {
     pub allowed_egress_cidrs: Vec<String>,
     #[serde(default)]
     pub services: Vec<Service>,
+    /// Outbound services allowed from the network to any destination (dport-matched).
+    #[serde(default)]
+    pub allowed_egress_services: Vec<Service>,
     #[serde(default = "default_drop_policy")]
     pub default_action: Action,
+    /// Rate limit on ICMP service allows while the default action drops.
+    #[serde(default)]
+    pub icmp_rate_limit: IcmpRateLimit,
+    /// Log (rate-limited) and count packets about to hit a drop/reject default.
+    #[serde(default)]
+    pub log_dropped: bool,
+    /// Count packets/bytes on the network's NAT and policy rules.
+    #[serde(default)]
+    pub counters: bool,
+}
+
+/// `limit` applied to ICMP/ICMPv6 allows so a drop-policy gateway can't be used
+/// as a ping amplifier; packets over the rate fall through to the default action.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IcmpRateLimit {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// Packets per second
+    #[serde(default = "default_icmp_rate")]
+    pub rate: u32,
+    #[serde(default = "default_icmp_burst")]
+    pub burst: u32,
+}
+
+impl Default for IcmpRateLimit {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            rate: default_icmp_rate(),
+            burst: default_icmp_burst(),
+        }
+    }
+}
+
+impl PolicyProfile {
+    /// The ICMP rate limit to emit, if any: only under a drop/reject default,
+    /// since an accept default lets ICMP through regardless.
+    pub fn effective_icmp_rate_limit(&self) -> Option<&IcmpRateLimit> {
+        let limited =
+            self.icmp_rate_limit.enabled && !matches!(self.default_action, Action::Accept);
+        limited.then_some(&self.icmp_rate_limit)
+    }
+
+    /// Whether to emit drop-logging rules; an accept default drops nothing.
+    pub fn logs_drops(&self) -> bool {
+        self.log_dropped && !matches!(self.default_action, Action::Accept)
+    }
+
+    /// Reject services that cannot be turned into nftables rules.
+    pub fn validate(&self) -> Result<()> {
+        if self.icmp_rate_limit.enabled && self.icmp_rate_limit.rate == 0 {
+            bail!(
+                "profile '{}' icmp_rate_limit: rate must be at least 1 (set enabled = false to disable)",
+                self.name
+            );
+        }
+        for service in &self.services {
+            service
+                .validate()
+                .map_err(|e| anyhow::anyhow!("profile '{}' service: {}", self.name, e))?;
+        }
+        for service in &self.allowed_egress_services {
+            service
+                .validate()
+                .map_err(|e| anyhow::anyhow!("profile '{}' egress service: {}", self.name, e))?;
+        }
+        Ok(())
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Service {
     pub protocol: Protocol,
+    #[serde(default)]
     pub port: u16,
+    /// Several destination ports matched by a single rule; alternative to `port`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ports: Vec<u16>,
     pub source: Option<String>,
+    /// nftables-style rate limit on the allow, e.g. `"10/minute"` or
+    /// `"10/minute burst 5"`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate_limit: Option<String>,
+}
+
+/// A parsed service `rate_limit`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RateLimit {
+    pub rate: u32,
+    /// `second`, `minute`, `hour`, `day` or `week`
+    pub per: String,
+    pub burst: Option<u32>,
+}
+
+const RATE_UNITS: [&str; 5] = ["second", "minute", "hour", "day", "week"];
+
+impl RateLimit {
+    /// Parse `<rate>/<unit>` with an optional ` burst <packets>`.
+    pub fn parse(spec: &str) -> Result<Self> {
+        let words: Vec<&str> = spec.split_whitespace().collect();
+        let (rate, burst) = match words.as_slice() {
+            [rate] => (*rate, None),
+            [rate, "burst", burst] => (*rate, Some(*burst)),
+            _ => bail!(
+                "invalid rate_limit '{}' (expected \"<rate>/<unit>\" or \"<rate>/<unit> burst <packets>\")",
+                spec
+            ),
+        };
+
+        let Some((rate, per)) = rate.split_once('/') else {
+            bail!("invalid rate_limit '{}': missing /<unit>", spec);
+        };
+        let Some(rate) = rate.parse::<u32>().ok().filter(|rate| *rate > 0) else {
+            bail!("invalid rate_limit '{}': rate must be at least 1", spec);
+        };
+        if !RATE_UNITS.contains(&per) {
+            bail!(
+                "invalid rate_limit '{}': unit must be one of {}",
+                spec,
+                RATE_UNITS.join(", ")
+            );
+        }
+        let burst = match burst {
+            Some(burst) => match burst.parse::<u32>() {
+                Ok(burst) if burst > 0 => Some(burst),
+                _ => bail!("invalid rate_limit '{}': burst must be at least 1", spec),
+            },
+            None => None,
+        };
+
+        Ok(Self {
+            rate,
+            per: per.to_string(),
+            burst,
+        })
+    }
+}
+
+impl Service {
+    /// Destination ports this service matches, sorted and deduplicated.
+    pub fn dports(&self) -> Vec<u16> {
+        let mut ports = if self.ports.is_empty() {
+            vec![self.port]
+        } else {
+            self.ports.clone()
+        };
+        ports.sort_unstable();
+        ports.dedup();
+        ports
+    }
+
+    /// The parsed `rate_limit`, if one is set.
+    pub fn parsed_rate_limit(&self) -> Result<Option<RateLimit>> {
+        self.rate_limit.as_deref().map(RateLimit::parse).transpose()
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if !matches!(self.protocol, Protocol::Icmp) {
+            if self.port != 0 && !self.ports.is_empty() {
+                bail!("set either port or ports, not both");
+            }
+            if self.dports().contains(&0) {
+                bail!("{:?} port must be between 1 and 65535", self.protocol);
+            }
+        }
+
+        if let Some(source) = &self.source {
+            let (addr, prefix) = match source.split_once('/') {
+                Some((addr, prefix)) => (addr, Some(prefix)),
+                None => (source.as_str(), None),
+            };
+            let Ok(addr) = addr.parse::<IpAddr>() else {
+                bail!("invalid source address '{}'", source);
+            };
+            let max_prefix = if addr.is_ipv4() { 32 } else { 128 };
+            if let Some(prefix) = prefix
+                && !prefix.parse::<u8>().is_ok_and(|p| p <= max_prefix)
+            {
+                bail!("invalid source prefix '{}'", source);
+            }
+        }
+
+        self.parsed_rate_limit()?;
+
+        Ok(())
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -26,6 +210,7 @@ pub struct Service {
 pub enum Protocol {
     Tcp,
     Udp,
+    Sctp,
     Icmp,
 }
 
@@ -40,3 +225,15 @@ pub enum Action {
 fn default_drop_policy() -> Action {
     Action::Drop
 }
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_icmp_rate() -> u32 {
+    10
+}
+
+fn default_icmp_burst() -> u32 {
+    20
+}
@@ -17,6 +17,9 @@ impl ProfileLoader {
         let format = crate::config_format::ConfigFormat::from_path(path)?;
 
         let profile: PolicyProfile = crate::config_format::from_str(&content, format)?;
+        profile
+            .validate()
+            .context(format!("Invalid policy profile {:?}", path))?;
 
         Ok(profile)
     }
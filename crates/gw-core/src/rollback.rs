@@ -3,8 +3,12 @@ use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
+use std::os::unix::fs::FileTypeExt;
+use std::path::{Path, PathBuf};
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::io::AsyncReadExt;
+use tokio::net::UnixListener;
+use tokio::net::unix::pipe;
 use tokio::time::{sleep, timeout};
 
 pub const ROLLBACK_FILENAME: &str = "rollback.json";
@@ -89,9 +93,107 @@ pub fn clear_record() -> Result<()> {
     Ok(())
 }
 
+/// Side channel an external supervisor writes to in order to confirm an
+/// apply (`--confirm-channel`). Any single byte confirms.
+pub enum ConfirmChannel {
+    /// An existing named pipe, held open read-write so it never reports EOF.
+    Fifo {
+        receiver: pipe::Receiver,
+        path: PathBuf,
+    },
+    /// A Unix socket gwarden listens on; removed again when dropped.
+    Socket {
+        listener: UnixListener,
+        path: PathBuf,
+    },
+}
+
+impl ConfirmChannel {
+    /// Open `path` as a FIFO if it is one, otherwise listen on it as a Unix
+    /// socket (replacing a stale socket file).
+    pub fn open(path: &Path) -> Result<Self> {
+        match fs::symlink_metadata(path) {
+            Ok(meta) if meta.file_type().is_fifo() => {
+                let receiver = pipe::OpenOptions::new()
+                    .read_write(true)
+                    .open_receiver(path)
+                    .with_context(|| format!("Failed to open FIFO {}", path.display()))?;
+                return Ok(Self::Fifo {
+                    receiver,
+                    path: path.to_path_buf(),
+                });
+            }
+            Ok(meta) if meta.file_type().is_socket() => fs::remove_file(path)
+                .with_context(|| format!("Failed to remove stale socket {}", path.display()))?,
+            Ok(_) => anyhow::bail!(
+                "Confirm channel {} exists and is neither a FIFO nor a socket",
+                path.display()
+            ),
+            Err(_) => {}
+        }
+
+        let listener = UnixListener::bind(path)
+            .with_context(|| format!("Failed to listen on {}", path.display()))?;
+        Ok(Self::Socket {
+            listener,
+            path: path.to_path_buf(),
+        })
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            Self::Fifo { path, .. } => format!("FIFO {}", path.display()),
+            Self::Socket { path, .. } => format!("socket {}", path.display()),
+        }
+    }
+
+    /// Resolve once any byte arrives. Socket clients that disconnect
+    /// without sending anything are ignored.
+    pub async fn recv(&mut self) -> std::io::Result<()> {
+        let mut byte = [0u8; 1];
+        match self {
+            Self::Fifo { receiver, .. } => loop {
+                if receiver.read(&mut byte).await? > 0 {
+                    return Ok(());
+                }
+            },
+            Self::Socket { listener, .. } => {
+                let (tx, mut rx) = tokio::sync::mpsc::channel::<()>(1);
+                loop {
+                    tokio::select! {
+                        accepted = listener.accept() => {
+                            let (mut stream, _) = accepted?;
+                            let tx = tx.clone();
+                            tokio::spawn(async move {
+                                let mut byte = [0u8; 1];
+                                if matches!(stream.read(&mut byte).await, Ok(n) if n > 0) {
+                                    let _ = tx.send(()).await;
+                                }
+                            });
+                        }
+                        Some(()) = rx.recv() => return Ok(()),
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Drop for ConfirmChannel {
+    fn drop(&mut self) {
+        if let Self::Socket { path, .. } = self {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
 pub struct RollbackManager {
     pub timeout_seconds: u64,
     pub ssh_check_enabled: bool,
+    /// Confirm immediately instead of waiting for ENTER (`--yes`).
+    pub assume_yes: bool,
+    /// Wait for a byte on this channel instead of ENTER on stdin.
+    pub confirm_channel: Option<ConfirmChannel>,
 }
 
 impl RollbackManager {
@@ -99,13 +201,47 @@ impl RollbackManager {
         Self {
             timeout_seconds,
             ssh_check_enabled: true,
+            assume_yes: false,
+            confirm_channel: None,
         }
     }
 
     /// Wait for user confirmation or timeout
     /// If timeout expires without confirmation, trigger rollback
-    pub async fn wait_for_confirmation(&self) -> Result<bool> {
+    pub async fn wait_for_confirmation(&mut self) -> Result<bool> {
+        if self.assume_yes {
+            println!(
+                "\n⚠️  --yes: confirming changes without waiting (auto-rollback window skipped)"
+            );
+            return Ok(true);
+        }
+
         println!("\n⏰ Auto-rollback armed for {}s", self.timeout_seconds);
+
+        if let Some(channel) = self.confirm_channel.as_mut() {
+            println!(
+                "   Waiting for confirmation on {}, or auto-rollback...",
+                channel.describe()
+            );
+            return match timeout(Duration::from_secs(self.timeout_seconds), channel.recv()).await {
+                Ok(Ok(())) => {
+                    println!("✅ Changes confirmed!");
+                    Ok(true)
+                }
+                Ok(Err(err)) => {
+                    println!(
+                        "\n❌ Confirm channel failed ({}). Rolling back changes...",
+                        err
+                    );
+                    Ok(false)
+                }
+                Err(_) => {
+                    println!("\n⚠️  Timeout reached! Rolling back changes...");
+                    Ok(false)
+                }
+            };
+        }
+
         println!("   Press ENTER to confirm changes, or wait for auto-rollback...");
 
         // Create a channel for user input
@@ -256,3 +392,62 @@ impl Default for RollbackState {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::AsyncWriteExt;
+
+    fn channel_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("gw-confirm-{}-{}", name, std::process::id()))
+    }
+
+    #[tokio::test]
+    async fn socket_channel_confirms_on_first_byte() {
+        let path = channel_path("sock");
+        let mut manager = RollbackManager::new(5);
+        manager.confirm_channel = Some(ConfirmChannel::open(&path).unwrap());
+
+        let client_path = path.clone();
+        tokio::spawn(async move {
+            // A client that hangs up without writing does not confirm.
+            drop(tokio::net::UnixStream::connect(&client_path).await.unwrap());
+            let mut stream = tokio::net::UnixStream::connect(&client_path).await.unwrap();
+            stream.write_all(b"y").await.unwrap();
+        });
+
+        assert!(manager.wait_for_confirmation().await.unwrap());
+        drop(manager);
+        assert!(!path.exists());
+    }
+
+    #[tokio::test]
+    async fn fifo_channel_confirms_and_times_out() {
+        let path = channel_path("fifo");
+        let created = std::process::Command::new("mkfifo")
+            .arg(&path)
+            .status()
+            .is_ok_and(|status| status.success());
+        if !created {
+            return;
+        }
+
+        let mut manager = RollbackManager::new(1);
+        manager.confirm_channel = Some(ConfirmChannel::open(&path).unwrap());
+        assert!(!manager.wait_for_confirmation().await.unwrap());
+
+        let mut writer = pipe::OpenOptions::new().open_sender(&path).unwrap();
+        writer.write_all(b"\n").await.unwrap();
+        assert!(manager.wait_for_confirmation().await.unwrap());
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn regular_files_are_rejected() {
+        let path = channel_path("file");
+        fs::write(&path, b"").unwrap();
+        assert!(ConfirmChannel::open(&path).is_err());
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,11 +1,15 @@
 use crate::planner::{Action, Plan};
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
-use std::fs;
+use std::fs::{self, File};
 use std::path::{Path, PathBuf};
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 pub const APPLY_STATE_FILENAME: &str = "applied-state.json";
+pub const APPLY_DURATIONS_FILENAME: &str = "apply-durations.json";
+
+/// Recorded apply durations kept on disk; older entries are dropped first.
+const MAX_APPLY_DURATIONS: usize = 500;
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct ApplyState {
@@ -21,7 +25,10 @@ pub enum OwnedResource {
     Address { iface: String, addr: String },
     NftTable { table: String },
     DnsmasqConfig { path: String },
+    CoreDnsConfig { path: String },
     Vlan { name: String },
+    Vxlan { name: String },
+    Route { dst: String, dev: String },
 }
 
 impl ApplyState {
@@ -60,6 +67,75 @@ pub fn default_apply_state_path() -> Result<PathBuf> {
     Ok(crate::rollback::default_state_dir()?.join(APPLY_STATE_FILENAME))
 }
 
+/// How long one committed apply took, persisted so `gwarden metrics serve`
+/// can report it from a different process.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ApplyDuration {
+    /// Increases by one per recorded apply, so readers can skip entries they
+    /// have already seen even after old ones are dropped.
+    pub seq: u64,
+    pub topology: String,
+    pub seconds: f64,
+    pub finished_at: u64,
+}
+
+pub fn default_apply_durations_path() -> Result<PathBuf> {
+    Ok(crate::rollback::default_state_dir()?.join(APPLY_DURATIONS_FILENAME))
+}
+
+/// Recorded apply durations at `path`, oldest first; empty if none exist yet.
+pub fn load_apply_durations(path: &Path) -> Result<Vec<ApplyDuration>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let data = fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    serde_json::from_slice(&data).with_context(|| format!("Failed to parse {}", path.display()))
+}
+
+/// Append an apply duration to the log at `path`, keeping the newest
+/// [`MAX_APPLY_DURATIONS`] entries.
+///
+/// Concurrent applies serialize on a `.lock` file next to the log, and the log
+/// is replaced by renaming a temp file, so a reader never sees a partial write.
+pub fn record_apply_duration(path: &Path, topology: &str, duration: Duration) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create state directory {}", parent.display()))?;
+    }
+    let lock_path = sibling_path(path, "lock");
+    let lock = File::create(&lock_path)
+        .with_context(|| format!("Failed to open {}", lock_path.display()))?;
+    lock.lock()
+        .with_context(|| format!("Failed to lock {}", lock_path.display()))?;
+
+    let mut durations = load_apply_durations(path)?;
+    let seq = durations.last().map_or(1, |last| last.seq + 1);
+    durations.push(ApplyDuration {
+        seq,
+        topology: topology.to_string(),
+        seconds: duration.as_secs_f64(),
+        finished_at: unix_timestamp(),
+    });
+    let excess = durations.len().saturating_sub(MAX_APPLY_DURATIONS);
+    durations.drain(..excess);
+
+    let tmp_path = sibling_path(path, "tmp");
+    let data = serde_json::to_vec_pretty(&durations)?;
+    fs::write(&tmp_path, data)
+        .with_context(|| format!("Failed to write {}", tmp_path.display()))?;
+    fs::rename(&tmp_path, path).with_context(|| format!("Failed to replace {}", path.display()))?;
+    Ok(())
+}
+
+/// `path` with `.<suffix>` appended to its file name.
+fn sibling_path(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".");
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
 pub fn new_transaction_id() -> String {
     format!("gw-{}", unix_timestamp())
 }
@@ -86,8 +162,20 @@ fn owned_resources_from_actions(actions: &[Action]) -> Vec<OwnedResource> {
             Action::StartDnsmasq { config_path } => Some(OwnedResource::DnsmasqConfig {
                 path: config_path.clone(),
             }),
+            Action::StartCoreDns { config_path } => Some(OwnedResource::CoreDnsConfig {
+                path: config_path.clone(),
+            }),
             Action::CreateVlan { name, .. } => Some(OwnedResource::Vlan { name: name.clone() }),
-            Action::EnableForwarding { .. } | Action::AttachVlanToBridge { .. } => None,
+            Action::CreateVxlan { name, .. } => Some(OwnedResource::Vxlan { name: name.clone() }),
+            Action::AddRoute { dst, dev, .. } => Some(OwnedResource::Route {
+                dst: dst.clone(),
+                dev: dev.clone(),
+            }),
+            Action::EnableForwarding { .. }
+            | Action::AttachVlanToBridge { .. }
+            | Action::SetBridgeVlanFiltering { .. }
+            | Action::SetBridgeStp { .. }
+            | Action::AttachVxlanToBridge { .. } => None,
         })
         .collect()
 }
@@ -103,6 +191,7 @@ mod tests {
                 Action::CreateBridge {
                     name: "br-test".into(),
                     cidr: None,
+                    mtu: None,
                 },
                 Action::AddAddress {
                     iface: "br-test".into(),
@@ -112,6 +201,7 @@ mod tests {
                     iface: "br-test".into(),
                 },
             ],
+            warnings: Vec::new(),
         };
 
         let state = ApplyState::from_plan("gw-test".into(), plan.clone(), &plan.actions);
@@ -131,6 +221,68 @@ mod tests {
         );
     }
 
+    #[test]
+    fn apply_durations_are_appended_and_capped() {
+        let dir = std::env::temp_dir().join(format!("gw-apply-durations-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let path = dir.join(APPLY_DURATIONS_FILENAME);
+
+        assert!(load_apply_durations(&path).unwrap().is_empty());
+
+        record_apply_duration(&path, "lab.toml", Duration::from_millis(1500)).unwrap();
+        record_apply_duration(&path, "lab.toml", Duration::from_secs(3)).unwrap();
+        let durations = load_apply_durations(&path).unwrap();
+        assert_eq!(
+            durations
+                .iter()
+                .map(|d| (d.seq, d.topology.as_str(), d.seconds))
+                .collect::<Vec<_>>(),
+            [(1, "lab.toml", 1.5), (2, "lab.toml", 3.0)]
+        );
+
+        for _ in 0..MAX_APPLY_DURATIONS {
+            record_apply_duration(&path, "lab.toml", Duration::from_secs(1)).unwrap();
+        }
+        let durations = load_apply_durations(&path).unwrap();
+        assert_eq!(durations.len(), MAX_APPLY_DURATIONS);
+        assert_eq!(durations[0].seq, 3);
+        assert_eq!(
+            durations.last().unwrap().seq,
+            MAX_APPLY_DURATIONS as u64 + 2
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn concurrent_apply_durations_are_all_recorded() {
+        let dir = std::env::temp_dir().join(format!(
+            "gw-apply-durations-concurrent-{}",
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        let path = dir.join(APPLY_DURATIONS_FILENAME);
+
+        std::thread::scope(|scope| {
+            for _ in 0..8 {
+                scope.spawn(|| {
+                    for _ in 0..5 {
+                        record_apply_duration(&path, "lab.toml", Duration::from_secs(1)).unwrap();
+                    }
+                });
+            }
+        });
+
+        let seqs: Vec<u64> = load_apply_durations(&path)
+            .unwrap()
+            .iter()
+            .map(|d| d.seq)
+            .collect();
+        assert_eq!(seqs, (1..=40).collect::<Vec<_>>());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
     #[test]
     fn apply_state_json_round_trip() {
         let plan = Plan {
@@ -138,6 +290,7 @@ mod tests {
                 table: "gw-test".into(),
                 policy_profile: Some("routed-tight".into()),
             }],
+            warnings: Vec::new(),
         };
         let state = ApplyState::from_plan("gw-test".into(), plan.clone(), &plan.actions);
 
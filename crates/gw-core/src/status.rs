@@ -1,4 +1,6 @@
+use crate::Topology;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NetworkStatus {
@@ -15,12 +17,44 @@ pub struct BridgeStatus {
     pub members: Vec<String>,
 }
 
+/// A counted nftables rule: (description, packets, bytes).
+pub type RuleCounter = (String, u64, u64);
+
+/// Interface counters as reported by the kernel, zero when it reports none.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LinkStats {
+    pub rx_bytes: u64,
+    pub tx_bytes: u64,
+    pub rx_packets: u64,
+    pub tx_packets: u64,
+    pub rx_errors: u64,
+    pub tx_errors: u64,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NftTableStatus {
     pub name: String,
     pub family: String,
     pub chains: usize,
     pub rules: usize,
+    #[serde(default)]
+    pub quotas: Vec<QuotaStatus>,
+}
+
+/// Live state of an nftables `quota` object.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuotaStatus {
+    pub name: String,
+    pub bytes: u64,
+    pub used: u64,
+    /// `over` quota: matching (and dropping) starts once `used` passes `bytes`.
+    pub over: bool,
+}
+
+impl QuotaStatus {
+    pub fn exceeded(&self) -> bool {
+        self.used > self.bytes
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -31,6 +65,38 @@ pub struct DhcpLease {
     pub expires: Option<String>,
 }
 
+/// Which interfaces status views and diagnostics report on.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum InterfaceFilter {
+    /// Every bridge on the host, including Docker, libvirt and k8s ones.
+    #[default]
+    All,
+    /// Only the named bridges; ports enslaved to them are reported as members.
+    Managed(BTreeSet<String>),
+}
+
+impl InterfaceFilter {
+    /// Scope to the bridges the topology defines.
+    pub fn managed(topology: &Topology) -> Self {
+        Self::Managed(topology.managed_bridges())
+    }
+
+    pub fn includes(&self, bridge: &str) -> bool {
+        match self {
+            Self::All => true,
+            Self::Managed(bridges) => bridges.contains(bridge),
+        }
+    }
+
+    /// Bridge names in scope, or `None` when every interface is reported.
+    pub fn bridges(&self) -> Option<&BTreeSet<String>> {
+        match self {
+            Self::All => None,
+            Self::Managed(bridges) => Some(bridges),
+        }
+    }
+}
+
 impl NetworkStatus {
     pub fn new() -> Self {
         Self {
@@ -40,6 +106,11 @@ impl NetworkStatus {
         }
     }
 
+    /// Drop bridges the filter does not cover.
+    pub fn retain_interfaces(&mut self, filter: &InterfaceFilter) {
+        self.bridges.retain(|bridge| filter.includes(&bridge.name));
+    }
+
     pub fn display(&self) {
         println!("📊 Network Status\n");
 
@@ -69,6 +140,18 @@ impl NetworkStatus {
                     "  • {} ({}) - {} chains, {} rules",
                     table.name, table.family, table.chains, table.rules
                 );
+                for quota in &table.quotas {
+                    let percent = quota.used as f64 * 100.0 / quota.bytes.max(1) as f64;
+                    let state = match (quota.exceeded(), quota.over) {
+                        (true, true) => ", exceeded - dropping",
+                        (true, false) => ", exceeded",
+                        _ => "",
+                    };
+                    println!(
+                        "    Quota {}: {} / {} bytes ({:.1}%{})",
+                        quota.name, quota.used, quota.bytes, percent, state
+                    );
+                }
             }
         }
 
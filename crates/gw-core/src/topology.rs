@@ -1,14 +1,20 @@
+use anyhow::Context;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::net::IpAddr;
+use serde_json::Value;
+use std::collections::{BTreeSet, HashMap};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct Topology {
     pub version: u32,
     pub interfaces: HashMap<String, String>,
     pub networks: HashMap<String, Network>,
 }
 
+// Topologies are loaded once and held by reference; boxing the routed variant
+// would only add indirection.
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "lowercase")]
 pub enum Network {
@@ -18,9 +24,16 @@ pub enum Network {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct RoutedNetwork {
     pub cidr: String,
     pub gw_ip: IpAddr,
+    /// IPv6 prefix for a dual-stack network, NATed in the same table as `cidr`.
+    #[serde(default)]
+    pub cidr6: Option<String>,
+    /// Bridge address inside `cidr6`; required with it.
+    #[serde(default)]
+    pub gw_ip6: Option<IpAddr>,
     #[serde(default)]
     pub dhcp: bool,
     #[serde(default)]
@@ -28,39 +41,1145 @@ pub struct RoutedNetwork {
     pub masq_out: Option<String>,
     #[serde(default)]
     pub forwards: Vec<PortForward>,
+    /// Also DNAT forwards for clients on the bridge that connect to the public
+    /// address (hairpin NAT).
+    #[serde(default)]
+    pub hairpin: bool,
     #[serde(default)]
     pub policy_profile: Option<String>,
+    /// Mark VM/tap ports on the bridge as isolated from each other.
+    #[serde(default)]
+    pub isolate_ports: bool,
+    /// Conntrack helpers (see [`CT_HELPERS`]) to attach for FTP/SIP-style protocols.
+    #[serde(default)]
+    pub ct_helpers: Vec<String>,
+    /// Cap on forwarded traffic, enforced with an nftables `quota` object.
+    #[serde(default)]
+    pub quota: Option<TrafficQuota>,
+    /// Extra DHCP options (NTP, domain search, MTU, ...) handed out by dnsmasq.
+    #[serde(default)]
+    pub dhcp_options: Vec<DhcpOption>,
+    /// First address of the DHCP pool; defaults to one derived from `cidr`.
+    #[serde(default)]
+    pub dhcp_start: Option<Ipv4Addr>,
+    /// Last address of the DHCP pool; defaults to one derived from `cidr`.
+    #[serde(default)]
+    pub dhcp_end: Option<Ipv4Addr>,
+    /// dnsmasq lease time (`12h`, `45m`, `86400`, `infinite`); defaults to 12h.
+    #[serde(default)]
+    pub lease_time: Option<String>,
+    /// Fixed leases, served outside the dynamic pool.
+    #[serde(default)]
+    pub reservations: Vec<DhcpReservation>,
+    /// How hosts get addresses in `cidr6` when `dhcp` is on.
+    #[serde(default)]
+    pub ipv6_mode: Ipv6AddressMode,
+    /// First DHCPv6 address with `ipv6_mode = "dhcpv6"`; defaults to `::1000`.
+    #[serde(default)]
+    pub dhcp6_start: Option<Ipv6Addr>,
+    /// Last DHCPv6 address with `ipv6_mode = "dhcpv6"`; defaults to `::1fff`.
+    #[serde(default)]
+    pub dhcp6_end: Option<Ipv6Addr>,
+    /// Network boot: the file DHCP clients load and where they fetch it.
+    #[serde(default)]
+    pub pxe: Option<PxeBoot>,
+    /// Subnets reached through a next hop on this network, e.g. a VM router.
+    #[serde(default)]
+    pub routes: Vec<StaticRoute>,
+    /// Bridge MTU, re-applied on every apply; left alone when unset.
+    #[serde(default)]
+    pub mtu: Option<u32>,
+}
+
+impl RoutedNetwork {
+    /// Whether `cidr` or `cidr6` is of `addr`'s address family, i.e. whether
+    /// traffic to `addr` can be NATed on this network.
+    pub fn has_prefix_for(&self, addr: IpAddr) -> bool {
+        std::iter::once(&self.cidr)
+            .chain(&self.cidr6)
+            .filter_map(|cidr| cidr.split_once('/'))
+            .filter_map(|(ip, _)| ip.parse::<IpAddr>().ok())
+            .any(|ip| ip.is_ipv6() == addr.is_ipv6())
+    }
+
+    /// Whether `addr` is a host address of `cidr`, e.g. one of its DHCP leases.
+    pub fn contains_host(&self, addr: Ipv4Addr) -> bool {
+        host_range(&self.cidr).is_some_and(|hosts| hosts.contains(&u32::from(addr)))
+    }
+
+    pub fn dhcp_pool(&self) -> DhcpPool {
+        DhcpPool {
+            start: self.dhcp_start,
+            end: self.dhcp_end,
+            lease_time: self.lease_time.clone(),
+            reservations: self.reservations.clone(),
+            ipv6: self
+                .cidr6
+                .as_ref()
+                .filter(|_| self.dhcp)
+                .map(|cidr6| Dhcp6Pool {
+                    cidr: cidr6.clone(),
+                    gateway: self.gw_ip6,
+                    mode: self.ipv6_mode,
+                    start: self.dhcp6_start,
+                    end: self.dhcp6_end,
+                }),
+            pxe: self.pxe.clone(),
+        }
+    }
+}
+
+/// A route installed on the network's bridge: `ip route add <dst> via <via>`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StaticRoute {
+    /// Destination in CIDR notation.
+    pub dst: String,
+    pub via: IpAddr,
+}
+
+impl StaticRoute {
+    /// Split `dst` into its network address and prefix length.
+    pub fn destination(&self) -> anyhow::Result<(IpAddr, u8)> {
+        let (addr, prefix) = self
+            .dst
+            .split_once('/')
+            .with_context(|| format!("route destination {} is not in CIDR notation", self.dst))?;
+        let addr: IpAddr = addr
+            .parse()
+            .with_context(|| format!("invalid route destination {}", self.dst))?;
+        let prefix: u8 = prefix
+            .parse()
+            .with_context(|| format!("invalid prefix length in {}", self.dst))?;
+
+        let max_prefix = if addr.is_ipv4() { 32 } else { 128 };
+        if prefix > max_prefix {
+            anyhow::bail!("prefix length in {} exceeds {}", self.dst, max_prefix);
+        }
+        Ok((addr, prefix))
+    }
+
+    pub fn validate(&self) -> anyhow::Result<()> {
+        let (addr, _) = self.destination()?;
+        if addr.is_ipv4() != self.via.is_ipv4() {
+            anyhow::bail!(
+                "next hop {} is not the same address family as {}",
+                self.via,
+                self.dst
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Byte budget for everything a routed network forwards, in both directions.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrafficQuota {
+    pub bytes: u64,
+    #[serde(default)]
+    pub mode: QuotaMode,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum QuotaMode {
+    /// Drop forwarded traffic once `bytes` have been used.
+    #[default]
+    Over,
+    /// Only meter consumption against `bytes`; nothing is dropped.
+    Until,
+}
+
+impl TrafficQuota {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.bytes == 0 {
+            anyhow::bail!("quota bytes must be greater than 0");
+        }
+        // nft's JSON parser reads integers as signed 64-bit values.
+        if self.bytes > i64::MAX as u64 {
+            anyhow::bail!("quota bytes {} is too large", self.bytes);
+        }
+        Ok(())
+    }
+}
+
+/// A conntrack helper Ghostwarden can bind, with the kernel modules it relies on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CtHelper {
+    pub name: &'static str,
+    pub protocol: &'static str,
+    pub port: u16,
+    pub module: &'static str,
+    pub nat_module: &'static str,
+}
+
+pub const CT_HELPERS: &[CtHelper] = &[
+    CtHelper {
+        name: "ftp",
+        protocol: "tcp",
+        port: 21,
+        module: "nf_conntrack_ftp",
+        nat_module: "nf_nat_ftp",
+    },
+    CtHelper {
+        name: "tftp",
+        protocol: "udp",
+        port: 69,
+        module: "nf_conntrack_tftp",
+        nat_module: "nf_nat_tftp",
+    },
+    CtHelper {
+        name: "sip",
+        protocol: "udp",
+        port: 5060,
+        module: "nf_conntrack_sip",
+        nat_module: "nf_nat_sip",
+    },
+    CtHelper {
+        name: "irc",
+        protocol: "tcp",
+        port: 6667,
+        module: "nf_conntrack_irc",
+        nat_module: "nf_nat_irc",
+    },
+    CtHelper {
+        name: "pptp",
+        protocol: "tcp",
+        port: 1723,
+        module: "nf_conntrack_pptp",
+        nat_module: "nf_nat_pptp",
+    },
+];
+
+impl CtHelper {
+    pub fn lookup(name: &str) -> Option<&'static CtHelper> {
+        CT_HELPERS.iter().find(|helper| helper.name == name)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct BridgeNetwork {
     pub iface: String,
     pub vlan: Option<u16>,
     #[serde(default)]
     pub policy_profile: Option<String>,
+    /// Mark VM/tap ports on the bridge as isolated from each other.
+    #[serde(default)]
+    pub isolate_ports: bool,
+    /// Enable VLAN filtering for a trunk bridge whose port VLANs are managed
+    /// elsewhere; implied by `vlan_default_pvid` and `port_vlans`.
+    #[serde(default)]
+    pub vlan_filtering: bool,
+    /// Enable VLAN filtering on the bridge with this default PVID for new ports.
+    #[serde(default)]
+    pub vlan_default_pvid: Option<u16>,
+    /// VLAN membership applied to VM ports attached to the bridge; implies
+    /// VLAN filtering.
+    #[serde(default)]
+    pub port_vlans: Option<PortVlans>,
+    /// Spanning Tree Protocol on the bridge; left as the kernel default (off)
+    /// when unset.
+    #[serde(default)]
+    pub stp: Option<bool>,
+    /// Bridge MTU, re-applied on every apply; left alone when unset.
+    #[serde(default)]
+    pub mtu: Option<u32>,
+}
+
+impl BridgeNetwork {
+    /// Default PVID to program when the bridge needs VLAN filtering at all.
+    pub fn vlan_filtering_pvid(&self) -> Option<u16> {
+        match (self.vlan_default_pvid, &self.port_vlans) {
+            (Some(pvid), _) => Some(pvid),
+            (None, Some(_)) => Some(1),
+            (None, None) => self.vlan_filtering.then_some(1),
+        }
+    }
+}
+
+/// VLAN membership of a port on a VLAN-filtering bridge.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PortVlans {
+    /// VLAN assigned to untagged ingress; must be one of `vlans`.
+    #[serde(default)]
+    pub pvid: Option<u16>,
+    /// VLANs the port carries, tagged on egress unless listed in `untagged`.
+    #[serde(default)]
+    pub vlans: Vec<u16>,
+    /// VLANs that egress untagged; must be a subset of `vlans`.
+    #[serde(default)]
+    pub untagged: Vec<u16>,
+}
+
+impl PortVlans {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.vlans.is_empty() {
+            anyhow::bail!("port VLAN list is empty");
+        }
+        for vid in &self.vlans {
+            validate_vlan_id(*vid)?;
+        }
+        for vid in &self.untagged {
+            if !self.vlans.contains(vid) {
+                anyhow::bail!("untagged VLAN {} is not in the port's vlans", vid);
+            }
+        }
+        if let Some(pvid) = self.pvid
+            && !self.vlans.contains(&pvid)
+        {
+            anyhow::bail!("pvid {} is not in the port's vlans", pvid);
+        }
+        Ok(())
+    }
+}
+
+/// 802.1Q IDs 0 and 4095 are reserved.
+pub fn validate_vlan_id(vid: u16) -> anyhow::Result<()> {
+    if !(1..=4094).contains(&vid) {
+        anyhow::bail!("VLAN ID {} is out of range (1-4094)", vid);
+    }
+    Ok(())
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct VxlanNetwork {
     pub vni: u32,
     pub peers: Vec<IpAddr>,
     pub bridge: String,
+    /// Source address for encapsulated packets; the kernel picks one when unset.
+    #[serde(default)]
+    pub local: Option<IpAddr>,
+    /// Multicast group flooded with unknown-destination traffic, alongside `peers`.
+    #[serde(default)]
+    pub group: Option<IpAddr>,
+    /// UDP destination port, [`VXLAN_DEFAULT_PORT`] when unset.
+    #[serde(default)]
+    pub dstport: Option<u16>,
+}
+
+/// IANA-assigned VXLAN port. Set explicitly because the kernel still defaults
+/// to the pre-standard 8472.
+pub const VXLAN_DEFAULT_PORT: u16 = 4789;
+
+/// VNIs are 24-bit.
+pub const VXLAN_MAX_VNI: u32 = (1 << 24) - 1;
+
+impl VxlanNetwork {
+    /// Kernel device name for the tunnel endpoint. VNIs are unique per
+    /// topology and fit in 24 bits, so this stays within IFNAMSIZ.
+    pub fn device_name(&self) -> String {
+        format!("vxlan{}", self.vni)
+    }
+}
+
+/// A DHCP option given by dnsmasq name (`"ntp-server"`) or number (`42`).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DhcpOption {
+    pub option: DhcpOptionId,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum DhcpOptionId {
+    Number(u16),
+    Name(String),
+}
+
+impl std::fmt::Display for DhcpOptionId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Number(code) => write!(f, "{}", code),
+            Self::Name(name) => f.write_str(name),
+        }
+    }
+}
+
+/// Value format a known DHCP option expects.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DhcpValueKind {
+    IpList,
+    Domain,
+    DomainList,
+    Mtu,
+    Text,
+}
+
+/// A DHCP option Ghostwarden knows how to validate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DhcpOptionSpec {
+    /// dnsmasq's `option:<name>` spelling; `None` for options it has no name for.
+    pub name: Option<&'static str>,
+    pub code: u8,
+    pub kind: DhcpValueKind,
+}
+
+pub const DHCP_OPTIONS: &[DhcpOptionSpec] = &[
+    DhcpOptionSpec {
+        name: Some("router"),
+        code: 3,
+        kind: DhcpValueKind::IpList,
+    },
+    DhcpOptionSpec {
+        name: Some("dns-server"),
+        code: 6,
+        kind: DhcpValueKind::IpList,
+    },
+    DhcpOptionSpec {
+        name: Some("domain-name"),
+        code: 15,
+        kind: DhcpValueKind::Domain,
+    },
+    DhcpOptionSpec {
+        name: Some("mtu"),
+        code: 26,
+        kind: DhcpValueKind::Mtu,
+    },
+    DhcpOptionSpec {
+        name: Some("ntp-server"),
+        code: 42,
+        kind: DhcpValueKind::IpList,
+    },
+    DhcpOptionSpec {
+        name: Some("netbios-ns"),
+        code: 44,
+        kind: DhcpValueKind::IpList,
+    },
+    DhcpOptionSpec {
+        name: Some("tftp-server"),
+        code: 66,
+        kind: DhcpValueKind::Text,
+    },
+    DhcpOptionSpec {
+        name: Some("bootfile-name"),
+        code: 67,
+        kind: DhcpValueKind::Text,
+    },
+    DhcpOptionSpec {
+        name: Some("posix-timezone"),
+        code: 100,
+        kind: DhcpValueKind::Text,
+    },
+    DhcpOptionSpec {
+        name: Some("tzdb-timezone"),
+        code: 101,
+        kind: DhcpValueKind::Text,
+    },
+    DhcpOptionSpec {
+        name: Some("domain-search"),
+        code: 119,
+        kind: DhcpValueKind::DomainList,
+    },
+    DhcpOptionSpec {
+        name: Some("classless-static-route"),
+        code: 121,
+        kind: DhcpValueKind::Text,
+    },
+    DhcpOptionSpec {
+        name: Some("tftp-server-address"),
+        code: 150,
+        kind: DhcpValueKind::IpList,
+    },
+    // WPAD proxy auto-config URL; dnsmasq has no name for it.
+    DhcpOptionSpec {
+        name: None,
+        code: 252,
+        kind: DhcpValueKind::Text,
+    },
+];
+
+impl DhcpOption {
+    /// The known option this refers to, if any.
+    pub fn spec(&self) -> Option<&'static DhcpOptionSpec> {
+        DHCP_OPTIONS.iter().find(|spec| match &self.option {
+            DhcpOptionId::Number(code) => u16::from(spec.code) == *code,
+            DhcpOptionId::Name(name) => spec.name == Some(name.as_str()),
+        })
+    }
+
+    /// Option number, resolving known names.
+    pub fn code(&self) -> Option<u8> {
+        match &self.option {
+            DhcpOptionId::Number(code) => u8::try_from(*code).ok(),
+            DhcpOptionId::Name(_) => self.spec().map(|spec| spec.code),
+        }
+    }
+
+    pub fn validate(&self) -> anyhow::Result<()> {
+        match &self.option {
+            DhcpOptionId::Number(code) if !(1..=254).contains(code) => {
+                anyhow::bail!("option number {} must be between 1 and 254", code);
+            }
+            DhcpOptionId::Name(name) if self.spec().is_none() => {
+                anyhow::bail!("unknown option name '{}'; use its number instead", name);
+            }
+            _ => {}
+        }
+
+        // Values are written verbatim into the dnsmasq config, one option per line.
+        if self.value.chars().any(char::is_control) {
+            anyhow::bail!("value must not contain newlines or other control characters");
+        }
+        let value = self.value.trim();
+        if value.is_empty() {
+            anyhow::bail!("value must not be empty");
+        }
+        let Some(spec) = self.spec() else {
+            // Unknown numbers are passed through as-is.
+            return Ok(());
+        };
+
+        let items = || value.split(',').map(str::trim);
+        match spec.kind {
+            DhcpValueKind::IpList => {
+                for item in items() {
+                    if item.parse::<std::net::Ipv4Addr>().is_err() {
+                        anyhow::bail!("'{}' is not an IPv4 address", item);
+                    }
+                }
+            }
+            DhcpValueKind::Domain => validate_domain(value)?,
+            DhcpValueKind::DomainList => {
+                for item in items() {
+                    validate_domain(item)?;
+                }
+            }
+            DhcpValueKind::Mtu => {
+                if !value.parse::<u16>().is_ok_and(|mtu| mtu >= 68) {
+                    anyhow::bail!("MTU '{}' must be a number between 68 and 65535", value);
+                }
+            }
+            DhcpValueKind::Text => {}
+        }
+        Ok(())
+    }
+
+    /// Argument for dnsmasq's `dhcp-option=`, e.g. `option:ntp-server,10.0.0.1`.
+    pub fn dnsmasq_value(&self) -> String {
+        let option = match &self.option {
+            DhcpOptionId::Number(code) => code.to_string(),
+            DhcpOptionId::Name(name) => format!("option:{}", name),
+        };
+        let value: Vec<&str> = self.value.split(',').map(str::trim).collect();
+        format!("{},{}", option, value.join(","))
+    }
+}
+
+/// The DHCP settings of a routed network: pool bounds (unset ones are derived
+/// from the prefix, see [`DhcpPool::range`]), lease time, fixed leases and
+/// network boot.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DhcpPool {
+    pub start: Option<Ipv4Addr>,
+    pub end: Option<Ipv4Addr>,
+    pub lease_time: Option<String>,
+    pub reservations: Vec<DhcpReservation>,
+    /// Router advertisements and DHCPv6 for a dual-stack network.
+    pub ipv6: Option<Dhcp6Pool>,
+    pub pxe: Option<PxeBoot>,
+}
+
+/// PXE boot settings. With `tftp_root` dnsmasq serves the files itself over
+/// TFTP on the bridge; otherwise clients fetch `boot_file` from `next_server`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PxeBoot {
+    /// Path of the boot loader relative to the TFTP root, e.g. `pxelinux.0`.
+    pub boot_file: String,
+    #[serde(default)]
+    pub tftp_root: Option<std::path::PathBuf>,
+    /// TFTP server clients boot from when it isn't the gateway.
+    #[serde(default)]
+    pub next_server: Option<Ipv4Addr>,
+}
+
+impl PxeBoot {
+    /// Check the boot file is one dnsmasq can quote, a TFTP server is named
+    /// and `tftp_root` is an existing directory.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.boot_file.is_empty()
+            || self
+                .boot_file
+                .contains(|c: char| c == ',' || c.is_whitespace())
+        {
+            anyhow::bail!(
+                "boot_file '{}' must be a path without commas or spaces",
+                self.boot_file
+            );
+        }
+        match (&self.tftp_root, self.next_server) {
+            (None, None) => {
+                anyhow::bail!("pxe needs tftp_root (serve over TFTP) or next_server")
+            }
+            (Some(root), _) if !root.is_dir() => {
+                anyhow::bail!("tftp_root {} is not a directory", root.display())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Argument for dnsmasq's `dhcp-boot=`, e.g. `pxelinux.0,,10.0.0.5`.
+    pub fn dnsmasq_value(&self) -> String {
+        match self.next_server {
+            Some(server) => format!("{},,{}", self.boot_file, server),
+            None => self.boot_file.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Ipv6AddressMode {
+    /// Hosts pick their own addresses from router advertisements (SLAAC);
+    /// DHCPv6 only hands out DNS settings.
+    #[default]
+    Slaac,
+    /// Router advertisements send hosts to DHCPv6, which leases addresses
+    /// from a pool.
+    Dhcpv6,
+}
+
+/// IPv6 addressing of a routed network's `cidr6`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dhcp6Pool {
+    pub cidr: String,
+    pub gateway: Option<IpAddr>,
+    pub mode: Ipv6AddressMode,
+    pub start: Option<Ipv6Addr>,
+    pub end: Option<Ipv6Addr>,
+}
+
+/// Offsets of the default DHCPv6 pool inside the prefix, clear of the
+/// low addresses usually given to the gateway and static hosts.
+const DEFAULT_DHCP6_POOL: (u128, u128) = (0x1000, 0x1fff);
+
+impl Dhcp6Pool {
+    /// Network address and prefix length of `cidr`.
+    pub fn prefix(&self) -> anyhow::Result<(Ipv6Addr, u8)> {
+        let (addr, prefix) = self
+            .cidr
+            .split_once('/')
+            .with_context(|| format!("Invalid CIDR format: {}", self.cidr))?;
+        let addr: Ipv6Addr = addr
+            .parse()
+            .with_context(|| format!("{} is not an IPv6 network", self.cidr))?;
+        let prefix: u8 = prefix
+            .parse()
+            .ok()
+            .filter(|prefix| *prefix <= 128)
+            .with_context(|| format!("Invalid CIDR format: {}", self.cidr))?;
+        let mask = (!0u128).checked_shl(128 - prefix as u32).unwrap_or(0);
+        Ok((Ipv6Addr::from(u128::from(addr) & mask), prefix))
+    }
+
+    /// Check SLAAC gets the /64 it needs and the DHCPv6 bounds are addresses
+    /// of `cidr` other than the gateway, in order.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        let (_, prefix) = self.prefix()?;
+        match self.mode {
+            Ipv6AddressMode::Slaac => {
+                if self.start.is_some() || self.end.is_some() {
+                    anyhow::bail!("dhcp6_start and dhcp6_end need ipv6_mode = \"dhcpv6\"");
+                }
+                if prefix != 64 {
+                    anyhow::bail!("SLAAC needs a /64 prefix, not {}", self.cidr);
+                }
+            }
+            Ipv6AddressMode::Dhcpv6 => {
+                let (start, end) = self.range()?;
+                for (label, addr) in [("start", start), ("end", end)] {
+                    if !self.contains(addr)? {
+                        anyhow::bail!("dhcp6_{} {} is not in {}", label, addr, self.cidr);
+                    }
+                    if self.gateway == Some(IpAddr::V6(addr)) {
+                        anyhow::bail!("dhcp6_{} {} is the gateway address", label, addr);
+                    }
+                }
+                if start > end {
+                    anyhow::bail!("dhcp6_start {} is after dhcp6_end {}", start, end);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// First and last DHCPv6 address: the explicit bounds, with unset ones
+    /// at `::1000` and `::1fff` of the prefix.
+    pub fn range(&self) -> anyhow::Result<(Ipv6Addr, Ipv6Addr)> {
+        if let (Some(start), Some(end)) = (self.start, self.end) {
+            return Ok((start, end));
+        }
+        let (network, prefix) = self.prefix()?;
+        if prefix > 112 {
+            anyhow::bail!(
+                "{} is too small for the default DHCPv6 pool; set dhcp6_start and dhcp6_end",
+                self.cidr
+            );
+        }
+        let network = u128::from(network);
+        Ok((
+            self.start
+                .unwrap_or(Ipv6Addr::from(network + DEFAULT_DHCP6_POOL.0)),
+            self.end
+                .unwrap_or(Ipv6Addr::from(network + DEFAULT_DHCP6_POOL.1)),
+        ))
+    }
+
+    fn contains(&self, addr: Ipv6Addr) -> anyhow::Result<bool> {
+        let (network, prefix) = self.prefix()?;
+        let mask = (!0u128).checked_shl(128 - prefix as u32).unwrap_or(0);
+        Ok(u128::from(addr) & mask == u128::from(network))
+    }
+}
+
+/// A fixed lease: the host with `mac` always gets `ip`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DhcpReservation {
+    pub mac: String,
+    pub ip: Ipv4Addr,
+    #[serde(default)]
+    pub hostname: Option<String>,
+}
+
+impl DhcpReservation {
+    /// Argument for dnsmasq's `dhcp-host=`, e.g. `52:54:00:12:34:56,10.0.0.5,db`.
+    pub fn dnsmasq_value(&self) -> String {
+        match &self.hostname {
+            Some(hostname) => format!("{},{},{}", self.mac, self.ip, hostname),
+            None => format!("{},{}", self.mac, self.ip),
+        }
+    }
+}
+
+/// dnsmasq refuses leases shorter than two minutes.
+const MIN_LEASE_SECS: u64 = 120;
+
+/// Smallest pool worth serving; /30 and longer prefixes are point-to-point links.
+const MIN_POOL_PREFIX: u32 = 29;
+
+impl DhcpPool {
+    pub const DEFAULT_LEASE_TIME: &'static str = "12h";
+
+    pub fn lease_time(&self) -> &str {
+        self.lease_time
+            .as_deref()
+            .unwrap_or(Self::DEFAULT_LEASE_TIME)
+    }
+
+    /// Check the explicit bounds are host addresses of `cidr` other than the
+    /// gateway, in order, that the lease time is one dnsmasq accepts, and the
+    /// IPv6 settings (see [`Dhcp6Pool::validate`]).
+    pub fn validate(&self, cidr: &str, gateway: IpAddr) -> anyhow::Result<()> {
+        if self.start.is_some() || self.end.is_some() {
+            let hosts = host_range(cidr).with_context(|| {
+                format!(
+                    "DHCP pool needs an IPv4 network of /30 or larger, not {}",
+                    cidr
+                )
+            })?;
+
+            for (label, bound) in [("start", self.start), ("end", self.end)] {
+                let Some(addr) = bound else { continue };
+                if !hosts.contains(&u32::from(addr)) {
+                    anyhow::bail!("dhcp_{} {} is not a host address of {}", label, addr, cidr);
+                }
+                if IpAddr::V4(addr) == gateway {
+                    anyhow::bail!("dhcp_{} {} is the gateway address", label, addr);
+                }
+            }
+            if let (Some(start), Some(end)) = (self.start, self.end)
+                && start > end
+            {
+                anyhow::bail!("dhcp_start {} is after dhcp_end {}", start, end);
+            }
+        }
+
+        if let Some(lease_time) = &self.lease_time {
+            validate_lease_time(lease_time)?;
+        }
+        if let Some(ipv6) = &self.ipv6 {
+            ipv6.validate()?;
+        }
+        Ok(())
+    }
+
+    /// Check each reservation has a valid MAC and hostname and an address of
+    /// `cidr` that is neither the gateway nor in the dynamic pool, and that
+    /// no MAC or address is reserved twice.
+    pub fn validate_reservations(&self, cidr: &str, gateway: IpAddr) -> anyhow::Result<()> {
+        if self.reservations.is_empty() {
+            return Ok(());
+        }
+
+        let hosts = host_range(cidr)
+            .with_context(|| format!("DHCP reservations need an IPv4 network, not {}", cidr))?;
+        let (start, end) = self.range(cidr, gateway)?;
+        let mut macs = BTreeSet::new();
+        let mut ips = BTreeSet::new();
+
+        for reservation in &self.reservations {
+            let DhcpReservation { mac, ip, hostname } = reservation;
+            if !is_mac_address(mac) {
+                anyhow::bail!(
+                    "'{}' is not a MAC address (expected aa:bb:cc:dd:ee:ff)",
+                    mac
+                );
+            }
+            if !hosts.contains(&u32::from(*ip)) {
+                anyhow::bail!("{} for {} is not a host address of {}", ip, mac, cidr);
+            }
+            if IpAddr::V4(*ip) == gateway {
+                anyhow::bail!("{} for {} is the gateway address", ip, mac);
+            }
+            if (start..=end).contains(ip) {
+                anyhow::bail!(
+                    "{} for {} is inside the dynamic pool {}-{}",
+                    ip,
+                    mac,
+                    start,
+                    end
+                );
+            }
+            if let Some(hostname) = hostname {
+                validate_domain(hostname)?;
+            }
+            if !macs.insert(mac.to_ascii_lowercase()) {
+                anyhow::bail!("MAC {} is reserved more than once", mac);
+            }
+            if !ips.insert(*ip) {
+                anyhow::bail!("{} is reserved more than once", ip);
+            }
+        }
+        Ok(())
+    }
+
+    /// First and last address handed out: the explicit bounds, with unset
+    /// ones taken from the default pool of `cidr`. Networks of /26 and larger
+    /// keep the first 10 and last 5 host addresses out of the default pool for
+    /// static assignments, so a /24 yields `.10`-`.250`; smaller networks hand
+    /// out every remaining host. When the gateway sits inside the pool, the
+    /// larger side of it is used.
+    pub fn range(&self, cidr: &str, gateway: IpAddr) -> anyhow::Result<(Ipv4Addr, Ipv4Addr)> {
+        let (start, end) = match (self.start, self.end) {
+            (Some(start), Some(end)) => (start, end),
+            (start, end) => {
+                let (default_start, default_end) = default_dhcp_range(cidr, gateway)?;
+                (start.unwrap_or(default_start), end.unwrap_or(default_end))
+            }
+        };
+        if start > end {
+            anyhow::bail!("DHCP range {} - {} is empty", start, end);
+        }
+        Ok((start, end))
+    }
+}
+
+fn default_dhcp_range(cidr: &str, gateway: IpAddr) -> anyhow::Result<(Ipv4Addr, Ipv4Addr)> {
+    let (addr, prefix) = cidr
+        .split_once('/')
+        .with_context(|| format!("Invalid CIDR format: {}", cidr))?;
+    let addr: IpAddr = addr
+        .parse()
+        .with_context(|| format!("Invalid CIDR format: {}", cidr))?;
+    let IpAddr::V4(addr) = addr else {
+        anyhow::bail!(
+            "DHCP ranges are only generated for IPv4 networks, not {}",
+            cidr
+        );
+    };
+    let prefix: u32 = prefix
+        .parse()
+        .ok()
+        .filter(|prefix| *prefix <= 32)
+        .with_context(|| format!("Invalid CIDR format: {}", cidr))?;
+    if prefix > MIN_POOL_PREFIX {
+        anyhow::bail!(
+            "{} is too small for a DHCP pool (need /{} or larger)",
+            cidr,
+            MIN_POOL_PREFIX
+        );
+    }
+
+    let mask = (!0u32).checked_shl(32 - prefix).unwrap_or(0);
+    let network = u32::from(addr) & mask;
+    let broadcast = network | !mask;
+    let (head, tail) = if broadcast - network + 1 >= 64 {
+        (10, 5)
+    } else {
+        (1, 1)
+    };
+    let mut start = network + head;
+    let mut end = broadcast - tail;
+
+    if let IpAddr::V4(gateway) = gateway {
+        let gateway = u32::from(gateway);
+        if (start..=end).contains(&gateway) {
+            if gateway - start >= end - gateway {
+                end = gateway - 1;
+            } else {
+                start = gateway + 1;
+            }
+        }
+    }
+
+    Ok((Ipv4Addr::from(start), Ipv4Addr::from(end)))
+}
+
+/// Host addresses (as integers) of an IPv4 `cidr` of /30 or larger.
+fn host_range(cidr: &str) -> Option<std::ops::RangeInclusive<u32>> {
+    let (ip, prefix) = cidr.split_once('/')?;
+    let ip: Ipv4Addr = ip.parse().ok()?;
+    let prefix: u32 = prefix.parse().ok().filter(|prefix| *prefix <= 30)?;
+    let mask = (!0u32).checked_shl(32 - prefix).unwrap_or(0);
+    let network = u32::from(ip) & mask;
+    Some(network + 1..=(network | !mask) - 1)
+}
+
+fn is_mac_address(mac: &str) -> bool {
+    let octets: Vec<&str> = mac.split(':').collect();
+    octets.len() == 6
+        && octets
+            .iter()
+            .all(|octet| octet.len() == 2 && octet.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+/// `infinite`, or a number of seconds with an optional s/m/h/d/w unit.
+fn validate_lease_time(lease_time: &str) -> anyhow::Result<()> {
+    if lease_time == "infinite" {
+        return Ok(());
+    }
+
+    let (digits, unit) = match lease_time.char_indices().last() {
+        Some((i, c)) if c.is_ascii_alphabetic() => (&lease_time[..i], Some(c)),
+        _ => (lease_time, None),
+    };
+    let multiplier = match unit {
+        None | Some('s') => 1,
+        Some('m') => 60,
+        Some('h') => 3600,
+        Some('d') => 86_400,
+        Some('w') => 604_800,
+        Some(_) => anyhow::bail!(
+            "lease_time '{}' has an unknown unit (use s, m, h, d or w)",
+            lease_time
+        ),
+    };
+    let seconds = digits
+        .parse::<u64>()
+        .ok()
+        .and_then(|n| n.checked_mul(multiplier))
+        .with_context(|| {
+            format!(
+                "lease_time '{}' is not a duration like 12h or 3600",
+                lease_time
+            )
+        })?;
+    if seconds < MIN_LEASE_SECS {
+        anyhow::bail!(
+            "lease_time '{}' is shorter than dnsmasq's 2 minute minimum",
+            lease_time
+        );
+    }
+    Ok(())
+}
+
+fn validate_domain(domain: &str) -> anyhow::Result<()> {
+    let valid = !domain.is_empty()
+        && domain.len() <= 253
+        && domain.trim_end_matches('.').split('.').all(|label| {
+            !label.is_empty()
+                && label.len() <= 63
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        });
+    if !valid {
+        anyhow::bail!("'{}' is not a valid domain name", domain);
+    }
+    Ok(())
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct DnsConfig {
     pub enabled: bool,
     #[serde(default)]
     pub zones: Vec<String>,
+    /// Server answering DNS on the gateway; DHCP stays with dnsmasq either way.
+    #[serde(default)]
+    pub backend: DnsBackend,
+    /// Resolvers non-local names are forwarded to (`ip` or `ip:port`);
+    /// empty uses the host's `/etc/resolv.conf`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub upstreams: Vec<String>,
+}
+
+impl DnsConfig {
+    /// True when CoreDNS rather than dnsmasq serves this network's DNS.
+    pub fn uses_coredns(&self) -> bool {
+        self.enabled && self.backend == DnsBackend::Coredns
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DnsBackend {
+    #[default]
+    Dnsmasq,
+    Coredns,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct PortForward {
     pub public: String, // "0.0.0.0:4022/tcp"
     pub dst: String,    // "10.33.0.10:22"
+    /// Higher priorities are matched first; nftables prerouting is first-match.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<i32>,
+}
+
+impl PortForward {
+    /// True when `public` does not pin a listen address (":80/tcp",
+    /// "0.0.0.0:80/tcp", "[::]:80/tcp").
+    pub fn is_wildcard(&self) -> bool {
+        let addr = self.public.split('/').next().unwrap_or(&self.public);
+        let host = split_host_port(addr).map(|(host, _)| host).unwrap_or("");
+        let host = host.trim();
+        host.is_empty() || host.parse::<IpAddr>().is_ok_and(|ip| ip.is_unspecified())
+    }
+
+    /// Lowercased protocol after the `/` in `public` ("tcp" for "0.0.0.0:80/tcp").
+    pub fn protocol(&self) -> String {
+        self.public
+            .rsplit_once('/')
+            .map(|(_, proto)| proto.trim().to_lowercase())
+            .unwrap_or_default()
+    }
+}
+
+/// Split `host:port`, where an IPv6 host is bracketed (`[fd00::10]:22`). The
+/// host comes back without brackets and is empty for `:port`; an unbracketed
+/// IPv6 address is ambiguous and rejected.
+pub fn split_host_port(addr: &str) -> Option<(&str, &str)> {
+    let addr = addr.trim();
+    if let Some(rest) = addr.strip_prefix('[') {
+        return rest.split_once("]:");
+    }
+    addr.rsplit_once(':')
+        .filter(|(host, _)| !host.contains(':'))
+}
+
+/// The port part of a forward spec: one port, or an inclusive range
+/// (`8000-8010`). A single port is a range of one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PortRange {
+    pub start: u16,
+    pub end: u16,
+}
+
+impl PortRange {
+    /// Parse `8080` or `8000-8010`, rejecting port 0 and inverted ranges.
+    pub fn parse(spec: &str) -> anyhow::Result<Self> {
+        let spec = spec.trim();
+        let (start, end) = spec.split_once('-').unwrap_or((spec, spec));
+        let start: u16 = start.trim().parse().context("Invalid port number")?;
+        let end: u16 = end.trim().parse().context("Invalid port number")?;
+
+        if start == 0 {
+            anyhow::bail!("Port 0 is not allowed");
+        }
+        if start > end {
+            anyhow::bail!("Inverted port range {}-{}", start, end);
+        }
+        Ok(Self { start, end })
+    }
+
+    pub fn is_single(&self) -> bool {
+        self.start == self.end
+    }
+
+    /// Number of ports covered.
+    pub fn size(&self) -> u32 {
+        u32::from(self.end - self.start) + 1
+    }
+
+    /// The destination ports this public range forwards to: `dest` itself when
+    /// it is a range of the same size, or a same-size range based at a single
+    /// `dest` port (`8000-8010` -> `9000` covers 9000-9010).
+    pub fn map_onto(&self, dest: PortRange) -> anyhow::Result<PortRange> {
+        if !dest.is_single() {
+            if dest.size() != self.size() {
+                anyhow::bail!(
+                    "Destination range {} has {} ports but public range {} has {}",
+                    dest,
+                    dest.size(),
+                    self,
+                    self.size()
+                );
+            }
+            return Ok(dest);
+        }
+
+        let end = dest
+            .start
+            .checked_add(self.end - self.start)
+            .with_context(|| format!("Forwarding {} to port {} runs past 65535", self, dest))?;
+        Ok(PortRange {
+            start: dest.start,
+            end,
+        })
+    }
+}
+
+impl std::fmt::Display for PortRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_single() {
+            write!(f, "{}", self.start)
+        } else {
+            write!(f, "{}-{}", self.start, self.end)
+        }
+    }
 }
 
 impl Topology {
+    /// Whether the network owning `bridge` asks for isolated VM ports.
+    pub fn bridge_isolates_ports(&self, bridge: &str) -> bool {
+        self.networks.iter().any(|(name, network)| match network {
+            Network::Routed(routed) => routed.isolate_ports && format!("br-{}", name) == bridge,
+            Network::Bridge(br) => br.isolate_ports && br.iface == bridge,
+            Network::Vxlan(_) => false,
+        })
+    }
+
+    /// Bridges GhostWarden creates or attaches to for this topology.
+    pub fn managed_bridges(&self) -> BTreeSet<String> {
+        self.networks
+            .iter()
+            .map(|(name, network)| match network {
+                Network::Routed(_) => format!("br-{}", name),
+                Network::Bridge(br) => br.iface.clone(),
+                Network::Vxlan(vxlan) => vxlan.bridge.clone(),
+            })
+            .collect()
+    }
+
+    /// Whether any routed network is IPv6, so hosts need IPv6 forwarding.
+    pub fn has_ipv6_networks(&self) -> bool {
+        self.networks.values().any(|network| match network {
+            Network::Routed(routed) => routed.gw_ip.is_ipv6() || routed.cidr.contains(':'),
+            _ => false,
+        })
+    }
+
+    /// VLAN membership for VM ports on `bridge`, when its network defines one.
+    pub fn bridge_port_vlans(&self, bridge: &str) -> Option<&PortVlans> {
+        self.networks.values().find_map(|network| match network {
+            Network::Bridge(br) if br.iface == bridge => br.port_vlans.as_ref(),
+            _ => None,
+        })
+    }
+
     pub fn from_yaml(yaml: &str) -> anyhow::Result<Self> {
         crate::config_format::from_str(yaml, crate::config_format::ConfigFormat::Yaml)
     }
@@ -75,6 +1194,34 @@ impl Topology {
         crate::config_format::from_str(&content, format)
     }
 
+    /// Load topology files in order, each one layered over the ones before it.
+    ///
+    /// Tables merge key by key, so a later file can override a single field of
+    /// a network or add a new one; scalars and lists are replaced outright. A
+    /// network whose `type` changes is replaced as a whole.
+    pub fn from_files<P: AsRef<std::path::Path>>(paths: &[P]) -> anyhow::Result<Self> {
+        let Some((first, rest)) = paths.split_first() else {
+            anyhow::bail!("No topology files given");
+        };
+        if rest.is_empty() {
+            return Self::from_file(first.as_ref());
+        }
+
+        let mut merged = read_topology_value(first.as_ref())?;
+        for path in rest {
+            merge_topology_value(&mut merged, read_topology_value(path.as_ref())?);
+        }
+
+        serde_json::from_value(merged).context("Merged topology is invalid")
+    }
+
+    /// Serialize with keys sorted, for showing an effective topology.
+    pub fn render(&self, format: crate::config_format::ConfigFormat) -> anyhow::Result<String> {
+        let mut value = serde_json::to_value(self)?;
+        strip_nulls(&mut value);
+        crate::config_format::to_string(&value, format)
+    }
+
     pub fn write_file(&self, path: &std::path::Path) -> anyhow::Result<()> {
         let format = crate::config_format::ConfigFormat::from_path(path)?;
         let content = crate::config_format::to_string(self, format)?;
@@ -83,6 +1230,51 @@ impl Topology {
     }
 }
 
+fn read_topology_value(path: &std::path::Path) -> anyhow::Result<Value> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let format = crate::config_format::ConfigFormat::from_path(path)?;
+    crate::config_format::from_str(&content, format)
+        .with_context(|| format!("Failed to parse {}", path.display()))
+}
+
+fn merge_topology_value(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Object(base), Value::Object(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(existing) if !changes_type(existing, &value) => {
+                        merge_topology_value(existing, value)
+                    }
+                    _ => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+fn changes_type(existing: &Value, overlay: &Value) -> bool {
+    match (existing.get("type"), overlay.get("type")) {
+        (Some(old), Some(new)) => old != new,
+        _ => false,
+    }
+}
+
+/// TOML has no null, so drop unset optional fields before serializing.
+fn strip_nulls(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            map.retain(|_, v| !v.is_null());
+            map.values_mut().for_each(strip_nulls);
+        }
+        Value::Array(items) => items.iter_mut().for_each(strip_nulls),
+        _ => {}
+    }
+}
+
 impl Default for Topology {
     fn default() -> Self {
         Self {
@@ -1,17 +1,49 @@
 use anyhow::{Context, Result};
-use std::collections::HashSet;
+use ipnet::IpNet;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::net::IpAddr;
 
-use crate::topology::{Network, Topology};
+use crate::policy::PolicyProfile;
+use crate::topology::{
+    CT_HELPERS, CtHelper, Network, PortForward, PortRange, Topology, VXLAN_MAX_VNI,
+    split_host_port, validate_vlan_id,
+};
+
+/// Smallest MTU the kernel accepts (IPv4's minimum datagram size)
+const MIN_MTU: u32 = 68;
+/// Largest MTU a bridge can carry
+const MAX_MTU: u32 = 65535;
 
 /// Validates a topology for correctness and safety
 pub struct TopologyValidator<'a> {
     topology: &'a Topology,
+    profiles: Option<&'a HashMap<String, PolicyProfile>>,
+    kernel_modules: Option<&'a HashSet<String>>,
 }
 
 impl<'a> TopologyValidator<'a> {
     pub fn new(topology: &'a Topology) -> Self {
-        Self { topology }
+        Self {
+            topology,
+            profiles: None,
+            kernel_modules: None,
+        }
+    }
+
+    /// Check `policy_profile` references against the loaded `profiles`;
+    /// without this they are not checked.
+    pub fn with_profiles(mut self, profiles: &'a HashMap<String, PolicyProfile>) -> Self {
+        self.profiles = Some(profiles);
+        self
+    }
+
+    /// Warn about conntrack helpers whose kernel modules are not in
+    /// `modules` (see [`available_kernel_modules`]); without this they are
+    /// not checked.
+    pub fn with_kernel_modules(mut self, modules: &'a HashSet<String>) -> Self {
+        self.kernel_modules = Some(modules);
+        self
     }
 
     /// Run all validations
@@ -24,6 +56,9 @@ impl<'a> TopologyValidator<'a> {
         // Validate port ranges
         warnings.extend(self.validate_port_ranges()?);
 
+        // Check networks don't forward the same public endpoint
+        warnings.extend(self.check_duplicate_forwards());
+
         // Validate IP addresses
         warnings.extend(self.validate_ip_addresses()?);
 
@@ -33,6 +68,36 @@ impl<'a> TopologyValidator<'a> {
         // Validate network references
         warnings.extend(self.validate_network_references()?);
 
+        // Validate conntrack helpers
+        warnings.extend(self.validate_ct_helpers());
+
+        // Validate VXLAN overlays
+        warnings.extend(self.validate_vxlan());
+
+        // Validate bridge VLAN filtering
+        warnings.extend(self.validate_bridge_vlans());
+
+        // Validate traffic quotas
+        warnings.extend(self.validate_quotas());
+
+        // Validate custom DHCP options
+        warnings.extend(self.validate_dhcp_options());
+
+        // Validate DHCP pools
+        warnings.extend(self.validate_dhcp_pools());
+
+        // Validate DNS upstreams
+        warnings.extend(self.validate_dns_upstreams());
+
+        // Check referenced policy profiles exist
+        warnings.extend(self.validate_policy_profiles());
+
+        // Validate static routes
+        warnings.extend(self.validate_static_routes());
+
+        // Validate bridge MTUs
+        warnings.extend(self.validate_mtus());
+
         Ok(warnings)
     }
 
@@ -41,16 +106,23 @@ impl<'a> TopologyValidator<'a> {
         let mut warnings = Vec::new();
         let mut cidrs: Vec<(&str, &str)> = Vec::new();
 
-        // Collect all CIDRs
+        // Collect all CIDRs, IPv4 and IPv6
         for (name, network) in &self.topology.networks {
             if let Network::Routed(routed) = network {
                 cidrs.push((name.as_str(), routed.cidr.as_str()));
+                if let Some(cidr6) = &routed.cidr6 {
+                    cidrs.push((name.as_str(), cidr6.as_str()));
+                }
             }
         }
 
-        // Check for overlaps
+        // Check for overlaps; an IPv4 and an IPv6 prefix never overlap
+        let is_ipv6 = |cidr: &str| cidr.contains(':');
         for i in 0..cidrs.len() {
             for j in (i + 1)..cidrs.len() {
+                if is_ipv6(cidrs[i].1) != is_ipv6(cidrs[j].1) {
+                    continue;
+                }
                 if Self::cidrs_overlap(cidrs[i].1, cidrs[j].1)? {
                     warnings.push(ValidationWarning::CidrOverlap {
                         net1: cidrs[i].0.to_string(),
@@ -65,46 +137,17 @@ impl<'a> TopologyValidator<'a> {
         Ok(warnings)
     }
 
-    /// Check if two CIDRs overlap
+    /// Check if two CIDRs of the same family overlap, i.e. one contains the other
     fn cidrs_overlap(cidr1: &str, cidr2: &str) -> Result<bool> {
-        use std::net::Ipv4Addr;
-
-        // Parse CIDR1
-        let parts1: Vec<&str> = cidr1.split('/').collect();
-        if parts1.len() != 2 {
-            anyhow::bail!("Invalid CIDR format: {}", cidr1);
-        }
-        let ip1: Ipv4Addr = parts1[0]
-            .parse()
-            .context(format!("Invalid IP in CIDR: {}", cidr1))?;
-        let prefix1: u8 = parts1[1]
-            .parse()
-            .context(format!("Invalid prefix in CIDR: {}", cidr1))?;
-
-        // Parse CIDR2
-        let parts2: Vec<&str> = cidr2.split('/').collect();
-        if parts2.len() != 2 {
-            anyhow::bail!("Invalid CIDR format: {}", cidr2);
-        }
-        let ip2: Ipv4Addr = parts2[0]
+        let net1: IpNet = cidr1
             .parse()
-            .context(format!("Invalid IP in CIDR: {}", cidr2))?;
-        let prefix2: u8 = parts2[1]
+            .context(format!("Invalid CIDR format: {}", cidr1))?;
+        let net2: IpNet = cidr2
             .parse()
-            .context(format!("Invalid prefix in CIDR: {}", cidr2))?;
-
-        // Calculate network addresses
-        let mask1 = (!0u32) << (32 - prefix1);
-        let mask2 = (!0u32) << (32 - prefix2);
+            .context(format!("Invalid CIDR format: {}", cidr2))?;
 
-        let net1 = u32::from(ip1) & mask1;
-        let net2 = u32::from(ip2) & mask2;
-
-        // Check if one network contains the other
-        let smaller_prefix = prefix1.min(prefix2);
-        let smaller_mask = (!0u32) << (32 - smaller_prefix);
-
-        Ok((net1 & smaller_mask) == (net2 & smaller_mask))
+        let (net1, net2) = (net1.trunc(), net2.trunc());
+        Ok(net1.contains(&net2) || net2.contains(&net1))
     }
 
     /// Validate port ranges in port forwards
@@ -115,20 +158,56 @@ impl<'a> TopologyValidator<'a> {
             if let Network::Routed(routed) = network {
                 for forward in &routed.forwards {
                     // Validate public port
-                    if let Err(e) = Self::validate_port_spec(&forward.public) {
-                        warnings.push(ValidationWarning::InvalidPort {
-                            network: net_name.clone(),
-                            port_spec: forward.public.clone(),
-                            reason: e.to_string(),
+                    let public = match Self::validate_port_spec(&forward.public) {
+                        Ok(public) => Some(public),
+                        Err(e) => {
+                            warnings.push(ValidationWarning::InvalidPort {
+                                network: net_name.clone(),
+                                port_spec: forward.public.clone(),
+                                reason: e.to_string(),
+                            });
+                            None
+                        }
+                    };
+
+                    // Validate destination port, that it lines up with the public
+                    // range, and that the network NATs its family
+                    let result =
+                        Self::validate_destination(&forward.dst).and_then(|(dest, ports)| {
+                            if let Some(public) = public {
+                                public.map_onto(ports)?;
+                            }
+                            if !routed.has_prefix_for(dest) {
+                                anyhow::bail!("Network has no prefix of {}'s address family", dest);
+                            }
+                            Ok(dest)
                         });
-                    }
-
-                    // Validate destination port
-                    if let Err(e) = Self::validate_destination(&forward.dst) {
-                        warnings.push(ValidationWarning::InvalidDestination {
+                    let dest = match result {
+                        Ok(dest) => dest,
+                        Err(e) => {
+                            warnings.push(ValidationWarning::InvalidDestination {
+                                network: net_name.clone(),
+                                dst_spec: forward.dst.clone(),
+                                reason: e.to_string(),
+                            });
+                            continue;
+                        }
+                    };
+
+                    // DNAT to a host off the bridge never reaches it
+                    let cidr = if dest.is_ipv6() {
+                        routed.cidr6.as_deref().unwrap_or(&routed.cidr)
+                    } else {
+                        &routed.cidr
+                    };
+                    if let Ok(net) = cidr.parse::<IpNet>()
+                        && net.addr().is_ipv6() == dest.is_ipv6()
+                        && !net.contains(&dest)
+                    {
+                        warnings.push(ValidationWarning::ForwardDestinationOutsideCidr {
                             network: net_name.clone(),
-                            dst_spec: forward.dst.clone(),
-                            reason: e.to_string(),
+                            dst: dest.to_string(),
+                            cidr: cidr.to_string(),
                         });
                     }
                 }
@@ -138,55 +217,133 @@ impl<'a> TopologyValidator<'a> {
         Ok(warnings)
     }
 
-    /// Validate a port spec (e.g., ":4022/tcp", "0.0.0.0:8080/udp")
-    fn validate_port_spec(spec: &str) -> Result<()> {
-        // Remove protocol suffix
-        let without_proto = spec.split('/').next().unwrap_or(spec);
-
-        // Parse host:port
-        let parts: Vec<&str> = without_proto.rsplit(':').collect();
-        if parts.is_empty() {
-            anyhow::bail!("Invalid port spec: {}", spec);
+    /// Check no two networks forward the same public endpoint. Each network
+    /// DNATs in its own table, so the priority ordering that settles overlaps
+    /// within a network can't apply across them. Endpoints collide when
+    /// protocol, address family and ports overlap and the addresses match or
+    /// either is a wildcard (`:8080` and `0.0.0.0:8080` are the same).
+    fn check_duplicate_forwards(&self) -> Vec<ValidationWarning> {
+        struct Endpoint<'a> {
+            network: &'a str,
+            public: &'a str,
+            addr: Option<IpAddr>,
+            ipv6: bool,
+            protocol: String,
+            ports: PortRange,
         }
 
-        let port_str = parts[0];
-        let port: u16 = port_str.parse().context("Invalid port number")?;
-
-        if port == 0 {
-            anyhow::bail!("Port 0 is not allowed");
+        let mut names: Vec<&String> = self.topology.networks.keys().collect();
+        names.sort();
+
+        // Malformed forwards are reported by validate_port_ranges
+        let mut endpoints = Vec::new();
+        for name in names {
+            let Network::Routed(routed) = &self.topology.networks[name] else {
+                continue;
+            };
+            for forward in &routed.forwards {
+                let (Ok(ports), Ok((dest, _))) = (
+                    Self::validate_port_spec(&forward.public),
+                    Self::validate_destination(&forward.dst),
+                ) else {
+                    continue;
+                };
+                let addr = forward
+                    .public
+                    .rsplit_once('/')
+                    .and_then(|(addr, _)| split_host_port(addr))
+                    .and_then(|(host, _)| host.trim().parse::<IpAddr>().ok())
+                    .filter(|addr| !addr.is_unspecified());
+                endpoints.push(Endpoint {
+                    network: name,
+                    public: &forward.public,
+                    addr,
+                    ipv6: dest.is_ipv6(),
+                    protocol: forward.protocol(),
+                    ports,
+                });
+            }
         }
 
-        // Validate protocol
-        if spec.contains('/') {
-            let proto = spec.split('/').nth(1).unwrap_or("");
-            if proto != "tcp" && proto != "udp" && proto != "sctp" {
-                anyhow::bail!("Invalid protocol: {}", proto);
+        let mut warnings = Vec::new();
+        for (i, a) in endpoints.iter().enumerate() {
+            for b in &endpoints[i + 1..] {
+                if a.network != b.network
+                    && a.ipv6 == b.ipv6
+                    && a.protocol == b.protocol
+                    && a.ports.start <= b.ports.end
+                    && b.ports.start <= a.ports.end
+                    && (a.addr.is_none() || b.addr.is_none() || a.addr == b.addr)
+                {
+                    warnings.push(ValidationWarning::DuplicateForward {
+                        net1: a.network.to_string(),
+                        public1: a.public.to_string(),
+                        net2: b.network.to_string(),
+                        public2: b.public.to_string(),
+                    });
+                }
             }
         }
 
+        warnings
+    }
+
+    /// Validate one port forward as `gwarden forward add` would store it: the
+    /// public spec, the destination, and that their port ranges line up.
+    pub fn validate_forward(forward: &PortForward) -> Result<()> {
+        let public = Self::validate_port_spec(&forward.public)
+            .with_context(|| format!("Invalid public spec '{}'", forward.public))?;
+        let (_, ports) = Self::validate_destination(&forward.dst)
+            .with_context(|| format!("Invalid destination '{}'", forward.dst))?;
+        public.map_onto(ports)?;
         Ok(())
     }
 
-    /// Validate a destination spec (e.g., "10.33.0.10:22")
-    fn validate_destination(spec: &str) -> Result<()> {
-        let parts: Vec<&str> = spec.rsplit(':').collect();
-        if parts.len() != 2 {
-            anyhow::bail!("Destination must be in format IP:PORT");
+    /// Validate a port spec (e.g., ":4022/tcp", "0.0.0.0:8080/udp", ":8000-8010/tcp").
+    /// Matches what the nftables generator accepts: the protocol is required and
+    /// is tcp, udp or sctp.
+    fn validate_port_spec(spec: &str) -> Result<PortRange> {
+        let (addr, proto) = spec
+            .rsplit_once('/')
+            .with_context(|| format!("Missing protocol in {} (e.g. :80/tcp)", spec))?;
+
+        // Validate protocol
+        let proto = proto.trim().to_ascii_lowercase();
+        if !matches!(proto.as_str(), "tcp" | "udp" | "sctp") {
+            anyhow::bail!(
+                "Invalid protocol: {} (port forwards support tcp, udp and sctp)",
+                proto
+            );
         }
 
-        let port_str = parts[0];
-        let ip_str = parts[1];
+        // Parse host:port; the host is optional
+        let (host, port) = if addr.contains(':') {
+            split_host_port(addr).context("IPv6 public address must be bracketed")?
+        } else {
+            ("", addr)
+        };
+        let host = host.trim();
+        if !matches!(host, "" | "0.0.0.0" | "::") {
+            host.parse::<IpAddr>()
+                .context("Invalid public IP address")?;
+        }
+
+        PortRange::parse(port)
+    }
+
+    /// Validate a destination spec (e.g., "10.33.0.10:22", "[fd00:33::10]:22",
+    /// "10.33.0.10:9000-9010")
+    fn validate_destination(spec: &str) -> Result<(IpAddr, PortRange)> {
+        let (ip_str, port_str) = split_host_port(spec)
+            .context("Destination must be in format IP:PORT or [IPv6]:PORT")?;
 
         // Validate IP
-        let _: IpAddr = ip_str.parse().context("Invalid IP address")?;
+        let ip: IpAddr = ip_str.parse().context("Invalid IP address")?;
 
         // Validate port
-        let port: u16 = port_str.parse().context("Invalid port number")?;
-        if port == 0 {
-            anyhow::bail!("Port 0 is not allowed");
-        }
+        let ports = PortRange::parse(port_str)?;
 
-        Ok(())
+        Ok((ip, ports))
     }
 
     /// Validate IP addresses and CIDR notations
@@ -215,12 +372,73 @@ impl<'a> TopologyValidator<'a> {
                         reason: e.to_string(),
                     });
                 }
+
+                // Dual-stack: the IPv6 prefix and its gateway come as a pair
+                match (&routed.cidr6, routed.gw_ip6) {
+                    (Some(cidr6), Some(gw_ip6)) => {
+                        if let Err(e) = Self::validate_cidr6(cidr6) {
+                            warnings.push(ValidationWarning::InvalidCidr {
+                                network: net_name.clone(),
+                                cidr: cidr6.clone(),
+                                reason: e.to_string(),
+                            });
+                        } else if let Err(e) = Self::validate_gateway_in_cidr6(gw_ip6, cidr6) {
+                            warnings.push(ValidationWarning::GatewayNotInCidr {
+                                network: net_name.clone(),
+                                gateway: gw_ip6.to_string(),
+                                cidr: cidr6.clone(),
+                                reason: e.to_string(),
+                            });
+                        }
+                    }
+                    (Some(cidr6), None) => warnings.push(ValidationWarning::InvalidCidr {
+                        network: net_name.clone(),
+                        cidr: cidr6.clone(),
+                        reason: "cidr6 needs gw_ip6".to_string(),
+                    }),
+                    (None, Some(gw_ip6)) => warnings.push(ValidationWarning::GatewayNotInCidr {
+                        network: net_name.clone(),
+                        gateway: gw_ip6.to_string(),
+                        cidr: String::new(),
+                        reason: "gw_ip6 needs cidr6".to_string(),
+                    }),
+                    (None, None) => {}
+                }
             }
         }
 
         Ok(warnings)
     }
 
+    /// Validate an IPv6 CIDR (e.g., "fd00:33::/64")
+    fn validate_cidr6(cidr: &str) -> Result<()> {
+        let (ip, prefix) = cidr
+            .split_once('/')
+            .context("CIDR must be in format IP/PREFIX")?;
+        let _: std::net::Ipv6Addr = ip.parse().context("Invalid IPv6 address")?;
+        let prefix: u8 = prefix.parse().context("Invalid prefix length")?;
+        if prefix > 128 {
+            anyhow::bail!("IPv6 prefix must be 0-128");
+        }
+        Ok(())
+    }
+
+    /// Validate that an IPv6 gateway is within its CIDR
+    fn validate_gateway_in_cidr6(gateway: IpAddr, cidr: &str) -> Result<()> {
+        let IpAddr::V6(gw_ip) = gateway else {
+            anyhow::bail!("Gateway {} is not an IPv6 address", gateway);
+        };
+        let (net_ip, prefix) = cidr.split_once('/').context("Invalid CIDR")?;
+        let net_ip: std::net::Ipv6Addr = net_ip.parse().context("Invalid network IP")?;
+        let prefix: u32 = prefix.parse().context("Invalid prefix")?;
+
+        let mask = (!0u128).checked_shl(128 - prefix).unwrap_or(0);
+        if u128::from(net_ip) & mask != u128::from(gw_ip) & mask {
+            anyhow::bail!("Gateway {} is not in network {}", gateway, cidr);
+        }
+        Ok(())
+    }
+
     /// Validate CIDR notation
     fn validate_cidr(cidr: &str) -> Result<()> {
         let parts: Vec<&str> = cidr.split('/').collect();
@@ -297,6 +515,361 @@ impl<'a> TopologyValidator<'a> {
         Ok(warnings)
     }
 
+    /// Check requested conntrack helpers are known and that their modules exist
+    fn validate_ct_helpers(&self) -> Vec<ValidationWarning> {
+        let mut warnings = Vec::new();
+
+        for (name, network) in &self.topology.networks {
+            let Network::Routed(routed) = network else {
+                continue;
+            };
+
+            for helper in &routed.ct_helpers {
+                let Some(known) = CtHelper::lookup(helper) else {
+                    warnings.push(ValidationWarning::UnknownCtHelper {
+                        network: name.clone(),
+                        helper: helper.clone(),
+                    });
+                    continue;
+                };
+
+                let Some(available) = self.kernel_modules else {
+                    continue;
+                };
+                let missing: Vec<String> = [known.module, known.nat_module]
+                    .into_iter()
+                    .filter(|module| !available.contains(*module))
+                    .map(str::to_string)
+                    .collect();
+                if !missing.is_empty() {
+                    warnings.push(ValidationWarning::CtHelperModule {
+                        network: name.clone(),
+                        helper: helper.clone(),
+                        modules: missing,
+                    });
+                }
+            }
+        }
+
+        warnings
+    }
+
+    /// Check VXLAN networks for out-of-range or duplicate VNIs, missing or
+    /// unusable peers and bridges the topology doesn't define.
+    fn validate_vxlan(&self) -> Vec<ValidationWarning> {
+        let mut warnings = Vec::new();
+        let mut vnis: BTreeMap<u32, Vec<String>> = BTreeMap::new();
+        let bridges = self.defined_bridges();
+
+        for (name, network) in &self.topology.networks {
+            let Network::Vxlan(vxlan) = network else {
+                continue;
+            };
+
+            if vxlan.vni > VXLAN_MAX_VNI {
+                warnings.push(ValidationWarning::InvalidVni {
+                    network: name.clone(),
+                    vni: vxlan.vni,
+                });
+            }
+            vnis.entry(vxlan.vni).or_default().push(name.clone());
+
+            // A multicast group floods unknown traffic in place of peers
+            if vxlan.peers.is_empty() && vxlan.group.is_none() {
+                warnings.push(ValidationWarning::VxlanWithoutPeers {
+                    network: name.clone(),
+                });
+            }
+            for peer in &vxlan.peers {
+                if let Err(e) = Self::validate_vxlan_peer(peer) {
+                    warnings.push(ValidationWarning::InvalidVxlanPeer {
+                        network: name.clone(),
+                        peer: *peer,
+                        reason: e.to_string(),
+                    });
+                }
+            }
+
+            if !bridges.contains(vxlan.bridge.as_str()) {
+                warnings.push(ValidationWarning::UndefinedVxlanBridge {
+                    network: name.clone(),
+                    bridge: vxlan.bridge.clone(),
+                });
+            }
+        }
+
+        for (vni, mut networks) in vnis {
+            if networks.len() > 1 {
+                networks.sort();
+                warnings.push(ValidationWarning::DuplicateVni { vni, networks });
+            }
+        }
+
+        warnings
+    }
+
+    /// Check bridge VLAN IDs, default PVIDs and per-port VLAN membership.
+    fn validate_bridge_vlans(&self) -> Vec<ValidationWarning> {
+        let mut warnings = Vec::new();
+
+        for (name, network) in &self.topology.networks {
+            let Network::Bridge(bridge) = network else {
+                continue;
+            };
+
+            if let Some(vlan) = bridge.vlan
+                && validate_vlan_id(vlan).is_err()
+            {
+                warnings.push(ValidationWarning::InvalidVlanId {
+                    network: name.clone(),
+                    vlan,
+                });
+            }
+
+            let pvid_result = bridge.vlan_default_pvid.map(validate_vlan_id);
+            let ports_result = bridge.port_vlans.as_ref().map(|vlans| vlans.validate());
+
+            for result in [pvid_result, ports_result].into_iter().flatten() {
+                if let Err(e) = result {
+                    warnings.push(ValidationWarning::InvalidBridgeVlans {
+                        network: name.clone(),
+                        reason: e.to_string(),
+                    });
+                }
+            }
+        }
+
+        warnings
+    }
+
+    /// Check routed-network traffic quotas
+    fn validate_quotas(&self) -> Vec<ValidationWarning> {
+        self.topology
+            .networks
+            .iter()
+            .filter_map(|(name, network)| match network {
+                Network::Routed(routed) => routed.quota.as_ref().map(|quota| (name, quota)),
+                _ => None,
+            })
+            .filter_map(|(name, quota)| {
+                quota
+                    .validate()
+                    .err()
+                    .map(|e| ValidationWarning::InvalidQuota {
+                        network: name.clone(),
+                        reason: e.to_string(),
+                    })
+            })
+            .collect()
+    }
+
+    /// Check static routes parse and that their next hop sits on the network
+    fn validate_static_routes(&self) -> Vec<ValidationWarning> {
+        let mut warnings = Vec::new();
+
+        for (name, network) in &self.topology.networks {
+            let Network::Routed(routed) = network else {
+                continue;
+            };
+
+            for route in &routed.routes {
+                let result = route.validate().and_then(|()| match route.via {
+                    IpAddr::V4(_) => {
+                        Self::validate_gateway_in_cidr(&route.via.to_string(), &routed.cidr)
+                    }
+                    IpAddr::V6(_) => Ok(()),
+                });
+                if let Err(e) = result {
+                    warnings.push(ValidationWarning::InvalidStaticRoute {
+                        network: name.clone(),
+                        route: format!("{} via {}", route.dst, route.via),
+                        reason: e.to_string(),
+                    });
+                }
+            }
+        }
+
+        warnings
+    }
+
+    /// Check bridge MTUs are ones the kernel accepts
+    fn validate_mtus(&self) -> Vec<ValidationWarning> {
+        let mut warnings = Vec::new();
+
+        for (name, network) in &self.topology.networks {
+            let mtu = match network {
+                Network::Routed(routed) => routed.mtu,
+                Network::Bridge(bridge) => bridge.mtu,
+                Network::Vxlan(_) => None,
+            };
+            if let Some(mtu) = mtu
+                && !(MIN_MTU..=MAX_MTU).contains(&mtu)
+            {
+                warnings.push(ValidationWarning::InvalidMtu {
+                    network: name.clone(),
+                    mtu,
+                });
+            }
+        }
+
+        warnings
+    }
+
+    /// Check custom DHCP options name a known option (or a valid number) and
+    /// carry a value in the format that option expects
+    fn validate_dhcp_options(&self) -> Vec<ValidationWarning> {
+        let mut warnings = Vec::new();
+
+        for (name, network) in &self.topology.networks {
+            let Network::Routed(routed) = network else {
+                continue;
+            };
+
+            for option in &routed.dhcp_options {
+                if let Err(e) = option.validate() {
+                    warnings.push(ValidationWarning::InvalidDhcpOption {
+                        network: name.clone(),
+                        option: option.option.to_string(),
+                        reason: e.to_string(),
+                    });
+                }
+            }
+        }
+
+        warnings
+    }
+
+    /// Check explicit DHCP and DHCPv6 pool bounds sit inside the network, the
+    /// lease time parses, reservations stay clear of the pool and of each other
+    /// and PXE boot settings are usable
+    fn validate_dhcp_pools(&self) -> Vec<ValidationWarning> {
+        let mut warnings = Vec::new();
+
+        for (name, network) in &self.topology.networks {
+            let Network::Routed(routed) = network else {
+                continue;
+            };
+
+            let pool = routed.dhcp_pool();
+            if let Err(e) = pool.validate(&routed.cidr, routed.gw_ip) {
+                warnings.push(ValidationWarning::InvalidDhcpRange {
+                    network: name.clone(),
+                    reason: e.to_string(),
+                });
+            } else if let Err(e) = pool.validate_reservations(&routed.cidr, routed.gw_ip) {
+                warnings.push(ValidationWarning::InvalidDhcpReservation {
+                    network: name.clone(),
+                    reason: e.to_string(),
+                });
+            }
+
+            if let Some(pxe) = &routed.pxe
+                && let Err(e) = pxe.validate()
+            {
+                warnings.push(ValidationWarning::InvalidPxe {
+                    network: name.clone(),
+                    reason: e.to_string(),
+                });
+            }
+        }
+
+        warnings
+    }
+
+    /// Check every network's `policy_profile` names a loaded profile; apply
+    /// would otherwise skip the policy with only a warning
+    fn validate_policy_profiles(&self) -> Vec<ValidationWarning> {
+        let Some(profiles) = self.profiles else {
+            return vec![];
+        };
+
+        let mut warnings = Vec::new();
+        for (name, network) in &self.topology.networks {
+            let profile = match network {
+                Network::Routed(routed) => routed.policy_profile.as_ref(),
+                Network::Bridge(bridge) => bridge.policy_profile.as_ref(),
+                Network::Vxlan(_) => None,
+            };
+            if let Some(profile) = profile
+                && !profiles.contains_key(profile)
+            {
+                warnings.push(ValidationWarning::PolicyProfileNotFound {
+                    network: name.clone(),
+                    profile: profile.clone(),
+                });
+            }
+        }
+
+        warnings
+    }
+
+    /// Check DNS upstreams are `ip` or `ip:port`, the only forms the
+    /// generated dnsmasq and CoreDNS configs forward to
+    fn validate_dns_upstreams(&self) -> Vec<ValidationWarning> {
+        let mut warnings = Vec::new();
+
+        for (name, network) in &self.topology.networks {
+            let Network::Routed(routed) = network else {
+                continue;
+            };
+            let Some(dns) = &routed.dns else {
+                continue;
+            };
+
+            for upstream in &dns.upstreams {
+                if upstream.parse::<IpAddr>().is_err()
+                    && upstream.parse::<std::net::SocketAddr>().is_err()
+                {
+                    warnings.push(ValidationWarning::InvalidDnsUpstream {
+                        network: name.clone(),
+                        upstream: upstream.clone(),
+                    });
+                }
+            }
+        }
+
+        warnings
+    }
+
+    /// Bridges created by the topology, plus interfaces it declares by name.
+    fn defined_bridges(&self) -> HashSet<String> {
+        let mut bridges: HashSet<String> = self.topology.interfaces.values().cloned().collect();
+
+        for (name, network) in &self.topology.networks {
+            match network {
+                Network::Routed(_) => {
+                    bridges.insert(format!("br-{}", name));
+                }
+                Network::Bridge(bridge) => {
+                    bridges.insert(bridge.iface.clone());
+                }
+                Network::Vxlan(_) => {}
+            }
+        }
+
+        bridges
+    }
+
+    /// Validate a VXLAN unicast peer address
+    fn validate_vxlan_peer(peer: &IpAddr) -> Result<()> {
+        if peer.is_unspecified() {
+            anyhow::bail!("Unspecified address cannot be a peer");
+        }
+        if peer.is_loopback() {
+            anyhow::bail!("Loopback address cannot be a peer");
+        }
+        if peer.is_multicast() {
+            anyhow::bail!("Multicast groups are not supported; list unicast peers");
+        }
+        if let IpAddr::V4(v4) = peer
+            && v4.is_broadcast()
+        {
+            anyhow::bail!("Broadcast address cannot be a peer");
+        }
+
+        Ok(())
+    }
+
     /// Validate network references (e.g., uplink interfaces exist)
     fn validate_network_references(&self) -> Result<Vec<ValidationWarning>> {
         let warnings: Vec<ValidationWarning> = Vec::new();
@@ -309,7 +882,11 @@ impl<'a> TopologyValidator<'a> {
 }
 
 /// Validation warnings that don't prevent apply but should be shown
-#[derive(Debug, Clone)]
+///
+/// Serializes with the variant name as `code` (e.g. `"code": "GatewayNotInCidr"`)
+/// next to the variant's fields.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "code")]
 pub enum ValidationWarning {
     CidrOverlap {
         net1: String,
@@ -317,6 +894,12 @@ pub enum ValidationWarning {
         net2: String,
         cidr2: String,
     },
+    DuplicateForward {
+        net1: String,
+        public1: String,
+        net2: String,
+        public2: String,
+    },
     InvalidPort {
         network: String,
         port_spec: String,
@@ -327,6 +910,11 @@ pub enum ValidationWarning {
         dst_spec: String,
         reason: String,
     },
+    ForwardDestinationOutsideCidr {
+        network: String,
+        dst: String,
+        cidr: String,
+    },
     InvalidCidr {
         network: String,
         cidr: String,
@@ -342,6 +930,81 @@ pub enum ValidationWarning {
         name: String,
         networks: Vec<String>,
     },
+    UnknownCtHelper {
+        network: String,
+        helper: String,
+    },
+    CtHelperModule {
+        network: String,
+        helper: String,
+        modules: Vec<String>,
+    },
+    InvalidVlanId {
+        network: String,
+        vlan: u16,
+    },
+    InvalidVni {
+        network: String,
+        vni: u32,
+    },
+    VxlanWithoutPeers {
+        network: String,
+    },
+    DuplicateVni {
+        vni: u32,
+        networks: Vec<String>,
+    },
+    InvalidVxlanPeer {
+        network: String,
+        peer: IpAddr,
+        reason: String,
+    },
+    UndefinedVxlanBridge {
+        network: String,
+        bridge: String,
+    },
+    InvalidBridgeVlans {
+        network: String,
+        reason: String,
+    },
+    InvalidQuota {
+        network: String,
+        reason: String,
+    },
+    InvalidDhcpOption {
+        network: String,
+        option: String,
+        reason: String,
+    },
+    InvalidDhcpRange {
+        network: String,
+        reason: String,
+    },
+    InvalidDhcpReservation {
+        network: String,
+        reason: String,
+    },
+    InvalidPxe {
+        network: String,
+        reason: String,
+    },
+    InvalidDnsUpstream {
+        network: String,
+        upstream: String,
+    },
+    PolicyProfileNotFound {
+        network: String,
+        profile: String,
+    },
+    InvalidStaticRoute {
+        network: String,
+        route: String,
+        reason: String,
+    },
+    InvalidMtu {
+        network: String,
+        mtu: u32,
+    },
 }
 
 impl ValidationWarning {
@@ -356,6 +1019,19 @@ impl ValidationWarning {
                 println!("⚠️  CIDR overlap detected:");
                 println!("   {} ({}) overlaps with {} ({})", net1, cidr1, net2, cidr2);
             }
+            Self::DuplicateForward {
+                net1,
+                public1,
+                net2,
+                public2,
+            } => {
+                println!("⚠️  Port forward claimed by two networks:");
+                println!(
+                    "   {} ({}) collides with {} ({})",
+                    net1, public1, net2, public2
+                );
+                println!("   Only one network's DNAT rule would take effect");
+            }
             Self::InvalidPort {
                 network,
                 port_spec,
@@ -374,6 +1050,10 @@ impl ValidationWarning {
                 println!("   Destination: {}", dst_spec);
                 println!("   Reason: {}", reason);
             }
+            Self::ForwardDestinationOutsideCidr { network, dst, cidr } => {
+                println!("⚠️  Forward destination outside network '{}':", network);
+                println!("   Destination: {} is not in {}", dst, cidr);
+            }
             Self::InvalidCidr {
                 network,
                 cidr,
@@ -398,6 +1078,128 @@ impl ValidationWarning {
                 println!("⚠️  Duplicate interface name: {}", name);
                 println!("   Used by networks: {}", networks.join(", "));
             }
+            Self::UnknownCtHelper { network, helper } => {
+                println!("⚠️  Unknown conntrack helper in network '{}':", network);
+                println!("   Helper: {}", helper);
+                let known: Vec<&str> = CT_HELPERS.iter().map(|h| h.name).collect();
+                println!("   Known helpers: {}", known.join(", "));
+            }
+            Self::CtHelperModule {
+                network,
+                helper,
+                modules,
+            } => {
+                println!(
+                    "⚠️  Conntrack helper '{}' in network '{}' needs missing kernel modules:",
+                    helper, network
+                );
+                println!("   {}", modules.join(", "));
+                println!("   Check with: gwarden doctor");
+            }
+            Self::InvalidVlanId { network, vlan } => {
+                println!("⚠️  Invalid VLAN ID in network '{}':", network);
+                println!(
+                    "   VLAN: {} (must be 1-4094; 0 and 4095 are reserved)",
+                    vlan
+                );
+            }
+            Self::InvalidVni { network, vni } => {
+                println!("⚠️  Invalid VXLAN VNI in network '{}':", network);
+                println!("   VNI: {} (must be 0-{})", vni, VXLAN_MAX_VNI);
+            }
+            Self::VxlanWithoutPeers { network } => {
+                println!("⚠️  VXLAN network '{}' has no peers:", network);
+                println!("   Set peers or a multicast group to reach other endpoints");
+            }
+            Self::DuplicateVni { vni, networks } => {
+                println!("⚠️  Duplicate VXLAN VNI: {}", vni);
+                println!("   Used by networks: {}", networks.join(", "));
+            }
+            Self::InvalidVxlanPeer {
+                network,
+                peer,
+                reason,
+            } => {
+                println!("⚠️  Invalid VXLAN peer in network '{}':", network);
+                println!("   Peer: {}", peer);
+                println!("   Reason: {}", reason);
+            }
+            Self::UndefinedVxlanBridge { network, bridge } => {
+                println!(
+                    "⚠️  VXLAN network '{}' uses bridge '{}' which the topology doesn't define",
+                    network, bridge
+                );
+                println!("   The bridge must already exist on the host");
+            }
+            Self::InvalidBridgeVlans { network, reason } => {
+                println!("⚠️  Invalid VLAN settings in network '{}':", network);
+                println!("   Reason: {}", reason);
+            }
+            Self::InvalidQuota { network, reason } => {
+                println!("⚠️  Invalid traffic quota in network '{}':", network);
+                println!("   Reason: {}", reason);
+            }
+            Self::InvalidDhcpOption {
+                network,
+                option,
+                reason,
+            } => {
+                println!("⚠️  Invalid DHCP option in network '{}':", network);
+                println!("   Option: {}", option);
+                println!("   Reason: {}", reason);
+            }
+            Self::InvalidDhcpRange { network, reason } => {
+                println!("⚠️  Invalid DHCP range in network '{}':", network);
+                println!("   Reason: {}", reason);
+            }
+            Self::InvalidDhcpReservation { network, reason } => {
+                println!("⚠️  Invalid DHCP reservation in network '{}':", network);
+                println!("   Reason: {}", reason);
+            }
+            Self::InvalidPxe { network, reason } => {
+                println!("⚠️  Invalid PXE boot settings in network '{}':", network);
+                println!("   Reason: {}", reason);
+            }
+            Self::InvalidDnsUpstream { network, upstream } => {
+                println!("⚠️  Invalid DNS upstream in network '{}':", network);
+                println!("   Upstream: {}", upstream);
+                println!("   Reason: expected ip or ip:port");
+            }
+            Self::PolicyProfileNotFound { network, profile } => {
+                println!("⚠️  Unknown policy profile in network '{}':", network);
+                println!("   Profile: {}", profile);
+                println!("   Reason: not found in examples/policies or /etc/gwarden/policies");
+            }
+            Self::InvalidStaticRoute {
+                network,
+                route,
+                reason,
+            } => {
+                println!("⚠️  Invalid static route in network '{}':", network);
+                println!("   Route: {}", route);
+                println!("   Reason: {}", reason);
+            }
+            Self::InvalidMtu { network, mtu } => {
+                println!("⚠️  Invalid MTU in network '{}':", network);
+                println!("   MTU: {}", mtu);
+                println!("   Reason: must be between {} and {}", MIN_MTU, MAX_MTU);
+            }
+        }
+    }
+
+    pub fn severity(&self) -> ValidationSeverity {
+        if self.is_error() {
+            ValidationSeverity::Error
+        } else {
+            ValidationSeverity::Warning
+        }
+    }
+
+    /// Structured form for `--json` output: severity, code and fields.
+    pub fn finding(&self) -> ValidationFinding<'_> {
+        ValidationFinding {
+            severity: self.severity(),
+            warning: self,
         }
     }
 
@@ -405,13 +1207,84 @@ impl ValidationWarning {
         match self {
             Self::InvalidPort { .. }
             | Self::InvalidDestination { .. }
+            | Self::DuplicateForward { .. }
+            | Self::ForwardDestinationOutsideCidr { .. }
             | Self::InvalidCidr { .. }
-            | Self::GatewayNotInCidr { .. } => true,
-            Self::CidrOverlap { .. } | Self::DuplicateInterfaceName { .. } => false, // Warnings only
+            | Self::GatewayNotInCidr { .. }
+            | Self::UnknownCtHelper { .. }
+            | Self::InvalidVlanId { .. }
+            | Self::InvalidVni { .. }
+            | Self::VxlanWithoutPeers { .. }
+            | Self::DuplicateVni { .. }
+            | Self::InvalidVxlanPeer { .. }
+            | Self::InvalidBridgeVlans { .. }
+            | Self::InvalidQuota { .. }
+            | Self::InvalidDhcpOption { .. }
+            | Self::InvalidDhcpRange { .. }
+            | Self::InvalidDhcpReservation { .. }
+            | Self::InvalidPxe { .. }
+            | Self::InvalidDnsUpstream { .. }
+            | Self::PolicyProfileNotFound { .. }
+            | Self::InvalidStaticRoute { .. }
+            | Self::InvalidMtu { .. } => true,
+            Self::CidrOverlap { .. }
+            | Self::DuplicateInterfaceName { .. }
+            | Self::CtHelperModule { .. }
+            | Self::UndefinedVxlanBridge { .. } => false, // Warnings only
         }
     }
 }
 
+/// Errors block `net apply`; warnings are advisory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ValidationSeverity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ValidationFinding<'a> {
+    pub severity: ValidationSeverity,
+    #[serde(flatten)]
+    pub warning: &'a ValidationWarning,
+}
+
+/// Kernel modules that are loaded, built in, or installed for the running
+/// kernel, with `-` normalised to `_`. `None` when the module index for the
+/// running kernel can't be read (e.g. in a container), so callers can skip
+/// module checks rather than report everything missing.
+pub fn available_kernel_modules() -> Option<HashSet<String>> {
+    let release = std::fs::read_to_string("/proc/sys/kernel/osrelease").ok()?;
+    let index = std::path::Path::new("/lib/modules").join(release.trim());
+    let installed = std::fs::read_to_string(index.join("modules.dep")).ok()?;
+    let builtin = std::fs::read_to_string(index.join("modules.builtin")).unwrap_or_default();
+    let loaded = std::fs::read_to_string("/proc/modules").unwrap_or_default();
+
+    Some(parse_kernel_modules(&loaded, &builtin, &installed))
+}
+
+/// Module names from `/proc/modules`, `modules.builtin` and `modules.dep`.
+fn parse_kernel_modules(loaded: &str, builtin: &str, installed: &str) -> HashSet<String> {
+    let module_name = |path: &str| {
+        let file = path.rsplit('/').next().unwrap_or(path);
+        file.split_once(".ko")
+            .map(|(name, _)| name.replace('-', "_"))
+    };
+
+    let loaded = loaded
+        .lines()
+        .filter_map(|line| line.split_whitespace().next())
+        .map(str::to_string);
+    let builtin = builtin.lines().filter_map(module_name);
+    let installed = installed
+        .lines()
+        .filter_map(|line| line.split(':').next())
+        .filter_map(module_name);
+
+    loaded.chain(builtin).chain(installed).collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -428,6 +1301,229 @@ mod tests {
         // Non-overlapping networks
         assert!(!TopologyValidator::cidrs_overlap("10.0.0.0/24", "10.1.0.0/24").unwrap());
         assert!(!TopologyValidator::cidrs_overlap("192.168.1.0/24", "172.16.0.0/16").unwrap());
+
+        // IPv6
+        assert!(TopologyValidator::cidrs_overlap("fd00:40::/64", "fd00:40::/48").unwrap());
+        assert!(TopologyValidator::cidrs_overlap("fd00:40::5/64", "fd00:40::/64").unwrap());
+        assert!(!TopologyValidator::cidrs_overlap("fd00:40::/64", "fd00:41::/64").unwrap());
+        assert!(TopologyValidator::cidrs_overlap("fd00:40::/64", "10.0.0.0/8").is_ok());
+    }
+
+    #[test]
+    fn test_policy_profile_references_are_checked() {
+        let topology = Topology::from_toml(
+            r#"
+version = 1
+
+[interfaces]
+
+[networks.nat_dev]
+type = "routed"
+cidr = "10.33.0.0/24"
+gw_ip = "10.33.0.1"
+policy_profile = "routed-tight"
+
+[networks.typo]
+type = "routed"
+cidr = "10.34.0.0/24"
+gw_ip = "10.34.0.1"
+policy_profile = "routed-tigth"
+
+[networks.work]
+type = "bridge"
+iface = "br-work"
+policy_profile = "l2-lann"
+"#,
+        )
+        .unwrap();
+        let profile: PolicyProfile =
+            toml::from_str("name = \"routed-tight\"\ndescription = \"\"").unwrap();
+        let profiles = HashMap::from([(profile.name.clone(), profile)]);
+
+        let unknown = |validator: TopologyValidator| -> Vec<(String, String)> {
+            let mut unknown: Vec<_> = validator
+                .validate()
+                .unwrap()
+                .into_iter()
+                .filter(|w| w.is_error())
+                .filter_map(|w| match w {
+                    ValidationWarning::PolicyProfileNotFound { network, profile } => {
+                        Some((network, profile))
+                    }
+                    _ => None,
+                })
+                .collect();
+            unknown.sort();
+            unknown
+        };
+
+        assert_eq!(
+            unknown(TopologyValidator::new(&topology).with_profiles(&profiles)),
+            vec![
+                ("typo".to_string(), "routed-tigth".to_string()),
+                ("work".to_string(), "l2-lann".to_string()),
+            ]
+        );
+        // Without loaded profiles there is nothing to check against
+        assert!(unknown(TopologyValidator::new(&topology)).is_empty());
+    }
+
+    #[test]
+    fn test_duplicate_forwards_across_networks() {
+        let topology = Topology::from_toml(
+            r#"
+version = 1
+
+[interfaces]
+
+[networks.a]
+type = "routed"
+cidr = "10.33.0.0/24"
+gw_ip = "10.33.0.1"
+cidr6 = "fd00:33::/64"
+gw_ip6 = "fd00:33::1"
+forwards = [
+    { public = ":8080/tcp", dst = "10.33.0.10:80" },
+    { public = ":8080/udp", dst = "10.33.0.10:80" },
+    { public = "203.0.113.5:9000-9010/tcp", dst = "10.33.0.11:9000-9010" },
+    { public = "[::]:2222/tcp", dst = "[fd00:33::10]:22" },
+    # Overlaps within one network are settled by priority
+    { public = "203.0.113.5:8080/tcp", dst = "10.33.0.12:80" },
+]
+
+[networks.b]
+type = "routed"
+cidr = "10.34.0.0/24"
+gw_ip = "10.34.0.1"
+forwards = [
+    { public = "0.0.0.0:8080/tcp", dst = "10.34.0.10:80" },
+    { public = ":9005/tcp", dst = "10.34.0.11:22" },
+    { public = "203.0.113.6:9000/tcp", dst = "10.34.0.12:22" },
+    { public = ":2222/tcp", dst = "10.34.0.13:22" },
+    { public = ":5353/udp", dst = "10.34.0.14:53" },
+]
+"#,
+        )
+        .unwrap();
+
+        let mut duplicates: Vec<(String, String)> = TopologyValidator::new(&topology)
+            .validate()
+            .unwrap()
+            .into_iter()
+            .filter(|w| w.is_error())
+            .filter_map(|w| match w {
+                ValidationWarning::DuplicateForward {
+                    public1, public2, ..
+                } => Some((public1, public2)),
+                _ => None,
+            })
+            .collect();
+        duplicates.sort();
+        assert_eq!(
+            duplicates,
+            vec![
+                (
+                    "203.0.113.5:8080/tcp".to_string(),
+                    "0.0.0.0:8080/tcp".to_string()
+                ),
+                (
+                    "203.0.113.5:9000-9010/tcp".to_string(),
+                    ":9005/tcp".to_string()
+                ),
+                (":8080/tcp".to_string(), "0.0.0.0:8080/tcp".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_forward_destination_must_be_in_the_network() {
+        let topology = Topology::from_toml(
+            r#"
+version = 1
+
+[interfaces]
+
+[networks.nat_dev]
+type = "routed"
+cidr = "10.33.0.0/24"
+gw_ip = "10.33.0.1"
+cidr6 = "fd00:33::/64"
+gw_ip6 = "fd00:33::1"
+forwards = [
+    { public = ":2222/tcp", dst = "10.33.0.10:22" },
+    { public = ":2223/tcp", dst = "[fd00:33::10]:22" },
+    { public = ":8080/tcp", dst = "192.168.9.9:80" },
+    { public = ":8443/tcp", dst = "[fd00:99::10]:443" },
+]
+"#,
+        )
+        .unwrap();
+
+        let warnings = TopologyValidator::new(&topology).validate().unwrap();
+        let mut outside: Vec<(&str, &str)> = warnings
+            .iter()
+            .filter(|w| w.is_error())
+            .filter_map(|w| match w {
+                ValidationWarning::ForwardDestinationOutsideCidr { dst, cidr, .. } => {
+                    Some((dst.as_str(), cidr.as_str()))
+                }
+                _ => None,
+            })
+            .collect();
+        outside.sort();
+        assert_eq!(
+            outside,
+            vec![
+                ("192.168.9.9", "10.33.0.0/24"),
+                ("fd00:99::10", "fd00:33::/64")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_ipv6_cidr_overlaps_are_reported() {
+        let topology = Topology::from_toml(
+            r#"
+version = 1
+
+[interfaces]
+
+[networks.a]
+type = "routed"
+cidr = "10.40.0.0/24"
+gw_ip = "10.40.0.1"
+cidr6 = "fd00:40::/64"
+gw_ip6 = "fd00:40::1"
+
+[networks.b]
+type = "routed"
+cidr = "10.41.0.0/24"
+gw_ip = "10.41.0.1"
+cidr6 = "fd00:40::/48"
+gw_ip6 = "fd00:40::2"
+
+[networks.c]
+type = "routed"
+cidr = "fd00:42::/64"
+gw_ip = "fd00:42::1"
+"#,
+        )
+        .unwrap();
+
+        let overlaps: Vec<(String, String)> = TopologyValidator::new(&topology)
+            .validate()
+            .unwrap()
+            .into_iter()
+            .filter_map(|w| match w {
+                ValidationWarning::CidrOverlap { cidr1, cidr2, .. } => Some((cidr1, cidr2)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(overlaps.len(), 1, "{:?}", overlaps);
+        let (cidr1, cidr2) = &overlaps[0];
+        let mut pair = [cidr1.as_str(), cidr2.as_str()];
+        pair.sort();
+        assert_eq!(pair, ["fd00:40::/48", "fd00:40::/64"]);
     }
 
     #[test]
@@ -439,6 +1535,59 @@ mod tests {
         assert!(TopologyValidator::validate_port_spec(":0/tcp").is_err());
         assert!(TopologyValidator::validate_port_spec(":99999/tcp").is_err());
         assert!(TopologyValidator::validate_port_spec(":22/invalid").is_err());
+        assert!(TopologyValidator::validate_port_spec(":53/UDP").is_ok());
+        assert!(TopologyValidator::validate_port_spec("[2001:db8::5]:53/udp").is_ok());
+
+        // The nftables generator needs a protocol and only forwards tcp/udp/sctp
+        assert!(TopologyValidator::validate_port_spec(":22").is_err());
+        assert!(TopologyValidator::validate_port_spec(":3868/sctp").is_ok());
+        assert!(TopologyValidator::validate_port_spec(":0/icmp").is_err());
+        assert!(TopologyValidator::validate_port_spec(":22/icmp").is_err());
+        assert!(TopologyValidator::validate_port_spec("10.0.0:22/tcp").is_err());
+
+        assert_eq!(
+            TopologyValidator::validate_port_spec(":8000-8010/tcp").unwrap(),
+            PortRange {
+                start: 8000,
+                end: 8010
+            }
+        );
+        assert!(TopologyValidator::validate_port_spec(":8010-8000/tcp").is_err());
+        assert!(TopologyValidator::validate_port_spec(":0-10/tcp").is_err());
+    }
+
+    #[test]
+    fn test_port_range_mapping() {
+        let public = PortRange::parse("8000-8010").unwrap();
+
+        assert_eq!(
+            public
+                .map_onto(PortRange::parse("9000-9010").unwrap())
+                .unwrap(),
+            PortRange {
+                start: 9000,
+                end: 9010
+            }
+        );
+        assert_eq!(
+            public.map_onto(PortRange::parse("9000").unwrap()).unwrap(),
+            PortRange {
+                start: 9000,
+                end: 9010
+            }
+        );
+
+        let err = public
+            .map_onto(PortRange::parse("9000-9005").unwrap())
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Destination range 9000-9005 has 6 ports but public range 8000-8010 has 11"
+        );
+        assert!(public.map_onto(PortRange::parse("65530").unwrap()).is_err());
+
+        let err = PortRange::parse("8010-8000").unwrap_err();
+        assert_eq!(err.to_string(), "Inverted port range 8010-8000");
     }
 
     #[test]
@@ -449,6 +1598,22 @@ mod tests {
         assert!(TopologyValidator::validate_destination("10.0.0.1").is_err());
         assert!(TopologyValidator::validate_destination("invalid:22").is_err());
         assert!(TopologyValidator::validate_destination("10.0.0.1:0").is_err());
+
+        assert!(TopologyValidator::validate_destination("10.0.0.1:9000-9010").is_ok());
+        assert!(TopologyValidator::validate_destination("10.0.0.1:9010-9000").is_err());
+
+        let forward = |public: &str, dst: &str| PortForward {
+            public: public.into(),
+            dst: dst.into(),
+            priority: None,
+        };
+        assert!(TopologyValidator::validate_forward(&forward(":80/tcp", "10.0.0.5:8080")).is_ok());
+        assert!(TopologyValidator::validate_forward(&forward(":53/udp", "10.0.0.5:53")).is_ok());
+        assert!(TopologyValidator::validate_forward(&forward(":80", "10.0.0.5:8080")).is_err());
+        assert!(
+            TopologyValidator::validate_forward(&forward(":8000-8001/tcp", "10.0.0.5:80-85"))
+                .is_err()
+        );
     }
 
     #[test]
@@ -474,4 +1639,779 @@ mod tests {
             TopologyValidator::validate_gateway_in_cidr("192.168.2.1", "192.168.1.0/24").is_err()
         );
     }
+
+    #[test]
+    fn test_findings_serialize_with_code_and_severity() {
+        let warning = ValidationWarning::GatewayNotInCidr {
+            network: "lab".into(),
+            gateway: "10.9.0.1".into(),
+            cidr: "10.40.0.0/24".into(),
+            reason: "Gateway IP is not within the network CIDR".into(),
+        };
+
+        let value = serde_json::to_value(warning.finding()).unwrap();
+        assert_eq!(value["code"], "GatewayNotInCidr");
+        assert_eq!(value["severity"], "error");
+        assert_eq!(value["network"], "lab");
+
+        let overlap = ValidationWarning::CidrOverlap {
+            net1: "a".into(),
+            cidr1: "10.0.0.0/16".into(),
+            net2: "b".into(),
+            cidr2: "10.0.1.0/24".into(),
+        };
+        let value = serde_json::to_value(overlap.finding()).unwrap();
+        assert_eq!(value["code"], "CidrOverlap");
+        assert_eq!(value["severity"], "warning");
+    }
+
+    #[test]
+    fn test_quota_validation() {
+        let topology = Topology::from_toml(
+            r#"
+version = 1
+
+[interfaces]
+
+[networks.guest]
+type = "routed"
+cidr = "10.60.0.0/24"
+gw_ip = "10.60.0.1"
+quota = { bytes = 0 }
+
+[networks.metered]
+type = "routed"
+cidr = "10.61.0.0/24"
+gw_ip = "10.61.0.1"
+quota = { bytes = 10737418240, mode = "until" }
+"#,
+        )
+        .unwrap();
+
+        let warnings = TopologyValidator::new(&topology).validate().unwrap();
+        let invalid: Vec<&str> = warnings
+            .iter()
+            .filter_map(|w| match w {
+                ValidationWarning::InvalidQuota { network, .. } if w.is_error() => {
+                    Some(network.as_str())
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(invalid, vec!["guest"]);
+    }
+
+    #[test]
+    fn test_static_route_validation() {
+        let topology = Topology::from_toml(
+            r#"
+version = 1
+
+[interfaces]
+
+[networks.lab]
+type = "routed"
+cidr = "10.40.0.0/24"
+gw_ip = "10.40.0.1"
+routes = [
+    { dst = "192.168.50.0/24", via = "10.40.0.254" },
+    { dst = "192.168.60.0/24", via = "10.41.0.254" },
+    { dst = "192.168.70.0/33", via = "10.40.0.254" },
+    { dst = "fd00:70::/64", via = "10.40.0.254" },
+]
+"#,
+        )
+        .unwrap();
+
+        let warnings = TopologyValidator::new(&topology).validate().unwrap();
+        let invalid: Vec<&str> = warnings
+            .iter()
+            .filter_map(|w| match w {
+                ValidationWarning::InvalidStaticRoute { route, .. } if w.is_error() => {
+                    Some(route.as_str())
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            invalid,
+            vec![
+                "192.168.60.0/24 via 10.41.0.254",
+                "192.168.70.0/33 via 10.40.0.254",
+                "fd00:70::/64 via 10.40.0.254",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_dual_stack_validation() {
+        let topology = Topology::from_toml(
+            r#"
+version = 1
+
+[interfaces]
+
+[networks.dual]
+type = "routed"
+cidr = "10.40.0.0/24"
+gw_ip = "10.40.0.1"
+cidr6 = "fd00:40::/64"
+gw_ip6 = "fd00:40::1"
+forwards = [{ public = "[::]:2222/tcp", dst = "[fd00:40::10]:22" }]
+
+[networks.stray_gw]
+type = "routed"
+cidr = "10.41.0.0/24"
+gw_ip = "10.41.0.1"
+cidr6 = "fd00:41::/64"
+gw_ip6 = "fd00:99::1"
+
+[networks.no_gw]
+type = "routed"
+cidr = "10.42.0.0/24"
+gw_ip = "10.42.0.1"
+cidr6 = "fd00:42::/64"
+
+[networks.v4_only]
+type = "routed"
+cidr = "10.43.0.0/24"
+gw_ip = "10.43.0.1"
+forwards = [{ public = ":2222/tcp", dst = "[fd00:43::10]:22" }]
+"#,
+        )
+        .unwrap();
+
+        let warnings = TopologyValidator::new(&topology).validate().unwrap();
+        let mut errors: Vec<(&str, &str)> = warnings
+            .iter()
+            .filter(|w| w.is_error())
+            .filter_map(|w| match w {
+                ValidationWarning::GatewayNotInCidr { network, .. } => {
+                    Some((network.as_str(), "gateway"))
+                }
+                ValidationWarning::InvalidCidr { network, .. } => Some((network.as_str(), "cidr")),
+                ValidationWarning::InvalidDestination { network, .. } => {
+                    Some((network.as_str(), "destination"))
+                }
+                _ => None,
+            })
+            .collect();
+        errors.sort();
+        assert_eq!(
+            errors,
+            vec![
+                ("no_gw", "cidr"),
+                ("stray_gw", "gateway"),
+                ("v4_only", "destination"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_mtu_validation() {
+        let topology = Topology::from_toml(
+            r#"
+version = 1
+
+[interfaces]
+
+[networks.jumbo]
+type = "routed"
+cidr = "10.40.0.0/24"
+gw_ip = "10.40.0.1"
+mtu = 9000
+
+[networks.tiny]
+type = "bridge"
+iface = "br-tiny"
+mtu = 40
+"#,
+        )
+        .unwrap();
+
+        let warnings = TopologyValidator::new(&topology).validate().unwrap();
+        let invalid: Vec<(&str, u32)> = warnings
+            .iter()
+            .filter_map(|w| match w {
+                ValidationWarning::InvalidMtu { network, mtu } if w.is_error() => {
+                    Some((network.as_str(), *mtu))
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(invalid, vec![("tiny", 40)]);
+    }
+
+    #[test]
+    fn test_dhcp_option_validation() {
+        let topology = Topology::from_toml(
+            r#"
+version = 1
+
+[interfaces]
+
+[networks.lab]
+type = "routed"
+cidr = "10.62.0.0/24"
+gw_ip = "10.62.0.1"
+dhcp_options = [
+  { option = "ntp-server", value = "10.62.0.1" },
+  { option = 252, value = "http://10.62.0.1/wpad.dat" },
+  { option = "time-machine", value = "x" },
+  { option = "dns-server", value = "10.62.0.300" },
+  { option = "mtu", value = "60" },
+  { option = 300, value = "1" },
+  { option = 224, value = "x\ndhcp-script=/tmp/evil" },
+  { option = "domain-search", value = "lab.internal\r" },
+]
+"#,
+        )
+        .unwrap();
+
+        let warnings = TopologyValidator::new(&topology).validate().unwrap();
+        let invalid: Vec<&str> = warnings
+            .iter()
+            .filter_map(|w| match w {
+                ValidationWarning::InvalidDhcpOption { option, .. } if w.is_error() => {
+                    Some(option.as_str())
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            invalid,
+            vec![
+                "time-machine",
+                "dns-server",
+                "mtu",
+                "300",
+                "224",
+                "domain-search"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_pxe_validation() {
+        let reason = |pxe: &str| -> Option<String> {
+            let topology = Topology::from_toml(&format!(
+                r#"
+version = 1
+
+[interfaces]
+
+[networks.lab]
+type = "routed"
+cidr = "10.62.0.0/24"
+gw_ip = "10.62.0.1"
+dhcp = true
+
+[networks.lab.pxe]
+{}
+"#,
+                pxe
+            ))
+            .unwrap();
+            TopologyValidator::new(&topology)
+                .validate()
+                .unwrap()
+                .into_iter()
+                .find_map(|w| match w {
+                    ValidationWarning::InvalidPxe { reason, .. } if w.is_error() => Some(reason),
+                    _ => None,
+                })
+        };
+
+        assert_eq!(
+            reason("boot_file = \"pxelinux.0\"\nnext_server = \"10.62.0.5\""),
+            None
+        );
+        for (pxe, expected) in [
+            (
+                "boot_file = \"pxelinux.0\"\ntftp_root = \"/nonexistent/tftp\"",
+                "tftp_root /nonexistent/tftp is not a directory",
+            ),
+            ("boot_file = \"pxelinux.0\"", "needs tftp_root"),
+            (
+                "boot_file = \"boot file.0\"\nnext_server = \"10.62.0.5\"",
+                "without commas or spaces",
+            ),
+        ] {
+            let reason = reason(pxe).unwrap_or_default();
+            assert!(reason.contains(expected), "{}: {}", pxe, reason);
+        }
+    }
+
+    #[test]
+    fn test_dhcp6_validation() {
+        let reason = |extra: &str| -> Option<String> {
+            let topology = Topology::from_toml(&format!(
+                r#"
+version = 1
+
+[interfaces]
+
+[networks.lab]
+type = "routed"
+cidr = "10.62.0.0/24"
+gw_ip = "10.62.0.1"
+cidr6 = "fd00:62::/64"
+gw_ip6 = "fd00:62::1"
+dhcp = true
+{}
+"#,
+                extra
+            ))
+            .unwrap();
+            TopologyValidator::new(&topology)
+                .validate()
+                .unwrap()
+                .into_iter()
+                .find_map(|w| match w {
+                    ValidationWarning::InvalidDhcpRange { reason, .. } => Some(reason),
+                    _ => None,
+                })
+        };
+
+        assert_eq!(reason(""), None);
+        assert_eq!(reason("ipv6_mode = \"dhcpv6\""), None);
+        assert_eq!(
+            reason(
+                "ipv6_mode = \"dhcpv6\"\ndhcp6_start = \"fd00:62::100\"\ndhcp6_end = \"fd00:62::1ff\""
+            ),
+            None
+        );
+
+        for (extra, expected) in [
+            (
+                "ipv6_mode = \"dhcpv6\"\ndhcp6_start = \"fd00:63::100\"",
+                "is not in fd00:62::/64",
+            ),
+            (
+                "ipv6_mode = \"dhcpv6\"\ndhcp6_start = \"fd00:62::1\"\ndhcp6_end = \"fd00:62::ff\"",
+                "is the gateway address",
+            ),
+            (
+                "ipv6_mode = \"dhcpv6\"\ndhcp6_start = \"fd00:62::200\"\ndhcp6_end = \"fd00:62::100\"",
+                "is after dhcp6_end",
+            ),
+            (
+                "dhcp6_start = \"fd00:62::100\"",
+                "need ipv6_mode = \"dhcpv6\"",
+            ),
+        ] {
+            let reason = reason(extra).unwrap_or_default();
+            assert!(reason.contains(expected), "{}: {}", extra, reason);
+        }
+    }
+
+    #[test]
+    fn test_dhcp_range_validation() {
+        let reason = |extra: &str| -> Option<String> {
+            let topology = Topology::from_toml(&format!(
+                r#"
+version = 1
+
+[interfaces]
+
+[networks.lab]
+type = "routed"
+cidr = "10.62.0.0/24"
+gw_ip = "10.62.0.1"
+dhcp = true
+{}
+"#,
+                extra
+            ))
+            .unwrap();
+            TopologyValidator::new(&topology)
+                .validate()
+                .unwrap()
+                .into_iter()
+                .find_map(|w| match w {
+                    ValidationWarning::InvalidDhcpRange { reason, .. } => Some(reason),
+                    _ => None,
+                })
+        };
+
+        assert_eq!(
+            reason(
+                "dhcp_start = \"10.62.0.100\"\ndhcp_end = \"10.62.0.199\"\nlease_time = \"45m\""
+            ),
+            None
+        );
+        assert_eq!(reason("lease_time = \"infinite\""), None);
+
+        for (extra, expected) in [
+            (
+                "dhcp_start = \"10.63.0.100\"",
+                "not a host address of 10.62.0.0/24",
+            ),
+            ("dhcp_end = \"10.62.0.255\"", "not a host address"),
+            ("dhcp_start = \"10.62.0.1\"", "is the gateway address"),
+            (
+                "dhcp_start = \"10.62.0.200\"\ndhcp_end = \"10.62.0.100\"",
+                "is after dhcp_end",
+            ),
+            ("lease_time = \"1m\"", "2 minute minimum"),
+            ("lease_time = \"12 hours\"", "not a duration"),
+            ("lease_time = \"3y\"", "unknown unit"),
+        ] {
+            let reason = reason(extra).unwrap_or_default();
+            assert!(reason.contains(expected), "{}: {}", extra, reason);
+        }
+    }
+
+    #[test]
+    fn test_dhcp_reservation_validation() {
+        let reason = |reservations: &str| -> Option<String> {
+            let topology = Topology::from_toml(&format!(
+                r#"
+version = 1
+
+[interfaces]
+
+[networks.lab]
+type = "routed"
+cidr = "10.62.0.0/24"
+gw_ip = "10.62.0.1"
+dhcp = true
+reservations = [{}]
+"#,
+                reservations
+            ))
+            .unwrap();
+            TopologyValidator::new(&topology)
+                .validate()
+                .unwrap()
+                .into_iter()
+                .find_map(|w| match w {
+                    ValidationWarning::InvalidDhcpReservation { reason, .. } => Some(reason),
+                    _ => None,
+                })
+        };
+
+        assert_eq!(
+            reason(
+                r#"{ mac = "52:54:00:aa:bb:01", ip = "10.62.0.5", hostname = "db" },
+                   { mac = "52:54:00:aa:bb:02", ip = "10.62.0.252" }"#
+            ),
+            None
+        );
+
+        for (reservations, expected) in [
+            (
+                r#"{ mac = "52:54:00:aa:bb:01", ip = "10.62.0.50" }"#,
+                "inside the dynamic pool 10.62.0.10-10.62.0.250",
+            ),
+            (
+                r#"{ mac = "52:54:00:aa:bb:01", ip = "10.63.0.5" }"#,
+                "not a host address",
+            ),
+            (
+                r#"{ mac = "52:54:00:aa:bb:01", ip = "10.62.0.1" }"#,
+                "gateway",
+            ),
+            (
+                r#"{ mac = "52-54-00-aa-bb-01", ip = "10.62.0.5" }"#,
+                "not a MAC",
+            ),
+            (
+                r#"{ mac = "52:54:00:aa:bb:01", ip = "10.62.0.5" },
+                   { mac = "52:54:00:AA:BB:01", ip = "10.62.0.6" }"#,
+                "reserved more than once",
+            ),
+        ] {
+            let reason = reason(reservations).unwrap_or_default();
+            assert!(reason.contains(expected), "{}: {}", reservations, reason);
+        }
+    }
+
+    #[test]
+    fn test_dns_upstream_validation() {
+        let topology = Topology::from_toml(
+            r#"
+version = 1
+
+[interfaces]
+
+[networks.lab]
+type = "routed"
+cidr = "10.62.0.0/24"
+gw_ip = "10.62.0.1"
+
+[networks.lab.dns]
+enabled = true
+backend = "coredns"
+upstreams = ["9.9.9.9", "[2620:fe::fe]:53", "10.62.0.53:5353", "dns.quad9.net"]
+"#,
+        )
+        .unwrap();
+
+        let warnings = TopologyValidator::new(&topology).validate().unwrap();
+        let invalid: Vec<&str> = warnings
+            .iter()
+            .filter_map(|w| match w {
+                ValidationWarning::InvalidDnsUpstream { upstream, .. } if w.is_error() => {
+                    Some(upstream.as_str())
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(invalid, vec!["dns.quad9.net"]);
+    }
+
+    #[test]
+    fn test_ct_helper_validation() {
+        let topology = Topology::from_toml(
+            r#"
+version = 1
+
+[interfaces]
+
+[networks.voip]
+type = "routed"
+cidr = "10.50.0.0/24"
+gw_ip = "10.50.0.1"
+masq_out = "eth0"
+ct_helpers = ["sip", "ftp", "h999"]
+"#,
+        )
+        .unwrap();
+
+        let warnings = TopologyValidator::new(&topology).validate().unwrap();
+        assert!(warnings.iter().any(|w| matches!(
+            w,
+            ValidationWarning::UnknownCtHelper { helper, .. } if helper == "h999"
+        ) && w.is_error()));
+        // Module availability is only checked when the caller supplies it.
+        assert!(
+            !warnings
+                .iter()
+                .any(|w| matches!(w, ValidationWarning::CtHelperModule { .. }))
+        );
+
+        let modules = parse_kernel_modules(
+            "nf_conntrack_sip 40960 0 - Live 0x0\n",
+            "kernel/net/netfilter/nf_conntrack_ftp.ko\n",
+            "kernel/net/netfilter/nf_nat_ftp.ko.zst: kernel/net/netfilter/nf_nat.ko.zst\n",
+        );
+        let warnings = TopologyValidator::new(&topology)
+            .with_kernel_modules(&modules)
+            .validate()
+            .unwrap();
+        let missing: Vec<(&str, &[String])> = warnings
+            .iter()
+            .filter_map(|w| match w {
+                ValidationWarning::CtHelperModule {
+                    helper, modules, ..
+                } => Some((helper.as_str(), modules.as_slice())),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(missing, [("sip", &["nf_nat_sip".to_string()][..])]);
+        assert!(
+            warnings
+                .iter()
+                .filter(|w| matches!(w, ValidationWarning::CtHelperModule { .. }))
+                .all(|w| !w.is_error())
+        );
+    }
+
+    #[test]
+    fn parses_kernel_module_index() {
+        let modules = parse_kernel_modules(
+            "nf_tables 1 0 - Live 0x0\nbridge 1 0 - Live 0x0\n",
+            "kernel/net/ipv4/ip-tunnel.ko\n",
+            "kernel/net/netfilter/nf_nat_sip.ko.xz: kernel/net/netfilter/nf_nat.ko.xz\n",
+        );
+        let mut names: Vec<&str> = modules.iter().map(String::as_str).collect();
+        names.sort_unstable();
+        assert_eq!(names, ["bridge", "ip_tunnel", "nf_nat_sip", "nf_tables"]);
+    }
+
+    #[test]
+    fn test_vxlan_validation() {
+        let topology = Topology::from_toml(
+            r#"
+version = 1
+
+[interfaces]
+
+[networks.lab]
+type = "routed"
+cidr = "10.60.0.0/24"
+gw_ip = "10.60.0.1"
+
+[networks.overlay_a]
+type = "vxlan"
+vni = 1200
+bridge = "br-lab"
+peers = ["10.0.0.11", "127.0.0.1"]
+
+[networks.overlay_b]
+type = "vxlan"
+vni = 1200
+bridge = "br-missing"
+peers = ["239.1.1.1"]
+"#,
+        )
+        .unwrap();
+
+        let warnings = TopologyValidator::new(&topology).validate().unwrap();
+
+        assert!(warnings.iter().any(|w| matches!(
+            w,
+            ValidationWarning::DuplicateVni { vni: 1200, networks }
+                if networks == &["overlay_a", "overlay_b"]
+        )));
+
+        let bad_peers: Vec<String> = warnings
+            .iter()
+            .filter_map(|w| match w {
+                ValidationWarning::InvalidVxlanPeer { peer, .. } => Some(peer.to_string()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(bad_peers.len(), 2);
+        assert!(bad_peers.contains(&"127.0.0.1".to_string()));
+        assert!(bad_peers.contains(&"239.1.1.1".to_string()));
+
+        let undefined: Vec<&str> = warnings
+            .iter()
+            .filter_map(|w| match w {
+                ValidationWarning::UndefinedVxlanBridge { bridge, .. } => Some(bridge.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(undefined, vec!["br-missing"]);
+    }
+
+    #[test]
+    fn test_vlan_id_and_vni_ranges() {
+        let topology = Topology::from_toml(
+            r#"
+version = 1
+
+[interfaces]
+
+[networks.vlan_min]
+type = "bridge"
+iface = "br-min"
+vlan = 1
+
+[networks.vlan_max]
+type = "bridge"
+iface = "br-max"
+vlan = 4094
+
+[networks.vlan_zero]
+type = "bridge"
+iface = "br-zero"
+vlan = 0
+
+[networks.vlan_reserved]
+type = "bridge"
+iface = "br-reserved"
+vlan = 4095
+
+[networks.vni_zero]
+type = "vxlan"
+vni = 0
+bridge = "br-min"
+peers = ["10.0.0.11"]
+
+[networks.vni_max]
+type = "vxlan"
+vni = 16777215
+bridge = "br-min"
+peers = ["10.0.0.11"]
+
+[networks.vni_over]
+type = "vxlan"
+vni = 16777216
+bridge = "br-min"
+peers = ["10.0.0.11"]
+
+[networks.no_peers]
+type = "vxlan"
+vni = 1300
+bridge = "br-min"
+peers = []
+
+[networks.multicast]
+type = "vxlan"
+vni = 1301
+bridge = "br-min"
+peers = []
+group = "239.1.1.1"
+"#,
+        )
+        .unwrap();
+
+        let mut errors: Vec<String> = TopologyValidator::new(&topology)
+            .validate()
+            .unwrap()
+            .into_iter()
+            .filter(|w| w.is_error())
+            .filter_map(|w| match w {
+                ValidationWarning::InvalidVlanId { network, vlan } => {
+                    Some(format!("{}: vlan {}", network, vlan))
+                }
+                ValidationWarning::InvalidVni { network, vni } => {
+                    Some(format!("{}: vni {}", network, vni))
+                }
+                ValidationWarning::VxlanWithoutPeers { network } => {
+                    Some(format!("{}: no peers", network))
+                }
+                _ => None,
+            })
+            .collect();
+        errors.sort();
+        assert_eq!(
+            errors,
+            vec![
+                "no_peers: no peers",
+                "vlan_reserved: vlan 4095",
+                "vlan_zero: vlan 0",
+                "vni_over: vni 16777216",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_bridge_vlan_validation() {
+        let topology = Topology::from_toml(
+            r#"
+version = 1
+
+[interfaces]
+
+[networks.trunk]
+type = "bridge"
+iface = "br-trunk"
+vlan_default_pvid = 4095
+
+[networks.trunk.port_vlans]
+pvid = 10
+vlans = [10, 20]
+untagged = [30]
+"#,
+        )
+        .unwrap();
+
+        let reasons: Vec<String> = TopologyValidator::new(&topology)
+            .validate()
+            .unwrap()
+            .into_iter()
+            .filter_map(|w| match w {
+                ValidationWarning::InvalidBridgeVlans { reason, .. } => Some(reason),
+                _ => None,
+            })
+            .collect();
+
+        assert_eq!(reasons.len(), 2);
+        assert!(reasons.iter().any(|r| r.contains("4095")));
+        assert!(reasons.iter().any(|r| r.contains("untagged VLAN 30")));
+    }
 }
@@ -1,4 +1,4 @@
-use gw_core::{Network, Plan, Topology};
+use gw_core::{BridgeStatus, InterfaceFilter, Network, NetworkStatus, Plan, PlanAction, Topology};
 
 #[test]
 fn parses_toml_topology_example() {
@@ -41,3 +41,274 @@ networks:
         Some(Network::Routed(_))
     ));
 }
+
+#[test]
+fn misspelled_topology_fields_are_rejected() {
+    let routed = r#"
+version = 1
+
+[interfaces]
+
+[networks.nat_dev]
+type = "routed"
+cidr = "10.33.0.0/24"
+gw_ip = "10.33.0.1"
+"#;
+
+    for (extra, key) in [
+        ("masq_ou = \"enp6s0\"", "masq_ou"),
+        ("policy_profle = \"strict\"", "policy_profle"),
+        (
+            "[networks.nat_dev.dns]\nenabled = true\nzone = [\"lab.lan\"]",
+            "zone",
+        ),
+        (
+            "[[networks.nat_dev.forwards]]\npublic = \"0.0.0.0:2222/tcp\"\ndest = \"10.33.0.10:22\"",
+            "dest",
+        ),
+        (
+            "[networks.work]\ntype = \"bridge\"\niface = \"br-work\"\nuplinks = [\"enp7s0\"]",
+            "uplinks",
+        ),
+        (
+            "[networks.overlay]\ntype = \"vxlan\"\nvni = 1200\nbridge = \"br-work\"\npeer = [\"192.0.2.7\"]",
+            "peer",
+        ),
+    ] {
+        let err = Topology::from_toml(&format!("{}{}\n", routed, extra)).unwrap_err();
+        let message = format!("{:#}", err);
+        assert!(
+            message.contains(&format!("unknown field `{}`", key)),
+            "{}",
+            message
+        );
+    }
+
+    let err =
+        Topology::from_toml(&format!("{}\n[metadata]\nowner = \"ops\"\n", routed)).unwrap_err();
+    assert!(format!("{:#}", err).contains("unknown field `metadata`"));
+
+    let yaml = r#"
+version: 1
+interfaces: {}
+networks:
+  nat_dev:
+    type: routed
+    cidr: 10.33.0.0/24
+    gw_ip: 10.33.0.1
+    masq_ou: enp6s0
+"#;
+    let err = Topology::from_yaml(yaml).unwrap_err();
+    assert!(format!("{:#}", err).contains("unknown field `masq_ou`"));
+}
+
+#[test]
+fn isolate_ports_flag_maps_to_bridge_names() {
+    let toml = r#"
+version = 1
+
+[interfaces]
+
+[networks.tenants]
+type = "routed"
+cidr = "10.50.0.0/24"
+gw_ip = "10.50.0.1"
+isolate_ports = true
+
+[networks.shared]
+type = "bridge"
+iface = "br-shared"
+"#;
+
+    let topology = Topology::from_toml(toml).unwrap();
+    assert!(topology.bridge_isolates_ports("br-tenants"));
+    assert!(!topology.bridge_isolates_ports("br-shared"));
+    assert!(!topology.bridge_isolates_ports("br-unknown"));
+}
+
+#[test]
+fn detects_ipv6_routed_networks() {
+    let toml = r#"
+version = 1
+
+[interfaces]
+
+[networks.v4]
+type = "routed"
+cidr = "10.50.0.0/24"
+gw_ip = "10.50.0.1"
+"#;
+    assert!(!Topology::from_toml(toml).unwrap().has_ipv6_networks());
+
+    let dual_stack = format!(
+        r#"{}
+[networks.v6]
+type = "routed"
+cidr = "fd00:50::/64"
+gw_ip = "fd00:50::1"
+"#,
+        toml
+    );
+    assert!(
+        Topology::from_toml(&dual_stack)
+            .unwrap()
+            .has_ipv6_networks()
+    );
+}
+
+#[test]
+fn port_vlans_enable_filtering_on_bridge() {
+    let toml = r#"
+version = 1
+
+[interfaces]
+
+[networks.trunk]
+type = "bridge"
+iface = "br-trunk"
+
+[networks.trunk.port_vlans]
+pvid = 10
+vlans = [10, 20, 30]
+untagged = [10]
+"#;
+
+    let topology = Topology::from_toml(toml).unwrap();
+    let vlans = topology.bridge_port_vlans("br-trunk").expect("port vlans");
+    assert_eq!(vlans.vlans, vec![10, 20, 30]);
+    vlans.validate().expect("valid port vlans");
+    assert!(topology.bridge_port_vlans("br-other").is_none());
+
+    let plan = Plan::from_topology(&topology).unwrap();
+    assert!(plan.actions.contains(&PlanAction::SetBridgeVlanFiltering {
+        bridge: "br-trunk".into(),
+        default_pvid: 1,
+    }));
+
+    let mut missing_pvid = vlans.clone();
+    missing_pvid.pvid = Some(40);
+    assert!(missing_pvid.validate().is_err());
+}
+
+#[test]
+fn managed_interface_filter_keeps_topology_bridges() {
+    let toml = r#"
+version = 1
+
+[interfaces]
+uplink = "enp6s0"
+
+[networks.lab]
+type = "routed"
+cidr = "10.40.0.0/24"
+gw_ip = "10.40.0.1"
+
+[networks.trunk]
+type = "bridge"
+iface = "br0"
+"#;
+
+    let topology = Topology::from_toml(toml).unwrap();
+    let filter = InterfaceFilter::managed(&topology);
+
+    let bridge = |name: &str| BridgeStatus {
+        name: name.into(),
+        state: "UP".into(),
+        addresses: Vec::new(),
+        members: vec!["vnet0".into()],
+    };
+    let mut status = NetworkStatus::new();
+    status.bridges = vec![
+        bridge("br-lab"),
+        bridge("br0"),
+        bridge("docker0"),
+        bridge("br-3f9a1c2d"),
+        bridge("virbr0"),
+    ];
+
+    status.retain_interfaces(&filter);
+    let names: Vec<&str> = status.bridges.iter().map(|b| b.name.as_str()).collect();
+    assert_eq!(names, vec!["br-lab", "br0"]);
+    assert_eq!(status.bridges[0].members, vec!["vnet0"]);
+
+    assert!(InterfaceFilter::All.includes("docker0"));
+    assert!(InterfaceFilter::All.bridges().is_none());
+}
+
+#[test]
+fn merges_layered_topology_files() {
+    let dir = std::env::temp_dir().join(format!("gw-topology-merge-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let base = dir.join("base.toml");
+    std::fs::write(
+        &base,
+        r#"
+version = 1
+
+[interfaces]
+uplink = "enp6s0"
+
+[networks.nat_dev]
+type = "routed"
+cidr = "10.33.0.0/24"
+gw_ip = "10.33.0.1"
+dhcp = true
+masq_out = "enp6s0"
+
+[[networks.nat_dev.forwards]]
+public = ":4022/tcp"
+dst = "10.33.0.10:22"
+
+[networks.lab]
+type = "bridge"
+iface = "br-lab"
+"#,
+    )
+    .unwrap();
+
+    let site = dir.join("site.yaml");
+    std::fs::write(
+        &site,
+        r#"
+interfaces:
+  uplink: eno1
+networks:
+  nat_dev:
+    cidr: 10.44.0.0/24
+    gw_ip: 10.44.0.1
+  lab:
+    type: routed
+    cidr: 10.45.0.0/24
+    gw_ip: 10.45.0.1
+  overlay:
+    type: vxlan
+    vni: 1200
+    bridge: br-nat_dev
+    peers: ["10.0.0.11"]
+"#,
+    )
+    .unwrap();
+
+    let topology = Topology::from_files(&[&base, &site]).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(topology.interfaces.get("uplink").unwrap(), "eno1");
+
+    let Some(Network::Routed(nat_dev)) = topology.networks.get("nat_dev") else {
+        panic!("nat_dev should stay routed");
+    };
+    assert_eq!(nat_dev.cidr, "10.44.0.0/24");
+    assert!(nat_dev.dhcp, "unset fields keep the base value");
+    assert_eq!(nat_dev.masq_out.as_deref(), Some("enp6s0"));
+    assert_eq!(nat_dev.forwards.len(), 1);
+
+    assert!(matches!(
+        topology.networks.get("lab"),
+        Some(Network::Routed(_))
+    ));
+    assert!(matches!(
+        topology.networks.get("overlay"),
+        Some(Network::Vxlan(_))
+    ));
+}
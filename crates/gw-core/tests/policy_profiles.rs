@@ -62,3 +62,87 @@ services:
     assert!(matches!(profile.services[0].protocol, Protocol::Tcp));
     assert_eq!(profile.services[1].port, 53);
 }
+
+#[test]
+fn parses_egress_services_and_rejects_zero_port() {
+    let toml = r#"
+name = "egress-only"
+description = "outbound dns and https"
+
+[[allowed_egress_services]]
+protocol = "tcp"
+port = 443
+
+[[allowed_egress_services]]
+protocol = "udp"
+port = 53
+"#;
+
+    let profile: PolicyProfile =
+        gw_core::config_format::from_str(toml, gw_core::config_format::ConfigFormat::Toml)
+            .expect("parse toml policy");
+
+    assert_eq!(profile.allowed_egress_services.len(), 2);
+    assert_eq!(profile.allowed_egress_services[0].port, 443);
+    profile.validate().expect("valid profile");
+
+    let mut broken = profile.clone();
+    broken.allowed_egress_services[1].port = 0;
+    assert!(broken.validate().is_err());
+}
+
+#[test]
+fn parses_multi_port_services() {
+    let toml = r#"
+name = "web"
+description = "http and https in one rule"
+
+[[services]]
+protocol = "tcp"
+ports = [443, 80]
+"#;
+
+    let profile: PolicyProfile =
+        gw_core::config_format::from_str(toml, gw_core::config_format::ConfigFormat::Toml)
+            .expect("parse toml policy");
+
+    assert_eq!(profile.services[0].dports(), vec![80, 443]);
+    profile.validate().expect("valid profile");
+
+    let mut zero = profile.clone();
+    zero.services[0].ports.push(0);
+    assert!(zero.validate().is_err());
+
+    let mut both = profile.clone();
+    both.services[0].port = 22;
+    assert!(both.validate().is_err());
+}
+
+#[test]
+fn icmp_rate_limit_defaults_on_and_rejects_zero_rate() {
+    let toml = r#"
+name = "ping-only"
+description = "icmp under a drop default"
+
+[[services]]
+protocol = "icmp"
+port = 0
+"#;
+
+    let profile: PolicyProfile =
+        gw_core::config_format::from_str(toml, gw_core::config_format::ConfigFormat::Toml)
+            .expect("parse toml policy");
+
+    let limit = profile
+        .effective_icmp_rate_limit()
+        .expect("limit applies under drop default");
+    assert_eq!((limit.rate, limit.burst), (10, 20));
+
+    let mut opted_out = profile.clone();
+    opted_out.icmp_rate_limit.enabled = false;
+    assert!(opted_out.effective_icmp_rate_limit().is_none());
+
+    let mut broken = profile.clone();
+    broken.icmp_rate_limit.rate = 0;
+    assert!(broken.validate().is_err());
+}
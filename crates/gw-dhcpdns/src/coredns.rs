@@ -0,0 +1,183 @@
+use anyhow::{Context, Result};
+use gw_core::{CommandRunner, SystemCommandRunner};
+use std::net::{IpAddr, SocketAddr};
+use std::path::Path;
+use std::sync::Arc;
+
+/// Directory Ghostwarden writes `gw-<net>.conf` Corefile snippets to. The host
+/// Corefile pulls them in with `import /etc/coredns/gw.d/*.conf`.
+pub const COREDNS_CONFIG_DIR: &str = "/etc/coredns/gw.d";
+
+/// Seconds CoreDNS caches forwarded answers.
+const CACHE_TTL: u32 = 30;
+
+pub struct CoreDnsManager {
+    runner: Arc<dyn CommandRunner>,
+}
+
+impl CoreDnsManager {
+    pub fn new() -> Self {
+        Self {
+            runner: Arc::new(SystemCommandRunner),
+        }
+    }
+
+    /// Run `systemctl` through `runner` instead of spawning it directly.
+    pub fn with_runner(mut self, runner: Arc<dyn CommandRunner>) -> Self {
+        self.runner = runner;
+        self
+    }
+
+    /// Server blocks answering on `bind`: one per local zone, served from
+    /// `/etc/hosts` and never forwarded (like dnsmasq's `local=`), and a root
+    /// block forwarding everything else to `upstreams`.
+    pub fn generate_corefile(
+        &self,
+        zones: &[String],
+        upstreams: &[String],
+        bind: IpAddr,
+    ) -> Result<String> {
+        for zone in zones {
+            if zone.is_empty() || zone.contains(|c: char| c.is_whitespace() || "{}#".contains(c)) {
+                anyhow::bail!("Invalid DNS zone '{}'", zone);
+            }
+        }
+        for upstream in upstreams {
+            if upstream.parse::<IpAddr>().is_err() && upstream.parse::<SocketAddr>().is_err() {
+                anyhow::bail!(
+                    "Invalid DNS upstream '{}' (expected ip or ip:port)",
+                    upstream
+                );
+            }
+        }
+
+        let mut config = String::new();
+        config.push_str(&format!("# Ghostwarden configuration for {}\n", bind));
+
+        for zone in zones {
+            config.push_str(&format!("{} {{\n", zone));
+            config.push_str(&format!("    bind {}\n", bind));
+            config.push_str("    hosts\n");
+            config.push_str("    errors\n");
+            config.push_str("}\n\n");
+        }
+
+        let forward_to = if upstreams.is_empty() {
+            "/etc/resolv.conf".to_string()
+        } else {
+            upstreams.join(" ")
+        };
+        config.push_str(". {\n");
+        config.push_str(&format!("    bind {}\n", bind));
+        config.push_str(&format!("    forward . {}\n", forward_to));
+        config.push_str(&format!("    cache {}\n", CACHE_TTL));
+        config.push_str("    errors\n");
+        config.push_str("}\n");
+
+        Ok(config)
+    }
+
+    pub fn write_config(&self, path: &str, content: &str) -> Result<()> {
+        let config_path = Path::new(path);
+
+        if let Some(parent) = config_path.parent() {
+            std::fs::create_dir_all(parent)
+                .context(format!("Failed to create directory {:?}", parent))?;
+        }
+
+        std::fs::write(config_path, content)
+            .context(format!("Failed to write config to {}", path))?;
+
+        println!("Wrote CoreDNS config to {}", path);
+        Ok(())
+    }
+
+    /// Reload the Corefile without dropping the server. The packaged unit's
+    /// `ExecReload` signals CoreDNS, which re-reads its imports.
+    pub async fn reload(&self) -> Result<()> {
+        let output = self
+            .runner
+            .run("systemctl", &["reload", "coredns"], None)
+            .await
+            .context("Failed to run systemctl reload coredns")?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            anyhow::bail!("Failed to reload CoreDNS: {}", stderr);
+        }
+
+        println!("Reloaded CoreDNS service");
+        Ok(())
+    }
+
+    /// Delete a CoreDNS config file
+    pub fn delete_config(&self, path: &str) -> Result<()> {
+        if Path::new(path).exists() {
+            std::fs::remove_file(path)
+                .context(format!("Failed to delete config file: {}", path))?;
+            println!("Deleted CoreDNS config: {}", path);
+        }
+        Ok(())
+    }
+}
+
+impl Default for CoreDnsManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn corefile_serves_zones_locally_and_forwards_the_rest() {
+        let config = CoreDnsManager::new()
+            .generate_corefile(
+                &["lab.lan".into()],
+                &["9.9.9.9".into(), "[2620:fe::fe]:53".into()],
+                "10.50.0.1".parse().unwrap(),
+            )
+            .unwrap();
+
+        assert!(config.contains("lab.lan {\n    bind 10.50.0.1\n    hosts\n"));
+        assert!(
+            config.contains(". {\n    bind 10.50.0.1\n    forward . 9.9.9.9 [2620:fe::fe]:53\n")
+        );
+    }
+
+    #[test]
+    fn corefile_defaults_to_host_resolvers_and_rejects_bad_input() {
+        let manager = CoreDnsManager::new();
+        let bind: IpAddr = "10.50.0.1".parse().unwrap();
+
+        let config = manager.generate_corefile(&[], &[], bind).unwrap();
+        assert!(config.contains("forward . /etc/resolv.conf\n"));
+
+        assert!(
+            manager
+                .generate_corefile(&[], &["dns.example".into()], bind)
+                .is_err()
+        );
+        assert!(
+            manager
+                .generate_corefile(&["lab.lan {".into()], &[], bind)
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn reload_reports_systemctl_failure() {
+        let runner = Arc::new(gw_core::MockCommandRunner::new().fail(
+            "systemctl reload coredns",
+            1,
+            "Unit coredns.service not loaded.",
+        ));
+        let manager = CoreDnsManager::new().with_runner(runner.clone());
+
+        let err = manager.reload().await.unwrap_err();
+        assert!(err.to_string().contains("Unit coredns.service not loaded."));
+        assert_eq!(runner.calls()[0].command, "systemctl reload coredns");
+    }
+}
@@ -1,17 +1,57 @@
 use anyhow::{Context, Result};
-use std::path::Path;
+use gw_core::{CommandRunner, DhcpOption, DhcpPool, Ipv6AddressMode, SystemCommandRunner};
+use std::collections::HashSet;
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
-pub struct DnsmasqManager;
+/// Drop-in directory dnsmasq reads; Ghostwarden writes `gw-<net>.conf` files here.
+pub const DNSMASQ_CONFIG_DIR: &str = "/etc/dnsmasq.d";
+
+pub struct DnsmasqManager {
+    runner: Arc<dyn CommandRunner>,
+}
 
 impl DnsmasqManager {
     pub fn new() -> Self {
-        Self
+        Self {
+            runner: Arc::new(SystemCommandRunner),
+        }
+    }
+
+    /// Run `systemctl` through `runner` instead of spawning it directly.
+    pub fn with_runner(mut self, runner: Arc<dyn CommandRunner>) -> Self {
+        self.runner = runner;
+        self
     }
 
-    pub fn generate_config(&self, bridge: &str, cidr: &str, zones: &[String]) -> Result<String> {
-        // Parse CIDR to get network range for DHCP
-        let (network, prefix) = parse_cidr(cidr)?;
-        let dhcp_range = calculate_dhcp_range(&network, prefix)?;
+    /// With `serve_dns` false dnsmasq only hands out leases (`port=0`) and
+    /// `zones` are left to whichever server does answer DNS on the gateway.
+    /// Non-empty `upstreams` replace `/etc/resolv.conf` as the resolvers
+    /// dnsmasq forwards to.
+    #[allow(clippy::too_many_arguments)]
+    pub fn generate_config(
+        &self,
+        bridge: &str,
+        cidr: &str,
+        gateway: IpAddr,
+        zones: &[String],
+        upstreams: &[String],
+        options: &[DhcpOption],
+        pool: &DhcpPool,
+        serve_dns: bool,
+    ) -> Result<String> {
+        pool.validate(cidr, gateway)?;
+        let servers = upstreams
+            .iter()
+            .map(|upstream| server_address(upstream))
+            .collect::<Result<Vec<_>>>()?;
+        pool.validate_reservations(cidr, gateway)?;
+        if let Some(pxe) = &pool.pxe {
+            pxe.validate()?;
+        }
+        let (start, end) = pool.range(cidr, gateway)?;
+        let dhcp_range = format!("{},{},{}", start, end, pool.lease_time());
 
         let mut config = String::new();
 
@@ -19,16 +59,71 @@ impl DnsmasqManager {
         config.push_str(&format!("# Ghostwarden configuration for {}\n", bridge));
         config.push_str(&format!("interface={}\n", bridge));
         config.push_str("bind-interfaces\n");
-        config.push_str("except-interface=lo\n\n");
+        config.push_str("except-interface=lo\n");
+        if !serve_dns {
+            config.push_str("port=0\n");
+        }
+        config.push('\n');
 
         // DHCP configuration
         config.push_str(&format!("# DHCP range for {}\n", bridge));
         config.push_str(&format!("dhcp-range={}\n", dhcp_range));
-        config.push_str(&format!("dhcp-option=option:router,{}\n", network));
-        config.push_str(&format!("dhcp-option=option:dns-server,{}\n\n", network));
+        // Custom router/dns-server options replace the gateway defaults.
+        let overrides = |code: u8| options.iter().any(|option| option.code() == Some(code));
+        if !overrides(3) {
+            config.push_str(&format!("dhcp-option=option:router,{}\n", gateway));
+        }
+        if !overrides(6) {
+            config.push_str(&format!("dhcp-option=option:dns-server,{}\n", gateway));
+        }
+        for option in options {
+            config.push_str(&format!("dhcp-option={}\n", option.dnsmasq_value()));
+        }
+        config.push('\n');
+
+        if let Some(ipv6) = &pool.ipv6 {
+            let (network, prefix) = ipv6.prefix()?;
+            let range = match ipv6.mode {
+                // Stateless DHCPv6 still answers DNS queries; ra-names
+                // publishes SLAAC hosts under their DHCPv4 names.
+                Ipv6AddressMode::Slaac => format!("{},ra-stateless,ra-names", network),
+                Ipv6AddressMode::Dhcpv6 => {
+                    let (start, end) = ipv6.range()?;
+                    format!("{},{}", start, end)
+                }
+            };
+            config.push_str(&format!("# IPv6 for {}\n", bridge));
+            config.push_str("enable-ra\n");
+            config.push_str(&format!(
+                "dhcp-range={},{},{}\n",
+                range,
+                prefix,
+                pool.lease_time()
+            ));
+            config.push('\n');
+        }
+
+        if let Some(pxe) = &pool.pxe {
+            config.push_str("# Network boot\n");
+            if let Some(root) = &pxe.tftp_root {
+                // Scoped to the bridge so other dnsmasq networks don't serve it
+                config.push_str(&format!("enable-tftp={}\n", bridge));
+                config.push_str(&format!("tftp-root={},{}\n", root.display(), bridge));
+            }
+            config.push_str(&format!("dhcp-boot={}\n", pxe.dnsmasq_value()));
+            config.push('\n');
+        }
+
+        if !pool.reservations.is_empty() {
+            config.push_str("# Static leases\n");
+            for reservation in &pool.reservations {
+                config.push_str(&format!("dhcp-host={}\n", reservation.dnsmasq_value()));
+            }
+            config.push('\n');
+        }
 
         // DNS configuration
-        if !zones.is_empty() {
+        if serve_dns && !zones.is_empty() {
             config.push_str("# Local DNS zones\n");
             for zone in zones {
                 config.push_str(&format!("local=/{}/\n", zone));
@@ -37,17 +132,33 @@ impl DnsmasqManager {
             config.push('\n');
         }
 
+        if serve_dns && !servers.is_empty() {
+            config.push_str("# Upstream resolvers\n");
+            config.push_str("no-resolv\n");
+            for server in &servers {
+                config.push_str(&format!("server={}\n", server));
+            }
+            config.push('\n');
+        }
+
         // Additional settings
         config.push_str("# Additional settings\n");
         config.push_str("dhcp-authoritative\n");
-        config.push_str("dhcp-leasefile=/var/lib/misc/dnsmasq.leases\n");
+        // One lease file per dnsmasq instance; leases are told apart by subnet
+        config.push_str(&format!("dhcp-leasefile={}\n", crate::DEFAULT_LEASE_FILE));
         config.push_str(&format!("log-facility=/var/log/dnsmasq-{}.log\n", bridge));
 
         Ok(config)
     }
 
-    pub fn write_config(&self, path: &str, content: &str) -> Result<()> {
+    /// Write `content` to `path`, returning whether the file changed; an
+    /// identical existing file is left untouched.
+    pub fn write_config(&self, path: &str, content: &str) -> Result<bool> {
         let config_path = Path::new(path);
+        if std::fs::read_to_string(config_path).is_ok_and(|existing| existing == content) {
+            println!("dnsmasq config {} is unchanged", path);
+            return Ok(false);
+        }
 
         // Create parent directory if it doesn't exist
         if let Some(parent) = config_path.parent() {
@@ -60,17 +171,35 @@ impl DnsmasqManager {
             .context(format!("Failed to write config to {}", path))?;
 
         println!("Wrote dnsmasq config to {}", path);
+        Ok(true)
+    }
+
+    /// Syntax-check the config file at `path` with `dnsmasq --test`, so a bad
+    /// directive fails the apply instead of taking DNS down on restart.
+    pub async fn validate_config(&self, path: &str) -> Result<()> {
+        let conf_file = format!("--conf-file={}", path);
+        let output = self
+            .runner
+            .run("dnsmasq", &["--test", &conf_file], None)
+            .await
+            .context("Failed to run dnsmasq --test")?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            anyhow::bail!("dnsmasq rejected {}: {}", path, stderr.trim());
+        }
+
         Ok(())
     }
 
+    /// Restart dnsmasq. Needed whenever a config file was added, changed or
+    /// removed: dnsmasq reads its config directory only at startup. Active
+    /// leases survive in the lease file but the DNS cache is dropped.
     pub async fn restart(&self) -> Result<()> {
-        use tokio::process::Command;
-
         // Try systemctl restart dnsmasq
-        let output = Command::new("systemctl")
-            .arg("restart")
-            .arg("dnsmasq")
-            .output()
+        let output = self
+            .runner
+            .run("systemctl", &["restart", "dnsmasq"], None)
             .await
             .context("Failed to run systemctl restart dnsmasq")?;
 
@@ -84,13 +213,10 @@ impl DnsmasqManager {
     }
 
     pub async fn enable(&self) -> Result<()> {
-        use tokio::process::Command;
-
         // Enable dnsmasq service
-        let output = Command::new("systemctl")
-            .arg("enable")
-            .arg("dnsmasq")
-            .output()
+        let output = self
+            .runner
+            .run("systemctl", &["enable", "dnsmasq"], None)
             .await
             .context("Failed to run systemctl enable dnsmasq")?;
 
@@ -112,6 +238,36 @@ impl DnsmasqManager {
         }
         Ok(())
     }
+
+    /// Find `gw-<net>.conf` files in `dir` whose network is not in `networks`
+    pub fn find_orphaned_configs(
+        &self,
+        dir: &Path,
+        networks: &HashSet<String>,
+    ) -> Result<Vec<PathBuf>> {
+        if !dir.exists() {
+            return Ok(vec![]);
+        }
+
+        let mut orphans = Vec::new();
+        for entry in std::fs::read_dir(dir).context(format!("Failed to read {:?}", dir))? {
+            let path = entry?.path();
+            let Some(network) = path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .and_then(network_from_config_name)
+            else {
+                continue;
+            };
+
+            if !networks.contains(network) {
+                orphans.push(path);
+            }
+        }
+
+        orphans.sort();
+        Ok(orphans)
+    }
 }
 
 impl Default for DnsmasqManager {
@@ -120,36 +276,512 @@ impl Default for DnsmasqManager {
     }
 }
 
-/// Parse CIDR notation: "10.33.0.0/24" -> ("10.33.0.0", 24)
-fn parse_cidr(cidr: &str) -> Result<(String, u8)> {
-    let parts: Vec<&str> = cidr.split('/').collect();
-    if parts.len() != 2 {
-        anyhow::bail!("Invalid CIDR format: {}", cidr);
+/// "9.9.9.9" -> "9.9.9.9", "[2620:fe::fe]:53" -> "2620:fe::fe#53": dnsmasq
+/// separates the port with `#`
+fn server_address(upstream: &str) -> Result<String> {
+    if let Ok(addr) = upstream.parse::<IpAddr>() {
+        return Ok(addr.to_string());
     }
+    let addr: SocketAddr = upstream.parse().map_err(|_| {
+        anyhow::anyhow!(
+            "Invalid DNS upstream '{}' (expected ip or ip:port)",
+            upstream
+        )
+    })?;
+    Ok(format!("{}#{}", addr.ip(), addr.port()))
+}
 
-    let network = parts[0].to_string();
-    let prefix: u8 = parts[1]
-        .parse()
-        .context(format!("Invalid prefix length: {}", parts[1]))?;
-
-    Ok((network, prefix))
+/// "gw-nat_dev.conf" -> Some("nat_dev"); anything not written by Ghostwarden -> None
+fn network_from_config_name(file_name: &str) -> Option<&str> {
+    file_name
+        .strip_prefix("gw-")?
+        .strip_suffix(".conf")
+        .filter(|name| !name.is_empty())
 }
 
-/// Calculate DHCP range from network CIDR
-/// For 10.33.0.0/24, returns "10.33.0.10,10.33.0.250,12h"
-fn calculate_dhcp_range(network: &str, _prefix: u8) -> Result<String> {
-    use std::net::Ipv4Addr;
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(cidr: &str, gateway: &str) -> Result<(String, String)> {
+        let (start, end) = DhcpPool::default().range(cidr, gateway.parse().unwrap())?;
+        Ok((start.to_string(), end.to_string()))
+    }
+
+    #[test]
+    fn dhcp_range_for_slash_24_keeps_static_margins() {
+        assert_eq!(
+            range("10.33.0.0/24", "10.33.0.1").unwrap(),
+            ("10.33.0.10".into(), "10.33.0.250".into())
+        );
+    }
+
+    #[test]
+    fn dhcp_range_for_slash_25_stays_inside_the_half() {
+        assert_eq!(
+            range("10.33.0.128/25", "10.33.0.129").unwrap(),
+            ("10.33.0.138".into(), "10.33.0.250".into())
+        );
+        assert_eq!(
+            range("10.33.0.0/25", "10.33.0.1").unwrap(),
+            ("10.33.0.10".into(), "10.33.0.122".into())
+        );
+    }
 
-    let ip: Ipv4Addr = network
-        .parse()
-        .context(format!("Invalid IP address: {}", network))?;
+    #[test]
+    fn dhcp_range_for_slash_28_skips_gateway_and_broadcast() {
+        assert_eq!(
+            range("10.33.0.16/28", "10.33.0.17").unwrap(),
+            ("10.33.0.18".into(), "10.33.0.30".into())
+        );
+        // Gateway in the middle: use the larger side.
+        assert_eq!(
+            range("10.33.0.16/28", "10.33.0.20").unwrap(),
+            ("10.33.0.21".into(), "10.33.0.30".into())
+        );
+    }
 
-    let octets = ip.octets();
+    #[test]
+    fn dhcp_range_for_slash_22_spans_the_whole_prefix() {
+        assert_eq!(
+            range("10.60.4.0/22", "10.60.4.1").unwrap(),
+            ("10.60.4.10".into(), "10.60.7.250".into())
+        );
+    }
 
-    // Simple range: .10 to .250 for /24 networks
-    // For other sizes, you'd need more sophisticated calculation
-    let start_ip = format!("{}.{}.{}.10", octets[0], octets[1], octets[2]);
-    let end_ip = format!("{}.{}.{}.250", octets[0], octets[1], octets[2]);
+    #[test]
+    fn explicit_bounds_override_the_computed_range() {
+        let pool = |start: Option<&str>, end: Option<&str>| DhcpPool {
+            start: start.map(|s| s.parse().unwrap()),
+            end: end.map(|s| s.parse().unwrap()),
+            ..DhcpPool::default()
+        };
+        let bounds = |cidr: &str, pool: &DhcpPool| -> Result<(String, String)> {
+            let (start, end) = pool.range(cidr, "10.60.4.1".parse().unwrap())?;
+            Ok((start.to_string(), end.to_string()))
+        };
 
-    Ok(format!("{},{},12h", start_ip, end_ip))
+        assert_eq!(
+            bounds(
+                "10.60.4.0/22",
+                &pool(Some("10.60.5.0"), Some("10.60.5.255"))
+            )
+            .unwrap(),
+            ("10.60.5.0".into(), "10.60.5.255".into())
+        );
+        // One bound set: the other keeps its computed default
+        assert_eq!(
+            bounds("10.60.4.0/24", &pool(Some("10.60.4.100"), None)).unwrap(),
+            ("10.60.4.100".into(), "10.60.4.250".into())
+        );
+        assert!(bounds("10.60.4.0/24", &pool(None, Some("10.60.4.5"))).is_err());
+    }
+
+    #[test]
+    fn custom_pool_and_lease_time_reach_the_config() {
+        let pool = DhcpPool {
+            start: Some("10.50.0.20".parse().unwrap()),
+            end: Some("10.50.0.29".parse().unwrap()),
+            lease_time: Some("1h".into()),
+            ..DhcpPool::default()
+        };
+        let config = DnsmasqManager::new()
+            .generate_config(
+                "br-lab",
+                "10.50.0.0/24",
+                "10.50.0.1".parse().unwrap(),
+                &[],
+                &[],
+                &[],
+                &pool,
+                true,
+            )
+            .unwrap();
+        assert!(config.contains("dhcp-range=10.50.0.20,10.50.0.29,1h\n"));
+
+        let outside = DhcpPool {
+            end: Some("10.50.1.29".parse().unwrap()),
+            ..pool
+        };
+        let err = DnsmasqManager::new()
+            .generate_config(
+                "br-lab",
+                "10.50.0.0/24",
+                "10.50.0.1".parse().unwrap(),
+                &[],
+                &[],
+                &[],
+                &outside,
+                true,
+            )
+            .unwrap_err();
+        assert!(err.to_string().contains("not a host address"), "{}", err);
+    }
+
+    #[test]
+    fn ipv6_prefix_gets_router_advertisements() {
+        let generate = |mode, start: Option<&str>, end: Option<&str>| {
+            let pool = DhcpPool {
+                ipv6: Some(gw_core::Dhcp6Pool {
+                    cidr: "fd00:50::/64".into(),
+                    gateway: Some("fd00:50::1".parse().unwrap()),
+                    mode,
+                    start: start.map(|addr| addr.parse().unwrap()),
+                    end: end.map(|addr| addr.parse().unwrap()),
+                }),
+                ..DhcpPool::default()
+            };
+            DnsmasqManager::new().generate_config(
+                "br-lab",
+                "10.50.0.0/24",
+                "10.50.0.1".parse().unwrap(),
+                &[],
+                &[],
+                &[],
+                &pool,
+                true,
+            )
+        };
+
+        let config = generate(Ipv6AddressMode::Slaac, None, None).unwrap();
+        assert!(config.contains("enable-ra\ndhcp-range=fd00:50::,ra-stateless,ra-names,64,12h\n"));
+
+        let config = generate(Ipv6AddressMode::Dhcpv6, None, None).unwrap();
+        assert!(config.contains("dhcp-range=fd00:50::1000,fd00:50::1fff,64,12h\n"));
+
+        let config = generate(
+            Ipv6AddressMode::Dhcpv6,
+            Some("fd00:50::100"),
+            Some("fd00:50::1ff"),
+        )
+        .unwrap();
+        assert!(config.contains("dhcp-range=fd00:50::100,fd00:50::1ff,64,12h\n"));
+
+        let err = generate(Ipv6AddressMode::Dhcpv6, Some("fd00:51::100"), None).unwrap_err();
+        assert!(
+            err.to_string().contains("is not in fd00:50::/64"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn pxe_block_in_the_topology_reaches_the_config() {
+        let tftp_root = std::env::temp_dir().join(format!("gw-tftp-{}", std::process::id()));
+        std::fs::create_dir_all(&tftp_root).unwrap();
+        let generate = |pxe: &str| {
+            let topology = gw_core::Topology::from_toml(&format!(
+                r#"
+version = 1
+
+[interfaces]
+
+[networks.lab]
+type = "routed"
+cidr = "10.50.0.0/24"
+gw_ip = "10.50.0.1"
+dhcp = true
+
+[networks.lab.pxe]
+{}
+"#,
+                pxe
+            ))
+            .unwrap();
+            let gw_core::Network::Routed(routed) = &topology.networks["lab"] else {
+                unreachable!()
+            };
+            DnsmasqManager::new().generate_config(
+                "br-lab",
+                &routed.cidr,
+                routed.gw_ip,
+                &[],
+                &[],
+                &[],
+                &routed.dhcp_pool(),
+                true,
+            )
+        };
+
+        let config = generate(&format!(
+            "boot_file = \"pxelinux.0\"\ntftp_root = \"{}\"",
+            tftp_root.display()
+        ))
+        .unwrap();
+        std::fs::remove_dir_all(&tftp_root).unwrap();
+        assert!(config.contains(&format!(
+            "enable-tftp=br-lab\ntftp-root={},br-lab\ndhcp-boot=pxelinux.0\n",
+            tftp_root.display()
+        )));
+
+        let config = generate("boot_file = \"ipxe.efi\"\nnext_server = \"10.50.0.5\"").unwrap();
+        assert!(config.contains("dhcp-boot=ipxe.efi,,10.50.0.5\n"));
+        assert!(!config.contains("enable-tftp"));
+
+        let err = generate(&format!(
+            "boot_file = \"pxelinux.0\"\ntftp_root = \"{}\"",
+            tftp_root.display()
+        ))
+        .unwrap_err();
+        assert!(err.to_string().contains("is not a directory"), "{}", err);
+    }
+
+    #[test]
+    fn reservations_become_dhcp_host_lines() {
+        let pool = DhcpPool {
+            reservations: vec![
+                gw_core::DhcpReservation {
+                    mac: "52:54:00:12:34:56".into(),
+                    ip: "10.50.0.5".parse().unwrap(),
+                    hostname: Some("db".into()),
+                },
+                gw_core::DhcpReservation {
+                    mac: "52:54:00:12:34:57".into(),
+                    ip: "10.50.0.6".parse().unwrap(),
+                    hostname: None,
+                },
+            ],
+            ..DhcpPool::default()
+        };
+        let config = DnsmasqManager::new()
+            .generate_config(
+                "br-lab",
+                "10.50.0.0/24",
+                "10.50.0.1".parse().unwrap(),
+                &[],
+                &[],
+                &[],
+                &pool,
+                true,
+            )
+            .unwrap();
+
+        assert!(config.contains("dhcp-host=52:54:00:12:34:56,10.50.0.5,db\n"));
+        assert!(config.contains("dhcp-host=52:54:00:12:34:57,10.50.0.6\n"));
+    }
+
+    #[test]
+    fn dhcp_range_rejects_point_to_point_prefixes() {
+        assert!(range("10.33.0.0/30", "10.33.0.1").is_err());
+        assert!(range("10.33.0.0/31", "10.33.0.0").is_err());
+        assert!(range("10.33.0.1/32", "10.33.0.1").is_err());
+        assert!(range("fd00::/64", "fd00::1").is_err());
+    }
+
+    #[test]
+    fn dhcp_range_for_slash_16_spans_all_octets() {
+        assert_eq!(
+            range("10.40.0.0/16", "10.40.0.1").unwrap(),
+            ("10.40.0.10".into(), "10.40.255.250".into())
+        );
+        // A non-aligned address still resolves to its network.
+        assert_eq!(
+            range("10.40.7.9/16", "10.40.0.1").unwrap(),
+            ("10.40.0.10".into(), "10.40.255.250".into())
+        );
+    }
+
+    #[test]
+    fn generated_config_points_clients_at_gateway() {
+        let config = DnsmasqManager::new()
+            .generate_config(
+                "br-lab",
+                "10.50.0.0/28",
+                "10.50.0.1".parse().unwrap(),
+                &[],
+                &[],
+                &[],
+                &DhcpPool::default(),
+                true,
+            )
+            .unwrap();
+
+        assert!(config.contains("dhcp-range=10.50.0.2,10.50.0.14,12h\n"));
+        assert!(config.contains("dhcp-option=option:router,10.50.0.1\n"));
+        assert!(!config.contains("port=0"));
+    }
+
+    #[test]
+    fn upstreams_replace_the_host_resolvers() {
+        let config = DnsmasqManager::new()
+            .generate_config(
+                "br-lab",
+                "10.50.0.0/24",
+                "10.50.0.1".parse().unwrap(),
+                &[],
+                &["10.8.0.53".into(), "[2620:fe::fe]:5353".into()],
+                &[],
+                &DhcpPool::default(),
+                true,
+            )
+            .unwrap();
+        assert!(config.contains("no-resolv\nserver=10.8.0.53\nserver=2620:fe::fe#5353\n"));
+
+        let config = DnsmasqManager::new()
+            .generate_config(
+                "br-lab",
+                "10.50.0.0/24",
+                "10.50.0.1".parse().unwrap(),
+                &[],
+                &[],
+                &[],
+                &DhcpPool::default(),
+                true,
+            )
+            .unwrap();
+        assert!(!config.contains("no-resolv"));
+
+        assert!(server_address("dns.quad9.net").is_err());
+    }
+
+    #[test]
+    fn dhcp_only_config_leaves_dns_to_another_server() {
+        let config = DnsmasqManager::new()
+            .generate_config(
+                "br-lab",
+                "10.50.0.0/24",
+                "10.50.0.1".parse().unwrap(),
+                &["lab.lan".into()],
+                &[],
+                &[],
+                &DhcpPool::default(),
+                false,
+            )
+            .unwrap();
+
+        assert!(config.contains("port=0\n"));
+        assert!(!config.contains("local=/lab.lan/"));
+        // Clients still resolve through the gateway, where CoreDNS listens.
+        assert!(config.contains("dhcp-option=option:dns-server,10.50.0.1\n"));
+    }
+
+    #[test]
+    fn custom_dhcp_options_are_emitted() {
+        use gw_core::DhcpOptionId;
+
+        let option = |id: DhcpOptionId, value: &str| DhcpOption {
+            option: id,
+            value: value.into(),
+        };
+        let options = [
+            option(
+                DhcpOptionId::Name("ntp-server".into()),
+                "10.50.0.1, 10.50.0.2",
+            ),
+            option(DhcpOptionId::Number(26), "1450"),
+            option(
+                DhcpOptionId::Name("domain-search".into()),
+                "lab.lan,corp.lan",
+            ),
+            option(DhcpOptionId::Name("dns-server".into()), "9.9.9.9"),
+        ];
+        for option in &options {
+            option.validate().unwrap();
+        }
+
+        let config = DnsmasqManager::new()
+            .generate_config(
+                "br-lab",
+                "10.50.0.0/24",
+                "10.50.0.1".parse().unwrap(),
+                &[],
+                &[],
+                &options,
+                &DhcpPool::default(),
+                true,
+            )
+            .unwrap();
+
+        assert!(config.contains("dhcp-option=option:ntp-server,10.50.0.1,10.50.0.2\n"));
+        assert!(config.contains("dhcp-option=26,1450\n"));
+        assert!(config.contains("dhcp-option=option:domain-search,lab.lan,corp.lan\n"));
+        assert!(config.contains("dhcp-option=option:router,10.50.0.1\n"));
+        // The custom DNS server replaces the gateway default.
+        assert!(config.contains("dhcp-option=option:dns-server,9.9.9.9\n"));
+        assert!(!config.contains("dhcp-option=option:dns-server,10.50.0.1"));
+    }
+
+    #[test]
+    fn network_name_from_config_file() {
+        assert_eq!(network_from_config_name("gw-nat_dev.conf"), Some("nat_dev"));
+        assert_eq!(network_from_config_name("gw-.conf"), None);
+        assert_eq!(network_from_config_name("libvirt.conf"), None);
+        assert_eq!(network_from_config_name("gw-nat_dev.conf.bak"), None);
+    }
+
+    #[test]
+    fn finds_configs_for_removed_networks() {
+        let dir = std::env::temp_dir().join(format!("gw-dnsmasq-orphans-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for name in ["gw-nat_dev.conf", "gw-old_lab.conf", "other.conf"] {
+            std::fs::write(dir.join(name), "").unwrap();
+        }
+
+        let networks: HashSet<String> = ["nat_dev".to_string()].into_iter().collect();
+        let orphans = DnsmasqManager::new()
+            .find_orphaned_configs(&dir, &networks)
+            .unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(orphans, vec![dir.join("gw-old_lab.conf")]);
+    }
+
+    #[test]
+    fn missing_config_dir_has_no_orphans() {
+        let orphans = DnsmasqManager::new()
+            .find_orphaned_configs(Path::new("/nonexistent/gw-dnsmasq.d"), &HashSet::new())
+            .unwrap();
+        assert!(orphans.is_empty());
+    }
+
+    #[tokio::test]
+    async fn restart_reports_systemctl_failure() {
+        let runner = Arc::new(gw_core::MockCommandRunner::new().fail(
+            "systemctl restart dnsmasq",
+            1,
+            "Job for dnsmasq.service failed",
+        ));
+        let manager = DnsmasqManager::new().with_runner(runner.clone());
+
+        let err = manager.restart().await.unwrap_err();
+        assert!(err.to_string().contains("Job for dnsmasq.service failed"));
+        assert_eq!(runner.calls()[0].command, "systemctl restart dnsmasq");
+    }
+
+    #[tokio::test]
+    async fn validate_config_reports_the_rejected_directive() {
+        let runner = Arc::new(
+            gw_core::MockCommandRunner::new()
+                .fail(
+                    "dnsmasq --test --conf-file=/etc/dnsmasq.d/gw-bad.conf",
+                    1,
+                    "dnsmasq: bad option at line 3 of /etc/dnsmasq.d/gw-bad.conf",
+                )
+                .ok("dnsmasq --test --conf-file=/etc/dnsmasq.d/gw-lab.conf", ""),
+        );
+        let manager = DnsmasqManager::new().with_runner(runner.clone());
+
+        let err = manager
+            .validate_config("/etc/dnsmasq.d/gw-bad.conf")
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("bad option at line 3"));
+        manager
+            .validate_config("/etc/dnsmasq.d/gw-lab.conf")
+            .await
+            .unwrap();
+    }
+
+    #[test]
+    fn write_config_reports_whether_the_file_changed() {
+        let dir = std::env::temp_dir().join(format!("gw-dnsmasq-write-{}", std::process::id()));
+        let path = dir.join("gw-lab.conf");
+        let path = path.to_str().unwrap();
+        let manager = DnsmasqManager::new();
+
+        assert!(manager.write_config(path, "interface=br-lab\n").unwrap());
+        assert!(!manager.write_config(path, "interface=br-lab\n").unwrap());
+        assert!(manager.write_config(path, "interface=br-lab2\n").unwrap());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
 }
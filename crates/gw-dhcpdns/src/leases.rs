@@ -1,6 +1,39 @@
 use anyhow::Result;
-use gw_core::DhcpLease;
+use gw_core::{DhcpLease, Network, Topology};
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::net::Ipv4Addr;
 use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Lease file written by the system dnsmasq instance. dnsmasq keeps a single
+/// lease file per instance, so every network's leases end up here; see
+/// `LeaseReader::read_leases_by_network` to tell them apart.
+pub const DEFAULT_LEASE_FILE: &str = "/var/lib/misc/dnsmasq.leases";
+
+/// Narrows what `LeaseReader::read_leases_with` returns. The default query
+/// returns every lease in file order, same as `read_leases`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LeaseQuery {
+    /// Only leases whose expiry timestamp is later than this Unix time.
+    /// dnsmasq rewrites the expiry on every renewal, so this picks up leases
+    /// granted or renewed since a previous poll.
+    pub since: Option<i64>,
+    /// Keep at most this many leases, most recent expiry first.
+    pub limit: Option<usize>,
+}
+
+impl LeaseQuery {
+    pub fn since(mut self, timestamp: i64) -> Self {
+        self.since = Some(timestamp);
+        self
+    }
+
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+}
 
 pub struct LeaseReader;
 
@@ -10,60 +43,136 @@ impl LeaseReader {
     }
 
     pub fn read_leases(&self, lease_file: &str) -> Result<Vec<DhcpLease>> {
+        self.read_leases_with(lease_file, &LeaseQuery::default())
+    }
+
+    pub fn read_leases_with(&self, lease_file: &str, query: &LeaseQuery) -> Result<Vec<DhcpLease>> {
         let path = Path::new(lease_file);
         if !path.exists() {
             return Ok(vec![]);
         }
 
         let content = std::fs::read_to_string(path)?;
-        let mut leases = vec![];
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+
+        let mut leases: Vec<(Option<i64>, DhcpLease)> = Vec::new();
 
         for line in content.lines() {
             // dnsmasq lease format: timestamp mac ip hostname client-id
             // Example: 1234567890 aa:bb:cc:dd:ee:ff 10.33.0.100 myhost *
             let parts: Vec<&str> = line.split_whitespace().collect();
-            if parts.len() >= 4 {
-                let timestamp = parts[0];
-                let mac = parts[1].to_string();
-                let ip = parts[2].to_string();
-                let hostname = if parts[3] != "*" {
-                    Some(parts[3].to_string())
-                } else {
-                    None
-                };
-
-                // Convert timestamp to expiry time
-                let expires = if let Ok(ts) = timestamp.parse::<i64>() {
-                    use std::time::{SystemTime, UNIX_EPOCH};
-                    let now = SystemTime::now()
-                        .duration_since(UNIX_EPOCH)
-                        .unwrap()
-                        .as_secs() as i64;
-
-                    if ts > now {
-                        let remaining = ts - now;
-                        Some(format!("{}s", remaining))
-                    } else {
-                        Some("expired".to_string())
-                    }
+            if parts.len() < 4 {
+                continue;
+            }
+
+            let timestamp = parts[0].parse::<i64>().ok();
+
+            // Filter before allocating, so incremental polls of a large file
+            // only pay for the leases they return.
+            if let Some(since) = query.since
+                && timestamp.is_none_or(|ts| ts <= since)
+            {
+                continue;
+            }
+
+            let hostname = if parts[3] != "*" {
+                Some(parts[3].to_string())
+            } else {
+                None
+            };
+
+            // Convert timestamp to expiry time
+            let expires = timestamp.map(|ts| {
+                if ts > now {
+                    format!("{}s", ts - now)
                 } else {
-                    None
-                };
+                    "expired".to_string()
+                }
+            });
 
-                leases.push(DhcpLease {
-                    ip,
-                    mac,
+            leases.push((
+                timestamp,
+                DhcpLease {
+                    ip: parts[2].to_string(),
+                    mac: parts[1].to_string(),
                     hostname,
                     expires,
-                });
-            }
+                },
+            ));
         }
 
-        Ok(leases)
+        if let Some(limit) = query.limit {
+            // Stable sort keeps file order among leases with the same expiry.
+            leases.sort_by_key(|(timestamp, _)| Reverse(*timestamp));
+            leases.truncate(limit);
+        }
+
+        Ok(leases.into_iter().map(|(_, lease)| lease).collect())
     }
 
     pub fn read_default_leases(&self) -> Result<Vec<DhcpLease>> {
-        self.read_leases("/var/lib/misc/dnsmasq.leases")
+        self.read_leases(DEFAULT_LEASE_FILE)
+    }
+
+    pub fn read_default_leases_with(&self, query: &LeaseQuery) -> Result<Vec<DhcpLease>> {
+        self.read_leases_with(DEFAULT_LEASE_FILE, query)
+    }
+
+    /// Leases in `lease_file` grouped by the routed DHCP network of
+    /// `topology` whose subnet holds them. Every such network gets an entry,
+    /// empty when it has no leases; leases outside all of them are dropped.
+    pub fn read_leases_by_network(
+        &self,
+        lease_file: &str,
+        topology: &Topology,
+    ) -> Result<BTreeMap<String, Vec<DhcpLease>>> {
+        let networks: Vec<_> = topology
+            .networks
+            .iter()
+            .filter_map(|(name, network)| match network {
+                Network::Routed(routed) if routed.dhcp => Some((name, routed)),
+                _ => None,
+            })
+            .collect();
+
+        let mut by_network: BTreeMap<String, Vec<DhcpLease>> = networks
+            .iter()
+            .map(|(name, _)| (name.to_string(), vec![]))
+            .collect();
+
+        for lease in self.read_leases(lease_file)? {
+            let Ok(ip) = lease.ip.parse::<Ipv4Addr>() else {
+                continue;
+            };
+            if let Some((name, _)) = networks.iter().find(|(_, routed)| routed.contains_host(ip))
+                && let Some(leases) = by_network.get_mut(name.as_str())
+            {
+                leases.push(lease);
+            }
+        }
+
+        Ok(by_network)
+    }
+
+    pub fn read_default_leases_by_network(
+        &self,
+        topology: &Topology,
+    ) -> Result<BTreeMap<String, Vec<DhcpLease>>> {
+        self.read_leases_by_network(DEFAULT_LEASE_FILE, topology)
+    }
+
+    /// Current leases of the DHCP network `name` in `topology`
+    pub fn read_leases_for_network(
+        &self,
+        topology: &Topology,
+        name: &str,
+    ) -> Result<Vec<DhcpLease>> {
+        self.read_default_leases_by_network(topology)?
+            .remove(name)
+            .ok_or_else(|| anyhow::anyhow!("Network '{}' does not serve DHCP", name))
     }
 }
 
@@ -72,3 +181,137 @@ impl Default for LeaseReader {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE_TS: i64 = 4_000_000_000;
+    const LEASE_COUNT: i64 = 20_000;
+
+    fn write_large_lease_file(name: &str) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("{}-{}", name, std::process::id()));
+        let mut content = String::new();
+        for i in 0..LEASE_COUNT {
+            // Interleave old and new expiries so results can't rely on file order.
+            let ts = if i % 2 == 0 { BASE_TS + i } else { BASE_TS - i };
+            content.push_str(&format!(
+                "{} 52:54:00:{:02x}:{:02x}:{:02x} 10.{}.{}.{} host{} *\n",
+                ts,
+                (i >> 16) & 0xff,
+                (i >> 8) & 0xff,
+                i & 0xff,
+                (i >> 16) & 0xff,
+                (i >> 8) & 0xff,
+                i & 0xff,
+                i
+            ));
+        }
+        content.push_str("malformed line\n");
+        std::fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn leases_are_grouped_by_the_network_holding_them() {
+        let topology = Topology::from_toml(
+            r#"
+version = 1
+
+[interfaces]
+
+[networks.lab]
+type = "routed"
+cidr = "10.50.0.0/24"
+gw_ip = "10.50.0.1"
+dhcp = true
+
+[networks.dev]
+type = "routed"
+cidr = "10.51.0.0/24"
+gw_ip = "10.51.0.1"
+dhcp = true
+
+[networks.static]
+type = "routed"
+cidr = "10.52.0.0/24"
+gw_ip = "10.52.0.1"
+"#,
+        )
+        .unwrap();
+
+        let path =
+            std::env::temp_dir().join(format!("gw-leases-by-network-{}", std::process::id()));
+        std::fs::write(
+            &path,
+            "4000000000 52:54:00:00:00:01 10.50.0.20 a *\n\
+             4000000000 52:54:00:00:00:02 10.50.0.21 b *\n\
+             4000000000 52:54:00:00:00:03 10.99.0.5 stray *\n",
+        )
+        .unwrap();
+        let by_network = LeaseReader::new()
+            .read_leases_by_network(path.to_str().unwrap(), &topology)
+            .unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let counts: Vec<_> = by_network
+            .iter()
+            .map(|(name, leases)| (name.as_str(), leases.len()))
+            .collect();
+        assert_eq!(counts, vec![("dev", 0), ("lab", 2)]);
+
+        // A missing lease file is just no leases yet.
+        let by_network = LeaseReader::new()
+            .read_leases_by_network("/nonexistent/dnsmasq.leases", &topology)
+            .unwrap();
+        assert!(by_network.values().all(Vec::is_empty));
+    }
+
+    #[test]
+    fn default_query_reads_every_lease_in_file_order() {
+        let path = write_large_lease_file("gw-leases-full");
+        let leases = LeaseReader::new()
+            .read_leases(path.to_str().unwrap())
+            .unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(leases.len(), LEASE_COUNT as usize);
+        assert_eq!(leases[0].hostname.as_deref(), Some("host0"));
+        assert_eq!(leases[1].hostname.as_deref(), Some("host1"));
+    }
+
+    #[test]
+    fn since_returns_only_newer_leases() {
+        let path = write_large_lease_file("gw-leases-since");
+        let query = LeaseQuery::default().since(BASE_TS + LEASE_COUNT - 10);
+        let leases = LeaseReader::new()
+            .read_leases_with(path.to_str().unwrap(), &query)
+            .unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let hostnames: Vec<_> = leases
+            .iter()
+            .filter_map(|l| l.hostname.as_deref())
+            .collect();
+        assert_eq!(
+            hostnames,
+            vec!["host19992", "host19994", "host19996", "host19998"]
+        );
+    }
+
+    #[test]
+    fn limit_keeps_most_recent_leases() {
+        let path = write_large_lease_file("gw-leases-limit");
+        let query = LeaseQuery::default().limit(3);
+        let leases = LeaseReader::new()
+            .read_leases_with(path.to_str().unwrap(), &query)
+            .unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let hostnames: Vec<_> = leases
+            .iter()
+            .filter_map(|l| l.hostname.as_deref())
+            .collect();
+        assert_eq!(hostnames, vec!["host19998", "host19996", "host19994"]);
+    }
+}
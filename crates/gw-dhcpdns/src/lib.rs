@@ -1,6 +1,8 @@
+pub mod coredns;
 pub mod dnsmasq;
 pub mod leases;
 
+pub use coredns::*;
 pub use dnsmasq::*;
 pub use leases::*;
 
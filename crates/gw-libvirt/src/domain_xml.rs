@@ -0,0 +1,274 @@
+//! Minimal reader for the parts of `virsh dumpxml` and `virsh net-dumpxml`
+//! output Ghostwarden uses.
+//!
+//! libvirt emits a stable, attribute-based format, so the few fields needed
+//! here are pulled out of start tags rather than pulling in an XML crate.
+
+use ipnet::IpNet;
+use std::fmt;
+use std::net::IpAddr;
+
+/// Domain details taken from `virsh dumpxml <name>`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DomainXml {
+    /// Runtime ID; only present while the domain is running.
+    pub id: Option<i32>,
+    pub interfaces: Vec<VmInterface>,
+}
+
+/// One `<interface>` device of a domain.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VmInterface {
+    /// Interface type (`bridge`, `network`, `direct`, ...).
+    pub kind: String,
+    /// Host-side device (`vnet0`); only present while the domain is running.
+    pub target: Option<String>,
+    pub mac: Option<String>,
+    pub model: Option<String>,
+    /// Bridge, network or device the interface is attached to.
+    pub source: Option<String>,
+}
+
+impl fmt::Display for VmInterface {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = self.target.as_deref().unwrap_or(self.kind.as_str());
+        let details: Vec<&str> = [&self.mac, &self.model, &self.source]
+            .into_iter()
+            .filter_map(|field| field.as_deref())
+            .collect();
+
+        if details.is_empty() {
+            write!(f, "{}", name)
+        } else {
+            write!(f, "{} ({})", name, details.join(", "))
+        }
+    }
+}
+
+/// Extract the domain ID and interface devices from `virsh dumpxml` output.
+pub fn parse_domain_xml(xml: &str) -> DomainXml {
+    let id = start_tag(xml, "domain")
+        .and_then(|tag| attribute(tag, "id"))
+        .and_then(|id| id.parse().ok());
+
+    let mut interfaces = Vec::new();
+    let mut rest = xml;
+    while let Some((tag, body, after)) = element(rest, "interface") {
+        interfaces.push(VmInterface {
+            kind: attribute(tag, "type").unwrap_or_default(),
+            target: start_tag(body, "target").and_then(|t| attribute(t, "dev")),
+            mac: start_tag(body, "mac").and_then(|t| attribute(t, "address")),
+            model: start_tag(body, "model").and_then(|t| attribute(t, "type")),
+            source: start_tag(body, "source").and_then(|t| {
+                ["bridge", "network", "dev"]
+                    .into_iter()
+                    .find_map(|name| attribute(t, name))
+            }),
+        });
+        rest = after;
+    }
+
+    DomainXml { id, interfaces }
+}
+
+/// Network details taken from `virsh net-dumpxml <name>`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NetworkXml {
+    pub bridge: Option<String>,
+    /// Subnet of the first `<ip>` element, e.g. `192.168.122.0/24`.
+    pub cidr: Option<IpNet>,
+}
+
+/// Extract the bridge and subnet from `virsh net-dumpxml` output.
+pub fn parse_network_xml(xml: &str) -> NetworkXml {
+    let bridge = start_tag(xml, "bridge").and_then(|tag| attribute(tag, "name"));
+
+    // libvirt writes either `netmask='255.255.255.0'` or `prefix='24'`
+    let cidr = start_tag(xml, "ip").and_then(|tag| {
+        let address: IpAddr = attribute(tag, "address")?.parse().ok()?;
+        let net = match attribute(tag, "prefix") {
+            Some(prefix) => IpNet::new(address, prefix.parse().ok()?).ok()?,
+            None => IpNet::with_netmask(address, attribute(tag, "netmask")?.parse().ok()?).ok()?,
+        };
+        Some(net.trunc())
+    });
+
+    NetworkXml { bridge, cidr }
+}
+
+/// Normalize `virsh domstate` output ("shut off", "in shutdown") to the
+/// single-token libvirt state names.
+pub fn normalize_state(state: &str) -> String {
+    let state = state.trim().to_lowercase();
+    match state.as_str() {
+        "shut off" => "shutoff".to_string(),
+        "in shutdown" => "shutdown".to_string(),
+        "" => "unknown".to_string(),
+        other => other.split_whitespace().collect::<Vec<_>>().join("-"),
+    }
+}
+
+/// Attribute text of the first `<name ...>` start tag in `xml`.
+fn start_tag<'a>(xml: &'a str, name: &str) -> Option<&'a str> {
+    let start = find_start(xml, name)?;
+    let end = xml[start..].find('>')?;
+    Some(&xml[start..start + end])
+}
+
+/// Split out the first `<name ...>...</name>` element as (attributes, body,
+/// remaining input). Self-closing elements have an empty body.
+fn element<'a>(xml: &'a str, name: &str) -> Option<(&'a str, &'a str, &'a str)> {
+    let start = find_start(xml, name)?;
+    let tag_end = start + xml[start..].find('>')?;
+    let tag = &xml[start..tag_end];
+    if tag.ends_with('/') {
+        return Some((tag, "", &xml[tag_end + 1..]));
+    }
+
+    let close = format!("</{}>", name);
+    let body_end = tag_end + 1 + xml[tag_end + 1..].find(&close)?;
+    Some((
+        tag,
+        &xml[tag_end + 1..body_end],
+        &xml[body_end + close.len()..],
+    ))
+}
+
+/// Offset just past `<name` for the first start tag called exactly `name`.
+fn find_start(xml: &str, name: &str) -> Option<usize> {
+    let needle = format!("<{}", name);
+    let mut offset = 0;
+    while let Some(pos) = xml[offset..].find(&needle) {
+        let after = offset + pos + needle.len();
+        if xml[after..].starts_with(|c: char| c.is_whitespace() || c == '>' || c == '/') {
+            return Some(after);
+        }
+        offset = after;
+    }
+    None
+}
+
+fn attribute(tag: &str, name: &str) -> Option<String> {
+    for quote in ['\'', '"'] {
+        let needle = format!(" {}={}", name, quote);
+        if let Some(pos) = tag.find(&needle) {
+            let value_start = pos + needle.len();
+            let value_end = value_start + tag[value_start..].find(quote)?;
+            return Some(unescape(&tag[value_start..value_end]));
+        }
+    }
+    None
+}
+
+fn unescape(value: &str) -> String {
+    value
+        .replace("&apos;", "'")
+        .replace("&quot;", "\"")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RUNNING_DOMAIN: &str = r#"<domain type='kvm' id='7'>
+  <name>web server</name>
+  <devices>
+    <interface type='bridge'>
+      <mac address='52:54:00:aa:bb:cc'/>
+      <source bridge='br-lab'/>
+      <target dev='vnet0'/>
+      <model type='virtio'/>
+      <alias name='net0'/>
+    </interface>
+    <interface type="network">
+      <mac address="52:54:00:11:22:33"/>
+      <source network="default" portid="0d6f"/>
+      <target dev="vnet1"/>
+      <model type="e1000"/>
+    </interface>
+    <interfaces-not-a-device/>
+  </devices>
+</domain>
+"#;
+
+    #[test]
+    fn extracts_interface_mac_model_and_source() {
+        let domain = parse_domain_xml(RUNNING_DOMAIN);
+
+        assert_eq!(domain.id, Some(7));
+        assert_eq!(
+            domain.interfaces,
+            vec![
+                VmInterface {
+                    kind: "bridge".into(),
+                    target: Some("vnet0".into()),
+                    mac: Some("52:54:00:aa:bb:cc".into()),
+                    model: Some("virtio".into()),
+                    source: Some("br-lab".into()),
+                },
+                VmInterface {
+                    kind: "network".into(),
+                    target: Some("vnet1".into()),
+                    mac: Some("52:54:00:11:22:33".into()),
+                    model: Some("e1000".into()),
+                    source: Some("default".into()),
+                },
+            ]
+        );
+        assert_eq!(
+            domain.interfaces[0].to_string(),
+            "vnet0 (52:54:00:aa:bb:cc, virtio, br-lab)"
+        );
+    }
+
+    #[test]
+    fn stopped_domains_have_no_id_or_target() {
+        let domain = parse_domain_xml(
+            "<domain type='kvm'>\n  <devices>\n    <interface type='bridge'>\n      <mac address='52:54:00:aa:bb:cc'/>\n    </interface>\n  </devices>\n</domain>",
+        );
+
+        assert_eq!(domain.id, None);
+        assert_eq!(domain.interfaces[0].target, None);
+        assert_eq!(
+            domain.interfaces[0].to_string(),
+            "bridge (52:54:00:aa:bb:cc)"
+        );
+    }
+
+    #[test]
+    fn extracts_network_bridge_and_subnet() {
+        let nat = parse_network_xml(
+            r#"<network>
+  <name>default</name>
+  <forward mode='nat'/>
+  <bridge name='virbr0' stp='on' delay='0'/>
+  <ip address='192.168.122.1' netmask='255.255.255.0'>
+    <dhcp><range start='192.168.122.2' end='192.168.122.254'/></dhcp>
+  </ip>
+</network>"#,
+        );
+        assert_eq!(nat.bridge.as_deref(), Some("virbr0"));
+        assert_eq!(nat.cidr, Some("192.168.122.0/24".parse().unwrap()));
+
+        let prefixed = parse_network_xml(
+            "<network><name>lab</name><bridge name='br-lab'/><ip address='10.0.5.1' prefix='26'></ip></network>",
+        );
+        assert_eq!(prefixed.cidr, Some("10.0.5.0/26".parse().unwrap()));
+
+        let bridged = parse_network_xml(
+            "<network><name>host</name><forward mode='bridge'/><bridge name='br0'/></network>",
+        );
+        assert_eq!(bridged.bridge.as_deref(), Some("br0"));
+        assert_eq!(bridged.cidr, None);
+    }
+
+    #[test]
+    fn normalizes_multi_word_states() {
+        assert_eq!(normalize_state("shut off\n"), "shutoff");
+        assert_eq!(normalize_state("in shutdown"), "shutdown");
+        assert_eq!(normalize_state("running"), "running");
+    }
+}
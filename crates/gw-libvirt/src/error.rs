@@ -0,0 +1,83 @@
+use std::io;
+
+pub type Result<T, E = LibvirtError> = std::result::Result<T, E>;
+
+/// Errors returned by [`crate::LibvirtManager`].
+///
+/// `virsh` failures are classified from its stderr where libvirt's message is
+/// stable enough to match, so callers can react to a missing VM or a duplicate
+/// interface without parsing strings themselves.
+#[derive(Debug, thiserror::Error)]
+pub enum LibvirtError {
+    #[error("virsh is not installed or not in PATH")]
+    VirshMissing,
+
+    #[error("Failed to run virsh: {0}")]
+    Io(#[source] io::Error),
+
+    #[error("VM '{0}' not found")]
+    DomainNotFound(String),
+
+    #[error("Interface is already attached to VM '{vm}': {stderr}")]
+    AlreadyAttached { vm: String, stderr: String },
+
+    #[error("Failed to attach interface: {stderr}")]
+    AttachFailed { stderr: String },
+
+    #[error("Failed to detach interface: {stderr}")]
+    DetachFailed { stderr: String },
+
+    #[error("Interface {mac} is still attached to VM '{vm}' after {}s", timeout.as_secs_f32())]
+    DetachTimeout {
+        vm: String,
+        mac: String,
+        timeout: std::time::Duration,
+    },
+
+    #[error("Failed to define libvirt network: {stderr}")]
+    NetworkDefineFailed { stderr: String },
+
+    #[error("Failed to delete libvirt network: {stderr}")]
+    NetworkDeleteFailed { stderr: String },
+
+    #[error("virsh {command} failed: {stderr}")]
+    CommandFailed { command: String, stderr: String },
+
+    #[error("Invalid CIDR format: {0}")]
+    InvalidCidr(String),
+
+    #[error("Invalid interface options: {0}")]
+    InvalidOptions(String),
+
+    #[error("{context}: {source}")]
+    TempFile {
+        context: String,
+        #[source]
+        source: io::Error,
+    },
+}
+
+impl From<io::Error> for LibvirtError {
+    fn from(err: io::Error) -> Self {
+        if err.kind() == io::ErrorKind::NotFound {
+            Self::VirshMissing
+        } else {
+            Self::Io(err)
+        }
+    }
+}
+
+/// libvirt reports unknown domains as "failed to get domain" or "Domain not found".
+pub(crate) fn is_domain_not_found(stderr: &str) -> bool {
+    stderr.contains("failed to get domain") || stderr.contains("Domain not found")
+}
+
+/// Duplicate target devices and MACs are both rejected as "already exists" /
+/// "already in use".
+pub(crate) fn is_already_attached(stderr: &str) -> bool {
+    stderr.contains("already exists") || stderr.contains("already in use")
+}
+
+pub(crate) fn is_network_not_found(stderr: &str) -> bool {
+    stderr.contains("Network not found") || stderr.contains("failed to get network")
+}
@@ -1,4 +1,16 @@
-use anyhow::{Context, Result};
+mod domain_xml;
+mod error;
+
+pub use domain_xml::{
+    DomainXml, NetworkXml, VmInterface, normalize_state, parse_domain_xml, parse_network_xml,
+};
+pub use error::{LibvirtError, Result};
+
+use error::{is_already_attached, is_domain_not_found, is_network_not_found};
+use gw_core::{CommandRunner, SystemCommandRunner};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
 
 /// Network interface model types
 #[derive(Debug, Clone, Copy)]
@@ -20,14 +32,63 @@ impl InterfaceModel {
     }
 }
 
+/// Smallest MTU accepted for a VM interface (the IPv4 minimum datagram size)
+pub const MIN_INTERFACE_MTU: u32 = 576;
+
+/// How long [`LibvirtManager::detach_vm_interface`] waits for the guest
+/// to release an interface by default
+pub const DETACH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Delay between `virsh domiflist` polls while waiting for a detach
+const DETACH_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Interface detach options
+#[derive(Debug, Clone)]
+pub struct DetachOptions {
+    pub live: bool, // Hot-unplug if VM is running
+    /// Poll `virsh domiflist` until the MAC is gone, since virsh returns
+    /// before the guest has released the device
+    pub wait_for_detach: bool,
+    /// How long to wait before giving up with [`LibvirtError::DetachTimeout`]
+    pub timeout: Duration,
+}
+
+impl Default for DetachOptions {
+    fn default() -> Self {
+        Self {
+            live: false,
+            wait_for_detach: false,
+            timeout: DETACH_TIMEOUT,
+        }
+    }
+}
+
 /// Interface attachment options
 #[derive(Debug, Clone)]
 pub struct InterfaceOptions {
     pub model: InterfaceModel,
     pub mac_address: Option<String>,
     pub bandwidth_in_kbps: Option<u32>,
+    /// Inbound peak rate in KiB/s; requires `bandwidth_in_kbps`
+    pub bandwidth_in_peak_kbps: Option<u32>,
+    /// Inbound burst size in KiB; requires `bandwidth_in_kbps`
+    pub bandwidth_in_burst_kb: Option<u32>,
     pub bandwidth_out_kbps: Option<u32>,
+    /// Outbound peak rate in KiB/s; requires `bandwidth_out_kbps`
+    pub bandwidth_out_peak_kbps: Option<u32>,
+    /// Outbound burst size in KiB; requires `bandwidth_out_kbps`
+    pub bandwidth_out_burst_kb: Option<u32>,
     pub live: bool, // Hot-plug if VM is running
+    /// Emit `<port isolated='yes'/>` so libvirt isolates the tap on every start
+    pub isolated: bool,
+    /// 802.1Q tag for the interface, emitted as `<vlan><tag id='N'/></vlan>`
+    pub vlan: Option<u16>,
+    /// Guest MTU, emitted as `<mtu size='N'/>` (at least 576)
+    pub mtu: Option<u32>,
+    /// virtio multiqueue count, emitted as `<driver name='vhost' queues='N'/>`
+    pub queues: Option<u8>,
+    /// Attach to an Open vSwitch bridge via `<virtualport type='openvswitch'/>`
+    pub ovs: bool,
 }
 
 impl Default for InterfaceOptions {
@@ -36,17 +97,60 @@ impl Default for InterfaceOptions {
             model: InterfaceModel::Virtio,
             mac_address: None,
             bandwidth_in_kbps: None,
+            bandwidth_in_peak_kbps: None,
+            bandwidth_in_burst_kb: None,
             bandwidth_out_kbps: None,
+            bandwidth_out_peak_kbps: None,
+            bandwidth_out_burst_kb: None,
             live: false,
+            isolated: false,
+            vlan: None,
+            mtu: None,
+            queues: None,
+            ovs: false,
         }
     }
 }
 
-pub struct LibvirtManager;
+pub struct LibvirtManager {
+    runner: Arc<dyn CommandRunner>,
+    /// libvirt connection URI passed as `virsh -c`; `None` uses virsh's default
+    /// (`qemu:///session` for non-root users unless `LIBVIRT_DEFAULT_URI` is set).
+    connection_uri: Option<String>,
+}
 
 impl LibvirtManager {
     pub fn new() -> Self {
-        Self
+        Self {
+            runner: Arc::new(SystemCommandRunner),
+            connection_uri: None,
+        }
+    }
+
+    /// Connect to `uri` (e.g. `qemu:///system`) instead of the default connection.
+    pub fn with_uri(mut self, uri: impl Into<String>) -> Self {
+        self.connection_uri = Some(uri.into());
+        self
+    }
+
+    /// Run `virsh` through `runner` instead of spawning it directly.
+    pub fn with_runner(mut self, runner: Arc<dyn CommandRunner>) -> Self {
+        self.runner = runner;
+        self
+    }
+
+    async fn virsh(&self, args: &[&str]) -> Result<std::process::Output> {
+        let output = match &self.connection_uri {
+            Some(uri) => {
+                let args: Vec<&str> = ["-c", uri.as_str()]
+                    .into_iter()
+                    .chain(args.iter().copied())
+                    .collect();
+                self.runner.run("virsh", &args, None).await
+            }
+            None => self.runner.run("virsh", args, None).await,
+        };
+        Ok(output?)
     }
 
     /// Generate a random MAC address in the range 52:54:00:xx:xx:xx (libvirt default)
@@ -60,68 +164,129 @@ impl LibvirtManager {
         )
     }
 
+    /// Derive a stable MAC address in 52:54:00:xx:xx:xx from the VM and bridge
+    /// names and the NIC's `index` on that bridge, so re-attaching the same VM
+    /// to the same bridge keeps its MAC (and any DHCP lease tied to it) while a
+    /// second NIC on the bridge gets a different one.
+    pub fn generate_mac_for(vm_name: &str, bridge: &str, index: u32) -> String {
+        use sha2::{Digest, Sha256};
+        let mut hasher = Sha256::new()
+            .chain_update(vm_name.as_bytes())
+            .chain_update([0])
+            .chain_update(bridge.as_bytes());
+        // The first NIC keeps the MAC derived before indexes were added
+        if index > 0 {
+            hasher.update([0]);
+            hasher.update(index.to_be_bytes());
+        }
+        let digest = hasher.finalize();
+        format!(
+            "52:54:00:{:02x}:{:02x}:{:02x}",
+            digest[0], digest[1], digest[2]
+        )
+    }
+
+    /// The derived MAC with the lowest index not already used by one of the
+    /// VM's `interfaces`.
+    fn next_mac_for(vm_name: &str, bridge: &str, interfaces: &[VmInterface]) -> String {
+        (0..)
+            .map(|index| Self::generate_mac_for(vm_name, bridge, index))
+            .find(|mac| {
+                !interfaces.iter().any(|iface| {
+                    iface
+                        .mac
+                        .as_deref()
+                        .is_some_and(|used| used.eq_ignore_ascii_case(mac))
+                })
+            })
+            .expect("unbounded index range")
+    }
+
     /// List all VMs and their network interfaces
     pub async fn list_vms(&self) -> Result<Vec<VmInfo>> {
-        use tokio::process::Command;
-
-        let output = Command::new("virsh")
-            .arg("list")
-            .arg("--all")
-            .output()
-            .await
-            .context("Failed to run virsh list")?;
+        // `--name` prints one name per line, so names with spaces survive.
+        let output = self.virsh(&["list", "--all", "--name"]).await?;
 
         if !output.status.success() {
-            anyhow::bail!("virsh list failed");
+            return Err(LibvirtError::CommandFailed {
+                command: "list".to_string(),
+                stderr: stderr_of(&output),
+            });
         }
 
         let stdout = String::from_utf8_lossy(&output.stdout);
         let mut vms = vec![];
 
-        for line in stdout.lines().skip(2) {
-            // Skip header lines
-            let parts: Vec<&str> = line.split_whitespace().collect();
-            if parts.len() >= 3 {
-                let id = parts[0].parse().ok();
-                let name = parts[1].to_string();
-                let state = parts[2..].join(" ");
-
-                vms.push(VmInfo {
-                    id,
-                    name: name.clone(),
-                    state,
-                    interfaces: self.get_vm_interfaces(&name).await?,
-                });
-            }
+        for name in stdout
+            .lines()
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+        {
+            let domain = self.inspect_vm(name).await?;
+            vms.push(VmInfo {
+                id: domain.id,
+                name: name.to_string(),
+                state: self.vm_state(name).await?,
+                interfaces: domain.interfaces,
+            });
         }
 
         Ok(vms)
     }
 
-    async fn get_vm_interfaces(&self, vm_name: &str) -> Result<Vec<String>> {
-        use tokio::process::Command;
+    /// Read a VM's ID and interfaces from `virsh dumpxml`
+    async fn inspect_vm(&self, vm_name: &str) -> Result<DomainXml> {
+        let output = self.virsh(&["dumpxml", vm_name]).await?;
 
-        let output = Command::new("virsh")
-            .arg("domiflist")
-            .arg(vm_name)
-            .output()
-            .await?;
+        if !output.status.success() {
+            // The VM may have been undefined since it was listed.
+            return Ok(DomainXml::default());
+        }
+
+        Ok(parse_domain_xml(&String::from_utf8_lossy(&output.stdout)))
+    }
+
+    async fn vm_state(&self, vm_name: &str) -> Result<String> {
+        let output = self.virsh(&["domstate", vm_name]).await?;
 
         if !output.status.success() {
-            return Ok(vec![]);
+            return Ok("unknown".to_string());
         }
 
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        let mut interfaces = vec![];
+        Ok(normalize_state(&String::from_utf8_lossy(&output.stdout)))
+    }
 
-        for line in stdout.lines().skip(2) {
-            let parts: Vec<&str> = line.split_whitespace().collect();
-            if parts.len() >= 3 {
-                interfaces.push(parts[0].to_string());
-            }
+    /// List all libvirt networks with their bridge, subnet and state
+    pub async fn list_libvirt_networks(&self) -> Result<Vec<LibvirtNetInfo>> {
+        let output = self.virsh(&["net-list", "--all"]).await?;
+
+        if !output.status.success() {
+            return Err(LibvirtError::CommandFailed {
+                command: "net-list".to_string(),
+                stderr: stderr_of(&output),
+            });
+        }
+
+        let mut networks = vec![];
+        for (name, active, autostart) in parse_net_list(&String::from_utf8_lossy(&output.stdout)) {
+            let output = self.virsh(&["net-dumpxml", &name]).await?;
+            // The network may have been undefined since it was listed.
+            let details = if output.status.success() {
+                parse_network_xml(&String::from_utf8_lossy(&output.stdout))
+            } else {
+                NetworkXml::default()
+            };
+
+            networks.push(LibvirtNetInfo {
+                name,
+                bridge: details.bridge,
+                active,
+                autostart,
+                cidr: details.cidr,
+            });
         }
 
-        Ok(interfaces)
+        Ok(networks)
     }
 
     /// Attach VM to a bridge network (simple version)
@@ -144,8 +309,6 @@ impl LibvirtManager {
         tap_name: Option<&str>,
         options: &InterfaceOptions,
     ) -> Result<()> {
-        use tokio::process::Command;
-
         println!(
             "Attaching VM {} to bridge {} with model {}",
             vm_name,
@@ -153,62 +316,45 @@ impl LibvirtManager {
             options.model.as_str()
         );
 
-        // Generate MAC address if not provided
-        let mac = options
-            .mac_address
-            .clone()
-            .unwrap_or_else(Self::generate_mac_address);
-
-        // Build interface XML
-        let mut xml = format!(
-            r#"<interface type='bridge'>
-  <source bridge='{}'/>
-  <model type='{}'/>
-  <mac address='{}'/>
-"#,
-            bridge,
-            options.model.as_str(),
-            mac
-        );
-
-        // Add target device if specified
-        if let Some(tap) = tap_name {
-            xml.push_str(&format!("  <target dev='{}'/>\n", tap));
-        }
-
-        // Add bandwidth limiting if specified
-        if options.bandwidth_in_kbps.is_some() || options.bandwidth_out_kbps.is_some() {
-            xml.push_str("  <bandwidth>\n");
-            if let Some(inbound) = options.bandwidth_in_kbps {
-                xml.push_str(&format!("    <inbound average='{}'/>\n", inbound));
-            }
-            if let Some(outbound) = options.bandwidth_out_kbps {
-                xml.push_str(&format!("    <outbound average='{}'/>\n", outbound));
+        // Derive the MAC from the VM, bridge and NIC index if not provided;
+        // anonymous attaches fall back to a random one
+        let mac = match &options.mac_address {
+            Some(mac) => mac.clone(),
+            None if vm_name.is_empty() => Self::generate_mac_address(),
+            None => {
+                let existing = self.inspect_vm(vm_name).await?.interfaces;
+                Self::next_mac_for(vm_name, bridge, &existing)
             }
-            xml.push_str("  </bandwidth>\n");
-        }
+        };
 
-        xml.push_str("</interface>");
+        let xml = interface_xml(bridge, tap_name, &mac, options)?;
 
-        // Write XML to temp file
-        let temp_file = "/tmp/gw-interface.xml";
-        std::fs::write(temp_file, &xml)?;
+        // Write XML to a private temp file; it is removed once virsh has read it
+        let temp_file = TempXml::new("gw-interface", &xml)?;
 
         // Attach interface
-        let mut cmd = Command::new("virsh");
-        cmd.arg("attach-device").arg(vm_name).arg(temp_file);
+        let mut args = vec!["attach-device", vm_name, temp_file.path()?];
 
         if options.live {
-            cmd.arg("--live").arg("--config"); // Hot-plug and persist
+            args.extend(["--live", "--config"]); // Hot-plug and persist
         } else {
-            cmd.arg("--config"); // Only update config
+            args.push("--config"); // Only update config
         }
 
-        let output = cmd.output().await?;
+        let output = self.virsh(&args).await?;
 
         if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            anyhow::bail!("Failed to attach interface: {}", stderr);
+            let stderr = stderr_of(&output);
+            return Err(if is_domain_not_found(&stderr) {
+                LibvirtError::DomainNotFound(vm_name.to_string())
+            } else if is_already_attached(&stderr) {
+                LibvirtError::AlreadyAttached {
+                    vm: vm_name.to_string(),
+                    stderr,
+                }
+            } else {
+                LibvirtError::AttachFailed { stderr }
+            });
         }
 
         println!(
@@ -225,12 +371,10 @@ impl LibvirtManager {
         bridge_name: &str,
         cidr: &str,
     ) -> Result<()> {
-        use tokio::process::Command;
-
         // Parse CIDR to extract network address and gateway
         let parts: Vec<&str> = cidr.split('/').collect();
         if parts.len() != 2 {
-            anyhow::bail!("Invalid CIDR format: {}", cidr);
+            return Err(LibvirtError::InvalidCidr(cidr.to_string()));
         }
 
         let network_addr = parts[0];
@@ -249,33 +393,20 @@ impl LibvirtManager {
         );
 
         // Write XML to temp file
-        let temp_file = "/tmp/gw-network.xml";
-        std::fs::write(temp_file, &xml)?;
+        let temp_file = TempXml::new("gw-network", &xml)?;
 
         // Define network
-        let output = Command::new("virsh")
-            .arg("net-define")
-            .arg(temp_file)
-            .output()
-            .await?;
+        let output = self.virsh(&["net-define", temp_file.path()?]).await?;
 
         if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            anyhow::bail!("Failed to define libvirt network: {}", stderr);
+            return Err(LibvirtError::NetworkDefineFailed {
+                stderr: stderr_of(&output),
+            });
         }
 
         // Start and autostart network
-        Command::new("virsh")
-            .arg("net-start")
-            .arg(network_name)
-            .output()
-            .await?;
-
-        Command::new("virsh")
-            .arg("net-autostart")
-            .arg(network_name)
-            .output()
-            .await?;
+        self.virsh(&["net-start", network_name]).await?;
+        self.virsh(&["net-autostart", network_name]).await?;
 
         println!(
             "✅ Created libvirt network '{}' on bridge {}",
@@ -286,27 +417,17 @@ impl LibvirtManager {
 
     /// Delete a libvirt network
     pub async fn delete_libvirt_network(&self, network_name: &str) -> Result<()> {
-        use tokio::process::Command;
-
         // Stop network
-        let _ = Command::new("virsh")
-            .arg("net-destroy")
-            .arg(network_name)
-            .output()
-            .await;
+        let _ = self.virsh(&["net-destroy", network_name]).await;
 
         // Undefine network
-        let output = Command::new("virsh")
-            .arg("net-undefine")
-            .arg(network_name)
-            .output()
-            .await?;
+        let output = self.virsh(&["net-undefine", network_name]).await?;
 
         if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
+            let stderr = stderr_of(&output);
             // Ignore "network not found" errors
-            if !stderr.contains("Network not found") {
-                anyhow::bail!("Failed to delete libvirt network: {}", stderr);
+            if !is_network_not_found(&stderr) {
+                return Err(LibvirtError::NetworkDeleteFailed { stderr });
             }
         }
 
@@ -314,28 +435,86 @@ impl LibvirtManager {
         Ok(())
     }
 
-    /// Detach VM from a bridge
-    pub async fn detach_vm_interface(&self, vm_name: &str, interface: &str) -> Result<()> {
-        use tokio::process::Command;
+    /// Detach the bridge interface with `mac` from a VM, optionally waiting
+    /// until libvirt no longer lists it
+    pub async fn detach_vm_interface(
+        &self,
+        vm_name: &str,
+        mac: &str,
+        options: &DetachOptions,
+    ) -> Result<()> {
+        println!("Detaching interface {} from VM {}", mac, vm_name);
 
-        println!("Detaching interface {} from VM {}", interface, vm_name);
+        let mut args = vec!["detach-interface", vm_name, "bridge", "--mac", mac];
+        if options.live {
+            args.extend(["--live", "--config"]); // Hot-unplug and persist
+        } else {
+            args.push("--config"); // Only update config
+        }
 
-        let output = Command::new("virsh")
-            .arg("detach-interface")
-            .arg(vm_name)
-            .arg("bridge")
-            .arg("--config")
-            .output()
-            .await?;
+        let output = self.virsh(&args).await?;
 
         if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            anyhow::bail!("Failed to detach interface: {}", stderr);
+            let stderr = stderr_of(&output);
+            return Err(if is_domain_not_found(&stderr) {
+                LibvirtError::DomainNotFound(vm_name.to_string())
+            } else {
+                LibvirtError::DetachFailed { stderr }
+            });
         }
 
-        println!("✅ Detached interface from VM {}", vm_name);
+        if options.wait_for_detach {
+            self.wait_for_detach(vm_name, mac, options).await?;
+        }
+
+        println!("✅ Detached interface {} from VM {}", mac, vm_name);
         Ok(())
     }
+
+    /// Poll `virsh domiflist` until `mac` disappears or `options.timeout` elapses
+    async fn wait_for_detach(
+        &self,
+        vm_name: &str,
+        mac: &str,
+        options: &DetachOptions,
+    ) -> Result<()> {
+        // A config-only detach never leaves the live definition
+        let args: &[&str] = if options.live {
+            &["domiflist", vm_name]
+        } else {
+            &["domiflist", vm_name, "--inactive"]
+        };
+        let deadline = tokio::time::Instant::now() + options.timeout;
+
+        loop {
+            let output = self.virsh(args).await?;
+            if !output.status.success() {
+                let stderr = stderr_of(&output);
+                // An undefined VM has no interfaces left to wait for
+                if is_domain_not_found(&stderr) {
+                    return Ok(());
+                }
+                return Err(LibvirtError::CommandFailed {
+                    command: "domiflist".to_string(),
+                    stderr,
+                });
+            }
+
+            let listed = String::from_utf8_lossy(&output.stdout).to_lowercase();
+            if !listed.contains(&mac.to_lowercase()) {
+                return Ok(());
+            }
+
+            if tokio::time::Instant::now() >= deadline {
+                return Err(LibvirtError::DetachTimeout {
+                    vm: vm_name.to_string(),
+                    mac: mac.to_string(),
+                    timeout: options.timeout,
+                });
+            }
+            tokio::time::sleep(DETACH_POLL_INTERVAL).await;
+        }
+    }
 }
 
 impl Default for LibvirtManager {
@@ -344,10 +523,684 @@ impl Default for LibvirtManager {
     }
 }
 
+/// XML handed to `virsh` through a uniquely named file that only the current
+/// user can read, created with `O_EXCL` so an existing file or symlink is never
+/// reused. The file is removed when the guard is dropped.
+struct TempXml {
+    path: PathBuf,
+}
+
+impl TempXml {
+    fn new(prefix: &str, xml: &str) -> Result<Self> {
+        use rand::RngExt;
+        use std::io::Write;
+        use std::os::unix::fs::OpenOptionsExt;
+
+        let dir = std::env::temp_dir();
+        for _ in 0..16 {
+            let suffix: u64 = rand::rng().random();
+            let path = dir.join(format!("{}-{:016x}.xml", prefix, suffix));
+            let file = std::fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .mode(0o600)
+                .open(&path);
+
+            match file {
+                Ok(mut file) => {
+                    let guard = Self { path };
+                    file.write_all(xml.as_bytes())
+                        .map_err(|source| LibvirtError::TempFile {
+                            context: format!("Failed to write {}", guard.path.display()),
+                            source,
+                        })?;
+                    return Ok(guard);
+                }
+                Err(err) if err.kind() == std::io::ErrorKind::AlreadyExists => continue,
+                Err(source) => {
+                    return Err(LibvirtError::TempFile {
+                        context: format!("Failed to create {}", path.display()),
+                        source,
+                    });
+                }
+            }
+        }
+
+        Err(LibvirtError::TempFile {
+            context: format!("Could not create a unique temp file in {}", dir.display()),
+            source: std::io::ErrorKind::AlreadyExists.into(),
+        })
+    }
+
+    fn path(&self) -> Result<&str> {
+        self.path.to_str().ok_or_else(|| LibvirtError::TempFile {
+            context: format!("Temp path {} is not UTF-8", self.path.display()),
+            source: std::io::ErrorKind::InvalidFilename.into(),
+        })
+    }
+}
+
+impl Drop for TempXml {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Build the `<interface>` device XML passed to `virsh attach-device`
+fn interface_xml(
+    bridge: &str,
+    tap_name: Option<&str>,
+    mac: &str,
+    options: &InterfaceOptions,
+) -> Result<String> {
+    let invalid = |msg: String| Err(LibvirtError::InvalidOptions(msg));
+
+    if let Some(vlan) = options.vlan
+        && let Err(err) = gw_core::validate_vlan_id(vlan)
+    {
+        return invalid(err.to_string());
+    }
+    if let Some(mtu) = options.mtu
+        && mtu < MIN_INTERFACE_MTU
+    {
+        return invalid(format!(
+            "MTU {} is below the minimum of {}",
+            mtu, MIN_INTERFACE_MTU
+        ));
+    }
+    if options.queues == Some(0) {
+        return invalid("queues must be at least 1".to_string());
+    }
+    // `<port isolated>` is a Linux bridge port flag; libvirt rejects it for OVS
+    if options.ovs && options.isolated {
+        return invalid(
+            "Port isolation is only supported on Linux bridges, not Open vSwitch".to_string(),
+        );
+    }
+    // libvirt rejects peak/burst without an average rate
+    if options.bandwidth_in_kbps.is_none()
+        && (options.bandwidth_in_peak_kbps.is_some() || options.bandwidth_in_burst_kb.is_some())
+    {
+        return invalid("Inbound peak/burst require an inbound average rate".to_string());
+    }
+    if options.bandwidth_out_kbps.is_none()
+        && (options.bandwidth_out_peak_kbps.is_some() || options.bandwidth_out_burst_kb.is_some())
+    {
+        return invalid("Outbound peak/burst require an outbound average rate".to_string());
+    }
+
+    let mut xml = format!(
+        r#"<interface type='bridge'>
+  <source bridge='{}'/>
+  <model type='{}'/>
+  <mac address='{}'/>
+"#,
+        bridge,
+        options.model.as_str(),
+        mac
+    );
+
+    // Add target device if specified
+    if let Some(tap) = tap_name {
+        xml.push_str(&format!("  <target dev='{}'/>\n", tap));
+    }
+
+    // OVS bridges need the port added through ovs-vsctl rather than brctl
+    if options.ovs {
+        xml.push_str("  <virtualport type='openvswitch'/>\n");
+    }
+
+    if options.isolated {
+        xml.push_str("  <port isolated='yes'/>\n");
+    }
+
+    // Add bandwidth limiting if specified
+    if options.bandwidth_in_kbps.is_some() || options.bandwidth_out_kbps.is_some() {
+        xml.push_str("  <bandwidth>\n");
+        if let Some(inbound) = options.bandwidth_in_kbps {
+            xml.push_str(&bandwidth_xml(
+                "inbound",
+                inbound,
+                options.bandwidth_in_peak_kbps,
+                options.bandwidth_in_burst_kb,
+            ));
+        }
+        if let Some(outbound) = options.bandwidth_out_kbps {
+            xml.push_str(&bandwidth_xml(
+                "outbound",
+                outbound,
+                options.bandwidth_out_peak_kbps,
+                options.bandwidth_out_burst_kb,
+            ));
+        }
+        xml.push_str("  </bandwidth>\n");
+    }
+
+    if let Some(mtu) = options.mtu {
+        xml.push_str(&format!("  <mtu size='{}'/>\n", mtu));
+    }
+
+    // Multiqueue needs the vhost backend
+    if let Some(queues) = options.queues {
+        xml.push_str(&format!("  <driver name='vhost' queues='{}'/>\n", queues));
+    }
+
+    // Tag the interface onto a VLAN if requested; on OVS libvirt sets this
+    // as the port's access tag
+    if let Some(vlan) = options.vlan {
+        xml.push_str(&format!("  <vlan>\n    <tag id='{}'/>\n  </vlan>\n", vlan));
+    }
+
+    xml.push_str("</interface>");
+    Ok(xml)
+}
+
+fn stderr_of(output: &std::process::Output) -> String {
+    String::from_utf8_lossy(&output.stderr).trim().to_string()
+}
+
+/// Parse the `virsh net-list --all` table into (name, active, autostart).
+///
+/// The header and separator are skipped, so a host with no networks yields
+/// nothing. Names are everything before the last three columns.
+fn parse_net_list(stdout: &str) -> Vec<(String, bool, bool)> {
+    stdout
+        .lines()
+        .skip_while(|line| !line.trim_start().starts_with("---"))
+        .skip(1)
+        .filter_map(|line| {
+            let columns: Vec<&str> = line.split_whitespace().collect();
+            let [name @ .., state, autostart, _persistent] = columns.as_slice() else {
+                return None;
+            };
+            if name.is_empty() {
+                return None;
+            }
+            Some((name.join(" "), *state == "active", *autostart == "yes"))
+        })
+        .collect()
+}
+
+/// One `<inbound>`/`<outbound>` line of a `<bandwidth>` block
+fn bandwidth_xml(direction: &str, average: u32, peak: Option<u32>, burst: Option<u32>) -> String {
+    let mut line = format!("    <{} average='{}'", direction, average);
+    if let Some(peak) = peak {
+        line.push_str(&format!(" peak='{}'", peak));
+    }
+    if let Some(burst) = burst {
+        line.push_str(&format!(" burst='{}'", burst));
+    }
+    line.push_str("/>\n");
+    line
+}
+
 #[derive(Debug, Clone)]
 pub struct VmInfo {
     pub id: Option<i32>,
     pub name: String,
+    /// Single-token libvirt state (`running`, `shutoff`, `paused`, ...)
     pub state: String,
-    pub interfaces: Vec<String>,
+    pub interfaces: Vec<VmInterface>,
+}
+
+#[derive(Debug, Clone)]
+pub struct LibvirtNetInfo {
+    pub name: String,
+    /// Bridge device backing the network, e.g. `virbr0`
+    pub bridge: Option<String>,
+    pub active: bool,
+    pub autostart: bool,
+    /// Subnet of the network's first `<ip>` element
+    pub cidr: Option<ipnet::IpNet>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gw_core::MockCommandRunner;
+
+    #[tokio::test]
+    async fn list_vms_uses_names_and_domain_xml() {
+        let runner = Arc::new(
+            MockCommandRunner::new()
+                .ok("virsh list --all --name", "web server\nbackup\n\n")
+                .ok(
+                    "virsh dumpxml web server",
+                    "<domain type='kvm' id='3'><devices><interface type='bridge'><mac address='52:54:00:aa:bb:cc'/><target dev='vnet0'/></interface></devices></domain>",
+                )
+                .ok("virsh domstate web server", "running\n\n")
+                .fail("virsh dumpxml backup", 1, "error: failed to get domain")
+                .ok("virsh domstate backup", "shut off\n\n"),
+        );
+        let manager = LibvirtManager::new().with_runner(runner);
+
+        let vms = manager.list_vms().await.unwrap();
+        assert_eq!(vms.len(), 2);
+        assert_eq!(vms[0].name, "web server");
+        assert_eq!(vms[0].id, Some(3));
+        assert_eq!(vms[0].state, "running");
+        assert_eq!(vms[0].interfaces[0].target.as_deref(), Some("vnet0"));
+        assert_eq!(vms[1].id, None);
+        assert_eq!(vms[1].state, "shutoff");
+        assert!(vms[1].interfaces.is_empty());
+    }
+
+    #[tokio::test]
+    async fn list_libvirt_networks_reads_table_and_network_xml() {
+        let runner = Arc::new(
+            MockCommandRunner::new()
+                .ok(
+                    "virsh net-list --all",
+                    " Name      State      Autostart   Persistent\n--------------------------------------------\n default   active     yes         yes\n lab       inactive   no          yes\n\n",
+                )
+                .ok(
+                    "virsh net-dumpxml default",
+                    "<network><name>default</name><bridge name='virbr0'/><ip address='192.168.122.1' netmask='255.255.255.0'/></network>",
+                )
+                .fail("virsh net-dumpxml lab", 1, "error: network not found"),
+        );
+        let manager = LibvirtManager::new().with_runner(runner);
+
+        let networks = manager.list_libvirt_networks().await.unwrap();
+        assert_eq!(networks.len(), 2);
+        assert_eq!(networks[0].name, "default");
+        assert_eq!(networks[0].bridge.as_deref(), Some("virbr0"));
+        assert!(networks[0].active && networks[0].autostart);
+        assert_eq!(networks[0].cidr, Some("192.168.122.0/24".parse().unwrap()));
+        assert_eq!(networks[1].name, "lab");
+        assert!(!networks[1].active && !networks[1].autostart);
+        assert_eq!(networks[1].bridge, None);
+    }
+
+    #[tokio::test]
+    async fn list_libvirt_networks_is_empty_without_networks() {
+        let runner = Arc::new(MockCommandRunner::new().ok(
+            "virsh net-list --all",
+            " Name   State   Autostart   Persistent\n----------------------------------------\n\n",
+        ));
+        let manager = LibvirtManager::new().with_runner(runner);
+
+        assert!(manager.list_libvirt_networks().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn virsh_failures_map_to_structured_errors() {
+        let runner = Arc::new(
+            MockCommandRunner::new()
+                .fail(
+                    "virsh attach-device ghost",
+                    1,
+                    "error: failed to get domain 'ghost'\n",
+                )
+                .fail(
+                    "virsh attach-device web",
+                    1,
+                    "error: operation failed: target vnet0 already exists\n",
+                )
+                .fail("virsh attach-device db", 1, "error: internal error\n")
+                .fail("virsh dumpxml", 1, "error: failed to get domain\n"),
+        );
+        let manager = LibvirtManager::new().with_runner(runner);
+        let options = InterfaceOptions::default();
+
+        let err = manager
+            .attach_vm_to_bridge_advanced("ghost", "br-lab", None, &options)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, LibvirtError::DomainNotFound(vm) if vm == "ghost"));
+
+        let err = manager
+            .attach_vm_to_bridge_advanced("web", "br-lab", None, &options)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, LibvirtError::AlreadyAttached { vm, .. } if vm == "web"));
+
+        let err = manager
+            .attach_vm_to_bridge_advanced("db", "br-lab", None, &options)
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, LibvirtError::AttachFailed { stderr } if stderr == "error: internal error")
+        );
+
+        let invalid = InterfaceOptions {
+            queues: Some(0),
+            ..Default::default()
+        };
+        let err = manager
+            .attach_vm_to_bridge_advanced("db", "br-lab", None, &invalid)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, LibvirtError::InvalidOptions(_)));
+    }
+
+    #[tokio::test]
+    async fn missing_virsh_is_reported_as_virsh_missing() {
+        // The mock answers unknown commands like a missing binary
+        let manager = LibvirtManager::new().with_runner(Arc::new(MockCommandRunner::new()));
+
+        let err = manager.list_vms().await.unwrap_err();
+        assert!(matches!(err, LibvirtError::VirshMissing));
+    }
+
+    #[tokio::test]
+    async fn detach_waits_until_mac_leaves_domiflist() {
+        let runner = Arc::new(
+            MockCommandRunner::new()
+                .ok("virsh detach-interface web bridge --mac 52:54:00:aa:bb:cc", "")
+                .ok(
+                    "virsh domiflist web",
+                    " Interface   Type     Source   Model    MAC\n-------------------------------------------------------\n vnet1       bridge   br-dmz   virtio   52:54:00:11:22:33\n",
+                ),
+        );
+        let manager = LibvirtManager::new().with_runner(runner.clone());
+        let options = DetachOptions {
+            live: true,
+            wait_for_detach: true,
+            ..Default::default()
+        };
+
+        manager
+            .detach_vm_interface("web", "52:54:00:aa:bb:cc", &options)
+            .await
+            .unwrap();
+
+        let calls: Vec<String> = runner
+            .calls()
+            .into_iter()
+            .map(|call| call.command)
+            .collect();
+        assert_eq!(
+            calls,
+            [
+                "virsh detach-interface web bridge --mac 52:54:00:aa:bb:cc --live --config",
+                "virsh domiflist web",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn detach_times_out_while_mac_is_still_listed() {
+        let runner = Arc::new(
+            MockCommandRunner::new()
+                .ok("virsh detach-interface", "")
+                .ok(
+                    "virsh domiflist web --inactive",
+                    " vnet0   bridge   br-lab   virtio   52:54:00:aa:bb:cc\n",
+                ),
+        );
+        let manager = LibvirtManager::new().with_runner(runner.clone());
+        let options = DetachOptions {
+            wait_for_detach: true,
+            timeout: Duration::from_millis(300),
+            ..Default::default()
+        };
+
+        let err = manager
+            .detach_vm_interface("web", "52:54:00:aa:bb:cc", &options)
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, LibvirtError::DetachTimeout { ref mac, .. } if mac == "52:54:00:aa:bb:cc")
+        );
+        let polls = runner
+            .calls()
+            .iter()
+            .filter(|call| call.command == "virsh domiflist web --inactive")
+            .count();
+        assert!(polls >= 2, "polled {} times", polls);
+    }
+
+    #[test]
+    fn generated_macs_are_stable_per_vm_and_bridge() {
+        let mac = LibvirtManager::generate_mac_for("web", "br-lab", 0);
+        assert_eq!(mac, LibvirtManager::generate_mac_for("web", "br-lab", 0));
+        assert!(mac.starts_with("52:54:00:"));
+        assert_eq!(mac.len(), 17);
+
+        assert_ne!(mac, LibvirtManager::generate_mac_for("db", "br-lab", 0));
+        assert_ne!(mac, LibvirtManager::generate_mac_for("web", "br-dmz", 0));
+        assert_ne!(mac, LibvirtManager::generate_mac_for("web", "br-lab", 1));
+        // The separator keeps ("ab", "c") and ("a", "bc") apart
+        assert_ne!(
+            LibvirtManager::generate_mac_for("ab", "c", 0),
+            LibvirtManager::generate_mac_for("a", "bc", 0)
+        );
+    }
+
+    #[test]
+    fn next_mac_skips_macs_the_vm_already_uses() {
+        let nic = |index| VmInterface {
+            kind: "bridge".into(),
+            mac: Some(LibvirtManager::generate_mac_for("web", "br-lab", index).to_uppercase()),
+            source: Some("br-lab".into()),
+            ..Default::default()
+        };
+
+        let first = LibvirtManager::next_mac_for("web", "br-lab", &[]);
+        assert_eq!(first, LibvirtManager::generate_mac_for("web", "br-lab", 0));
+
+        let second = LibvirtManager::next_mac_for("web", "br-lab", &[nic(0)]);
+        assert_eq!(second, LibvirtManager::generate_mac_for("web", "br-lab", 1));
+
+        // A freed slot is reused, so re-attaching the first NIC keeps its MAC
+        assert_eq!(
+            LibvirtManager::next_mac_for("web", "br-lab", &[nic(1)]),
+            first
+        );
+    }
+
+    #[test]
+    fn interface_xml_tags_vlan() {
+        let options = InterfaceOptions {
+            vlan: Some(30),
+            ..Default::default()
+        };
+        let xml = interface_xml("br-trunk", None, "52:54:00:aa:bb:cc", &options).unwrap();
+        assert!(xml.contains("  <vlan>\n    <tag id='30'/>\n  </vlan>\n</interface>"));
+
+        let untagged = interface_xml(
+            "br-trunk",
+            None,
+            "52:54:00:aa:bb:cc",
+            &InterfaceOptions::default(),
+        )
+        .unwrap();
+        assert!(!untagged.contains("<vlan>"));
+
+        for vlan in [0, 4095] {
+            let options = InterfaceOptions {
+                vlan: Some(vlan),
+                ..Default::default()
+            };
+            assert!(interface_xml("br-trunk", None, "52:54:00:aa:bb:cc", &options).is_err());
+        }
+    }
+
+    #[tokio::test]
+    async fn connection_uri_is_passed_to_every_virsh_call() {
+        let runner = Arc::new(
+            MockCommandRunner::new()
+                .ok("virsh -c qemu:///system list --all --name", "web\n")
+                .ok(
+                    "virsh -c qemu:///system dumpxml web",
+                    "<domain type='kvm'/>",
+                )
+                .ok("virsh -c qemu:///system domstate web", "paused\n"),
+        );
+        let manager = LibvirtManager::new()
+            .with_runner(runner.clone())
+            .with_uri("qemu:///system");
+
+        let vms = manager.list_vms().await.unwrap();
+        assert_eq!(vms[0].state, "paused");
+        assert!(
+            runner
+                .calls()
+                .iter()
+                .all(|call| call.command.starts_with("virsh -c qemu:///system "))
+        );
+    }
+
+    #[test]
+    fn interface_xml_adds_openvswitch_virtualport() {
+        let mac = "52:54:00:aa:bb:cc";
+        let bridge = InterfaceOptions {
+            vlan: Some(30),
+            ..Default::default()
+        };
+        let ovs = InterfaceOptions {
+            ovs: true,
+            ..bridge.clone()
+        };
+
+        assert_eq!(
+            interface_xml("br-int", Some("vnet7"), mac, &bridge).unwrap(),
+            "<interface type='bridge'>
+  <source bridge='br-int'/>
+  <model type='virtio'/>
+  <mac address='52:54:00:aa:bb:cc'/>
+  <target dev='vnet7'/>
+  <vlan>
+    <tag id='30'/>
+  </vlan>
+</interface>"
+        );
+        assert_eq!(
+            interface_xml("br-int", Some("vnet7"), mac, &ovs).unwrap(),
+            "<interface type='bridge'>
+  <source bridge='br-int'/>
+  <model type='virtio'/>
+  <mac address='52:54:00:aa:bb:cc'/>
+  <target dev='vnet7'/>
+  <virtualport type='openvswitch'/>
+  <vlan>
+    <tag id='30'/>
+  </vlan>
+</interface>"
+        );
+    }
+
+    #[test]
+    fn interface_xml_renders_bandwidth_peak_and_burst() {
+        let mac = "52:54:00:aa:bb:cc";
+        let average_only = InterfaceOptions {
+            bandwidth_in_kbps: Some(1000),
+            bandwidth_out_kbps: Some(500),
+            ..Default::default()
+        };
+        let xml = interface_xml("br-lab", None, mac, &average_only).unwrap();
+        assert!(xml.contains(
+            "  <bandwidth>\n    <inbound average='1000'/>\n    <outbound average='500'/>\n  </bandwidth>\n"
+        ));
+
+        let bursty = InterfaceOptions {
+            bandwidth_in_peak_kbps: Some(5000),
+            bandwidth_in_burst_kb: Some(1024),
+            bandwidth_out_burst_kb: Some(256),
+            ..average_only.clone()
+        };
+        let xml = interface_xml("br-lab", None, mac, &bursty).unwrap();
+        assert!(xml.contains("    <inbound average='1000' peak='5000' burst='1024'/>\n"));
+        assert!(xml.contains("    <outbound average='500' burst='256'/>\n"));
+
+        let no_average = InterfaceOptions {
+            bandwidth_out_peak_kbps: Some(5000),
+            ..Default::default()
+        };
+        assert!(interface_xml("br-lab", None, mac, &no_average).is_err());
+    }
+
+    #[test]
+    fn interface_xml_sets_mtu_and_queues() {
+        let options = InterfaceOptions {
+            mtu: Some(9000),
+            queues: Some(4),
+            ..Default::default()
+        };
+        let xml = interface_xml("br-jumbo", None, "52:54:00:aa:bb:cc", &options).unwrap();
+        assert!(xml.contains("  <mtu size='9000'/>\n"));
+        assert!(xml.contains("  <driver name='vhost' queues='4'/>\n"));
+
+        let too_small = InterfaceOptions {
+            mtu: Some(575),
+            ..Default::default()
+        };
+        assert!(interface_xml("br-jumbo", None, "52:54:00:aa:bb:cc", &too_small).is_err());
+
+        let no_queues = InterfaceOptions {
+            queues: Some(0),
+            ..Default::default()
+        };
+        assert!(interface_xml("br-jumbo", None, "52:54:00:aa:bb:cc", &no_queues).is_err());
+
+        let isolated_ovs = InterfaceOptions {
+            ovs: true,
+            isolated: true,
+            ..Default::default()
+        };
+        assert!(matches!(
+            interface_xml("ovsbr0", None, "52:54:00:aa:bb:cc", &isolated_ovs),
+            Err(LibvirtError::InvalidOptions(_))
+        ));
+    }
+
+    /// Reads the XML file passed to `virsh attach-device` after yielding, so
+    /// concurrent attaches interleave between writing and reading it.
+    #[derive(Default)]
+    struct CapturingRunner {
+        seen: std::sync::Mutex<Vec<(String, String)>>,
+    }
+
+    impl CommandRunner for CapturingRunner {
+        fn run<'a>(
+            &'a self,
+            _program: &'a str,
+            args: &'a [&'a str],
+            _stdin: Option<&'a [u8]>,
+        ) -> gw_core::CommandFuture<'a> {
+            Box::pin(async move {
+                tokio::task::yield_now().await;
+                let path = args[2].to_string();
+                let xml = std::fs::read_to_string(&path)?;
+                self.seen.lock().unwrap().push((path, xml));
+                Ok(std::process::Output {
+                    status: std::os::unix::process::ExitStatusExt::from_raw(0),
+                    stdout: Vec::new(),
+                    stderr: Vec::new(),
+                })
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn concurrent_attaches_use_separate_xml_files() {
+        let runner = Arc::new(CapturingRunner::default());
+        let manager = LibvirtManager::new().with_runner(runner.clone());
+        let options = InterfaceOptions {
+            mac_address: Some("52:54:00:aa:bb:cc".into()),
+            ..Default::default()
+        };
+
+        let (web, db) = tokio::join!(
+            manager.attach_vm_to_bridge_advanced("web", "br-web", None, &options),
+            manager.attach_vm_to_bridge_advanced("db", "br-db", None, &options),
+        );
+        web.unwrap();
+        db.unwrap();
+
+        let seen = runner.seen.lock().unwrap();
+        assert_eq!(seen.len(), 2);
+        assert_ne!(seen[0].0, seen[1].0);
+        for bridge in ["br-web", "br-db"] {
+            let source = format!("<source bridge='{}'/>", bridge);
+            assert_eq!(
+                seen.iter().filter(|(_, xml)| xml.contains(&source)).count(),
+                1
+            );
+        }
+        for (path, _) in seen.iter() {
+            assert!(!std::path::Path::new(path).exists());
+        }
+    }
 }
@@ -1,8 +1,18 @@
 use anyhow::Result;
 use axum::{Router, routing::get};
-use prometheus::{Encoder, IntCounterVec, IntGaugeVec, Opts, Registry, TextEncoder};
-use std::sync::Arc;
-use tokio::sync::RwLock;
+use prometheus::core::{MetricVec, MetricVecBuilder};
+use prometheus::{
+    Encoder, HistogramOpts, HistogramTimer, HistogramVec, IntCounterVec, IntGaugeVec, Opts,
+    Registry, TextEncoder,
+};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use tokio::sync::{RwLock, watch};
+use tower_http::compression::CompressionLayer;
+
+/// Histogram buckets for apply durations; applies with probes and confirmation
+/// windows routinely take several seconds
+pub const APPLY_DURATION_BUCKETS: &[f64] = &[0.5, 1.0, 2.0, 5.0, 10.0, 30.0, 60.0];
 
 /// Metrics collector for GhostWarden
 pub struct MetricsCollector {
@@ -10,19 +20,42 @@ pub struct MetricsCollector {
 
     // Bridge metrics
     bridge_status: IntGaugeVec,
+    bridge_rx_bytes: IntCounterVec,
+    bridge_tx_bytes: IntCounterVec,
+    bridge_rx_packets: IntCounterVec,
+    bridge_tx_packets: IntCounterVec,
+    bridge_rx_errors: IntCounterVec,
+    bridge_tx_errors: IntCounterVec,
 
     // nftables metrics
     nft_tables_count: IntGaugeVec,
     nft_chains_count: IntGaugeVec,
     nft_rules_count: IntGaugeVec,
+    nft_quota_bytes: IntGaugeVec,
+    nft_quota_used_bytes: IntGaugeVec,
+    nft_rule_packets: IntCounterVec,
+    nft_rule_bytes: IntCounterVec,
 
     // DHCP metrics
     dhcp_leases_count: IntGaugeVec,
 
+    // Port forward metrics
+    port_forwards: IntGaugeVec,
+
     // Apply/rollback metrics
     apply_success: IntCounterVec,
     apply_failure: IntCounterVec,
+    apply_duration: HistogramVec,
     rollback_triggered: IntCounterVec,
+    /// Unlabeled; a vec so the series can be removed while no snapshot exists
+    rollback_snapshot_age: IntGaugeVec,
+    /// `(finished_at, seq)` of the newest persisted apply duration already
+    /// observed; a recreated log restarts `seq` but not the clock
+    last_apply: Mutex<(u64, u64)>,
+
+    /// Label sets written by the last update of each gauge, keyed by metric
+    /// name, so series for bridges/tables/networks that vanished can be removed
+    seen_labels: Mutex<HashMap<&'static str, HashSet<Vec<String>>>>,
 }
 
 impl MetricsCollector {
@@ -39,6 +72,34 @@ impl MetricsCollector {
         )?;
         registry.register(Box::new(bridge_status.clone()))?;
 
+        let bridge_counter = |name: &str, help: &str| -> Result<IntCounterVec> {
+            let counter = IntCounterVec::new(Opts::new(name, help), &["bridge_name"])?;
+            registry.register(Box::new(counter.clone()))?;
+            Ok(counter)
+        };
+        let bridge_rx_bytes = bridge_counter(
+            "ghostwarden_bridge_rx_bytes",
+            "Bytes received on the bridge",
+        )?;
+        let bridge_tx_bytes =
+            bridge_counter("ghostwarden_bridge_tx_bytes", "Bytes sent from the bridge")?;
+        let bridge_rx_packets = bridge_counter(
+            "ghostwarden_bridge_rx_packets",
+            "Packets received on the bridge",
+        )?;
+        let bridge_tx_packets = bridge_counter(
+            "ghostwarden_bridge_tx_packets",
+            "Packets sent from the bridge",
+        )?;
+        let bridge_rx_errors = bridge_counter(
+            "ghostwarden_bridge_rx_errors",
+            "Receive errors on the bridge",
+        )?;
+        let bridge_tx_errors = bridge_counter(
+            "ghostwarden_bridge_tx_errors",
+            "Transmit errors on the bridge",
+        )?;
+
         // nftables metrics
         let nft_tables_count = IntGaugeVec::new(
             Opts::new("ghostwarden_nft_tables_count", "Number of nftables tables"),
@@ -58,6 +119,42 @@ impl MetricsCollector {
         )?;
         registry.register(Box::new(nft_rules_count.clone()))?;
 
+        let nft_quota_bytes = IntGaugeVec::new(
+            Opts::new(
+                "ghostwarden_nft_quota_bytes",
+                "nftables quota budget in bytes",
+            ),
+            &["table_name", "quota"],
+        )?;
+        registry.register(Box::new(nft_quota_bytes.clone()))?;
+
+        let nft_quota_used_bytes = IntGaugeVec::new(
+            Opts::new(
+                "ghostwarden_nft_quota_used_bytes",
+                "Bytes consumed against an nftables quota",
+            ),
+            &["table_name", "quota"],
+        )?;
+        registry.register(Box::new(nft_quota_used_bytes.clone()))?;
+
+        let nft_rule_packets = IntCounterVec::new(
+            Opts::new(
+                "ghostwarden_nft_rule_packets",
+                "Packets matched by a counted nftables rule",
+            ),
+            &["table_name", "rule"],
+        )?;
+        registry.register(Box::new(nft_rule_packets.clone()))?;
+
+        let nft_rule_bytes = IntCounterVec::new(
+            Opts::new(
+                "ghostwarden_nft_rule_bytes",
+                "Bytes matched by a counted nftables rule",
+            ),
+            &["table_name", "rule"],
+        )?;
+        registry.register(Box::new(nft_rule_bytes.clone()))?;
+
         // DHCP metrics
         let dhcp_leases_count = IntGaugeVec::new(
             Opts::new(
@@ -68,6 +165,16 @@ impl MetricsCollector {
         )?;
         registry.register(Box::new(dhcp_leases_count.clone()))?;
 
+        // Port forward metrics
+        let port_forwards = IntGaugeVec::new(
+            Opts::new(
+                "ghostwarden_port_forwards",
+                "Number of configured DNAT port forwards",
+            ),
+            &["network", "protocol"],
+        )?;
+        registry.register(Box::new(port_forwards.clone()))?;
+
         // Apply/rollback metrics
         let apply_success = IntCounterVec::new(
             Opts::new(
@@ -87,6 +194,16 @@ impl MetricsCollector {
         )?;
         registry.register(Box::new(apply_failure.clone()))?;
 
+        let apply_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "ghostwarden_apply_duration_seconds",
+                "Wall-clock duration of apply operations",
+            )
+            .buckets(APPLY_DURATION_BUCKETS.to_vec()),
+            &["topology"],
+        )?;
+        registry.register(Box::new(apply_duration.clone()))?;
+
         let rollback_triggered = IntCounterVec::new(
             Opts::new(
                 "ghostwarden_rollback_triggered_total",
@@ -96,21 +213,46 @@ impl MetricsCollector {
         )?;
         registry.register(Box::new(rollback_triggered.clone()))?;
 
+        let rollback_snapshot_age = IntGaugeVec::new(
+            Opts::new(
+                "ghostwarden_rollback_snapshot_age_seconds",
+                "Age of the rollback snapshot on disk, absent when there is none",
+            ),
+            &[],
+        )?;
+        registry.register(Box::new(rollback_snapshot_age.clone()))?;
+
         Ok(Self {
             registry,
             bridge_status,
+            bridge_rx_bytes,
+            bridge_tx_bytes,
+            bridge_rx_packets,
+            bridge_tx_packets,
+            bridge_rx_errors,
+            bridge_tx_errors,
             nft_tables_count,
             nft_chains_count,
             nft_rules_count,
+            nft_quota_bytes,
+            nft_quota_used_bytes,
+            nft_rule_packets,
+            nft_rule_bytes,
             dhcp_leases_count,
+            port_forwards,
             apply_success,
             apply_failure,
+            apply_duration,
             rollback_triggered,
+            rollback_snapshot_age,
+            last_apply: Mutex::new((0, 0)),
+            seen_labels: Mutex::new(HashMap::new()),
         })
     }
 
     /// Update bridge metrics from network status
     pub fn update_bridge_metrics(&self, bridges: &[gw_core::BridgeStatus]) -> Result<()> {
+        let mut current = HashSet::new();
         for bridge in bridges {
             // Update status (1 for UP, 0 for DOWN)
             let status_value = if bridge.state.to_uppercase() == "UP" {
@@ -121,6 +263,34 @@ impl MetricsCollector {
             self.bridge_status
                 .with_label_values(&[&bridge.name])
                 .set(status_value);
+            current.insert(vec![bridge.name.clone()]);
+        }
+        self.remove_stale("bridge_status", &self.bridge_status, current);
+        Ok(())
+    }
+
+    /// Update bridge traffic counters from the kernel's per-link statistics
+    pub fn update_bridge_traffic(&self, stats: &[(String, gw_core::LinkStats)]) -> Result<()> {
+        let mut current = HashSet::new();
+        for (bridge, stats) in stats {
+            set_counter(&self.bridge_rx_bytes, &[bridge], stats.rx_bytes);
+            set_counter(&self.bridge_tx_bytes, &[bridge], stats.tx_bytes);
+            set_counter(&self.bridge_rx_packets, &[bridge], stats.rx_packets);
+            set_counter(&self.bridge_tx_packets, &[bridge], stats.tx_packets);
+            set_counter(&self.bridge_rx_errors, &[bridge], stats.rx_errors);
+            set_counter(&self.bridge_tx_errors, &[bridge], stats.tx_errors);
+            current.insert(vec![bridge.clone()]);
+        }
+
+        for (key, counter) in [
+            ("bridge_rx_bytes", &self.bridge_rx_bytes),
+            ("bridge_tx_bytes", &self.bridge_tx_bytes),
+            ("bridge_rx_packets", &self.bridge_rx_packets),
+            ("bridge_tx_packets", &self.bridge_tx_packets),
+            ("bridge_rx_errors", &self.bridge_rx_errors),
+            ("bridge_tx_errors", &self.bridge_tx_errors),
+        ] {
+            self.remove_stale(key, counter, current.clone());
         }
         Ok(())
     }
@@ -128,7 +298,9 @@ impl MetricsCollector {
     /// Update nftables metrics
     pub fn update_nft_metrics(&self, nft_status: &[gw_core::NftTableStatus]) -> Result<()> {
         // Count tables by family
-        let mut family_counts = std::collections::HashMap::new();
+        let mut family_counts = HashMap::new();
+        let mut tables = HashSet::new();
+        let mut quotas = HashSet::new();
         for table in nft_status {
             *family_counts.entry(&table.family).or_insert(0) += 1;
 
@@ -139,14 +311,60 @@ impl MetricsCollector {
             self.nft_rules_count
                 .with_label_values(&[&table.name])
                 .set(table.rules as i64);
+            tables.insert(vec![table.name.clone()]);
+
+            for quota in &table.quotas {
+                self.nft_quota_bytes
+                    .with_label_values(&[&table.name, &quota.name])
+                    .set(quota.bytes as i64);
+                self.nft_quota_used_bytes
+                    .with_label_values(&[&table.name, &quota.name])
+                    .set(quota.used as i64);
+                quotas.insert(vec![table.name.clone(), quota.name.clone()]);
+            }
         }
 
+        let mut families = HashSet::new();
         for (family, count) in family_counts {
             self.nft_tables_count
                 .with_label_values(&[family])
                 .set(count);
+            families.insert(vec![family.clone()]);
         }
 
+        self.remove_stale("nft_tables_count", &self.nft_tables_count, families);
+        self.remove_stale("nft_chains_count", &self.nft_chains_count, tables.clone());
+        self.remove_stale("nft_rules_count", &self.nft_rules_count, tables);
+        self.remove_stale("nft_quota_bytes", &self.nft_quota_bytes, quotas.clone());
+        self.remove_stale("nft_quota_used_bytes", &self.nft_quota_used_bytes, quotas);
+
+        Ok(())
+    }
+
+    /// Update per-rule counters from `NftManager::read_counters`, per table.
+    /// Rules sharing a description are summed into one series.
+    pub fn update_rule_counters(
+        &self,
+        tables: &[(String, Vec<gw_core::RuleCounter>)],
+    ) -> Result<()> {
+        let mut totals: HashMap<Vec<String>, (u64, u64)> = HashMap::new();
+        for (table, rules) in tables {
+            for (rule, packets, bytes) in rules {
+                let total = totals.entry(vec![table.clone(), rule.clone()]).or_default();
+                total.0 += packets;
+                total.1 += bytes;
+            }
+        }
+
+        for (labels, (packets, bytes)) in &totals {
+            let labels: Vec<&str> = labels.iter().map(String::as_str).collect();
+            set_counter(&self.nft_rule_packets, &labels, *packets);
+            set_counter(&self.nft_rule_bytes, &labels, *bytes);
+        }
+
+        let current: HashSet<Vec<String>> = totals.into_keys().collect();
+        self.remove_stale("nft_rule_packets", &self.nft_rule_packets, current.clone());
+        self.remove_stale("nft_rule_bytes", &self.nft_rule_bytes, current);
         Ok(())
     }
 
@@ -155,6 +373,51 @@ impl MetricsCollector {
         self.dhcp_leases_count
             .with_label_values(&[network])
             .set(leases.len() as i64);
+        self.seen_labels
+            .lock()
+            .unwrap()
+            .entry("dhcp_leases_count")
+            .or_default()
+            .insert(vec![network.to_string()]);
+        Ok(())
+    }
+
+    /// Drop lease counts for every network not in `networks`. Leases are
+    /// updated one network at a time, so call this once per refresh with the
+    /// networks that still exist.
+    pub fn retain_dhcp_networks(&self, networks: &[&str]) {
+        let current = networks
+            .iter()
+            .map(|network| vec![network.to_string()])
+            .collect();
+        self.remove_stale("dhcp_leases_count", &self.dhcp_leases_count, current);
+    }
+
+    /// Update port forward counts from the routed networks in `topology`
+    pub fn update_forward_metrics(&self, topology: &gw_core::Topology) -> Result<()> {
+        let mut current = HashSet::new();
+        for (name, network) in &topology.networks {
+            let gw_core::Network::Routed(routed) = network else {
+                continue;
+            };
+
+            // Keep tcp/udp series at 0 so alerts can tell "no forwards" from "no data"
+            let mut counts: std::collections::BTreeMap<String, i64> =
+                [("tcp".to_string(), 0), ("udp".to_string(), 0)].into();
+            for forward in &routed.forwards {
+                *counts.entry(forward.protocol()).or_insert(0) += 1;
+            }
+
+            for (protocol, count) in counts {
+                self.port_forwards
+                    .with_label_values(&[name.as_str(), protocol.as_str()])
+                    .set(count);
+                current.insert(vec![name.clone(), protocol]);
+            }
+        }
+
+        // Drop series for networks that are no longer in the topology
+        self.remove_stale("port_forwards", &self.port_forwards, current);
         Ok(())
     }
 
@@ -170,6 +433,51 @@ impl MetricsCollector {
             .inc();
     }
 
+    /// Start timing an apply; the duration is observed when the guard is dropped
+    pub fn start_apply_timer(&self, topology: &str) -> HistogramTimer {
+        self.apply_duration
+            .with_label_values(&[topology])
+            .start_timer()
+    }
+
+    /// Skip the apply durations already persisted at
+    /// `state::default_apply_durations_path`, so a restarted server only
+    /// reports applies that finish after it started
+    pub fn skip_recorded_apply_durations(&self) -> Result<()> {
+        let durations = gw_core::load_apply_durations(&gw_core::default_apply_durations_path()?)?;
+        self.mark_apply_durations_seen(&durations);
+        Ok(())
+    }
+
+    /// Observe apply durations that `gwarden net apply` persisted at
+    /// `state::default_apply_durations_path` since the last call
+    pub fn update_apply_durations(&self) -> Result<()> {
+        let durations = gw_core::load_apply_durations(&gw_core::default_apply_durations_path()?)?;
+        self.observe_apply_durations(&durations);
+        Ok(())
+    }
+
+    fn mark_apply_durations_seen(&self, durations: &[gw_core::ApplyDuration]) {
+        let mut last = self.last_apply.lock().unwrap();
+        if let Some(newest) = durations.iter().map(apply_key).max() {
+            *last = (*last).max(newest);
+        }
+    }
+
+    fn observe_apply_durations(&self, durations: &[gw_core::ApplyDuration]) {
+        let mut last = self.last_apply.lock().unwrap();
+        let seen = *last;
+        for duration in durations {
+            if apply_key(duration) <= seen {
+                continue;
+            }
+            self.apply_duration
+                .with_label_values(&[duration.topology.as_str()])
+                .observe(duration.seconds);
+            *last = (*last).max(apply_key(duration));
+        }
+    }
+
     /// Record rollback
     pub fn record_rollback(&self, topology: &str, reason: &str) {
         self.rollback_triggered
@@ -177,6 +485,52 @@ impl MetricsCollector {
             .inc();
     }
 
+    /// Report how old the rollback snapshot at `rollback::default_record_path`
+    /// is; a lingering snapshot usually means an apply was never confirmed
+    pub fn update_rollback_age(&self) -> Result<()> {
+        let record = gw_core::load_record()?;
+        self.set_rollback_age(record.as_ref(), std::time::SystemTime::now());
+        Ok(())
+    }
+
+    fn set_rollback_age(
+        &self,
+        record: Option<&gw_core::RollbackRecord>,
+        now: std::time::SystemTime,
+    ) {
+        let Some(record) = record else {
+            // No snapshot: drop the series instead of claiming an age of 0
+            let _ = self
+                .rollback_snapshot_age
+                .remove_label_values(&[] as &[&str]);
+            return;
+        };
+
+        let created = std::time::UNIX_EPOCH + std::time::Duration::from_secs(record.created_at);
+        let age = now.duration_since(created).unwrap_or_default().as_secs();
+        self.rollback_snapshot_age
+            .with_label_values(&[] as &[&str])
+            .set(age as i64);
+    }
+
+    /// Remove series of `gauge` written last time under `key` but missing from
+    /// `current`, then remember `current` for the next update
+    fn remove_stale<T: MetricVecBuilder>(
+        &self,
+        key: &'static str,
+        gauge: &MetricVec<T>,
+        current: HashSet<Vec<String>>,
+    ) {
+        let mut seen_labels = self.seen_labels.lock().unwrap();
+        let seen = seen_labels.entry(key).or_default();
+        for labels in seen.difference(&current) {
+            let labels: Vec<&str> = labels.iter().map(String::as_str).collect();
+            // Already gone if something else removed it; nothing to do
+            let _ = gauge.remove_label_values(&labels);
+        }
+        *seen = current;
+    }
+
     /// Get the registry for HTTP server
     pub fn registry(&self) -> Arc<Registry> {
         self.registry.clone()
@@ -192,16 +546,41 @@ impl MetricsCollector {
     }
 }
 
+/// Ordering key of a persisted apply duration; `finished_at` first so a log
+/// that was deleted and recreated still sorts after the entries it replaced.
+fn apply_key(duration: &gw_core::ApplyDuration) -> (u64, u64) {
+    (duration.finished_at, duration.seq)
+}
+
+/// Bring the `labels` series of `counter` up to the kernel's `value`. Kernel
+/// counters start over when a bridge is recreated or a table reapplied, and so
+/// does the series.
+fn set_counter(counter: &IntCounterVec, labels: &[&str], value: u64) {
+    let series = counter.with_label_values(labels);
+    let previous = series.get();
+    if value >= previous {
+        series.inc_by(value - previous);
+    } else {
+        let _ = counter.remove_label_values(labels);
+        counter.with_label_values(labels).inc_by(value);
+    }
+}
+
 impl Default for MetricsCollector {
     fn default() -> Self {
         Self::new().expect("Failed to create metrics collector")
     }
 }
 
+/// Hook run before each scrape to bring the collector up to date
+pub type RefreshFn = Arc<dyn Fn(&MetricsCollector) -> Result<()> + Send + Sync>;
+
 /// HTTP server for Prometheus metrics endpoint
 pub struct MetricsServer {
     collector: Arc<RwLock<MetricsCollector>>,
     addr: std::net::SocketAddr,
+    refresh: Option<RefreshFn>,
+    shutdown: Option<watch::Receiver<bool>>,
 }
 
 impl MetricsServer {
@@ -210,26 +589,30 @@ impl MetricsServer {
         Self {
             collector: Arc::new(RwLock::new(collector)),
             addr,
+            refresh: None,
+            shutdown: None,
         }
     }
 
+    /// Stop serving once `true` is sent on `shutdown` (or its sender is
+    /// dropped); in-flight scrapes finish before [`Self::serve`] returns.
+    pub fn with_shutdown(mut self, shutdown: watch::Receiver<bool>) -> Self {
+        self.shutdown = Some(shutdown);
+        self
+    }
+
+    /// Run `refresh` against the collector before every scrape is rendered.
+    pub fn with_refresh(
+        mut self,
+        refresh: impl Fn(&MetricsCollector) -> Result<()> + Send + Sync + 'static,
+    ) -> Self {
+        self.refresh = Some(Arc::new(refresh));
+        self
+    }
+
     /// Start the metrics HTTP server
     pub async fn serve(self) -> Result<()> {
-        let collector = self.collector.clone();
-
-        let app = Router::new().route(
-            "/metrics",
-            get(move || {
-                let collector = collector.clone();
-                async move {
-                    let collector = collector.read().await;
-                    match collector.render_metrics() {
-                        Ok(metrics) => metrics,
-                        Err(e) => format!("# Error rendering metrics: {}", e),
-                    }
-                }
-            }),
-        );
+        let app = self.router();
 
         println!(
             "📊 Metrics server listening on http://{}/metrics",
@@ -237,16 +620,159 @@ impl MetricsServer {
         );
 
         let listener = tokio::net::TcpListener::bind(self.addr).await?;
-        axum::serve(listener, app).await?;
+        match self.shutdown {
+            Some(mut shutdown) => {
+                axum::serve(listener, app)
+                    .with_graceful_shutdown(async move {
+                        // An error means the sender is gone, which also ends serving
+                        let _ = shutdown.wait_for(|stop| *stop).await;
+                    })
+                    .await?
+            }
+            None => axum::serve(listener, app).await?,
+        }
 
         Ok(())
     }
+
+    fn router(&self) -> Router {
+        let collector = self.collector.clone();
+        let refresh = self.refresh.clone();
+
+        Router::new()
+            // Liveness only: answers without locking or rendering the registry
+            .route("/healthz", get(|| async { "ok" }))
+            .route(
+                "/metrics",
+                get(move || {
+                    let collector = collector.clone();
+                    let refresh = refresh.clone();
+                    async move {
+                        let collector = collector.read().await;
+                        // A failed refresh still serves the last known values
+                        if let Some(refresh) = &refresh
+                            && let Err(e) = refresh(&collector)
+                        {
+                            eprintln!("⚠️  Failed to refresh metrics: {}", e);
+                        }
+                        match collector.render_metrics() {
+                            Ok(metrics) => metrics,
+                            Err(e) => format!("# Error rendering metrics: {}", e),
+                        }
+                    }
+                }),
+            )
+            // gzip for scrapers that send `Accept-Encoding: gzip`, as Prometheus does
+            .layer(CompressionLayer::new())
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[tokio::test]
+    async fn test_healthz_skips_the_registry() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let server = MetricsServer::new(MetricsCollector::new().unwrap(), 0);
+        // Hold the write lock so a handler that touched the collector would hang
+        let _stalled = server.collector.clone().write_owned().await;
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(axum::serve(listener, server.router()).into_future());
+
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(b"GET /healthz HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+            .await
+            .unwrap();
+        let mut response = String::new();
+        tokio::time::timeout(
+            std::time::Duration::from_secs(5),
+            stream.read_to_string(&mut response),
+        )
+        .await
+        .expect("healthz should not wait on the collector")
+        .unwrap();
+
+        assert!(response.starts_with("HTTP/1.1 200 OK"), "{}", response);
+        assert!(response.ends_with("\r\n\r\nok"), "{}", response);
+    }
+
+    /// Send a raw HTTP/1.1 request to a fresh server and return the response bytes
+    async fn request(server: &MetricsServer, request: &str) -> Vec<u8> {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(axum::serve(listener, server.router()).into_future());
+
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = Vec::new();
+        stream.read_to_end(&mut response).await.unwrap();
+        response
+    }
+
+    #[tokio::test]
+    async fn test_metrics_are_gzipped_on_request() {
+        use std::io::Read;
+
+        let collector = MetricsCollector::new().unwrap();
+        collector
+            .bridge_status
+            .with_label_values(&["br-lab"])
+            .set(1);
+        let server = MetricsServer::new(collector, 0);
+
+        // HTTP/1.0 keeps the body unchunked, so it can be decoded as is
+        let response = request(
+            &server,
+            "GET /metrics HTTP/1.0\r\nHost: localhost\r\nAccept-Encoding: gzip\r\n\r\n",
+        )
+        .await;
+        let split = response
+            .windows(4)
+            .position(|window| window == b"\r\n\r\n")
+            .expect("response head");
+        let head = String::from_utf8_lossy(&response[..split]).to_lowercase();
+        assert!(head.contains("content-encoding: gzip"), "{}", head);
+
+        let mut body = String::new();
+        flate2::read::GzDecoder::new(&response[split + 4..])
+            .read_to_string(&mut body)
+            .unwrap();
+        assert!(body.contains("ghostwarden_bridge_status{bridge_name=\"br-lab\"} 1"));
+
+        // Clients that do not ask for gzip get plain text
+        let response = request(
+            &server,
+            "GET /metrics HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n",
+        )
+        .await;
+        let response = String::from_utf8_lossy(&response);
+        assert!(response.starts_with("HTTP/1.1 200 OK"), "{}", response);
+        assert!(!response.to_lowercase().contains("content-encoding"));
+        assert!(response.contains("ghostwarden_bridge_status{bridge_name=\"br-lab\"} 1"));
+    }
+
+    #[tokio::test]
+    async fn test_serve_returns_after_shutdown_signal() {
+        let (stop, shutdown) = watch::channel(false);
+        let server =
+            MetricsServer::new(MetricsCollector::new().unwrap(), 0).with_shutdown(shutdown);
+        let serving = tokio::spawn(server.serve());
+
+        stop.send(true).unwrap();
+        let result = tokio::time::timeout(std::time::Duration::from_secs(5), serving)
+            .await
+            .expect("serve should stop after the shutdown signal")
+            .unwrap();
+        assert!(result.is_ok());
+    }
+
     #[test]
     fn test_metrics_collector_creation() {
         let collector = MetricsCollector::new();
@@ -280,4 +806,249 @@ mod tests {
         assert!(output.contains("ghostwarden_nft_tables_count"));
         assert!(output.contains("ghostwarden_dhcp_leases_count"));
     }
+
+    #[test]
+    fn test_apply_duration_histogram() {
+        let collector = MetricsCollector::new().unwrap();
+
+        drop(collector.start_apply_timer("lab.yaml"));
+        drop(collector.start_apply_timer("lab.yaml"));
+
+        let output = collector.render_metrics().unwrap();
+        assert!(
+            output.contains("ghostwarden_apply_duration_seconds_count{topology=\"lab.yaml\"} 2")
+        );
+        assert!(output.contains(
+            "ghostwarden_apply_duration_seconds_bucket{topology=\"lab.yaml\",le=\"0.5\"} 2"
+        ));
+    }
+
+    #[test]
+    fn test_persisted_apply_durations() {
+        let collector = MetricsCollector::new().unwrap();
+        let duration = |seq, finished_at, seconds| gw_core::ApplyDuration {
+            seq,
+            topology: "lab.yaml".into(),
+            seconds,
+            finished_at,
+        };
+
+        // History recorded before the server started is skipped
+        collector.mark_apply_durations_seen(&[duration(1, 100, 9.0), duration(2, 110, 9.0)]);
+        collector.observe_apply_durations(&[
+            duration(1, 100, 9.0),
+            duration(2, 110, 9.0),
+            duration(3, 120, 0.2),
+        ]);
+        // Entries seen on an earlier scrape are not observed twice
+        collector.observe_apply_durations(&[duration(3, 120, 0.2), duration(4, 130, 3.0)]);
+        // A recreated log restarts seq, but its entries finish later
+        collector.observe_apply_durations(&[duration(1, 140, 0.3)]);
+
+        let output = collector.render_metrics().unwrap();
+        assert!(
+            output.contains("ghostwarden_apply_duration_seconds_count{topology=\"lab.yaml\"} 3")
+        );
+        assert!(output.contains(
+            "ghostwarden_apply_duration_seconds_bucket{topology=\"lab.yaml\",le=\"0.5\"} 2"
+        ));
+        assert!(output.contains(
+            "ghostwarden_apply_duration_seconds_bucket{topology=\"lab.yaml\",le=\"60\"} 3"
+        ));
+    }
+
+    #[test]
+    fn test_port_forward_metrics() {
+        let collector = MetricsCollector::new().unwrap();
+        let topology = gw_core::Topology::from_toml(
+            r#"
+version = 1
+
+[interfaces]
+
+[networks.web]
+type = "routed"
+cidr = "10.33.0.0/24"
+gw_ip = "10.33.0.1"
+forwards = [
+    { public = "0.0.0.0:4022/tcp", dst = "10.33.0.10:22" },
+    { public = ":8443/TCP", dst = "10.33.0.10:443" },
+    { public = ":5353/udp", dst = "10.33.0.11:53" },
+]
+
+[networks.quiet]
+type = "routed"
+cidr = "10.34.0.0/24"
+gw_ip = "10.34.0.1"
+"#,
+        )
+        .unwrap();
+
+        collector.update_forward_metrics(&topology).unwrap();
+
+        let output = collector.render_metrics().unwrap();
+        assert!(output.contains("ghostwarden_port_forwards{network=\"web\",protocol=\"tcp\"} 2"));
+        assert!(output.contains("ghostwarden_port_forwards{network=\"web\",protocol=\"udp\"} 1"));
+        assert!(output.contains("ghostwarden_port_forwards{network=\"quiet\",protocol=\"tcp\"} 0"));
+        assert!(output.contains("ghostwarden_port_forwards{network=\"quiet\",protocol=\"udp\"} 0"));
+    }
+
+    #[test]
+    fn test_vanished_bridges_tables_and_networks_are_removed() {
+        let collector = MetricsCollector::new().unwrap();
+        let bridge = |name: &str| gw_core::BridgeStatus {
+            name: name.into(),
+            state: "UP".into(),
+            addresses: vec![],
+            members: vec![],
+        };
+        let table = |name: &str| gw_core::NftTableStatus {
+            name: name.into(),
+            family: "inet".into(),
+            chains: 3,
+            rules: 4,
+            quotas: vec![],
+        };
+
+        collector.update_bridge_metrics(&[bridge("br-a")]).unwrap();
+        collector.update_nft_metrics(&[table("gw-a")]).unwrap();
+        collector.update_dhcp_metrics(&[], "net-a").unwrap();
+        collector.retain_dhcp_networks(&["net-a"]);
+
+        collector.update_bridge_metrics(&[bridge("br-b")]).unwrap();
+        collector.update_nft_metrics(&[table("gw-b")]).unwrap();
+        collector.update_dhcp_metrics(&[], "net-b").unwrap();
+        collector.retain_dhcp_networks(&["net-b"]);
+
+        let output = collector.render_metrics().unwrap();
+        assert!(!output.contains("br-a"), "{}", output);
+        assert!(output.contains("ghostwarden_bridge_status{bridge_name=\"br-b\"} 1"));
+        assert!(!output.contains("gw-a"), "{}", output);
+        assert!(output.contains("ghostwarden_nft_rules_count{table_name=\"gw-b\"} 4"));
+        assert!(!output.contains("net-a"), "{}", output);
+        assert!(output.contains("ghostwarden_dhcp_leases_count{network=\"net-b\"} 0"));
+
+        collector.update_nft_metrics(&[]).unwrap();
+        let output = collector.render_metrics().unwrap();
+        assert!(
+            !output.contains("ghostwarden_nft_tables_count{"),
+            "{}",
+            output
+        );
+    }
+
+    #[test]
+    fn test_bridge_traffic_counters() {
+        let collector = MetricsCollector::new().unwrap();
+        let stats = |rx_bytes| gw_core::LinkStats {
+            rx_bytes,
+            tx_packets: 3,
+            ..Default::default()
+        };
+
+        collector
+            .update_bridge_traffic(&[("br-a".into(), stats(1_000))])
+            .unwrap();
+        collector
+            .update_bridge_traffic(&[("br-a".into(), stats(1_500))])
+            .unwrap();
+        let output = collector.render_metrics().unwrap();
+        assert!(output.contains("ghostwarden_bridge_rx_bytes{bridge_name=\"br-a\"} 1500"));
+        assert!(output.contains("ghostwarden_bridge_tx_packets{bridge_name=\"br-a\"} 3"));
+        assert!(output.contains("ghostwarden_bridge_rx_errors{bridge_name=\"br-a\"} 0"));
+
+        // Recreated bridge: the kernel counter restarted
+        collector
+            .update_bridge_traffic(&[("br-a".into(), stats(200))])
+            .unwrap();
+        let output = collector.render_metrics().unwrap();
+        assert!(output.contains("ghostwarden_bridge_rx_bytes{bridge_name=\"br-a\"} 200"));
+
+        collector
+            .update_bridge_traffic(&[("br-b".into(), stats(10))])
+            .unwrap();
+        let output = collector.render_metrics().unwrap();
+        assert!(!output.contains("br-a"), "{}", output);
+        assert!(output.contains("ghostwarden_bridge_rx_bytes{bridge_name=\"br-b\"} 10"));
+    }
+
+    #[test]
+    fn test_nft_rule_counters() {
+        let collector = MetricsCollector::new().unwrap();
+        let rules = |packets, bytes| {
+            vec![(
+                "gw-lab".to_string(),
+                vec![
+                    ("masquerade out eth0".to_string(), packets, bytes),
+                    ("policy service tcp/22".to_string(), 1, 60),
+                    ("policy service tcp/22".to_string(), 2, 120),
+                ],
+            )]
+        };
+
+        collector.update_rule_counters(&rules(10, 800)).unwrap();
+        let output = collector.render_metrics().unwrap();
+        assert!(output.contains(
+            "ghostwarden_nft_rule_packets{rule=\"masquerade out eth0\",table_name=\"gw-lab\"} 10"
+        ));
+        assert!(output.contains(
+            "ghostwarden_nft_rule_bytes{rule=\"policy service tcp/22\",table_name=\"gw-lab\"} 180"
+        ));
+
+        // Reapplied table: counters restarted
+        collector.update_rule_counters(&rules(4, 300)).unwrap();
+        let output = collector.render_metrics().unwrap();
+        assert!(output.contains(
+            "ghostwarden_nft_rule_bytes{rule=\"masquerade out eth0\",table_name=\"gw-lab\"} 300"
+        ));
+
+        collector.update_rule_counters(&[]).unwrap();
+        let output = collector.render_metrics().unwrap();
+        assert!(!output.contains("gw-lab"), "{}", output);
+    }
+
+    #[test]
+    fn test_rollback_snapshot_age() {
+        let collector = MetricsCollector::new().unwrap();
+        let mut record =
+            gw_core::RollbackRecord::new("tx-1".into(), None, vec![], Default::default());
+        record.created_at = 1_000;
+        let now = std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_090);
+
+        collector.set_rollback_age(Some(&record), now);
+        let output = collector.render_metrics().unwrap();
+        assert!(output.contains("ghostwarden_rollback_snapshot_age_seconds 90"));
+
+        collector.set_rollback_age(None, now);
+        let output = collector.render_metrics().unwrap();
+        assert!(!output.contains("ghostwarden_rollback_snapshot_age_seconds"));
+    }
+
+    #[test]
+    fn test_quota_metrics() {
+        let collector = MetricsCollector::new().unwrap();
+
+        collector
+            .update_nft_metrics(&[gw_core::NftTableStatus {
+                name: "gw-lab".into(),
+                family: "inet".into(),
+                chains: 5,
+                rules: 12,
+                quotas: vec![gw_core::QuotaStatus {
+                    name: "traffic".into(),
+                    bytes: 1_000_000,
+                    used: 250_000,
+                    over: true,
+                }],
+            }])
+            .unwrap();
+
+        let output = collector.render_metrics().unwrap();
+        assert!(output.contains(
+            "ghostwarden_nft_quota_used_bytes{quota=\"traffic\",table_name=\"gw-lab\"} 250000"
+        ));
+        assert!(output.contains(
+            "ghostwarden_nft_quota_bytes{quota=\"traffic\",table_name=\"gw-lab\"} 1000000"
+        ));
+    }
 }
@@ -1,11 +1,20 @@
 use anyhow::{Context, Result, anyhow};
-use gw_core::policy::{Action, PolicyProfile, Protocol};
+use gw_core::policy::{Action, IcmpRateLimit, PolicyProfile, Protocol, Service};
+use gw_core::{
+    CommandRunner, CtHelper, PortRange, QuotaMode, RuleCounter, SystemCommandRunner, TrafficQuota,
+};
 use ipnet::IpNet;
 use serde_json::{Value, json};
 use similar::{ChangeTag, TextDiff};
 use std::net::IpAddr;
+use std::path::Path;
+use std::process::Output;
+use std::sync::Arc;
 
-pub struct NftManager;
+pub struct NftManager {
+    runner: Arc<dyn CommandRunner>,
+    cidr_sets: bool,
+}
 
 pub struct NftDiff {
     pub table: String,
@@ -14,9 +23,46 @@ pub struct NftDiff {
     pub diff: Option<String>,
 }
 
+impl NftDiff {
+    /// Lines the desired ruleset adds over the live table.
+    pub fn added_lines(&self) -> Vec<&str> {
+        self.changed_lines('+')
+    }
+
+    /// Lines of the live table the desired ruleset removes.
+    pub fn removed_lines(&self) -> Vec<&str> {
+        self.changed_lines('-')
+    }
+
+    fn changed_lines(&self, sign: char) -> Vec<&str> {
+        self.diff
+            .as_deref()
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|line| line.strip_prefix(sign))
+            .collect()
+    }
+}
+
 impl NftManager {
     pub fn new() -> Self {
-        Self
+        Self {
+            runner: Arc::new(SystemCommandRunner),
+            cidr_sets: true,
+        }
+    }
+
+    /// Run `nft` through `runner` instead of spawning it directly.
+    pub fn with_runner(mut self, runner: Arc<dyn CommandRunner>) -> Self {
+        self.runner = runner;
+        self
+    }
+
+    /// Match policy CIDR lists through named interval sets (the default), or
+    /// with one rule per CIDR for nft builds without interval set support.
+    pub fn with_cidr_sets(mut self, enabled: bool) -> Self {
+        self.cidr_sets = enabled;
+        self
     }
 
     /// Generate a complete nftables ruleset for NAT/routing
@@ -44,216 +90,45 @@ impl NftManager {
         nftables.extend(build_nat_rules(
             table_name,
             bridge_name,
-            &bridge_net,
-            &gateway,
+            &[(bridge_net, gateway)],
             masq_iface,
             &parsed_forwards,
-        ));
+            false,
+        )?);
 
         let ruleset = json!({"nftables": nftables});
         Ok(serde_json::to_string_pretty(&ruleset)?)
     }
 
-    /// Generate nftables filter rules from a policy profile
+    /// Generate nftables filter rules from a policy profile, with the same
+    /// policy rules as [`Self::create_complete_ruleset`] but no NAT rules
     pub fn create_policy_ruleset(
         &self,
         table_name: &str,
         bridge_name: &str,
         policy: &PolicyProfile,
     ) -> Result<String> {
-        let mut nftables = vec![
-            // Flush existing table if it exists
-            json!({"flush": {"table": {"family": "inet", "name": table_name}}}),
-            // Create table
-            json!({"table": {"family": "inet", "name": table_name}}),
-        ];
+        let mut nftables = base_table_definition(table_name);
 
-        // Convert default_action to nftables policy
         let default_policy = match policy.default_action {
             Action::Accept => "accept",
-            Action::Drop => "drop",
-            Action::Reject => "drop", // nftables base chain policy doesn't support reject, use drop
+            Action::Drop | Action::Reject => "drop",
         };
 
-        // Add input chain with default policy
-        nftables.push(json!({
-            "chain": {
-                "family": "inet",
-                "table": table_name,
-                "name": "input",
-                "type": "filter",
-                "hook": "input",
-                "prio": 0,
-                "policy": default_policy
-            }
-        }));
-
-        // Add forward chain
-        nftables.push(json!({
-            "chain": {
-                "family": "inet",
-                "table": table_name,
-                "name": "forward",
-                "type": "filter",
-                "hook": "forward",
-                "prio": 0,
-                "policy": default_policy
-            }
-        }));
-
-        // Add output chain (usually permissive)
-        nftables.push(json!({
-            "chain": {
-                "family": "inet",
-                "table": table_name,
-                "name": "output",
-                "type": "filter",
-                "hook": "output",
-                "prio": 0,
-                "policy": "accept"
-            }
-        }));
-
-        // Add postrouting chain for NAT
-        nftables.push(json!({
-            "chain": {
-                "family": "inet",
-                "table": table_name,
-                "name": "postrouting",
-                "type": "nat",
-                "hook": "postrouting",
-                "prio": 100,
-                "policy": "accept"
-            }
-        }));
-
-        // Add prerouting chain for NAT
-        nftables.push(json!({
-            "chain": {
-                "family": "inet",
-                "table": table_name,
-                "name": "prerouting",
-                "type": "nat",
-                "hook": "prerouting",
-                "prio": -100,
-                "policy": "accept"
-            }
-        }));
-
-        // Allow established/related connections (critical for stateful firewall)
-        nftables.push(json!({
-            "rule": {
-                "family": "inet",
-                "table": table_name,
-                "chain": "input",
-                "expr": [
-                    {"match": {"left": {"ct": {"key": "state"}}, "op": "in", "right": ["established", "related"]}},
-                    {"accept": null}
-                ]
-            }
-        }));
-
-        nftables.push(json!({
-            "rule": {
-                "family": "inet",
-                "table": table_name,
-                "chain": "forward",
-                "expr": [
-                    {"match": {"left": {"ct": {"key": "state"}}, "op": "in", "right": ["established", "related"]}},
-                    {"accept": null}
-                ]
-            }
-        }));
-
-        // Allow loopback
-        nftables.push(json!({
-            "rule": {
-                "family": "inet",
-                "table": table_name,
-                "chain": "input",
-                "expr": [
-                    {"match": {"left": {"meta": {"key": "iifname"}}, "op": "==", "right": "lo"}},
-                    {"accept": null}
-                ]
-            }
-        }));
-
-        // Add service rules (ingress)
-        for service in &policy.services {
-            let proto = match service.protocol {
-                Protocol::Tcp => "tcp",
-                Protocol::Udp => "udp",
-                Protocol::Icmp => "icmp",
-            };
-
-            // Build expression based on protocol
-            let mut expr = vec![];
-
-            // Add interface match for bridge
-            expr.push(json!({"match": {"left": {"meta": {"key": "iifname"}}, "op": "==", "right": bridge_name}}));
-
-            // Add protocol match (except for ICMP)
-            if !matches!(service.protocol, Protocol::Icmp) {
-                expr.push(json!({"match": {"left": {"meta": {"key": "l4proto"}}, "op": "==", "right": proto}}));
-                expr.push(json!({"match": {"left": {"payload": {"protocol": proto, "field": "dport"}}, "op": "==", "right": service.port}}));
-            } else {
-                expr.push(json!({"match": {"left": {"meta": {"key": "l4proto"}}, "op": "==", "right": "icmp"}}));
-            }
-
-            // Add source CIDR match if specified
-            if let Some(ref source) = service.source {
-                expr.push(json!({"match": {"left": {"payload": {"protocol": "ip", "field": "saddr"}}, "op": "==", "right": source}}));
-            }
-
-            expr.push(json!({"accept": null}));
-
-            nftables.push(json!({
-                "rule": {
-                    "family": "inet",
-                    "table": table_name,
-                    "chain": "input",
-                    "expr": expr
-                }
-            }));
-        }
-
-        // Add ingress CIDR allow rules
-        for cidr in &policy.allowed_ingress_cidrs {
-            nftables.push(json!({
-                "rule": {
-                    "family": "inet",
-                    "table": table_name,
-                    "chain": "input",
-                    "expr": [
-                        {"match": {"left": {"meta": {"key": "iifname"}}, "op": "==", "right": bridge_name}},
-                        {"match": {"left": {"payload": {"protocol": "ip", "field": "saddr"}}, "op": "==", "right": cidr}},
-                        {"accept": null}
-                    ]
-                }
-            }));
-        }
-
-        // Add egress CIDR allow rules (for forward chain)
-        for cidr in &policy.allowed_egress_cidrs {
-            nftables.push(json!({
-                "rule": {
-                    "family": "inet",
-                    "table": table_name,
-                    "chain": "forward",
-                    "expr": [
-                        {"match": {"left": {"meta": {"key": "iifname"}}, "op": "==", "right": bridge_name}},
-                        {"match": {"left": {"payload": {"protocol": "ip", "field": "daddr"}}, "op": "==", "right": cidr}},
-                        {"accept": null}
-                    ]
-                }
-            }));
-        }
+        nftables.extend(base_filter_chain(table_name, default_policy));
+        nftables.extend(base_output_chain(table_name));
+        nftables.extend(base_nat_chains(table_name));
+        nftables.extend(stateful_allow_rules(table_name));
+        nftables.push(loopback_rule(table_name));
+        nftables.extend(self.policy_rules(table_name, bridge_name, policy)?);
 
         let ruleset = json!({"nftables": nftables});
         Ok(serde_json::to_string_pretty(&ruleset)?)
     }
 
-    /// Generate a complete ruleset with NAT + policy filtering
+    /// Generate a complete ruleset with NAT + policy filtering. `ipv6` is the
+    /// (CIDR, gateway) of a dual-stack network's second prefix; `hairpin` also
+    /// DNATs forwards for bridge clients that connect to the public address.
     #[allow(clippy::too_many_arguments)]
     pub fn create_complete_ruleset(
         &self,
@@ -261,9 +136,13 @@ impl NftManager {
         bridge_name: &str,
         bridge_cidr: &str,
         gateway_ip: &str,
+        ipv6: Option<(&str, &str)>,
         masq_iface: &str,
         forwards: &[(String, String)],
+        hairpin: bool,
         policy: Option<&PolicyProfile>,
+        ct_helpers: &[String],
+        quota: Option<&TrafficQuota>,
     ) -> Result<String> {
         let bridge_net: IpNet = bridge_cidr
             .parse()
@@ -271,6 +150,17 @@ impl NftManager {
         let gateway: IpAddr = gateway_ip
             .parse()
             .with_context(|| format!("Invalid gateway IP '{}'", gateway_ip))?;
+        let mut bridge_nets = vec![(bridge_net, gateway)];
+        if let Some((cidr6, gateway6)) = ipv6 {
+            bridge_nets.push((
+                cidr6
+                    .parse()
+                    .with_context(|| format!("Invalid bridge IPv6 CIDR '{}'", cidr6))?,
+                gateway6
+                    .parse()
+                    .with_context(|| format!("Invalid IPv6 gateway IP '{}'", gateway6))?,
+            ));
+        }
         let parsed_forwards = parse_forward_rules(forwards)?;
 
         let mut nftables = base_table_definition(table_name);
@@ -286,28 +176,84 @@ impl NftManager {
         nftables.extend(base_output_chain(table_name));
         nftables.extend(base_nat_chains(table_name));
 
+        // Quota rules precede the established/related accept so every packet is counted.
+        if let Some(quota) = quota {
+            nftables.extend(quota_rules(table_name, bridge_name, quota));
+        }
+
         nftables.extend(stateful_allow_rules(table_name));
         nftables.push(loopback_rule(table_name));
 
+        let counters = policy.is_some_and(|policy| policy.counters);
+
         if let Some(policy) = policy {
-            nftables.extend(policy_service_rules(table_name, bridge_name, policy)?);
-            nftables.extend(policy_ingress_rules(table_name, bridge_name, policy)?);
-            nftables.extend(policy_egress_rules(table_name, bridge_name, policy)?);
+            nftables.extend(self.policy_rules(table_name, bridge_name, policy)?);
         }
 
+        let nat_rules = nftables.len();
         nftables.extend(build_nat_rules(
             table_name,
             bridge_name,
-            &bridge_net,
-            &gateway,
+            &bridge_nets,
             masq_iface,
             &parsed_forwards,
-        ));
+            hairpin,
+        )?);
+        if counters {
+            add_counters(&mut nftables[nat_rules..]);
+        }
+
+        nftables.extend(ct_helper_rules(
+            table_name,
+            bridge_name,
+            &bridge_nets,
+            ct_helpers,
+        )?);
 
         let ruleset = json!({"nftables": nftables});
         Ok(serde_json::to_string_pretty(&ruleset)?)
     }
 
+    /// Service, CIDR and egress rules of `policy`, followed by the drop log and
+    /// reject rules that end the input and forward chains
+    fn policy_rules(
+        &self,
+        table_name: &str,
+        bridge_name: &str,
+        policy: &PolicyProfile,
+    ) -> Result<Vec<Value>> {
+        let mut rules = policy_service_rules(table_name, bridge_name, policy)?;
+        rules.extend(policy_ingress_rules(
+            table_name,
+            bridge_name,
+            policy,
+            self.cidr_sets,
+        )?);
+        rules.extend(policy_egress_rules(
+            table_name,
+            bridge_name,
+            policy,
+            self.cidr_sets,
+        )?);
+        rules.extend(policy_egress_service_rules(
+            table_name,
+            bridge_name,
+            policy,
+        )?);
+        if policy.counters {
+            add_counters(&mut rules);
+        }
+
+        // Last in input/forward, so only packets headed for the chain policy match.
+        if policy.logs_drops() {
+            rules.extend(drop_log_rules(table_name));
+        }
+        if matches!(policy.default_action, Action::Reject) {
+            rules.extend(reject_rules(table_name));
+        }
+        Ok(rules)
+    }
+
     /// Compare desired ruleset with the live table and return a textual diff
     pub async fn diff_ruleset(&self, table_name: &str, desired_ruleset: &str) -> Result<NftDiff> {
         let normalized_desired = normalize_json(desired_ruleset)
@@ -355,46 +301,141 @@ impl NftManager {
         Ok(snapshot)
     }
 
-    async fn apply_ruleset_payload(&self, payload: &str) -> Result<()> {
-        use tokio::io::AsyncWriteExt;
-        use tokio::process::Command;
+    /// Validate a ruleset with `nft -c` (check only, nothing is applied), so a
+    /// payload the running nft/kernel rejects is caught before apply.
+    pub async fn check_ruleset(&self, ruleset: &str) -> Result<()> {
+        let output = self
+            .runner
+            .run("nft", &["-j", "-c", "-f", "-"], Some(ruleset.as_bytes()))
+            .await
+            .context("Failed to run nft command")?;
 
-        let mut child = Command::new("nft")
-            .arg("-j")
-            .arg("-f")
-            .arg("-")
-            .stdin(std::process::Stdio::piped())
-            .stdout(std::process::Stdio::piped())
-            .stderr(std::process::Stdio::piped())
-            .spawn()
-            .context("Failed to spawn nft command")?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            let mut message = format!("nft rejected the ruleset: {}", stderr.trim());
+
+            if let Some((index, command)) = self.locate_failing_command(ruleset).await {
+                message.push_str(&format!(
+                    "\n  offending command #{}: {}",
+                    index + 1,
+                    describe_nft_command(&command)
+                ));
+            }
 
-        if let Some(mut stdin) = child.stdin.take() {
-            stdin.write_all(payload.as_bytes()).await?;
-            drop(stdin);
+            anyhow::bail!(message);
         }
 
-        let output = child.wait_with_output().await?;
+        Ok(())
+    }
+
+    async fn apply_ruleset_payload(&self, payload: &str) -> Result<()> {
+        let output = self
+            .runner
+            .run("nft", &["-j", "-f", "-"], Some(payload.as_bytes()))
+            .await
+            .context("Failed to run nft command")?;
 
         if !output.status.success() {
             let stderr = String::from_utf8_lossy(&output.stderr);
-            anyhow::bail!("nft command failed: {}", stderr);
+            let mut message = format!("nft command failed: {}", stderr.trim());
+
+            if let Some((index, command)) = self.locate_failing_command(payload).await {
+                message.push_str(&format!(
+                    "\n  offending command #{}: {}",
+                    index + 1,
+                    describe_nft_command(&command)
+                ));
+            }
+
+            match dump_failed_payload(payload) {
+                Ok(path) => {
+                    message.push_str(&format!("\n  full payload saved to {}", path.display()))
+                }
+                Err(err) => message.push_str(&format!("\n  could not save payload: {}", err)),
+            }
+
+            anyhow::bail!(message);
         }
 
         Ok(())
     }
 
+    /// Find the first command in a failed payload that nft rejects, by
+    /// binary-searching prefixes with `nft -c` (check only, nothing applied).
+    async fn locate_failing_command(&self, payload: &str) -> Option<(usize, Value)> {
+        let parsed: Value = serde_json::from_str(payload).ok()?;
+        let commands = parsed.get("nftables")?.as_array()?;
+        if commands.is_empty() {
+            return None;
+        }
+
+        if self.check_commands(commands).await? {
+            // The failure does not reproduce in check mode (e.g. a runtime conflict).
+            return None;
+        }
+
+        let (mut low, mut high) = (0, commands.len() - 1);
+        while low < high {
+            let mid = (low + high) / 2;
+            if self.check_commands(&commands[..=mid]).await? {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+
+        Some((low, commands[low].clone()))
+    }
+
+    /// `Some(true)` when nft accepts the commands in check mode.
+    async fn check_commands(&self, commands: &[Value]) -> Option<bool> {
+        let payload = json!({"nftables": commands}).to_string();
+        let output = self
+            .runner
+            .run("nft", &["-c", "-j", "-f", "-"], Some(payload.as_bytes()))
+            .await
+            .ok()?;
+
+        Some(output.status.success())
+    }
+
+    /// Packet and byte counts of the table's rules that carry a counter, each
+    /// named after the topology/policy element that produced it.
+    pub async fn read_counters(&self, table_name: &str) -> Result<Vec<RuleCounter>> {
+        let raw = self
+            .snapshot_table(table_name)
+            .await?
+            .ok_or_else(|| anyhow!("Table {} not found", table_name))?;
+        let parsed: Value = serde_json::from_str(&raw)
+            .with_context(|| format!("Failed to parse live ruleset for {}", table_name))?;
+
+        let commands = parsed
+            .get("nftables")
+            .and_then(Value::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        Ok(commands
+            .iter()
+            .filter_map(|command| {
+                let counter = command
+                    .pointer("/rule/expr")?
+                    .as_array()?
+                    .iter()
+                    .find_map(|expr| expr.get("counter"))?;
+                Some((
+                    describe_nft_command(command),
+                    counter.get("packets")?.as_u64()?,
+                    counter.get("bytes")?.as_u64()?,
+                ))
+            })
+            .collect())
+    }
+
     /// Snapshot an existing table, returning the JSON definition if it exists
     pub async fn snapshot_table(&self, table_name: &str) -> Result<Option<String>> {
-        use tokio::process::Command;
-
-        let output = Command::new("nft")
-            .arg("-j")
-            .arg("list")
-            .arg("table")
-            .arg("inet")
-            .arg(table_name)
-            .output()
+        let output = self
+            .runner
+            .run("nft", &["-j", "list", "table", "inet", table_name], None)
             .await
             .context("Failed to list nftables table")?;
 
@@ -411,6 +452,65 @@ impl NftManager {
         anyhow::bail!("Failed to snapshot table {}: {}", table_name, stderr);
     }
 
+    /// Write the full live ruleset (`nft -j list ruleset`, every table, not only
+    /// ghostwarden's) to `path` as JSON, for backup or offline review.
+    pub async fn dump_ruleset_to_file(&self, path: &Path) -> Result<()> {
+        let output = self.run_nft(&["-j", "list", "ruleset"], None).await?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            if is_permission_error(&stderr) {
+                anyhow::bail!("Permission denied listing the nftables ruleset (run as root)");
+            }
+            anyhow::bail!("Failed to list nftables ruleset: {}", stderr.trim());
+        }
+
+        std::fs::write(path, &output.stdout).map_err(|err| file_error(err, "write", path))?;
+        println!("Saved nftables ruleset to {}", path.display());
+        Ok(())
+    }
+
+    /// Replace the live ruleset with one saved by [`Self::dump_ruleset_to_file`].
+    /// The flush and the load go to nft as one transaction, so a file nft
+    /// rejects leaves the current ruleset in place.
+    pub async fn apply_ruleset_from_file(&self, path: &Path) -> Result<()> {
+        let data = std::fs::read_to_string(path).map_err(|err| file_error(err, "read", path))?;
+        let parsed: Value = serde_json::from_str(&data)
+            .with_context(|| format!("{} is not valid JSON", path.display()))?;
+        let commands = parsed
+            .get("nftables")
+            .and_then(Value::as_array)
+            .ok_or_else(|| anyhow!("{} has no top-level \"nftables\" array", path.display()))?;
+
+        let mut payload = vec![json!({"flush": {"ruleset": null}})];
+        payload.extend(commands.iter().cloned());
+        let payload = json!({"nftables": payload}).to_string();
+
+        let output = self
+            .run_nft(&["-j", "-f", "-"], Some(payload.as_bytes()))
+            .await?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            if is_permission_error(&stderr) {
+                anyhow::bail!("Permission denied loading the nftables ruleset (run as root)");
+            }
+            anyhow::bail!("nft rejected {}: {}", path.display(), stderr.trim());
+        }
+
+        println!("Loaded nftables ruleset from {}", path.display());
+        Ok(())
+    }
+
+    /// Run nft, reporting a missing binary separately from other failures.
+    async fn run_nft(&self, args: &[&str], stdin: Option<&[u8]>) -> Result<Output> {
+        match self.runner.run("nft", args, stdin).await {
+            Ok(output) => Ok(output),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                Err(anyhow!("nft not found; install nftables"))
+            }
+            Err(err) => Err(err).context("Failed to run nft command"),
+        }
+    }
+
     /// Restore a table from a snapshot, or delete it if no snapshot existed
     pub async fn restore_table_from_snapshot(
         &self,
@@ -432,14 +532,9 @@ impl NftManager {
 
     /// Delete nftables table
     pub async fn delete_table(&self, table_name: &str) -> Result<()> {
-        use tokio::process::Command;
-
-        let output = Command::new("nft")
-            .arg("delete")
-            .arg("table")
-            .arg("inet")
-            .arg(table_name)
-            .output()
+        let output = self
+            .runner
+            .run("nft", &["delete", "table", "inet", table_name], None)
             .await
             .context("Failed to run nft delete table")?;
 
@@ -457,13 +552,9 @@ impl NftManager {
 
     /// List existing tables
     pub async fn list_tables(&self) -> Result<Vec<String>> {
-        use tokio::process::Command;
-
-        let output = Command::new("nft")
-            .arg("-j")
-            .arg("list")
-            .arg("tables")
-            .output()
+        let output = self
+            .runner
+            .run("nft", &["-j", "list", "tables"], None)
             .await
             .context("Failed to list nftables tables")?;
 
@@ -518,58 +609,271 @@ fn render_diff(current: &str, desired: &str) -> String {
     output
 }
 
-fn base_table_definition(table_name: &str) -> Vec<Value> {
-    vec![
-        json!({"flush": {"table": {"family": "inet", "name": table_name}}}),
-        json!({"table": {"family": "inet", "name": table_name}}),
-    ]
+fn is_permission_error(stderr: &str) -> bool {
+    stderr.contains("Operation not permitted") || stderr.contains("Permission denied")
 }
 
-fn base_filter_chain(table_name: &str, default_policy: &str) -> Vec<Value> {
-    vec![
-        json!({
-            "chain": {
-                "family": "inet",
-                "table": table_name,
-                "name": "input",
-                "type": "filter",
-                "hook": "input",
-                "prio": 0,
-                "policy": default_policy,
-            },
-        }),
-        json!({
-            "chain": {
-                "family": "inet",
-                "table": table_name,
-                "name": "forward",
-                "type": "filter",
-                "hook": "forward",
-                "prio": 0,
-                "policy": default_policy,
-            },
-        }),
-    ]
+fn file_error(err: std::io::Error, verb: &str, path: &Path) -> anyhow::Error {
+    if err.kind() == std::io::ErrorKind::PermissionDenied {
+        anyhow!("Permission denied: cannot {} {}", verb, path.display())
+    } else {
+        anyhow!(err).context(format!("Failed to {} {}", verb, path.display()))
+    }
 }
 
-fn base_output_chain(table_name: &str) -> Vec<Value> {
-    vec![json!({
-        "chain": {
-            "family": "inet",
-            "table": table_name,
-            "name": "output",
-            "type": "filter",
-            "hook": "output",
-            "prio": 0,
-            "policy": "accept",
+/// Save a rejected payload for inspection under a random name that only the
+/// current user can read. The file is created with `O_EXCL`, so a file or
+/// symlink planted at a guessable path is never written through.
+fn dump_failed_payload(payload: &str) -> std::io::Result<std::path::PathBuf> {
+    use rand::RngExt;
+    use std::io::Write;
+    use std::os::unix::fs::OpenOptionsExt;
+
+    let dir = std::env::temp_dir();
+    for _ in 0..16 {
+        let suffix: u64 = rand::rng().random();
+        let path = dir.join(format!("gwarden-nft-failed-{:016x}.json", suffix));
+        let file = std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(&path);
+
+        match file {
+            Ok(mut file) => {
+                file.write_all(payload.as_bytes())?;
+                return Ok(path);
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(err) => return Err(err),
         }
-    })]
+    }
+
+    Err(std::io::ErrorKind::AlreadyExists.into())
 }
 
-fn base_nat_chains(table_name: &str) -> Vec<Value> {
-    vec![
-        json!({
-            "chain": {
+/// Map a generated nftables JSON command back to the topology/policy element
+/// that produced it, for error messages.
+fn describe_nft_command(command: &Value) -> String {
+    if let Some(table) = command.pointer("/table/name").and_then(Value::as_str) {
+        return format!("create table {}", table);
+    }
+    if let Some(table) = command.pointer("/flush/table/name").and_then(Value::as_str) {
+        return format!("flush table {}", table);
+    }
+    if let Some(chain) = command.pointer("/chain/name").and_then(Value::as_str) {
+        return format!("create chain {}", chain);
+    }
+    if let Some(quota) = command.pointer("/quota/name").and_then(Value::as_str) {
+        return format!("create quota {}", quota);
+    }
+    if let Some(set) = command.pointer("/set/name").and_then(Value::as_str) {
+        return format!("create set {}", set);
+    }
+
+    let Some(rule) = command.get("rule") else {
+        return command.to_string();
+    };
+    let chain = rule.get("chain").and_then(Value::as_str).unwrap_or("?");
+    let exprs = rule
+        .get("expr")
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+
+    let proto = match_right(exprs, "l4proto");
+    let dport = match_right(exprs, "dport");
+    let saddr = match_right(exprs, "saddr");
+    let daddr = match_right(exprs, "daddr");
+    let has = |key: &str| exprs.iter().any(|expr| expr.get(key).is_some());
+
+    if let Some(dnat) = exprs.iter().find_map(|expr| expr.get("dnat")) {
+        let mut addr = dnat.get("addr").map(render_value).unwrap_or_default();
+        if addr.contains(':') {
+            addr = format!("[{}]", addr);
+        }
+        let port = dnat.get("port").map(render_dnat_port).unwrap_or_default();
+        return format!(
+            "{}port forward {}/{} -> {}:{}",
+            if saddr.is_some() { "hairpin " } else { "" },
+            dport.unwrap_or_default(),
+            proto.unwrap_or_default(),
+            addr,
+            port
+        );
+    }
+    if has("snat") {
+        return format!(
+            "hairpin SNAT for port forward to {}",
+            daddr.unwrap_or_default()
+        );
+    }
+    if has("masquerade") {
+        return format!(
+            "masquerade out {}",
+            match_right(exprs, "oifname").unwrap_or_default()
+        );
+    }
+    if let Some(quota) = exprs
+        .iter()
+        .find_map(|expr| expr.get("quota"))
+        .and_then(Value::as_str)
+    {
+        let iface = match_right(exprs, "iifname")
+            .map(|iface| format!("from {}", iface))
+            .or_else(|| match_right(exprs, "oifname").map(|iface| format!("to {}", iface)))
+            .unwrap_or_default();
+        return format!("traffic quota {} {}", quota, iface);
+    }
+    if has("log") {
+        return format!("drop log in {}", chain);
+    }
+    if has("reject") {
+        return format!("default reject in {}", chain);
+    }
+    if has("match")
+        && exprs
+            .iter()
+            .any(|expr| expr.pointer("/match/left/ct").is_some())
+    {
+        return format!("established/related accept in {}", chain);
+    }
+
+    match (chain, dport, saddr.as_deref(), daddr) {
+        ("input", None, source, _) if proto.as_deref().is_some_and(|p| p.contains("icmp")) => {
+            format!(
+                "policy service icmp{}",
+                source.map(|s| format!(" from {}", s)).unwrap_or_default()
+            )
+        }
+        ("input", Some(port), source, _) => format!(
+            "policy service {}/{}{}",
+            proto.unwrap_or_default(),
+            port,
+            source.map(|s| format!(" from {}", s)).unwrap_or_default()
+        ),
+        ("input", None, Some(source), _) => format!("policy ingress CIDR {}", source),
+        ("forward", Some(port), _, _) => format!(
+            "policy egress service {}/{}",
+            proto.unwrap_or_default(),
+            port
+        ),
+        ("forward", None, _, Some(dest)) => format!("policy egress CIDR {}", dest),
+        _ if match_right(exprs, "iifname").as_deref() == Some("lo") => {
+            "loopback accept".to_string()
+        }
+        _ => format!("rule in chain {}", chain),
+    }
+}
+
+/// Right-hand side of the `match` whose left side is `meta.key` or `payload.field` == `key`.
+fn match_right(exprs: &[Value], key: &str) -> Option<String> {
+    exprs.iter().find_map(|expr| {
+        let m = expr.get("match")?;
+        let left = m.get("left")?;
+        let matches_key = left.pointer("/meta/key").and_then(Value::as_str) == Some(key)
+            || left.pointer("/payload/field").and_then(Value::as_str) == Some(key);
+        if !matches_key {
+            return None;
+        }
+        m.get("right").map(render_value)
+    })
+}
+
+/// A port-map DNAT renders as the destination range it covers.
+fn render_dnat_port(port: &Value) -> String {
+    let targets: Vec<String> = port
+        .pointer("/map/data/set")
+        .and_then(Value::as_array)
+        .map(|pairs| {
+            pairs
+                .iter()
+                .filter_map(|pair| pair.get(1))
+                .map(render_value)
+                .collect()
+        })
+        .unwrap_or_default();
+    match targets.as_slice() {
+        [first, .., last] => format!("{}-{}", first, last),
+        _ => render_value(port),
+    }
+}
+
+fn render_value(value: &Value) -> String {
+    if let Some(prefix) = value.get("prefix") {
+        let addr = prefix.get("addr").map(render_value).unwrap_or_default();
+        let len = prefix.get("len").map(render_value).unwrap_or_default();
+        return format!("{}/{}", addr, len);
+    }
+    if let Some(items) = value.get("set").and_then(Value::as_array) {
+        let items: Vec<String> = items.iter().map(render_value).collect();
+        return format!("{{ {} }}", items.join(", "));
+    }
+    if let Some([start, end]) = value
+        .get("range")
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+    {
+        return format!("{}-{}", render_value(start), render_value(end));
+    }
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn base_table_definition(table_name: &str) -> Vec<Value> {
+    vec![
+        json!({"flush": {"table": {"family": "inet", "name": table_name}}}),
+        json!({"table": {"family": "inet", "name": table_name}}),
+    ]
+}
+
+fn base_filter_chain(table_name: &str, default_policy: &str) -> Vec<Value> {
+    vec![
+        json!({
+            "chain": {
+                "family": "inet",
+                "table": table_name,
+                "name": "input",
+                "type": "filter",
+                "hook": "input",
+                "prio": 0,
+                "policy": default_policy,
+            },
+        }),
+        json!({
+            "chain": {
+                "family": "inet",
+                "table": table_name,
+                "name": "forward",
+                "type": "filter",
+                "hook": "forward",
+                "prio": 0,
+                "policy": default_policy,
+            },
+        }),
+    ]
+}
+
+fn base_output_chain(table_name: &str) -> Vec<Value> {
+    vec![json!({
+        "chain": {
+            "family": "inet",
+            "table": table_name,
+            "name": "output",
+            "type": "filter",
+            "hook": "output",
+            "prio": 0,
+            "policy": "accept",
+        }
+    })]
+}
+
+fn base_nat_chains(table_name: &str) -> Vec<Value> {
+    vec![
+        json!({
+            "chain": {
                 "family": "inet",
                 "table": table_name,
                 "name": "postrouting",
@@ -619,141 +923,339 @@ fn policy_service_rules(
     bridge_name: &str,
     policy: &PolicyProfile,
 ) -> Result<Vec<Value>> {
-    let mut rules = Vec::new();
+    let icmp_limit = policy.effective_icmp_rate_limit();
+    policy
+        .services
+        .iter()
+        .map(|service| service_rule(table_name, "input", bridge_name, service, icmp_limit))
+        .collect()
+}
 
-    for service in &policy.services {
-        let proto = match service.protocol {
-            Protocol::Tcp => ForwardProtocol::Tcp,
-            Protocol::Udp => ForwardProtocol::Udp,
-            Protocol::Icmp => ForwardProtocol::Icmp,
-        };
+/// Egress services match on protocol/dport only, so they apply to any destination.
+fn policy_egress_service_rules(
+    table_name: &str,
+    bridge_name: &str,
+    policy: &PolicyProfile,
+) -> Result<Vec<Value>> {
+    policy
+        .allowed_egress_services
+        .iter()
+        .map(|service| service_rule(table_name, "forward", bridge_name, service, None))
+        .collect()
+}
 
-        let mut expr = vec![match_iface("iifname", bridge_name)];
+fn service_rule(
+    table_name: &str,
+    chain: &str,
+    bridge_name: &str,
+    service: &Service,
+    icmp_limit: Option<&IcmpRateLimit>,
+) -> Result<Value> {
+    service.validate()?;
+
+    let proto = match service.protocol {
+        Protocol::Tcp => ForwardProtocol::Tcp,
+        Protocol::Udp => ForwardProtocol::Udp,
+        Protocol::Sctp => ForwardProtocol::Sctp,
+        Protocol::Icmp => ForwardProtocol::Icmp,
+    };
 
-        match proto {
-            ForwardProtocol::Icmp => {
-                expr.push(match_l4proto("icmp"));
-            }
-            ForwardProtocol::Tcp | ForwardProtocol::Udp => {
-                let proto_str = proto.as_str();
-                expr.push(match_l4proto(proto_str));
-                expr.push(match_port(proto_str, "dport", service.port));
-            }
-        }
+    let mut expr = vec![match_iface("iifname", bridge_name)];
 
-        if let Some(ref source) = service.source {
-            let net = parse_ipnet(source)?;
-            expr.push(match_ip_prefix_expr("saddr", &net));
+    match proto {
+        ForwardProtocol::Icmp => {
+            // The table is inet, so an ICMP service covers ICMPv6 (ping, PMTU) too.
+            expr.push(json!({
+                "match": {
+                    "left": {"meta": {"key": "l4proto"}},
+                    "op": "==",
+                    "right": {"set": ["icmp", "ipv6-icmp"]},
+                }
+            }));
         }
+        ForwardProtocol::Tcp | ForwardProtocol::Udp | ForwardProtocol::Sctp => {
+            let proto_str = proto.as_str();
+            expr.push(match_l4proto(proto_str));
+            expr.push(match_ports(proto_str, "dport", &service.dports()));
+        }
+    }
 
-        expr.push(accept_expr());
+    if let Some(ref source) = service.source {
+        let net = parse_ipnet(source)?;
+        expr.push(match_ip_prefix_expr("saddr", &net));
+    }
 
-        rules.push(json!({
-            "rule": {
-                "family": "inet",
-                "table": table_name,
-                "chain": "input",
-                "expr": expr,
-            }
+    // A service's own limit replaces the default ICMP one.
+    if let Some(limit) = service.parsed_rate_limit()? {
+        let mut limit_expr = json!({"rate": limit.rate, "per": limit.per});
+        if let Some(burst) = limit.burst {
+            limit_expr["burst"] = json!(burst);
+        }
+        expr.push(json!({"limit": limit_expr}));
+    } else if matches!(proto, ForwardProtocol::Icmp)
+        && let Some(limit) = icmp_limit
+    {
+        expr.push(json!({
+            "limit": {"rate": limit.rate, "per": "second", "burst": limit.burst}
         }));
     }
 
-    Ok(rules)
+    expr.push(accept_expr());
+
+    Ok(json!({
+        "rule": {
+            "family": "inet",
+            "table": table_name,
+            "chain": chain,
+            "expr": expr,
+        }
+    }))
+}
+
+/// Insert an anonymous counter before the verdict (last expression) of each rule.
+fn add_counters(commands: &mut [Value]) {
+    for command in commands {
+        if let Some(expr) = command
+            .pointer_mut("/rule/expr")
+            .and_then(Value::as_array_mut)
+            && !expr.is_empty()
+        {
+            expr.insert(expr.len() - 1, json!({"counter": null}));
+        }
+    }
+}
+
+/// Log prefix of packets dropped by a policy's default action.
+const DROP_LOG_PREFIX: &str = "gw-drop ";
+
+/// Logged drops per second (and burst); a scan or flood would otherwise fill the
+/// kernel log. The counter still sees every dropped packet.
+const DROP_LOG_RATE: u32 = 5;
+const DROP_LOG_BURST: u32 = 10;
+
+fn drop_log_rules(table_name: &str) -> Vec<Value> {
+    ["input", "forward"]
+        .into_iter()
+        .map(|chain| {
+            json!({
+                "rule": {
+                    "family": "inet",
+                    "table": table_name,
+                    "chain": chain,
+                    "expr": [
+                        {"counter": null},
+                        {"limit": {"rate": DROP_LOG_RATE, "per": "second", "burst": DROP_LOG_BURST}},
+                        {"log": {"prefix": DROP_LOG_PREFIX}},
+                    ],
+                }
+            })
+        })
+        .collect()
+}
+
+/// Base chain policies can only accept or drop, so a reject default keeps the
+/// `drop` policy and ends `input`/`forward` with an explicit reject (ICMP
+/// admin-prohibited for either family, TCP included).
+fn reject_rules(table_name: &str) -> Vec<Value> {
+    ["input", "forward"]
+        .into_iter()
+        .map(|chain| {
+            json!({
+                "rule": {
+                    "family": "inet",
+                    "table": table_name,
+                    "chain": chain,
+                    "expr": [
+                        {"reject": {"type": "icmpx", "expr": "admin-prohibited"}},
+                    ],
+                }
+            })
+        })
+        .collect()
 }
 
 fn policy_ingress_rules(
     table_name: &str,
     bridge_name: &str,
     policy: &PolicyProfile,
+    cidr_sets: bool,
 ) -> Result<Vec<Value>> {
-    let mut rules = Vec::new();
-
-    for cidr in &policy.allowed_ingress_cidrs {
-        let net = parse_ipnet(cidr)?;
-        let expr = vec![
-            match_iface("iifname", bridge_name),
-            match_ip_prefix_expr("saddr", &net),
-            accept_expr(),
-        ];
-
-        rules.push(json!({
-            "rule": {
-                "family": "inet",
-                "table": table_name,
-                "chain": "input",
-                "expr": expr,
-            }
-        }));
-    }
-
-    Ok(rules)
+    cidr_accept_rules(
+        table_name,
+        bridge_name,
+        ("input", "saddr", "ingress"),
+        &policy.allowed_ingress_cidrs,
+        cidr_sets,
+    )
 }
 
 fn policy_egress_rules(
     table_name: &str,
     bridge_name: &str,
     policy: &PolicyProfile,
+    cidr_sets: bool,
 ) -> Result<Vec<Value>> {
-    let mut rules = Vec::new();
-
-    for cidr in &policy.allowed_egress_cidrs {
-        let net = parse_ipnet(cidr)?;
-        let expr = vec![
-            match_iface("iifname", bridge_name),
-            match_ip_prefix_expr("daddr", &net),
-            accept_expr(),
-        ];
+    cidr_accept_rules(
+        table_name,
+        bridge_name,
+        ("forward", "daddr", "egress"),
+        &policy.allowed_egress_cidrs,
+        cidr_sets,
+    )
+}
 
-        rules.push(json!({
+/// Accept traffic from the bridge whose `field` address is in `cidrs`, in
+/// `chain`. With `cidr_sets` each address family gets a named interval set
+/// (`<name>_v4`, `<name>_v6`) and one rule matching membership; otherwise
+/// there is one rule per CIDR.
+fn cidr_accept_rules(
+    table_name: &str,
+    bridge_name: &str,
+    (chain, field, name): (&str, &str, &str),
+    cidrs: &[String],
+    cidr_sets: bool,
+) -> Result<Vec<Value>> {
+    let nets = cidrs
+        .iter()
+        .map(|cidr| parse_ipnet(cidr))
+        .collect::<Result<Vec<_>>>()?;
+    let rule = |address_match: Value| {
+        json!({
             "rule": {
                 "family": "inet",
                 "table": table_name,
-                "chain": "forward",
-                "expr": expr,
+                "chain": chain,
+                "expr": [
+                    match_iface("iifname", bridge_name),
+                    address_match,
+                    accept_expr(),
+                ],
+            }
+        })
+    };
+
+    if !cidr_sets {
+        return Ok(nets
+            .iter()
+            .map(|net| rule(match_ip_prefix_expr(field, net)))
+            .collect());
+    }
+
+    let mut commands = Vec::new();
+    for (protocol, set_type, suffix) in [("ip", "ipv4_addr", "v4"), ("ip6", "ipv6_addr", "v6")] {
+        let elements: Vec<Value> = nets
+            .iter()
+            .filter(|net| ipnet_protocol(net) == protocol)
+            .map(|net| {
+                let net = net.trunc();
+                json!({"prefix": {"addr": net.addr().to_string(), "len": net.prefix_len()}})
+            })
+            .collect();
+        if elements.is_empty() {
+            continue;
+        }
+
+        let set_name = format!("{}_{}", name, suffix);
+        // auto-merge folds overlapping CIDRs, which interval sets otherwise reject
+        commands.push(json!({
+            "set": {
+                "family": "inet",
+                "table": table_name,
+                "name": set_name,
+                "type": set_type,
+                "flags": ["interval"],
+                "auto-merge": true,
+                "elem": elements,
             }
         }));
+        commands.push(rule(json!({
+            "match": {
+                "left": {"payload": {"protocol": protocol, "field": field}},
+                "op": "==",
+                "right": format!("@{}", set_name),
+            }
+        })));
     }
 
-    Ok(rules)
+    Ok(commands)
 }
 
+/// Forwards are emitted in the order given. Prerouting is first-match, so callers
+/// pass them highest-precedence first (see `gw_core::nft_config_for_table`).
+///
+/// `bridge_nets` holds a (prefix, gateway) pair per address family; each gets
+/// its own masquerade rule and serves the forwards of its family.
+///
+/// With `hairpin`, each forward also gets a DNAT for bridge clients addressing
+/// the public side: its `public_addr`, or any local address for wildcard
+/// forwards. The hairpin SNAT then routes replies back through the gateway.
 fn build_nat_rules(
     table_name: &str,
     bridge_name: &str,
-    bridge_net: &IpNet,
-    gateway: &IpAddr,
+    bridge_nets: &[(IpNet, IpAddr)],
     masq_iface: &str,
     forwards: &[ForwardRule],
-) -> Vec<Value> {
+    hairpin: bool,
+) -> Result<Vec<Value>> {
     let mut rules = Vec::new();
 
     if !masq_iface.is_empty() {
-        rules.push(json!({
-            "rule": {
-                "family": "inet",
-                "table": table_name,
-                "chain": "postrouting",
-                "expr": [
-                    match_iface("oifname", masq_iface),
-                    match_ip_prefix_expr("saddr", bridge_net),
-                    json!({"masquerade": null}),
-                ],
-            }
-        }));
+        for (bridge_net, _) in bridge_nets {
+            rules.push(json!({
+                "rule": {
+                    "family": "inet",
+                    "table": table_name,
+                    "chain": "postrouting",
+                    "expr": [
+                        match_iface("oifname", masq_iface),
+                        match_ip_prefix_expr("saddr", bridge_net),
+                        json!({"masquerade": null}),
+                    ],
+                }
+            }));
+        }
     }
 
     for forward in forwards {
-        let mut prerouting_expr = vec![
-            match_iface("iifname", masq_iface),
-            match_l4proto(forward.protocol.as_str()),
-            match_port(forward.protocol.as_str(), "dport", forward.public_port),
-        ];
+        let (bridge_net, gateway) = bridge_nets
+            .iter()
+            .find(|(net, _)| ipnet_protocol(net) == ip_protocol(&forward.dest_addr))
+            .ok_or_else(|| {
+                anyhow!(
+                    "Forward to {} needs a bridge prefix of the same address family",
+                    forward.dest_addr
+                )
+            })?;
+        if let Some(addr) = forward.public_addr
+            && ip_protocol(&addr) != ip_protocol(&forward.dest_addr)
+        {
+            anyhow::bail!(
+                "Forward from {} to {} crosses address families",
+                addr,
+                forward.dest_addr
+            );
+        }
+
+        // Policy-only tables have no uplink, so DNAT matches on any interface
+        // except the bridge itself; bridge clients only reach it via hairpin.
+        let mut prerouting_expr = Vec::new();
+        if masq_iface.is_empty() {
+            prerouting_expr.push(exclude_iface("iifname", bridge_name));
+        } else {
+            prerouting_expr.push(match_iface("iifname", masq_iface));
+        }
+        prerouting_expr.push(match_l4proto(forward.protocol.as_str()));
+        prerouting_expr.push(match_port_range(
+            forward.protocol.as_str(),
+            "dport",
+            forward.public_port,
+        ));
 
         if let Some(addr) = forward.public_addr {
             prerouting_expr.push(match_ip_addr_expr("daddr", &addr));
         }
 
-        prerouting_expr.push(dnat_expr(&forward.dest_addr, forward.dest_port));
+        prerouting_expr.push(dnat_expr(&forward.dest_addr, forward_dnat_port(forward)));
 
         rules.push(json!({
             "rule": {
@@ -764,11 +1266,39 @@ fn build_nat_rules(
             }
         }));
 
+        if hairpin {
+            let public_match = match forward.public_addr {
+                Some(addr) => match_ip_addr_expr("daddr", &addr),
+                None => json!({
+                    "match": {
+                        "left": {"fib": {"result": "type", "flags": ["daddr"]}},
+                        "op": "==",
+                        "right": "local",
+                    }
+                }),
+            };
+            rules.push(json!({
+                "rule": {
+                    "family": "inet",
+                    "table": table_name,
+                    "chain": "prerouting",
+                    "expr": [
+                        match_iface("iifname", bridge_name),
+                        match_ip_prefix_expr("saddr", bridge_net),
+                        public_match,
+                        match_l4proto(forward.protocol.as_str()),
+                        match_port_range(forward.protocol.as_str(), "dport", forward.public_port),
+                        dnat_expr(&forward.dest_addr, forward_dnat_port(forward)),
+                    ],
+                }
+            }));
+        }
+
         let postrouting_expr = vec![
             match_iface("iifname", bridge_name),
             match_iface("oifname", bridge_name),
             match_l4proto(forward.protocol.as_str()),
-            match_port(forward.protocol.as_str(), "dport", forward.dest_port),
+            match_port_range(forward.protocol.as_str(), "dport", forward.dest_port),
             match_ip_prefix_expr("saddr", bridge_net),
             match_ip_addr_expr("daddr", &forward.dest_addr),
             snat_expr(gateway),
@@ -784,23 +1314,125 @@ fn build_nat_rules(
         }));
     }
 
-    rules
+    Ok(rules)
 }
 
-fn ct_state_accept_rule(table_name: &str, chain: &str) -> Value {
-    let expr = vec![
-        json!({
-            "match": {
-                "left": {"ct": {"key": "state"}},
-                "op": "in",
-                "right": ["established", "related"],
-            }
-        }),
-        accept_expr(),
-    ];
+/// Declare `ct helper` objects and bind them in a prerouting filter chain, for
+/// both traffic leaving the bridge and (post-DNAT) traffic forwarded into it.
+fn ct_helper_rules(
+    table_name: &str,
+    bridge_name: &str,
+    bridge_nets: &[(IpNet, IpAddr)],
+    ct_helpers: &[String],
+) -> Result<Vec<Value>> {
+    if ct_helpers.is_empty() {
+        return Ok(Vec::new());
+    }
 
-    json!({
-        "rule": {
+    let mut rules = vec![json!({
+        "chain": {
+            "family": "inet",
+            "table": table_name,
+            "name": "helpers",
+            "type": "filter",
+            "hook": "prerouting",
+            "prio": 0,
+            "policy": "accept",
+        }
+    })];
+
+    for name in ct_helpers {
+        let helper =
+            CtHelper::lookup(name).ok_or_else(|| anyhow!("Unknown conntrack helper '{}'", name))?;
+
+        rules.push(json!({
+            "ct helper": {
+                "family": "inet",
+                "table": table_name,
+                "name": helper.name,
+                "type": helper.name,
+                "protocol": helper.protocol,
+            }
+        }));
+
+        let scopes = std::iter::once(match_iface("iifname", bridge_name)).chain(
+            bridge_nets
+                .iter()
+                .map(|(net, _)| match_ip_prefix_expr("daddr", net)),
+        );
+        for scope in scopes {
+            rules.push(json!({
+                "rule": {
+                    "family": "inet",
+                    "table": table_name,
+                    "chain": "helpers",
+                    "expr": [
+                        scope,
+                        match_l4proto(helper.protocol),
+                        match_port(helper.protocol, "dport", helper.port),
+                        {"mangle": {"key": {"ct": {"key": "helper"}}, "value": helper.name}},
+                    ],
+                }
+            }));
+        }
+    }
+
+    Ok(rules)
+}
+
+/// Name of the per-table `quota` object backing a network's traffic quota.
+pub const QUOTA_OBJECT: &str = "traffic";
+
+/// Declare the network's `quota` object and reference it for forwarded traffic
+/// entering and leaving the bridge. In `over` mode the reference drops packets
+/// once the budget is spent; in `until` mode it only meters them.
+fn quota_rules(table_name: &str, bridge_name: &str, quota: &TrafficQuota) -> Vec<Value> {
+    let over = quota.mode == QuotaMode::Over;
+    let mut rules = vec![json!({
+        "quota": {
+            "family": "inet",
+            "table": table_name,
+            "name": QUOTA_OBJECT,
+            "bytes": quota.bytes,
+            "inv": over,
+        }
+    })];
+
+    for field in ["iifname", "oifname"] {
+        let mut expr = vec![
+            match_iface(field, bridge_name),
+            json!({"quota": QUOTA_OBJECT}),
+        ];
+        if over {
+            expr.push(json!({"drop": null}));
+        }
+        rules.push(json!({
+            "rule": {
+                "family": "inet",
+                "table": table_name,
+                "chain": "forward",
+                "expr": expr,
+            }
+        }));
+    }
+
+    rules
+}
+
+fn ct_state_accept_rule(table_name: &str, chain: &str) -> Value {
+    let expr = vec![
+        json!({
+            "match": {
+                "left": {"ct": {"key": "state"}},
+                "op": "in",
+                "right": ["established", "related"],
+            }
+        }),
+        accept_expr(),
+    ];
+
+    json!({
+        "rule": {
             "family": "inet",
             "table": table_name,
             "chain": chain,
@@ -819,6 +1451,16 @@ fn match_iface(key: &str, iface: &str) -> Value {
     })
 }
 
+fn exclude_iface(key: &str, iface: &str) -> Value {
+    json!({
+        "match": {
+            "left": {"meta": {"key": key}},
+            "op": "!=",
+            "right": iface,
+        }
+    })
+}
+
 fn match_l4proto(proto: &str) -> Value {
     json!({
         "match": {
@@ -839,6 +1481,40 @@ fn match_port(proto: &str, field: &str, port: u16) -> Value {
     })
 }
 
+/// Matches one port directly, or several through an anonymous set.
+fn match_ports(proto: &str, field: &str, ports: &[u16]) -> Value {
+    json!({
+        "match": {
+            "left": {"payload": {"protocol": proto, "field": field}},
+            "op": "==",
+            "right": ports_value(ports),
+        }
+    })
+}
+
+/// Matches one port directly, or an inclusive range.
+fn match_port_range(proto: &str, field: &str, ports: PortRange) -> Value {
+    let right = if ports.is_single() {
+        json!(ports.start)
+    } else {
+        json!({"range": [ports.start, ports.end]})
+    };
+    json!({
+        "match": {
+            "left": {"payload": {"protocol": proto, "field": field}},
+            "op": "==",
+            "right": right,
+        }
+    })
+}
+
+fn ports_value(ports: &[u16]) -> Value {
+    match ports {
+        [port] => json!(port),
+        _ => json!({"set": ports}),
+    }
+}
+
 fn match_ip_addr_expr(field: &str, ip: &IpAddr) -> Value {
     json!({
         "match": {
@@ -859,12 +1535,37 @@ fn match_ip_prefix_expr(field: &str, net: &IpNet) -> Value {
     })
 }
 
-fn dnat_expr(dest: &IpAddr, port: u16) -> Value {
-    json!({"dnat": {"addr": dest.to_string(), "port": port}})
+/// DNAT target port for a forward. A range DNAT only keeps each port when the
+/// ranges coincide; otherwise the kernel may pick any port in it, so shifted
+/// ranges map every public port to its counterpart (`dport map { 8000 : 9000, ... }`).
+fn forward_dnat_port(forward: &ForwardRule) -> Value {
+    let (public, dest) = (forward.public_port, forward.dest_port);
+    if public.is_single() {
+        return json!(dest.start);
+    }
+    if public == dest {
+        return json!({"range": [dest.start, dest.end]});
+    }
+
+    let pairs: Vec<Value> = (public.start..=public.end)
+        .zip(dest.start..=dest.end)
+        .map(|(from, to)| json!([from, to]))
+        .collect();
+    json!({
+        "map": {
+            "key": {"payload": {"protocol": forward.protocol.as_str(), "field": "dport"}},
+            "data": {"set": pairs},
+        }
+    })
+}
+
+// `inet` tables need the family spelled out (`dnat ip to`, `dnat ip6 to`)
+fn dnat_expr(dest: &IpAddr, port: Value) -> Value {
+    json!({"dnat": {"family": ip_protocol(dest), "addr": dest.to_string(), "port": port}})
 }
 
 fn snat_expr(gateway: &IpAddr) -> Value {
-    json!({"snat": {"addr": gateway.to_string()}})
+    json!({"snat": {"family": ip_protocol(gateway), "addr": gateway.to_string()}})
 }
 
 fn accept_expr() -> Value {
@@ -894,16 +1595,19 @@ fn parse_ipnet(value: &str) -> Result<IpNet> {
 #[derive(Clone, Copy, Debug)]
 struct ForwardRule {
     public_addr: Option<IpAddr>,
-    public_port: u16,
+    public_port: PortRange,
     protocol: ForwardProtocol,
     dest_addr: IpAddr,
-    dest_port: u16,
+    /// Same size as `public_port`; a single destination port is expanded from
+    /// its base when the public side is a range.
+    dest_port: PortRange,
 }
 
 #[derive(Clone, Copy, Debug)]
 enum ForwardProtocol {
     Tcp,
     Udp,
+    Sctp,
     Icmp,
 }
 
@@ -912,6 +1616,7 @@ impl ForwardProtocol {
         match proto.to_ascii_lowercase().as_str() {
             "tcp" => Ok(Self::Tcp),
             "udp" => Ok(Self::Udp),
+            "sctp" => Ok(Self::Sctp),
             "icmp" => Ok(Self::Icmp),
             other => anyhow::bail!("Unsupported protocol '{}'", other),
         }
@@ -921,6 +1626,7 @@ impl ForwardProtocol {
         match self {
             Self::Tcp => "tcp",
             Self::Udp => "udp",
+            Self::Sctp => "sctp",
             Self::Icmp => "icmp",
         }
     }
@@ -931,12 +1637,14 @@ impl ForwardRule {
         let (addr_part, proto_part) = split_proto(public)?;
         let protocol = ForwardProtocol::from_str(proto_part)?;
 
+        // Port-less ICMP has nothing to DNAT; nf_nat rewrites tcp, udp and sctp ports.
         if matches!(protocol, ForwardProtocol::Icmp) {
             anyhow::bail!("ICMP is not supported for port forwards");
         }
 
         let (public_addr, public_port) = split_host_port(addr_part)?;
         let (dest_addr, dest_port) = split_destination(dest)?;
+        let dest_port = public_port.map_onto(dest_port)?;
 
         Ok(Self {
             public_addr,
@@ -967,40 +1675,40 @@ fn split_proto(spec: &str) -> Result<(&str, &str)> {
     Ok((addr_part, proto))
 }
 
-fn split_host_port(addr_part: &str) -> Result<(Option<IpAddr>, u16)> {
+fn split_host_port(addr_part: &str) -> Result<(Option<IpAddr>, PortRange)> {
     let trimmed = addr_part.trim();
-    let (host, port_str) = if let Some(idx) = trimmed.rfind(':') {
-        (&trimmed[..idx], &trimmed[idx + 1..])
+    let (host, port_str) = if trimmed.contains(':') {
+        gw_core::split_host_port(trimmed)
+            .ok_or_else(|| anyhow!("IPv6 public address must be bracketed: {}", trimmed))?
     } else {
         ("", trimmed)
     };
 
-    let port: u16 = port_str.parse().context("Invalid public port")?;
+    let port = PortRange::parse(port_str).context("Invalid public port")?;
 
     let host = host.trim();
-    let addr = if host.is_empty() || host == "0.0.0.0" {
+    let addr = if host.is_empty() {
         None
     } else {
-        Some(host.parse().context("Invalid public IP address")?)
+        let addr: IpAddr = host.parse().context("Invalid public IP address")?;
+        (!addr.is_unspecified()).then_some(addr)
     };
 
     Ok((addr, port))
 }
 
-fn split_destination(dest: &str) -> Result<(IpAddr, u16)> {
-    let trimmed = dest.trim();
-    let (ip_str, port_str) = trimmed
-        .rsplit_once(':')
-        .ok_or_else(|| anyhow!("Destination must be IP:PORT"))?;
+fn split_destination(dest: &str) -> Result<(IpAddr, PortRange)> {
+    let (ip_str, port_str) = gw_core::split_host_port(dest)
+        .ok_or_else(|| anyhow!("Destination must be IP:PORT or [IPv6]:PORT"))?;
     let ip = ip_str.parse().context("Invalid destination IP address")?;
-    let port: u16 = port_str.parse().context("Invalid destination port")?;
+    let port = PortRange::parse(port_str).context("Invalid destination port")?;
     Ok((ip, port))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use gw_core::policy::Service;
+    use gw_core::{Topology, nft_config_for_table};
     use serde_json::Value;
 
     fn load_ruleset(value: &str) -> Vec<Value> {
@@ -1050,9 +1758,15 @@ mod tests {
             services: vec![Service {
                 protocol: Protocol::Tcp,
                 port: 80,
+                ports: Vec::new(),
                 source: None,
+                rate_limit: None,
             }],
+            allowed_egress_services: Vec::new(),
             default_action: Action::Drop,
+            icmp_rate_limit: IcmpRateLimit::default(),
+            log_dropped: false,
+            counters: false,
         };
 
         let forwards = vec![(":8080/tcp".to_string(), "10.33.0.10:8080".to_string())];
@@ -1063,9 +1777,13 @@ mod tests {
                 "br-test",
                 "10.33.0.0/24",
                 "10.33.0.1",
+                None,
                 "eth0",
                 &forwards,
+                false,
                 Some(&policy),
+                &[],
+                None,
             )
             .expect("ruleset generation");
 
@@ -1095,4 +1813,1491 @@ mod tests {
             })
         }));
     }
+
+    #[test]
+    fn specific_forward_emitted_before_wildcard_on_same_port() {
+        let topology = Topology::from_toml(
+            r#"
+version = 1
+
+[interfaces]
+
+[networks.lab]
+type = "routed"
+cidr = "10.40.0.0/24"
+gw_ip = "10.40.0.1"
+masq_out = "eth0"
+
+[[networks.lab.forwards]]
+public = ":8080/tcp"
+dst = "10.40.0.10:80"
+
+[[networks.lab.forwards]]
+public = "203.0.113.5:8080/tcp"
+dst = "10.40.0.20:80"
+"#,
+        )
+        .expect("topology");
+
+        let config = nft_config_for_table(&topology, "gw-lab").expect("nft config");
+        let ruleset = NftManager::new()
+            .create_complete_ruleset(
+                "gw-lab",
+                "br-lab",
+                &config.cidr,
+                &config.gateway_ip,
+                config
+                    .ipv6
+                    .as_ref()
+                    .map(|(cidr, gw)| (cidr.as_str(), gw.as_str())),
+                &config.masq_iface,
+                &config.forwards,
+                false,
+                None,
+                &[],
+                None,
+            )
+            .expect("ruleset generation");
+
+        let prerouting = chain_exprs(&load_ruleset(&ruleset), "prerouting");
+        let dnat_targets: Vec<&str> = prerouting
+            .iter()
+            .filter_map(|exprs| {
+                exprs
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .find_map(|expr| expr.get("dnat")?.get("addr")?.as_str())
+            })
+            .collect();
+
+        assert_eq!(dnat_targets, vec!["10.40.0.20", "10.40.0.10"]);
+    }
+
+    #[test]
+    fn dual_stack_network_gets_ipv6_masquerade_and_dnat() {
+        let topology = Topology::from_toml(
+            r#"
+version = 1
+
+[interfaces]
+
+[networks.lab]
+type = "routed"
+cidr = "10.40.0.0/24"
+gw_ip = "10.40.0.1"
+cidr6 = "fd00:40::/64"
+gw_ip6 = "fd00:40::1"
+masq_out = "eth0"
+
+[[networks.lab.forwards]]
+public = ":2222/tcp"
+dst = "10.40.0.10:22"
+
+[[networks.lab.forwards]]
+public = "[2001:db8::5]:2222/tcp"
+dst = "[fd00:40::10]:22"
+"#,
+        )
+        .expect("topology");
+
+        let config = nft_config_for_table(&topology, "gw-lab").expect("nft config");
+        let ruleset = NftManager::new()
+            .create_complete_ruleset(
+                "gw-lab",
+                "br-lab",
+                &config.cidr,
+                &config.gateway_ip,
+                config
+                    .ipv6
+                    .as_ref()
+                    .map(|(cidr, gw)| (cidr.as_str(), gw.as_str())),
+                &config.masq_iface,
+                &config.forwards,
+                false,
+                None,
+                &[],
+                None,
+            )
+            .expect("ruleset generation");
+        let nftables = load_ruleset(&ruleset);
+        let postrouting = chain_exprs(&nftables, "postrouting");
+
+        let masquerade_sources: Vec<(&str, &str)> = postrouting
+            .iter()
+            .filter(|exprs| expr_has_key(exprs, "masquerade"))
+            .filter_map(|exprs| {
+                let saddr = exprs
+                    .as_array()?
+                    .iter()
+                    .filter_map(|expr| expr.get("match"))
+                    .find(|m| m["left"].get("payload").is_some())?;
+                Some((
+                    saddr["left"]["payload"]["protocol"].as_str()?,
+                    saddr["right"]["prefix"]["addr"].as_str()?,
+                ))
+            })
+            .collect();
+        assert_eq!(
+            masquerade_sources,
+            vec![("ip", "10.40.0.0"), ("ip6", "fd00:40::")]
+        );
+
+        let prerouting = chain_exprs(&nftables, "prerouting");
+        let dnat: Vec<&Value> = prerouting
+            .iter()
+            .filter_map(|exprs| exprs.as_array()?.iter().find_map(|expr| expr.get("dnat")))
+            .collect();
+        assert_eq!(
+            dnat,
+            vec![
+                &json!({"family": "ip6", "addr": "fd00:40::10", "port": 22}),
+                &json!({"family": "ip", "addr": "10.40.0.10", "port": 22}),
+            ]
+        );
+
+        // The v6 hairpin SNAT goes out from the v6 gateway
+        let snat: Vec<&Value> = postrouting
+            .iter()
+            .filter_map(|exprs| exprs.as_array()?.iter().find_map(|expr| expr.get("snat")))
+            .collect();
+        assert!(snat.contains(&&json!({"family": "ip6", "addr": "fd00:40::1"})));
+    }
+
+    #[test]
+    fn forwards_keep_protocol_and_destination_port_through_nft_config() {
+        let topology = Topology::from_toml(
+            r#"
+version = 1
+
+[interfaces]
+
+[networks.lab]
+type = "routed"
+cidr = "10.40.0.0/24"
+gw_ip = "10.40.0.1"
+masq_out = "eth0"
+
+[[networks.lab.forwards]]
+public = ":80/tcp"
+dst = "10.40.0.5:8080"
+
+[[networks.lab.forwards]]
+public = ":53/udp"
+dst = "10.40.0.6:53"
+"#,
+        )
+        .expect("topology");
+
+        let config = nft_config_for_table(&topology, "gw-lab").expect("nft config");
+        let bridge_nets = [(
+            config.cidr.parse().unwrap(),
+            config.gateway_ip.parse().unwrap(),
+        )];
+        let rules = build_nat_rules(
+            "gw-lab",
+            "br-lab",
+            &bridge_nets,
+            &config.masq_iface,
+            &parse_forward_rules(&config.forwards).unwrap(),
+            false,
+        )
+        .unwrap();
+
+        // (chain, l4proto, dport, dnat port) per forward rule
+        let forwards: Vec<(String, Value, Value, Option<Value>)> = rules
+            .iter()
+            .filter_map(|rule| {
+                let rule = rule.get("rule")?;
+                let exprs = rule["expr"].as_array()?;
+                let proto = exprs.iter().find_map(|expr| {
+                    let m = expr.get("match")?;
+                    (m["left"]["meta"]["key"] == "l4proto").then(|| m["right"].clone())
+                })?;
+                let dport = exprs.iter().find_map(|expr| {
+                    let m = expr.get("match")?;
+                    (m["left"]["payload"]["field"] == "dport").then(|| {
+                        assert_eq!(m["left"]["payload"]["protocol"], proto);
+                        m["right"].clone()
+                    })
+                })?;
+                let dnat_port = exprs
+                    .iter()
+                    .find_map(|expr| expr.pointer("/dnat/port").cloned());
+                Some((rule["chain"].as_str()?.to_string(), proto, dport, dnat_port))
+            })
+            .collect();
+
+        assert_eq!(
+            forwards,
+            vec![
+                (
+                    "prerouting".into(),
+                    json!("tcp"),
+                    json!(80),
+                    Some(json!(8080))
+                ),
+                ("postrouting".into(), json!("tcp"), json!(8080), None),
+                (
+                    "prerouting".into(),
+                    json!("udp"),
+                    json!(53),
+                    Some(json!(53))
+                ),
+                ("postrouting".into(), json!("udp"), json!(53), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn hairpin_adds_bridge_side_dnat_per_forward() {
+        let prerouting = |hairpin: &str| -> Vec<Value> {
+            let topology = Topology::from_toml(&format!(
+                r#"
+version = 1
+
+[interfaces]
+
+[networks.lab]
+type = "routed"
+cidr = "10.40.0.0/24"
+gw_ip = "10.40.0.1"
+masq_out = "eth0"
+{}
+
+[[networks.lab.forwards]]
+public = "203.0.113.7:443/tcp"
+dst = "10.40.0.5:8443"
+
+[[networks.lab.forwards]]
+public = ":80/tcp"
+dst = "10.40.0.5:8080"
+"#,
+                hairpin
+            ))
+            .expect("topology");
+            let config = nft_config_for_table(&topology, "gw-lab").expect("nft config");
+            let ruleset = NftManager::new()
+                .create_complete_ruleset(
+                    "gw-lab",
+                    "br-lab",
+                    &config.cidr,
+                    &config.gateway_ip,
+                    None,
+                    &config.masq_iface,
+                    &config.forwards,
+                    config.hairpin,
+                    None,
+                    &[],
+                    None,
+                )
+                .unwrap();
+            chain_exprs(&load_ruleset(&ruleset), "prerouting")
+        };
+
+        assert_eq!(prerouting("").len(), 2);
+
+        let rules = prerouting("hairpin = true");
+        let hairpin: Vec<&Value> = rules
+            .iter()
+            .filter(|exprs| {
+                exprs.as_array().unwrap().contains(&json!({
+                    "match": {"left": {"meta": {"key": "iifname"}}, "op": "==", "right": "br-lab"}
+                }))
+            })
+            .collect();
+        assert_eq!(hairpin.len(), 2);
+
+        // Pinned public address: match it; wildcard: any local address
+        let specific = hairpin[0].as_array().unwrap();
+        assert!(specific.contains(&match_ip_addr_expr(
+            "daddr",
+            &"203.0.113.7".parse().unwrap()
+        )));
+        assert!(specific.contains(&match_ip_prefix_expr(
+            "saddr",
+            &"10.40.0.0/24".parse().unwrap()
+        )));
+        assert!(
+            specific
+                .contains(&json!({"dnat": {"family": "ip", "addr": "10.40.0.5", "port": 8443}}))
+        );
+
+        let wildcard = hairpin[1].as_array().unwrap();
+        assert!(
+            wildcard
+                .iter()
+                .any(|expr| expr.pointer("/match/left/fib").is_some())
+        );
+        assert!(
+            wildcard
+                .contains(&json!({"dnat": {"family": "ip", "addr": "10.40.0.5", "port": 8080}}))
+        );
+
+        let descriptions: Vec<String> = hairpin
+            .iter()
+            .map(|exprs| {
+                describe_nft_command(&json!({"rule": {"chain": "prerouting", "expr": exprs}}))
+            })
+            .collect();
+        assert_eq!(
+            descriptions[1],
+            "hairpin port forward 80/tcp -> 10.40.0.5:8080"
+        );
+    }
+
+    #[test]
+    fn policy_only_dnat_skips_bridge_originated_traffic() {
+        let forwards = vec![(":80/tcp".to_string(), "10.40.0.5:8080".to_string())];
+        let ruleset = NftManager::new()
+            .create_complete_ruleset(
+                "gw-lab",
+                "br-lab",
+                "10.40.0.0/24",
+                "10.40.0.1",
+                None,
+                "",
+                &forwards,
+                false,
+                None,
+                &[],
+                None,
+            )
+            .unwrap();
+
+        let prerouting = chain_exprs(&load_ruleset(&ruleset), "prerouting");
+        assert_eq!(prerouting.len(), 1);
+        let dnat = prerouting[0].as_array().unwrap();
+        assert!(dnat.contains(&json!({
+            "match": {"left": {"meta": {"key": "iifname"}}, "op": "!=", "right": "br-lab"}
+        })));
+        assert!(
+            dnat.contains(&json!({"dnat": {"family": "ip", "addr": "10.40.0.5", "port": 8080}}))
+        );
+    }
+
+    #[test]
+    fn ipv6_forward_needs_an_ipv6_prefix() {
+        let forwards = vec![(":2222/tcp".to_string(), "[fd00:40::10]:22".to_string())];
+        let err = NftManager::new()
+            .create_complete_ruleset(
+                "gw-test",
+                "br-test",
+                "10.33.0.0/24",
+                "10.33.0.1",
+                None,
+                "eth0",
+                &forwards,
+                false,
+                None,
+                &[],
+                None,
+            )
+            .unwrap_err();
+        assert!(err.to_string().contains("same address family"), "{}", err);
+    }
+
+    /// Prerouting (dport match, dnat) and hairpin (dport match) pairs for `forwards`.
+    fn forward_ports(forwards: &[(&str, &str)]) -> Result<Vec<(Value, Value, Value)>> {
+        let forwards: Vec<(String, String)> = forwards
+            .iter()
+            .map(|(public, dest)| (public.to_string(), dest.to_string()))
+            .collect();
+        let ruleset = NftManager::new().create_complete_ruleset(
+            "gw-test",
+            "br-test",
+            "10.33.0.0/24",
+            "10.33.0.1",
+            None,
+            "eth0",
+            &forwards,
+            false,
+            None,
+            &[],
+            None,
+        )?;
+        let nftables = load_ruleset(&ruleset);
+
+        let dport = |exprs: &Value| {
+            exprs
+                .as_array()
+                .unwrap()
+                .iter()
+                .filter_map(|expr| expr.get("match"))
+                .find(|m| m["left"]["payload"]["field"] == "dport")
+                .map(|m| m["right"].clone())
+                .unwrap()
+        };
+        let prerouting = chain_exprs(&nftables, "prerouting");
+        let hairpin: Vec<Value> = chain_exprs(&nftables, "postrouting")
+            .into_iter()
+            .filter(|exprs| expr_has_key(exprs, "snat"))
+            .collect();
+
+        Ok(prerouting
+            .iter()
+            .zip(&hairpin)
+            .map(|(pre, post)| {
+                let dnat = pre
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .find_map(|expr| expr.get("dnat"))
+                    .unwrap();
+                (dport(pre), dnat["port"].clone(), dport(post))
+            })
+            .collect())
+    }
+
+    #[test]
+    fn single_port_forward_is_unchanged() {
+        assert_eq!(
+            forward_ports(&[(":8080/tcp", "10.33.0.10:80")]).unwrap(),
+            vec![(json!(8080), json!(80), json!(80))]
+        );
+    }
+
+    #[test]
+    fn port_range_forward_matches_and_maps_the_range() {
+        let ports = forward_ports(&[
+            (":8000-8010/tcp", "10.33.0.10:8000-8010"),
+            (":6000-6002/udp", "10.33.0.11:7000"),
+        ])
+        .unwrap();
+
+        // Equal ranges keep each port
+        assert_eq!(
+            ports[0],
+            (
+                json!({"range": [8000, 8010]}),
+                json!({"range": [8000, 8010]}),
+                json!({"range": [8000, 8010]}),
+            )
+        );
+
+        // A shifted range maps each public port onto its counterpart
+        assert_eq!(
+            ports[1],
+            (
+                json!({"range": [6000, 6002]}),
+                json!({"map": {
+                    "key": {"payload": {"protocol": "udp", "field": "dport"}},
+                    "data": {"set": [[6000, 7000], [6001, 7001], [6002, 7002]]},
+                }}),
+                json!({"range": [7000, 7002]}),
+            )
+        );
+
+        let ruleset = NftManager::new()
+            .create_complete_ruleset(
+                "gw-test",
+                "br-test",
+                "10.33.0.0/24",
+                "10.33.0.1",
+                None,
+                "eth0",
+                &[(":6000-6002/udp".to_string(), "10.33.0.11:7000".to_string())],
+                false,
+                None,
+                &[],
+                None,
+            )
+            .unwrap();
+        let descriptions: Vec<String> = load_ruleset(&ruleset)
+            .iter()
+            .map(describe_nft_command)
+            .collect();
+        assert!(
+            descriptions
+                .contains(&"port forward 6000-6002/udp -> 10.33.0.11:7000-7002".to_string()),
+            "{:?}",
+            descriptions
+        );
+    }
+
+    #[test]
+    fn mismatched_or_inverted_port_ranges_are_rejected() {
+        let err = forward_ports(&[(":8000-8010/tcp", "10.33.0.10:9000-9005")]).unwrap_err();
+        assert!(err.to_string().contains("has 6 ports"), "{:#}", err);
+
+        let err = forward_ports(&[(":8010-8000/tcp", "10.33.0.10:8000")]).unwrap_err();
+        assert!(
+            format!("{:#}", err).contains("Inverted port range 8010-8000"),
+            "{:#}",
+            err
+        );
+    }
+
+    #[test]
+    fn sctp_forward_matches_sctp_dport_and_icmp_stays_rejected() {
+        let ports = forward_ports(&[(":3868/sctp", "10.33.0.10:3868")]).unwrap();
+        assert_eq!(ports, vec![(json!(3868), json!(3868), json!(3868))]);
+
+        let ruleset = NftManager::new()
+            .create_nat_ruleset(
+                "gw-test",
+                "br-test",
+                "10.33.0.0/24",
+                "10.33.0.1",
+                "eth0",
+                &[(":3868/sctp".into(), "10.33.0.10:3868".into())],
+            )
+            .unwrap();
+        let prerouting = chain_exprs(&load_ruleset(&ruleset), "prerouting");
+        let exprs = prerouting[0].as_array().unwrap();
+        assert!(exprs.contains(&match_l4proto("sctp")));
+        assert!(exprs.contains(&match_ports("sctp", "dport", &[3868])));
+
+        let err = forward_ports(&[(":0/icmp", "10.33.0.10:0")]).unwrap_err();
+        assert!(
+            err.to_string().contains("ICMP is not supported"),
+            "{:#}",
+            err
+        );
+    }
+
+    #[test]
+    fn sctp_service_rule_matches_sctp_dport() {
+        let policy = PolicyProfile {
+            services: vec![Service {
+                protocol: Protocol::Sctp,
+                port: 3868,
+                ports: Vec::new(),
+                source: None,
+                rate_limit: None,
+            }],
+            ..icmp_policy(Action::Drop, IcmpRateLimit::default())
+        };
+        let rule = service_rule("gw-test", "input", "br-test", &policy.services[0], None).unwrap();
+        let exprs = rule.pointer("/rule/expr").unwrap().as_array().unwrap();
+
+        assert_eq!(exprs[1], match_l4proto("sctp"));
+        assert_eq!(exprs[2], match_ports("sctp", "dport", &[3868]));
+        assert_eq!(exprs.last(), Some(&accept_expr()));
+    }
+
+    #[test]
+    fn egress_service_rule_lands_in_forward_chain() {
+        let policy = PolicyProfile {
+            name: "egress-web".into(),
+            description: "Outbound HTTPS only".into(),
+            allowed_ingress_cidrs: Vec::new(),
+            allowed_egress_cidrs: Vec::new(),
+            services: Vec::new(),
+            allowed_egress_services: vec![Service {
+                protocol: Protocol::Tcp,
+                port: 443,
+                ports: Vec::new(),
+                source: None,
+                rate_limit: None,
+            }],
+            default_action: Action::Drop,
+            icmp_rate_limit: IcmpRateLimit::default(),
+            log_dropped: false,
+            counters: false,
+        };
+
+        let ruleset = NftManager::new()
+            .create_complete_ruleset(
+                "gw-test",
+                "br-test",
+                "10.33.0.0/24",
+                "10.33.0.1",
+                None,
+                "eth0",
+                &[],
+                false,
+                Some(&policy),
+                &[],
+                None,
+            )
+            .expect("ruleset generation");
+
+        let forward_chain = chain_exprs(&load_ruleset(&ruleset), "forward");
+        let egress_rule = forward_chain
+            .iter()
+            .find(|exprs| {
+                exprs.as_array().unwrap().iter().any(|expr| {
+                    expr.get("match")
+                        .and_then(|m| m.get("right"))
+                        .and_then(|v| v.as_u64())
+                        == Some(443)
+                })
+            })
+            .expect("egress service rule in forward chain");
+
+        let exprs = egress_rule.as_array().unwrap();
+        assert!(exprs.iter().any(|expr| expr.get("accept").is_some()));
+        assert!(!exprs.iter().any(|expr| {
+            expr.pointer("/match/left/payload/field")
+                .and_then(|f| f.as_str())
+                == Some("daddr")
+        }));
+    }
+
+    #[test]
+    fn egress_service_with_zero_port_is_rejected() {
+        let policy = PolicyProfile {
+            name: "broken".into(),
+            description: "Invalid egress port".into(),
+            allowed_ingress_cidrs: Vec::new(),
+            allowed_egress_cidrs: Vec::new(),
+            services: Vec::new(),
+            allowed_egress_services: vec![Service {
+                protocol: Protocol::Udp,
+                port: 0,
+                ports: Vec::new(),
+                source: None,
+                rate_limit: None,
+            }],
+            default_action: Action::Drop,
+            icmp_rate_limit: IcmpRateLimit::default(),
+            log_dropped: false,
+            counters: false,
+        };
+
+        let result = NftManager::new().create_complete_ruleset(
+            "gw-test",
+            "br-test",
+            "10.33.0.0/24",
+            "10.33.0.1",
+            None,
+            "eth0",
+            &[],
+            false,
+            Some(&policy),
+            &[],
+            None,
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn failed_payloads_are_dumped_to_private_unique_files() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let first = dump_failed_payload("{\"nftables\": []}").unwrap();
+        let second = dump_failed_payload("{}").unwrap();
+        assert_ne!(first, second);
+        assert_eq!(
+            std::fs::read_to_string(&first).unwrap(),
+            "{\"nftables\": []}"
+        );
+        let mode = std::fs::metadata(&first).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+
+        std::fs::remove_file(first).unwrap();
+        std::fs::remove_file(second).unwrap();
+    }
+
+    #[test]
+    fn describes_generated_commands_by_origin() {
+        let policy = PolicyProfile {
+            name: "routed-tight".into(),
+            description: "Demo profile".into(),
+            allowed_ingress_cidrs: Vec::new(),
+            allowed_egress_cidrs: vec!["8.8.8.8/32".into()],
+            services: vec![Service {
+                protocol: Protocol::Tcp,
+                port: 22,
+                ports: Vec::new(),
+                source: Some("10.33.0.0/24".into()),
+                rate_limit: None,
+            }],
+            allowed_egress_services: Vec::new(),
+            default_action: Action::Drop,
+            icmp_rate_limit: IcmpRateLimit::default(),
+            log_dropped: false,
+            counters: false,
+        };
+        let forwards = vec![(":8080/tcp".to_string(), "10.33.0.10:80".to_string())];
+
+        let ruleset = NftManager::new()
+            .create_complete_ruleset(
+                "gw-test",
+                "br-test",
+                "10.33.0.0/24",
+                "10.33.0.1",
+                None,
+                "eth0",
+                &forwards,
+                false,
+                Some(&policy),
+                &[],
+                None,
+            )
+            .expect("ruleset generation");
+
+        let descriptions: Vec<String> = load_ruleset(&ruleset)
+            .iter()
+            .map(describe_nft_command)
+            .collect();
+
+        for expected in [
+            "flush table gw-test",
+            "create chain prerouting",
+            "policy service tcp/22 from 10.33.0.0/24",
+            "create set egress_v4",
+            "policy egress CIDR @egress_v4",
+            "port forward 8080/tcp -> 10.33.0.10:80",
+            "hairpin SNAT for port forward to 10.33.0.10",
+            "masquerade out eth0",
+            "loopback accept",
+        ] {
+            assert!(
+                descriptions.iter().any(|d| d == expected),
+                "missing '{}' in {:?}",
+                expected,
+                descriptions
+            );
+        }
+    }
+
+    #[test]
+    fn policy_cidrs_use_named_sets_unless_disabled() {
+        let mut policy = icmp_policy(Action::Drop, IcmpRateLimit::default());
+        policy.allowed_ingress_cidrs = vec![
+            "10.33.0.0/24".into(),
+            "192.168.7.5/32".into(),
+            "fd00:33::/64".into(),
+        ];
+        let generate = |manager: NftManager| {
+            let ruleset = manager
+                .create_complete_ruleset(
+                    "gw-test",
+                    "br-test",
+                    "10.33.0.0/24",
+                    "10.33.0.1",
+                    None,
+                    "eth0",
+                    &[],
+                    false,
+                    Some(&policy),
+                    &[],
+                    None,
+                )
+                .unwrap();
+            load_ruleset(&ruleset)
+        };
+        let saddr_matches = |nftables: &[Value]| -> Vec<Value> {
+            chain_exprs(nftables, "input")
+                .iter()
+                .flat_map(|exprs| exprs.as_array().unwrap().clone())
+                .filter_map(|expr| expr.get("match").cloned())
+                .filter(|m| m["left"]["payload"]["field"] == "saddr")
+                .map(|m| m["right"].clone())
+                .collect()
+        };
+
+        // One set per family, one rule per set
+        let nftables = generate(NftManager::new());
+        let sets: Vec<&Value> = nftables
+            .iter()
+            .filter_map(|entry| entry.get("set"))
+            .collect();
+        assert_eq!(sets.len(), 2);
+        assert_eq!(sets[0]["name"], "ingress_v4");
+        assert_eq!(sets[0]["type"], "ipv4_addr");
+        assert_eq!(sets[0]["flags"], json!(["interval"]));
+        assert_eq!(
+            sets[0]["elem"],
+            json!([
+                {"prefix": {"addr": "10.33.0.0", "len": 24}},
+                {"prefix": {"addr": "192.168.7.5", "len": 32}},
+            ])
+        );
+        assert_eq!(sets[1]["name"], "ingress_v6");
+        assert_eq!(sets[1]["type"], "ipv6_addr");
+        assert_eq!(
+            saddr_matches(&nftables),
+            vec![json!("@ingress_v4"), json!("@ingress_v6")]
+        );
+
+        // Per-rule fallback: no sets, one prefix match per CIDR
+        let nftables = generate(NftManager::new().with_cidr_sets(false));
+        assert!(nftables.iter().all(|entry| entry.get("set").is_none()));
+        assert_eq!(
+            saddr_matches(&nftables),
+            vec![
+                json!({"prefix": {"addr": "10.33.0.0", "len": 24}}),
+                json!({"prefix": {"addr": "192.168.7.5", "len": 32}}),
+                json!({"prefix": {"addr": "fd00:33::", "len": 64}}),
+            ]
+        );
+    }
+
+    #[test]
+    fn multi_port_service_emits_one_rule_with_port_set() {
+        let policy = PolicyProfile {
+            name: "public-web".into(),
+            description: "HTTP and HTTPS".into(),
+            allowed_ingress_cidrs: Vec::new(),
+            allowed_egress_cidrs: Vec::new(),
+            services: vec![Service {
+                protocol: Protocol::Tcp,
+                port: 0,
+                ports: vec![443, 80],
+                source: None,
+                rate_limit: None,
+            }],
+            allowed_egress_services: Vec::new(),
+            default_action: Action::Drop,
+            icmp_rate_limit: IcmpRateLimit::default(),
+            log_dropped: false,
+            counters: false,
+        };
+        policy.validate().expect("valid profile");
+
+        let ruleset = NftManager::new()
+            .create_complete_ruleset(
+                "gw-test",
+                "br-test",
+                "10.33.0.0/24",
+                "10.33.0.1",
+                None,
+                "eth0",
+                &[],
+                false,
+                Some(&policy),
+                &[],
+                None,
+            )
+            .expect("ruleset generation");
+
+        let dport_matches: Vec<Value> = chain_exprs(&load_ruleset(&ruleset), "input")
+            .iter()
+            .flat_map(|exprs| exprs.as_array().unwrap().clone())
+            .filter_map(|expr| {
+                let m = expr.get("match")?;
+                (m.pointer("/left/payload/field")? == "dport").then(|| m["right"].clone())
+            })
+            .collect();
+
+        assert_eq!(dport_matches, vec![json!({"set": [80, 443]})]);
+    }
+
+    fn quota_ruleset(mode: QuotaMode) -> Vec<Value> {
+        let quota = TrafficQuota {
+            bytes: 10_737_418_240,
+            mode,
+        };
+        let ruleset = NftManager::new()
+            .create_complete_ruleset(
+                "gw-test",
+                "br-test",
+                "10.33.0.0/24",
+                "10.33.0.1",
+                None,
+                "eth0",
+                &[],
+                false,
+                None,
+                &[],
+                Some(&quota),
+            )
+            .expect("ruleset generation");
+        load_ruleset(&ruleset)
+    }
+
+    #[test]
+    fn over_quota_drops_forwarded_traffic_before_established_accept() {
+        let nftables = quota_ruleset(QuotaMode::Over);
+
+        let object = nftables
+            .iter()
+            .find_map(|entry| entry.get("quota"))
+            .expect("quota object");
+        assert_eq!(object["name"], QUOTA_OBJECT);
+        assert_eq!(object["bytes"], 10_737_418_240u64);
+        assert_eq!(object["inv"], true);
+
+        let forward = chain_exprs(&nftables, "forward");
+        let quota_rules: Vec<&Value> = forward
+            .iter()
+            .take_while(|exprs| {
+                exprs.as_array().unwrap().iter().all(|e| {
+                    e.get("match")
+                        .is_none_or(|m| m.pointer("/left/ct").is_none())
+                })
+            })
+            .collect();
+        assert_eq!(
+            quota_rules.len(),
+            2,
+            "quota rules must precede ct state accept"
+        );
+        for exprs in quota_rules {
+            let exprs = exprs.as_array().unwrap();
+            assert!(exprs.contains(&json!({"quota": QUOTA_OBJECT})));
+            assert!(exprs.contains(&json!({"drop": null})));
+        }
+
+        let descriptions: Vec<String> = nftables.iter().map(describe_nft_command).collect();
+        assert!(descriptions.contains(&"create quota traffic".to_string()));
+        assert!(descriptions.contains(&"traffic quota traffic from br-test".to_string()));
+        assert!(descriptions.contains(&"traffic quota traffic to br-test".to_string()));
+    }
+
+    #[test]
+    fn until_quota_only_meters() {
+        let nftables = quota_ruleset(QuotaMode::Until);
+
+        let object = nftables
+            .iter()
+            .find_map(|entry| entry.get("quota"))
+            .expect("quota object");
+        assert_eq!(object["inv"], false);
+        assert!(
+            chain_exprs(&nftables, "forward")
+                .iter()
+                .all(|exprs| !exprs.as_array().unwrap().contains(&json!({"drop": null})))
+        );
+    }
+
+    #[test]
+    fn ct_helper_object_bound_by_helpers_chain() {
+        let ruleset = NftManager::new()
+            .create_complete_ruleset(
+                "gw-test",
+                "br-test",
+                "10.33.0.0/24",
+                "10.33.0.1",
+                None,
+                "eth0",
+                &[],
+                false,
+                None,
+                &["ftp".to_string()],
+                None,
+            )
+            .expect("ruleset generation");
+
+        let nftables = load_ruleset(&ruleset);
+
+        let helper = nftables
+            .iter()
+            .find_map(|entry| entry.get("ct helper"))
+            .expect("ct helper object");
+        assert_eq!(helper["name"], "ftp");
+        assert_eq!(helper["type"], "ftp");
+        assert_eq!(helper["protocol"], "tcp");
+
+        let helpers = chain_exprs(&nftables, "helpers");
+        assert!(!helpers.is_empty());
+        assert!(helpers.iter().all(|exprs| {
+            exprs.as_array().unwrap().iter().any(|expr| {
+                expr.get("mangle")
+                    .and_then(|m| m.get("value"))
+                    .and_then(|v| v.as_str())
+                    == Some("ftp")
+            })
+        }));
+    }
+
+    fn icmp_policy(default_action: Action, icmp_rate_limit: IcmpRateLimit) -> PolicyProfile {
+        PolicyProfile {
+            name: "icmp".into(),
+            description: "Ping only".into(),
+            allowed_ingress_cidrs: Vec::new(),
+            allowed_egress_cidrs: Vec::new(),
+            services: vec![Service {
+                protocol: Protocol::Icmp,
+                port: 0,
+                ports: Vec::new(),
+                source: None,
+                rate_limit: None,
+            }],
+            allowed_egress_services: Vec::new(),
+            default_action,
+            icmp_rate_limit,
+            log_dropped: false,
+            counters: false,
+        }
+    }
+
+    fn input_limits(policy: &PolicyProfile) -> Vec<Value> {
+        let ruleset = NftManager::new()
+            .create_complete_ruleset(
+                "gw-test",
+                "br-test",
+                "10.33.0.0/24",
+                "10.33.0.1",
+                None,
+                "eth0",
+                &[],
+                false,
+                Some(policy),
+                &[],
+                None,
+            )
+            .expect("ruleset generation");
+
+        chain_exprs(&load_ruleset(&ruleset), "input")
+            .iter()
+            .flat_map(|exprs| exprs.as_array().unwrap().clone())
+            .filter_map(|expr| expr.get("limit").cloned())
+            .collect()
+    }
+
+    #[test]
+    fn icmp_allow_is_rate_limited_under_drop_policy() {
+        let policy = icmp_policy(
+            Action::Drop,
+            IcmpRateLimit {
+                enabled: true,
+                rate: 5,
+                burst: 10,
+            },
+        );
+
+        let limits = input_limits(&policy);
+        assert_eq!(
+            limits,
+            vec![json!({"rate": 5, "per": "second", "burst": 10})]
+        );
+    }
+
+    #[test]
+    fn service_rate_limit_emits_limit_before_accept() {
+        let ssh = |rate_limit: Option<&str>| {
+            let mut policy = icmp_policy(Action::Drop, IcmpRateLimit::default());
+            policy.services = vec![Service {
+                protocol: Protocol::Tcp,
+                port: 22,
+                ports: Vec::new(),
+                source: None,
+                rate_limit: rate_limit.map(String::from),
+            }];
+            policy
+        };
+
+        assert_eq!(
+            input_limits(&ssh(Some("10/minute burst 5"))),
+            vec![json!({"rate": 10, "per": "minute", "burst": 5})]
+        );
+        assert_eq!(
+            input_limits(&ssh(Some("100/second"))),
+            vec![json!({"rate": 100, "per": "second"})]
+        );
+        assert!(input_limits(&ssh(None)).is_empty());
+
+        for malformed in [
+            "10",
+            "0/minute",
+            "10/fortnight",
+            "10/minute burst",
+            "x/second",
+        ] {
+            assert!(
+                ssh(Some(malformed)).validate().is_err(),
+                "{} should be rejected",
+                malformed
+            );
+        }
+    }
+
+    #[test]
+    fn reject_default_ends_chains_with_reject_rule() {
+        let reject = json!({"reject": {"type": "icmpx", "expr": "admin-prohibited"}});
+        let tails = |default_action: Action| -> Vec<Vec<Value>> {
+            let policy = icmp_policy(default_action, IcmpRateLimit::default());
+            let manager = NftManager::new();
+            let complete = manager
+                .create_complete_ruleset(
+                    "gw-test",
+                    "br-test",
+                    "10.33.0.0/24",
+                    "10.33.0.1",
+                    None,
+                    "eth0",
+                    &[],
+                    false,
+                    Some(&policy),
+                    &[],
+                    None,
+                )
+                .unwrap();
+            let policy_only = manager
+                .create_policy_ruleset("gw-test", "br-test", &policy)
+                .unwrap();
+
+            [complete, policy_only]
+                .iter()
+                .flat_map(|ruleset| {
+                    let nftables = load_ruleset(ruleset);
+                    ["input", "forward"].map(|chain| {
+                        chain_exprs(&nftables, chain)
+                            .last()
+                            .and_then(|exprs| exprs.as_array().cloned())
+                            .unwrap_or_default()
+                    })
+                })
+                .collect()
+        };
+
+        for tail in tails(Action::Reject) {
+            assert_eq!(tail, vec![reject.clone()]);
+        }
+        for tail in tails(Action::Drop) {
+            assert!(!tail.contains(&reject));
+        }
+    }
+
+    #[test]
+    fn counters_precede_verdicts_when_enabled() {
+        let forwards = vec![(":8080/tcp".to_string(), "10.33.0.10:80".to_string())];
+        let mut policy = icmp_policy(Action::Drop, IcmpRateLimit::default());
+        policy.allowed_egress_cidrs = vec!["0.0.0.0/0".into()];
+        let counted_verdicts = |policy: &PolicyProfile| -> Vec<String> {
+            let ruleset = NftManager::new()
+                .create_complete_ruleset(
+                    "gw-test",
+                    "br-test",
+                    "10.33.0.0/24",
+                    "10.33.0.1",
+                    None,
+                    "eth0",
+                    &forwards,
+                    false,
+                    Some(policy),
+                    &[],
+                    None,
+                )
+                .unwrap();
+            ["input", "forward", "prerouting", "postrouting"]
+                .into_iter()
+                .flat_map(|chain| chain_exprs(&load_ruleset(&ruleset), chain))
+                .filter_map(|exprs| {
+                    let exprs = exprs.as_array()?;
+                    let position = exprs.iter().position(|e| e == &json!({"counter": null}))?;
+                    assert_eq!(
+                        position,
+                        exprs.len() - 2,
+                        "counter must precede the verdict"
+                    );
+                    exprs.last()?.as_object()?.keys().next().cloned()
+                })
+                .collect()
+        };
+
+        assert!(counted_verdicts(&policy).is_empty());
+
+        policy.counters = true;
+        let mut verdicts = counted_verdicts(&policy);
+        verdicts.sort();
+        // icmp service + egress CIDR, forward DNAT + hairpin SNAT, masquerade
+        assert_eq!(
+            verdicts,
+            vec!["accept", "accept", "dnat", "masquerade", "snat"]
+        );
+
+        let policy_only = NftManager::new()
+            .create_policy_ruleset("gw-test", "br-test", &policy)
+            .unwrap();
+        let counted = chain_exprs(&load_ruleset(&policy_only), "input")
+            .iter()
+            .filter(|exprs| expr_has_key(exprs, "counter"))
+            .count();
+        assert_eq!(counted, 1);
+    }
+
+    #[test]
+    fn policy_ruleset_matches_complete_ruleset_filtering() {
+        let mut policy = icmp_policy(Action::Drop, IcmpRateLimit::default());
+        policy.allowed_ingress_cidrs = vec!["10.0.0.0/8".into(), "fd00::/8".into()];
+        policy.allowed_egress_cidrs = vec!["192.0.2.0/24".into(), "198.51.100.0/24".into()];
+        policy.log_dropped = true;
+
+        let manager = NftManager::new();
+        let complete = manager
+            .create_complete_ruleset(
+                "gw-test",
+                "br-test",
+                "10.33.0.0/24",
+                "10.33.0.1",
+                None,
+                "",
+                &[],
+                false,
+                Some(&policy),
+                &[],
+                None,
+            )
+            .unwrap();
+        let policy_only = manager
+            .create_policy_ruleset("gw-test", "br-test", &policy)
+            .unwrap();
+
+        let (complete, policy_only) = (load_ruleset(&complete), load_ruleset(&policy_only));
+        for chain in ["input", "forward"] {
+            assert_eq!(
+                chain_exprs(&policy_only, chain),
+                chain_exprs(&complete, chain),
+                "{} chain",
+                chain
+            );
+        }
+        // Named CIDR sets and the ICMP rate limit come from the shared builders
+        assert!(policy_only.iter().any(|entry| entry.get("set").is_some()));
+        assert!(
+            chain_exprs(&policy_only, "input")
+                .iter()
+                .any(|exprs| expr_has_key(exprs, "limit"))
+        );
+    }
+
+    #[tokio::test]
+    async fn read_counters_names_counted_rules() {
+        let live = r#"{"nftables": [
+            {"metainfo": {"json_schema_version": 1}},
+            {"table": {"family": "inet", "name": "gw-lab", "handle": 3}},
+            {"rule": {"family": "inet", "table": "gw-lab", "chain": "postrouting", "handle": 7,
+                "expr": [
+                    {"match": {"op": "==", "left": {"meta": {"key": "oifname"}}, "right": "eth0"}},
+                    {"counter": {"packets": 12, "bytes": 960}},
+                    {"masquerade": null}
+                ]}},
+            {"rule": {"family": "inet", "table": "gw-lab", "chain": "input", "handle": 8,
+                "expr": [
+                    {"match": {"op": "==", "left": {"meta": {"key": "iifname"}}, "right": "lo"}},
+                    {"accept": null}
+                ]}}
+        ]}"#;
+        let runner = Arc::new(gw_core::MockCommandRunner::new().ok("nft -j list table", live));
+        let manager = NftManager::new().with_runner(runner);
+
+        assert_eq!(
+            manager.read_counters("gw-lab").await.unwrap(),
+            vec![("masquerade out eth0".to_string(), 12, 960)]
+        );
+    }
+
+    #[test]
+    fn drop_logging_is_opt_in() {
+        let log_rules = |policy: &PolicyProfile| -> Vec<(String, Value)> {
+            let ruleset = NftManager::new()
+                .create_complete_ruleset(
+                    "gw-test",
+                    "br-test",
+                    "10.33.0.0/24",
+                    "10.33.0.1",
+                    None,
+                    "eth0",
+                    &[],
+                    false,
+                    Some(policy),
+                    &[],
+                    None,
+                )
+                .unwrap();
+            ["input", "forward"]
+                .into_iter()
+                .flat_map(|chain| {
+                    chain_exprs(&load_ruleset(&ruleset), chain)
+                        .into_iter()
+                        .filter(|exprs| expr_has_key(exprs, "log"))
+                        .map(move |exprs| (chain.to_string(), exprs))
+                })
+                .collect()
+        };
+
+        let mut policy = icmp_policy(Action::Drop, IcmpRateLimit::default());
+        assert!(log_rules(&policy).is_empty());
+
+        policy.log_dropped = true;
+        let rules = log_rules(&policy);
+        let chains: Vec<&str> = rules.iter().map(|(chain, _)| chain.as_str()).collect();
+        assert_eq!(chains, vec!["input", "forward"]);
+        for (_, exprs) in &rules {
+            let exprs = exprs.as_array().unwrap();
+            assert!(exprs.contains(&json!({"counter": null})));
+            assert!(exprs.contains(&json!({"log": {"prefix": "gw-drop "}})));
+            assert!(exprs.iter().any(|expr| expr.get("limit").is_some()));
+        }
+
+        // Nothing is dropped under an accept default.
+        policy.default_action = Action::Accept;
+        assert!(log_rules(&policy).is_empty());
+    }
+
+    #[test]
+    fn icmp_rate_limit_can_be_disabled() {
+        let disabled = IcmpRateLimit {
+            enabled: false,
+            ..IcmpRateLimit::default()
+        };
+        assert!(input_limits(&icmp_policy(Action::Drop, disabled)).is_empty());
+
+        // An accept default lets ICMP through anyway, so no limit is emitted.
+        let accept = icmp_policy(Action::Accept, IcmpRateLimit::default());
+        assert!(input_limits(&accept).is_empty());
+    }
+
+    #[test]
+    fn diff_splits_added_and_removed_lines() {
+        let diff = NftDiff {
+            table: "gw-test".into(),
+            matches: false,
+            current_exists: true,
+            diff: Some(render_diff("a\nold\nb\n", "a\nnew\nb\nextra\n")),
+        };
+
+        assert_eq!(diff.added_lines(), vec!["new", "extra"]);
+        assert_eq!(diff.removed_lines(), vec!["old"]);
+    }
+
+    #[tokio::test]
+    async fn snapshot_of_missing_table_is_none() {
+        let runner = Arc::new(
+            gw_core::MockCommandRunner::new()
+                .fail(
+                    "nft -j list table inet gw-missing",
+                    1,
+                    "Error: No such file or directory; did you mean table 'gw-lab'?",
+                )
+                .fail(
+                    "nft -j list table inet gw-denied",
+                    1,
+                    "Operation not permitted",
+                )
+                .ok("nft -j list table inet gw-lab", r#"{"nftables":[]}"#),
+        );
+        let manager = NftManager::new().with_runner(runner.clone());
+
+        assert_eq!(manager.snapshot_table("gw-missing").await.unwrap(), None);
+        assert_eq!(
+            manager.snapshot_table("gw-lab").await.unwrap().as_deref(),
+            Some(r#"{"nftables":[]}"#)
+        );
+        let err = manager.snapshot_table("gw-denied").await.unwrap_err();
+        assert!(err.to_string().contains("Operation not permitted"));
+    }
+
+    #[tokio::test]
+    async fn ruleset_round_trips_through_a_file() {
+        let saved = r#"{"nftables":[{"metainfo":{"json_schema_version":1}},{"table":{"family":"inet","name":"gw-lab"}}]}"#;
+        let runner = Arc::new(
+            gw_core::MockCommandRunner::new()
+                .ok("nft -j list ruleset", saved)
+                .ok("nft -j -f -", ""),
+        );
+        let manager = NftManager::new().with_runner(runner.clone());
+        let path = std::env::temp_dir().join(format!("gw-nft-export-{}.json", std::process::id()));
+
+        manager.dump_ruleset_to_file(&path).await.unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), saved);
+
+        manager.apply_ruleset_from_file(&path).await.unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let calls = runner.calls();
+        let loaded: Value = serde_json::from_str(calls[1].stdin.as_deref().unwrap()).unwrap();
+        let commands = loaded["nftables"].as_array().unwrap();
+        assert_eq!(commands[0], json!({"flush": {"ruleset": null}}));
+        assert_eq!(
+            commands[2],
+            json!({"table": {"family": "inet", "name": "gw-lab"}})
+        );
+    }
+
+    #[tokio::test]
+    async fn ruleset_file_errors_name_their_cause() {
+        let denied =
+            NftManager::new().with_runner(Arc::new(gw_core::MockCommandRunner::new().fail(
+                "nft -j list ruleset",
+                1,
+                "netlink: Error: Operation not permitted",
+            )));
+        let path = std::env::temp_dir().join("gw-nft-unused.json");
+        let err = denied.dump_ruleset_to_file(&path).await.unwrap_err();
+        assert!(err.to_string().starts_with("Permission denied listing"));
+
+        // No canned output: the mock reports the program as missing
+        let missing = NftManager::new().with_runner(Arc::new(gw_core::MockCommandRunner::new()));
+        let err = missing.dump_ruleset_to_file(&path).await.unwrap_err();
+        assert_eq!(err.to_string(), "nft not found; install nftables");
+
+        let err = missing
+            .apply_ruleset_from_file(&std::env::temp_dir().join("gw-nft-does-not-exist.json"))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().starts_with("Failed to read"));
+    }
+
+    #[tokio::test]
+    async fn apply_pipes_payload_and_returns_previous_snapshot() {
+        let runner = Arc::new(
+            gw_core::MockCommandRunner::new()
+                .fail("nft -j list table", 1, "No such file or directory")
+                .ok("nft -j -f -", ""),
+        );
+        let manager = NftManager::new().with_runner(runner.clone());
+
+        let previous = manager
+            .apply_ruleset("gw-lab", r#"{"nftables":[]}"#)
+            .await
+            .unwrap();
+        assert_eq!(previous, None);
+
+        let calls = runner.calls();
+        assert_eq!(calls[1].command, "nft -j -f -");
+        assert_eq!(calls[1].stdin.as_deref(), Some(r#"{"nftables":[]}"#));
+    }
+
+    #[tokio::test]
+    async fn check_ruleset_runs_nft_in_check_mode() {
+        let runner = Arc::new(gw_core::MockCommandRunner::new().ok("nft -j -c -f -", ""));
+        let manager = NftManager::new().with_runner(runner.clone());
+        manager.check_ruleset(r#"{"nftables":[]}"#).await.unwrap();
+        assert_eq!(runner.calls()[0].command, "nft -j -c -f -");
+        assert_eq!(
+            runner.calls()[0].stdin.as_deref(),
+            Some(r#"{"nftables":[]}"#)
+        );
+    }
+
+    #[tokio::test]
+    async fn check_ruleset_surfaces_nft_parser_error() {
+        let runner = Arc::new(gw_core::MockCommandRunner::new().fail(
+            "nft -j -c -f -",
+            1,
+            "Error: Failed to parse JSON input: '[' or '{' expected near 'nftables'",
+        ));
+        let manager = NftManager::new().with_runner(runner.clone());
+
+        let err = manager.check_ruleset("nftables: [").await.unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "nft rejected the ruleset: Error: Failed to parse JSON input: '[' or '{' expected near 'nftables'"
+        );
+        // Nothing was applied: the only call was the check.
+        assert_eq!(runner.calls().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn check_ruleset_rejecting_empty_payload_names_no_command() {
+        let runner = Arc::new(gw_core::MockCommandRunner::new().fail("nft", 1, "Error: no tables"));
+        let manager = NftManager::new().with_runner(runner.clone());
+
+        let err = manager
+            .check_ruleset(r#"{"nftables":[]}"#)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "nft rejected the ruleset: Error: no tables"
+        );
+        assert_eq!(runner.calls().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn list_tables_reads_names() {
+        let runner = Arc::new(gw_core::MockCommandRunner::new().ok(
+            "nft -j list tables",
+            r#"{"nftables":[{"metainfo":{}},{"table":{"family":"inet","name":"gw-lab"}}]}"#,
+        ));
+        let manager = NftManager::new().with_runner(runner);
+
+        assert_eq!(manager.list_tables().await.unwrap(), vec!["gw-lab"]);
+    }
 }
@@ -1,5 +1,5 @@
 use anyhow::Result;
-use gw_core::NftTableStatus;
+use gw_core::{NftTableStatus, QuotaStatus};
 use tokio::process::Command;
 
 pub struct NftStatusCollector;
@@ -33,14 +33,15 @@ impl NftStatusCollector {
                         table.get("family").and_then(|f| f.as_str()),
                     )
                 {
-                    // Get chain and rule counts for this table
-                    let (chains, rules) = self.count_chains_and_rules(family, name).await?;
+                    // Get chain and rule counts and quota usage for this table
+                    let summary = self.inspect_table(family, name).await?;
 
                     tables.push(NftTableStatus {
                         name: name.to_string(),
                         family: family.to_string(),
-                        chains,
-                        rules,
+                        chains: summary.chains,
+                        rules: summary.rules,
+                        quotas: summary.quotas,
                     });
                 }
             }
@@ -49,7 +50,7 @@ impl NftStatusCollector {
         Ok(tables)
     }
 
-    async fn count_chains_and_rules(&self, family: &str, table: &str) -> Result<(usize, usize)> {
+    async fn inspect_table(&self, family: &str, table: &str) -> Result<TableSummary> {
         let output = Command::new("nft")
             .arg("-j")
             .arg("list")
@@ -60,28 +61,50 @@ impl NftStatusCollector {
             .await?;
 
         if !output.status.success() {
-            return Ok((0, 0));
+            return Ok(TableSummary::default());
         }
 
         let json_output = String::from_utf8_lossy(&output.stdout);
         let parsed: serde_json::Value = serde_json::from_str(&json_output)?;
+        Ok(summarize_table(&parsed))
+    }
+}
 
-        let mut chain_count = 0;
-        let mut rule_count = 0;
+#[derive(Debug, Default)]
+struct TableSummary {
+    chains: usize,
+    rules: usize,
+    quotas: Vec<QuotaStatus>,
+}
 
-        if let Some(nftables) = parsed.get("nftables").and_then(|n| n.as_array()) {
-            for item in nftables {
-                if item.get("chain").is_some() {
-                    chain_count += 1;
-                }
-                if item.get("rule").is_some() {
-                    rule_count += 1;
-                }
-            }
-        }
+/// Count chains and rules and read `quota` objects from `nft -j list table` output.
+fn summarize_table(parsed: &serde_json::Value) -> TableSummary {
+    let mut summary = TableSummary::default();
+
+    let Some(nftables) = parsed.get("nftables").and_then(|n| n.as_array()) else {
+        return summary;
+    };
 
-        Ok((chain_count, rule_count))
+    for item in nftables {
+        if item.get("chain").is_some() {
+            summary.chains += 1;
+        }
+        if item.get("rule").is_some() {
+            summary.rules += 1;
+        }
+        if let Some(quota) = item.get("quota")
+            && let Some(name) = quota.get("name").and_then(|n| n.as_str())
+        {
+            summary.quotas.push(QuotaStatus {
+                name: name.to_string(),
+                bytes: quota.get("bytes").and_then(|b| b.as_u64()).unwrap_or(0),
+                used: quota.get("used").and_then(|u| u.as_u64()).unwrap_or(0),
+                over: quota.get("inv").and_then(|i| i.as_bool()).unwrap_or(false),
+            });
+        }
     }
+
+    summary
 }
 
 impl Default for NftStatusCollector {
@@ -89,3 +112,36 @@ impl Default for NftStatusCollector {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn reads_quota_usage_from_live_table() {
+        let listing = json!({"nftables": [
+            {"metainfo": {"json_schema_version": 1}},
+            {"table": {"family": "inet", "name": "gw-lab", "handle": 7}},
+            {"chain": {"family": "inet", "table": "gw-lab", "name": "forward", "handle": 2}},
+            {"quota": {"family": "inet", "table": "gw-lab", "name": "traffic", "handle": 3,
+                       "bytes": 1_000_000, "used": 250_000, "inv": true}},
+            {"rule": {"family": "inet", "table": "gw-lab", "chain": "forward", "handle": 4,
+                      "expr": [{"quota": "traffic"}, {"drop": null}]}},
+        ]});
+
+        let summary = summarize_table(&listing);
+        assert_eq!(summary.chains, 1);
+        assert_eq!(summary.rules, 1);
+        assert_eq!(
+            summary.quotas,
+            vec![QuotaStatus {
+                name: "traffic".into(),
+                bytes: 1_000_000,
+                used: 250_000,
+                over: true,
+            }]
+        );
+        assert!(!summary.quotas[0].exceeded());
+    }
+}
@@ -1,6 +1,8 @@
 use anyhow::{Context, Result};
+use rtnetlink::packet_route::AddressFamily;
+use rtnetlink::packet_route::address::{AddressAttribute, AddressMessage, AddressScope};
 use rtnetlink::{Handle, new_connection};
-use std::net::IpAddr;
+use std::net::{IpAddr, Ipv6Addr};
 
 pub struct AddressManager {
     handle: Handle,
@@ -8,6 +10,7 @@ pub struct AddressManager {
 
 impl AddressManager {
     pub async fn new() -> Result<Self> {
+        crate::ensure_net_admin()?;
         let (connection, handle, _) = new_connection()?;
         tokio::spawn(connection);
         Ok(Self { handle })
@@ -28,6 +31,15 @@ impl AddressManager {
             .parse()
             .context(format!("Invalid prefix length: {}", parts[1]))?;
 
+        if self
+            .list_addresses(iface)
+            .await?
+            .contains(&(addr, prefix_len))
+        {
+            println!("Address {} already present on {}, skipping", cidr, iface);
+            return Ok(());
+        }
+
         // Get link index
         let link_index = self.get_link_by_name(iface).await?;
 
@@ -57,7 +69,6 @@ impl AddressManager {
 
     pub async fn delete_address(&self, iface: &str, cidr: &str) -> Result<()> {
         use futures::stream::TryStreamExt;
-        use rtnetlink::packet_route::address::AddressAttribute;
 
         // Parse CIDR notation
         let parts: Vec<&str> = cidr.split('/').collect();
@@ -85,26 +96,7 @@ impl AddressManager {
             .execute();
 
         while let Some(addr_msg) = addrs.try_next().await? {
-            // Check if this address matches what we want to delete
-            let mut matches = false;
-
-            // Check prefix length matches
-            if addr_msg.header.prefix_len != prefix_len {
-                continue;
-            }
-
-            // Check if the address matches
-            for attr in &addr_msg.attributes {
-                if let AddressAttribute::Address(msg_addr) = attr
-                    && *msg_addr == addr
-                {
-                    matches = true;
-                    break;
-                }
-            }
-
-            // Delete the matching address
-            if matches {
+            if address_matches(&addr_msg, addr, prefix_len) {
                 self.handle
                     .address()
                     .del(addr_msg)
@@ -122,6 +114,27 @@ impl AddressManager {
         Ok(())
     }
 
+    /// Addresses on `iface` as address/prefix pairs
+    pub async fn list_addresses(&self, iface: &str) -> Result<Vec<(IpAddr, u8)>> {
+        use futures::stream::TryStreamExt;
+
+        let link_index = self.get_link_by_name(iface).await?;
+        let messages: Vec<AddressMessage> = self
+            .handle
+            .address()
+            .get()
+            .set_link_index_filter(link_index)
+            .execute()
+            .try_collect()
+            .await
+            .context(format!("Failed to list addresses on {}", iface))?;
+
+        Ok(messages
+            .iter()
+            .filter_map(|msg| message_address(msg).map(|addr| (addr, msg.header.prefix_len)))
+            .collect())
+    }
+
     async fn get_link_by_name(&self, name: &str) -> Result<u32> {
         use futures::stream::TryStreamExt;
 
@@ -139,3 +152,134 @@ impl AddressManager {
         }
     }
 }
+
+/// Whether `message` is `addr/prefix_len`.
+///
+/// The kernel derives IPv6 scope from the address, so a message whose scope
+/// disagrees does not describe it.
+fn address_matches(message: &AddressMessage, addr: IpAddr, prefix_len: u8) -> bool {
+    let family = match addr {
+        IpAddr::V4(_) => AddressFamily::Inet,
+        IpAddr::V6(_) => AddressFamily::Inet6,
+    };
+    if message.header.family != family || message.header.prefix_len != prefix_len {
+        return false;
+    }
+    if let IpAddr::V6(v6) = addr
+        && message.header.scope != ipv6_scope(&v6)
+    {
+        return false;
+    }
+
+    message_address(message) == Some(addr)
+}
+
+/// The interface's own address in `message`: `Local` when present, since on
+/// point-to-point links `Address` holds the peer.
+pub(crate) fn message_address(message: &AddressMessage) -> Option<IpAddr> {
+    let local = message.attributes.iter().find_map(|attr| match attr {
+        AddressAttribute::Local(local) => Some(*local),
+        _ => None,
+    });
+    let address = message.attributes.iter().find_map(|attr| match attr {
+        AddressAttribute::Address(address) => Some(*address),
+        _ => None,
+    });
+    local.or(address)
+}
+
+/// Scope the kernel assigns an IPv6 address added without an explicit one
+fn ipv6_scope(addr: &Ipv6Addr) -> AddressScope {
+    if addr.is_loopback() {
+        AddressScope::Host
+    } else if addr.is_unicast_link_local() {
+        AddressScope::Link
+    } else {
+        AddressScope::Universe
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(
+        family: AddressFamily,
+        prefix_len: u8,
+        scope: AddressScope,
+        attributes: Vec<AddressAttribute>,
+    ) -> AddressMessage {
+        let mut message = AddressMessage::default();
+        message.header.family = family;
+        message.header.prefix_len = prefix_len;
+        message.header.scope = scope;
+        message.attributes = attributes;
+        message
+    }
+
+    #[test]
+    fn matches_ipv6_global_address() {
+        let addr: IpAddr = "fd00:33::1".parse().unwrap();
+        let msg = message(
+            AddressFamily::Inet6,
+            64,
+            AddressScope::Universe,
+            vec![AddressAttribute::Address(addr)],
+        );
+
+        assert!(address_matches(&msg, addr, 64));
+        assert!(!address_matches(&msg, addr, 48));
+        assert!(!address_matches(&msg, "fd00:33::2".parse().unwrap(), 64));
+    }
+
+    #[test]
+    fn ipv6_scope_mismatch_is_not_deleted() {
+        let link_local: IpAddr = "fe80::1".parse().unwrap();
+        let link_scoped = message(
+            AddressFamily::Inet6,
+            64,
+            AddressScope::Link,
+            vec![AddressAttribute::Address(link_local)],
+        );
+        let global_scoped = message(
+            AddressFamily::Inet6,
+            64,
+            AddressScope::Universe,
+            vec![AddressAttribute::Address(link_local)],
+        );
+
+        assert!(address_matches(&link_scoped, link_local, 64));
+        assert!(!address_matches(&global_scoped, link_local, 64));
+    }
+
+    #[test]
+    fn prefers_local_over_peer_address() {
+        let local: IpAddr = "10.33.0.1".parse().unwrap();
+        let peer: IpAddr = "10.33.0.2".parse().unwrap();
+        let point_to_point = message(
+            AddressFamily::Inet,
+            32,
+            AddressScope::Universe,
+            vec![
+                AddressAttribute::Address(peer),
+                AddressAttribute::Local(local),
+            ],
+        );
+
+        assert!(address_matches(&point_to_point, local, 32));
+        assert!(!address_matches(&point_to_point, peer, 32));
+    }
+
+    #[test]
+    fn family_must_match() {
+        let addr: IpAddr = "10.33.0.1".parse().unwrap();
+        let mislabeled = message(
+            AddressFamily::Inet6,
+            24,
+            AddressScope::Universe,
+            vec![AddressAttribute::Address(addr)],
+        );
+
+        assert!(!address_matches(&mislabeled, addr, 24));
+    }
+}
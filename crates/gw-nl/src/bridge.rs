@@ -1,7 +1,18 @@
 use anyhow::{Context, Result};
 use futures::stream::TryStreamExt;
-use rtnetlink::packet_route::link::{LinkAttribute, LinkFlags, LinkMessage};
-use rtnetlink::{Handle, LinkBridge, LinkUnspec, new_connection};
+use gw_core::PortVlans;
+use rtnetlink::packet_route::link::{
+    BridgeStpState, BridgeVlanInfoFlags, InfoBridge, InfoData, LinkAttribute, LinkFlags, LinkInfo,
+    LinkMessage,
+};
+use rtnetlink::{
+    Handle, LinkBridge, LinkBridgePort, LinkBridgeVlan, LinkMessageBuilder, LinkUnspec, LinkVeth,
+    new_connection,
+};
+use std::time::Duration;
+
+/// How often [`BridgeManager::wait_until_up`] re-reads the link flags
+const LINK_POLL_INTERVAL: Duration = Duration::from_millis(100);
 
 pub struct BridgeManager {
     handle: Handle,
@@ -9,18 +20,43 @@ pub struct BridgeManager {
 
 impl BridgeManager {
     pub async fn new() -> Result<Self> {
+        crate::ensure_net_admin()?;
         let (connection, handle, _) = new_connection()?;
         tokio::spawn(connection);
         Ok(Self { handle })
     }
 
     pub async fn create_bridge(&self, name: &str) -> Result<()> {
-        // Check if bridge already exists
+        self.ensure_bridge(name, None).await
+    }
+
+    /// Create a bridge, or bring an existing one back to the desired state:
+    /// up and, when `mtu` is given, with that MTU.
+    pub async fn ensure_bridge(&self, name: &str, mtu: Option<u32>) -> Result<()> {
         if self.bridge_exists(name).await? {
-            println!("Bridge {} already exists, skipping creation", name);
+            let info = self.get_bridge_info(name).await?;
+            if info.is_up {
+                println!("Bridge {} already exists, skipping creation", name);
+            } else {
+                self.set_link_up(name, info.index).await?;
+                println!("Bridge {} already exists, set it up", name);
+            }
+            if let Some(mtu) = mtu
+                && info.mtu != mtu
+            {
+                self.set_mtu(name, mtu).await?;
+            }
             return Ok(());
         }
 
+        self.add_bridge(name).await?;
+        if let Some(mtu) = mtu {
+            self.set_mtu(name, mtu).await?;
+        }
+        Ok(())
+    }
+
+    async fn add_bridge(&self, name: &str) -> Result<()> {
         // Create bridge
         self.handle
             .link()
@@ -33,14 +69,19 @@ impl BridgeManager {
 
         // Set bridge up
         let link = self.get_link_by_name(name).await?;
+        self.set_link_up(name, link).await?;
+
+        println!("Set bridge {} up", name);
+        Ok(())
+    }
+
+    async fn set_link_up(&self, name: &str, index: u32) -> Result<()> {
         self.handle
             .link()
-            .set(LinkUnspec::new_with_index(link).up().build())
+            .set(LinkUnspec::new_with_index(index).up().build())
             .execute()
             .await
             .context(format!("Failed to set bridge {} up", name))?;
-
-        println!("Set bridge {} up", name);
         Ok(())
     }
 
@@ -156,30 +197,8 @@ impl BridgeManager {
         let bridge = self.get_link_message_by_name(bridge_name).await?;
         let bridge_index = bridge.header.index;
 
-        let mut links = self.handle.link().get().execute();
-        let mut members = Vec::new();
-
-        while let Some(link) = links.try_next().await? {
-            // Check if this link is attached to our bridge (has this bridge as controller)
-            for attr in &link.attributes {
-                if let LinkAttribute::Controller(controller_index) = attr
-                    && *controller_index == bridge_index
-                {
-                    // This link is attached to our bridge
-                    if let Some(name) = link.attributes.iter().find_map(|a| {
-                        if let LinkAttribute::IfName(n) = a {
-                            Some(n.clone())
-                        } else {
-                            None
-                        }
-                    }) {
-                        members.push(name);
-                    }
-                }
-            }
-        }
-
-        Ok(members)
+        let links: Vec<LinkMessage> = self.handle.link().get().execute().try_collect().await?;
+        Ok(bridge_members(&links, bridge_index))
     }
 
     /// Set bridge MTU
@@ -197,8 +216,15 @@ impl BridgeManager {
         Ok(())
     }
 
-    /// Attach an interface to a bridge
-    pub async fn attach_interface_to_bridge(&self, interface: &str, bridge: &str) -> Result<()> {
+    /// Attach an interface to a bridge, optionally as an isolated port and with
+    /// VLAN membership (the bridge must have VLAN filtering enabled)
+    pub async fn attach_interface_to_bridge(
+        &self,
+        interface: &str,
+        bridge: &str,
+        isolated: bool,
+        vlans: Option<&PortVlans>,
+    ) -> Result<()> {
         let iface_index = self.get_link_by_name(interface).await?;
         let bridge_index = self.get_link_by_name(bridge).await?;
 
@@ -217,6 +243,312 @@ impl BridgeManager {
             ))?;
 
         println!("Attached interface {} to bridge {}", interface, bridge);
+
+        if isolated {
+            self.set_port_isolated(interface, true).await?;
+        }
+        if let Some(vlans) = vlans {
+            self.set_port_vlans(interface, bridge, vlans).await?;
+        }
+        Ok(())
+    }
+
+    /// Poll until `iface` is up with carrier (`LowerUp`). A bridge with ports
+    /// only has carrier while one of them does, so call this after enslaving one.
+    pub async fn wait_until_up(&self, iface: &str, timeout: Duration) -> Result<()> {
+        let deadline = tokio::time::Instant::now() + timeout;
+        loop {
+            let flags = self.get_link_message_by_name(iface).await?.header.flags;
+            if flags.contains(LinkFlags::Up | LinkFlags::LowerUp) {
+                return Ok(());
+            }
+            if tokio::time::Instant::now() >= deadline {
+                anyhow::bail!(
+                    "{} did not come up within {:?} (last flags: {:?})",
+                    iface,
+                    timeout,
+                    flags
+                );
+            }
+            tokio::time::sleep(LINK_POLL_INTERVAL).await;
+        }
+    }
+
+    /// Create a veth pair `name_a` <-> `name_b`; both ends start down.
+    pub async fn create_veth_pair(&self, name_a: &str, name_b: &str) -> Result<()> {
+        if self.get_link_by_name(name_a).await.is_ok() {
+            println!("Veth {} already exists, skipping creation", name_a);
+            return Ok(());
+        }
+
+        self.handle
+            .link()
+            .add(LinkVeth::new(name_a, name_b).build())
+            .execute()
+            .await
+            .context(format!("Failed to create veth pair {}/{}", name_a, name_b))?;
+
+        println!("Created veth pair: {} <-> {}", name_a, name_b);
+        Ok(())
+    }
+
+    /// Delete a veth pair by either end; the kernel removes the peer with it.
+    pub async fn delete_veth(&self, name: &str) -> Result<()> {
+        let link = self.get_link_by_name(name).await?;
+
+        self.handle
+            .link()
+            .del(link)
+            .execute()
+            .await
+            .context(format!("Failed to delete veth {}", name))?;
+
+        println!("Deleted veth pair: {}", name);
+        Ok(())
+    }
+
+    /// Create a veth pair, enslave `bridge_end` to `bridge` and bring both
+    /// ends up. `peer_end` is left for the caller to move into a namespace.
+    pub async fn create_veth_on_bridge(
+        &self,
+        bridge_end: &str,
+        peer_end: &str,
+        bridge: &str,
+    ) -> Result<()> {
+        self.create_veth_pair(bridge_end, peer_end).await?;
+        self.attach_interface_to_bridge(bridge_end, bridge, false, None)
+            .await?;
+
+        for name in [bridge_end, peer_end] {
+            let link = self.get_link_by_name(name).await?;
+            self.handle
+                .link()
+                .set(LinkUnspec::new_with_index(link).up().build())
+                .execute()
+                .await
+                .context(format!("Failed to set veth {} up", name))?;
+        }
+
+        println!("Set veth pair {} <-> {} up", bridge_end, peer_end);
+        Ok(())
+    }
+
+    /// Turn VLAN filtering on or off for a bridge.
+    pub async fn set_vlan_filtering(&self, bridge: &str, enabled: bool) -> Result<()> {
+        let bridge_index = self.get_link_by_name(bridge).await?;
+
+        // RTM_SETLINK ignores bridge link info, so this goes out as RTM_NEWLINK
+        self.handle
+            .link()
+            .change(vlan_filtering_message(bridge, bridge_index, enabled))
+            .execute()
+            .await
+            .context(format!("Failed to set VLAN filtering on {}", bridge))?;
+
+        println!(
+            "{} VLAN filtering on {}",
+            if enabled { "Enabled" } else { "Disabled" },
+            bridge
+        );
+        Ok(())
+    }
+
+    /// Set the PVID new ports of a VLAN-filtering bridge join untagged.
+    pub async fn set_vlan_default_pvid(&self, bridge: &str, default_pvid: u16) -> Result<()> {
+        gw_core::validate_vlan_id(default_pvid)?;
+        let bridge_index = self.get_link_by_name(bridge).await?;
+
+        self.handle
+            .link()
+            .change(
+                LinkMessageBuilder::<LinkBridge>::new(bridge)
+                    .index(bridge_index)
+                    .vlan_default_pvid(default_pvid)
+                    .build(),
+            )
+            .execute()
+            .await
+            .context(format!("Failed to set default PVID on {}", bridge))?;
+
+        println!("Set default PVID {} on {}", default_pvid, bridge);
+        Ok(())
+    }
+
+    /// Add one VLAN to a bridge port, equivalent to
+    /// `bridge vlan add dev <port> vid <vid> [pvid] [untagged]`.
+    pub async fn add_bridge_vlan(
+        &self,
+        port: &str,
+        vid: u16,
+        pvid: bool,
+        untagged: bool,
+    ) -> Result<()> {
+        gw_core::validate_vlan_id(vid)?;
+        let port_index = self.get_link_by_name(port).await?;
+
+        self.handle
+            .link()
+            .set(bridge_vlan_message(port_index, vid, pvid, untagged))
+            .execute()
+            .await
+            .context(format!(
+                "Failed to add VLAN {} to {} (is VLAN filtering enabled?)",
+                vid, port
+            ))?;
+
+        println!(
+            "Added VLAN {} to bridge port {}{}{}",
+            vid,
+            port,
+            if pvid { " (pvid)" } else { "" },
+            if untagged { " (untagged)" } else { "" }
+        );
+        Ok(())
+    }
+
+    /// Turn the kernel Spanning Tree Protocol on or off for a bridge. Falls back
+    /// to sysfs when the netlink request is rejected.
+    pub async fn set_stp(&self, bridge: &str, enabled: bool) -> Result<()> {
+        let bridge_index = self.get_link_by_name(bridge).await?;
+        let state = if enabled {
+            BridgeStpState::KernelStp
+        } else {
+            BridgeStpState::Disabled
+        };
+
+        // RTM_SETLINK ignores bridge link info, so this goes out as RTM_NEWLINK
+        let result = self
+            .handle
+            .link()
+            .change(
+                LinkMessageBuilder::<LinkBridge>::new(bridge)
+                    .index(bridge_index)
+                    .stp_state(state)
+                    .build(),
+            )
+            .execute()
+            .await;
+
+        if let Err(e) = result {
+            let sysfs_path = format!("/sys/class/net/{}/bridge/stp_state", bridge);
+            std::fs::write(&sysfs_path, if enabled { "1" } else { "0" })
+                .map_err(|_| e)
+                .context(format!("Failed to set STP on {}", bridge))?;
+        }
+
+        println!(
+            "{} STP on {}",
+            if enabled { "Enabled" } else { "Disabled" },
+            bridge
+        );
+        Ok(())
+    }
+
+    /// Program a bridge port's VLAN membership, equivalent to `bridge vlan add`
+    /// per VLAN. The bridge's default PVID membership, which every new port
+    /// gets, is removed unless the port lists it.
+    pub async fn set_port_vlans(
+        &self,
+        interface: &str,
+        bridge: &str,
+        vlans: &PortVlans,
+    ) -> Result<()> {
+        vlans.validate()?;
+        let iface_index = self.get_link_by_name(interface).await?;
+
+        let mut message = LinkBridgeVlan::new(iface_index);
+        for vid in &vlans.vlans {
+            let flags = bridge_vlan_flags(vlans.pvid == Some(*vid), vlans.untagged.contains(vid));
+            message = message.vlan(*vid, flags);
+        }
+
+        self.handle
+            .link()
+            .set(message.build())
+            .execute()
+            .await
+            .context(format!(
+                "Failed to set VLANs on {} (is VLAN filtering enabled on {}?)",
+                interface, bridge
+            ))?;
+
+        let default_pvid = self.bridge_default_pvid(bridge).await?;
+        if default_pvid != 0 && !vlans.vlans.contains(&default_pvid) {
+            self.handle
+                .link()
+                .del_with_message(
+                    LinkBridgeVlan::new(iface_index)
+                        .vlan(default_pvid, BridgeVlanInfoFlags::empty())
+                        .build(),
+                )
+                .execute()
+                .await
+                .context(format!(
+                    "Failed to remove default VLAN {} from {}",
+                    default_pvid, interface
+                ))?;
+        }
+
+        println!(
+            "Set bridge port {} VLANs {:?} (pvid {}, untagged {:?})",
+            interface,
+            vlans.vlans,
+            vlans
+                .pvid
+                .map(|pvid| pvid.to_string())
+                .unwrap_or_else(|| "none".to_string()),
+            vlans.untagged
+        );
+        Ok(())
+    }
+
+    /// The bridge's `vlan_default_pvid`; 0 means new ports join no VLAN.
+    async fn bridge_default_pvid(&self, bridge: &str) -> Result<u16> {
+        let link = self.get_link_message_by_name(bridge).await?;
+
+        let pvid = link
+            .attributes
+            .iter()
+            .filter_map(|attr| match attr {
+                LinkAttribute::LinkInfo(infos) => Some(infos),
+                _ => None,
+            })
+            .flatten()
+            .filter_map(|info| match info {
+                LinkInfo::Data(InfoData::Bridge(data)) => Some(data),
+                _ => None,
+            })
+            .flatten()
+            .find_map(|data| match data {
+                InfoBridge::VlanDefaultPvid(pvid) => Some(*pvid),
+                _ => None,
+            });
+
+        // Kernel default when the attribute is absent.
+        Ok(pvid.unwrap_or(1))
+    }
+
+    /// Set the bridge port `isolated` flag on an enslaved interface.
+    /// Isolated ports can only talk to non-isolated ports (the bridge itself
+    /// and uplinks), never to each other.
+    pub async fn set_port_isolated(&self, interface: &str, isolated: bool) -> Result<()> {
+        let iface_index = self.get_link_by_name(interface).await?;
+
+        self.handle
+            .link()
+            .set(LinkBridgePort::new(iface_index).isolated(isolated).build())
+            .execute()
+            .await
+            .context(format!(
+                "Failed to set port isolation on {} (is it attached to a bridge?)",
+                interface
+            ))?;
+
+        println!(
+            "Set bridge port {} isolated {}",
+            interface,
+            if isolated { "on" } else { "off" }
+        );
         Ok(())
     }
 
@@ -251,6 +583,52 @@ impl BridgeManager {
     }
 }
 
+fn bridge_vlan_flags(pvid: bool, untagged: bool) -> BridgeVlanInfoFlags {
+    let mut flags = BridgeVlanInfoFlags::empty();
+    if pvid {
+        flags |= BridgeVlanInfoFlags::Pvid;
+    }
+    if untagged {
+        flags |= BridgeVlanInfoFlags::Untagged;
+    }
+    flags
+}
+
+/// Bridge link message that turns VLAN filtering on or off for the bridge at
+/// `bridge_index`; must be sent with `change()` (RTM_NEWLINK) to take effect.
+fn vlan_filtering_message(bridge: &str, bridge_index: u32, enabled: bool) -> LinkMessage {
+    LinkMessageBuilder::<LinkBridge>::new(bridge)
+        .index(bridge_index)
+        .vlan_filtering(enabled)
+        .build()
+}
+
+/// AF_BRIDGE link message carrying a single VLAN for the port at `port_index`.
+fn bridge_vlan_message(port_index: u32, vid: u16, pvid: bool, untagged: bool) -> LinkMessage {
+    LinkBridgeVlan::new(port_index)
+        .vlan(vid, bridge_vlan_flags(pvid, untagged))
+        .build()
+}
+
+/// Names of the links enslaved to the bridge at `bridge_index`, i.e. those
+/// whose `Controller` attribute points at it.
+pub(crate) fn bridge_members(links: &[LinkMessage], bridge_index: u32) -> Vec<String> {
+    links
+        .iter()
+        .filter(|link| {
+            link.attributes.iter().any(
+                |attr| matches!(attr, LinkAttribute::Controller(index) if *index == bridge_index),
+            )
+        })
+        .filter_map(|link| {
+            link.attributes.iter().find_map(|attr| match attr {
+                LinkAttribute::IfName(name) => Some(name.clone()),
+                _ => None,
+            })
+        })
+        .collect()
+}
+
 /// Bridge information structure
 #[derive(Debug, Clone)]
 pub struct BridgeInfo {
@@ -260,3 +638,98 @@ pub struct BridgeInfo {
     pub mtu: u32,
     pub members: Vec<String>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rtnetlink::packet_route::AddressFamily;
+    use rtnetlink::packet_route::link::{AfSpecBridge, InfoKind};
+
+    fn link(index: u32, name: &str, controller: Option<u32>) -> LinkMessage {
+        let mut link = LinkMessage::default();
+        link.header.index = index;
+        link.attributes.push(LinkAttribute::IfName(name.into()));
+        if let Some(controller) = controller {
+            link.attributes.push(LinkAttribute::Controller(controller));
+        }
+        link
+    }
+
+    fn vlan_infos(message: &LinkMessage) -> Vec<(u16, BridgeVlanInfoFlags)> {
+        message
+            .attributes
+            .iter()
+            .filter_map(|attr| match attr {
+                LinkAttribute::AfSpecBridge(specs) => Some(specs),
+                _ => None,
+            })
+            .flatten()
+            .filter_map(|spec| match spec {
+                AfSpecBridge::VlanInfo(info) => Some((info.vid, info.flags)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn vlan_filtering_message_carries_bridge_link_info() {
+        let message = vlan_filtering_message("br-lab", 4, true);
+        assert_eq!(message.header.index, 4);
+
+        let infos: Vec<&LinkInfo> = message
+            .attributes
+            .iter()
+            .filter_map(|attr| match attr {
+                LinkAttribute::LinkInfo(infos) => Some(infos),
+                _ => None,
+            })
+            .flatten()
+            .collect();
+        assert!(infos.contains(&&LinkInfo::Kind(InfoKind::Bridge)));
+        assert!(infos.contains(&&LinkInfo::Data(InfoData::Bridge(vec![
+            InfoBridge::VlanFiltering(true)
+        ]))));
+    }
+
+    #[test]
+    fn bridge_vlan_message_sets_pvid_and_untagged_flags() {
+        let access = bridge_vlan_message(7, 10, true, true);
+        assert_eq!(access.header.index, 7);
+        assert_eq!(access.header.interface_family, AddressFamily::Bridge);
+        assert_eq!(
+            vlan_infos(&access),
+            vec![(
+                10,
+                BridgeVlanInfoFlags::Pvid | BridgeVlanInfoFlags::Untagged
+            )]
+        );
+
+        let tagged = bridge_vlan_message(7, 20, false, false);
+        assert_eq!(
+            vlan_infos(&tagged),
+            vec![(20, BridgeVlanInfoFlags::empty())]
+        );
+
+        let tagged_pvid = bridge_vlan_message(7, 30, true, false);
+        assert_eq!(
+            vlan_infos(&tagged_pvid),
+            vec![(30, BridgeVlanInfoFlags::Pvid)]
+        );
+    }
+
+    #[test]
+    fn members_are_matched_by_controller_index() {
+        let links = vec![
+            link(1, "lo", None),
+            link(2, "br-lab", None),
+            link(3, "br-dmz", None),
+            link(4, "vnet0", Some(2)),
+            link(5, "vxlan1200", Some(2)),
+            link(6, "vnet1", Some(3)),
+        ];
+
+        assert_eq!(bridge_members(&links, 2), vec!["vnet0", "vxlan1200"]);
+        assert_eq!(bridge_members(&links, 3), vec!["vnet1"]);
+        assert!(bridge_members(&links, 1).is_empty());
+    }
+}
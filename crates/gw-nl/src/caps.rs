@@ -0,0 +1,56 @@
+use anyhow::{Result, bail};
+
+/// `CAP_NET_ADMIN` bit number from `linux/capability.h`.
+const CAP_NET_ADMIN: u32 = 12;
+
+/// Whether this process may change links, addresses and nftables rules.
+///
+/// Netlink sockets open fine without privileges; only the first write fails
+/// with EPERM, so check the effective capability set up front instead.
+pub fn has_net_admin() -> bool {
+    std::fs::read_to_string("/proc/self/status")
+        .ok()
+        .and_then(|status| effective_cap(&status, CAP_NET_ADMIN))
+        // Without /proc we cannot tell; let the kernel decide.
+        .unwrap_or(true)
+}
+
+/// Fail early with guidance when network changes are bound to hit EPERM.
+pub fn ensure_net_admin() -> Result<()> {
+    if !has_net_admin() {
+        bail!(
+            "GhostWarden needs CAP_NET_ADMIN / root to modify network configuration. \
+             Re-run with sudo (read-only commands such as `net plan` and `net status` work without it)."
+        );
+    }
+    Ok(())
+}
+
+fn effective_cap(status: &str, cap: u32) -> Option<bool> {
+    let hex = status
+        .lines()
+        .find_map(|line| line.strip_prefix("CapEff:"))?
+        .trim();
+    let caps = u64::from_str_radix(hex, 16).ok()?;
+    Some(caps & (1 << cap) != 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_net_admin_from_effective_set() {
+        let root = "Name:\tgwarden\nCapInh:\t0000000000000000\nCapEff:\t000001ffffffffff\n";
+        assert_eq!(effective_cap(root, CAP_NET_ADMIN), Some(true));
+
+        let user = "Name:\tgwarden\nCapEff:\t0000000000000000\n";
+        assert_eq!(effective_cap(user, CAP_NET_ADMIN), Some(false));
+
+        // Only CAP_NET_ADMIN granted, e.g. via `setcap cap_net_admin+ep`.
+        let file_cap = "CapEff:\t0000000000001000\n";
+        assert_eq!(effective_cap(file_cap, CAP_NET_ADMIN), Some(true));
+
+        assert_eq!(effective_cap("Name:\tgwarden\n", CAP_NET_ADMIN), None);
+    }
+}
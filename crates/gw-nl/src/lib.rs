@@ -1,11 +1,17 @@
 pub mod addr;
 pub mod bridge;
+pub mod caps;
+pub mod route;
 pub mod status;
 pub mod vlan;
+pub mod vxlan;
 
 pub use addr::*;
 pub use bridge::*;
+pub use caps::*;
+pub use route::*;
 pub use status::*;
 pub use vlan::*;
+pub use vxlan::*;
 
 // Netlink operations for managing links, bridges, VLANs, VXLAN
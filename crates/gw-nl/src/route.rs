@@ -0,0 +1,278 @@
+use anyhow::{Context, Result};
+use futures::stream::TryStreamExt;
+use rtnetlink::packet_route::route::{RouteAddress, RouteAttribute, RouteHeader, RouteMessage};
+use rtnetlink::{Handle, RouteMessageBuilder, new_connection};
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+/// A route in the main table, as listed by [`RouteManager::list_routes`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RouteInfo {
+    /// Destination in CIDR notation, `default` for the default route.
+    pub destination: String,
+    pub gateway: Option<IpAddr>,
+    pub dev: Option<String>,
+}
+
+pub struct RouteManager {
+    handle: Handle,
+}
+
+impl RouteManager {
+    pub async fn new() -> Result<Self> {
+        crate::ensure_net_admin()?;
+        let (connection, handle, _) = new_connection()?;
+        tokio::spawn(connection);
+        Ok(Self { handle })
+    }
+
+    /// Add a static route; a route that already exists is left alone.
+    /// Example: add_route("192.168.50.0/24", "10.40.0.254".parse()?, "br-lab")
+    pub async fn add_route(&self, dst_cidr: &str, gateway: IpAddr, dev: &str) -> Result<()> {
+        let (dst, prefix_len) = parse_cidr(dst_cidr)?;
+        let dev_index = self.get_link_by_name(dev).await?;
+
+        let existing = self.routes(dst).await?;
+        if existing
+            .iter()
+            .any(|route| route_matches(route, dst, prefix_len, gateway, dev_index))
+        {
+            println!(
+                "Route {} via {} dev {} already exists, skipping",
+                dst_cidr, gateway, dev
+            );
+            return Ok(());
+        }
+
+        let message = route_message(dst, prefix_len, gateway, dev_index)?;
+        self.handle
+            .route()
+            .add(message)
+            .execute()
+            .await
+            .context(format!(
+                "Failed to add route {} via {} dev {}",
+                dst_cidr, gateway, dev
+            ))?;
+
+        println!("Added route {} via {} dev {}", dst_cidr, gateway, dev);
+        Ok(())
+    }
+
+    pub async fn delete_route(&self, dst_cidr: &str, gateway: IpAddr, dev: &str) -> Result<()> {
+        let (dst, prefix_len) = parse_cidr(dst_cidr)?;
+        let dev_index = self.get_link_by_name(dev).await?;
+
+        let route = self
+            .routes(dst)
+            .await?
+            .into_iter()
+            .find(|route| route_matches(route, dst, prefix_len, gateway, dev_index))
+            .ok_or_else(|| {
+                anyhow::anyhow!("Route {} via {} dev {} not found", dst_cidr, gateway, dev)
+            })?;
+
+        self.handle
+            .route()
+            .del(route)
+            .execute()
+            .await
+            .context(format!(
+                "Failed to delete route {} via {} dev {}",
+                dst_cidr, gateway, dev
+            ))?;
+
+        println!("Deleted route {} via {} dev {}", dst_cidr, gateway, dev);
+        Ok(())
+    }
+
+    /// IPv4 and IPv6 routes in the main table
+    pub async fn list_routes(&self) -> Result<Vec<RouteInfo>> {
+        let names = self.link_names().await?;
+
+        let mut routes = self.routes(IpAddr::V4(Ipv4Addr::UNSPECIFIED)).await?;
+        routes.extend(self.routes(IpAddr::V6(Ipv6Addr::UNSPECIFIED)).await?);
+
+        Ok(routes
+            .iter()
+            .filter(|route| route.header.table == RouteHeader::RT_TABLE_MAIN)
+            .map(|route| route_info(route, &names))
+            .collect())
+    }
+
+    /// Dump the routes of `family`'s address family
+    async fn routes(&self, family: IpAddr) -> Result<Vec<RouteMessage>> {
+        let filter = match family {
+            IpAddr::V4(_) => RouteMessageBuilder::<Ipv4Addr>::new().build(),
+            IpAddr::V6(_) => RouteMessageBuilder::<Ipv6Addr>::new().build(),
+        };
+        let routes = self
+            .handle
+            .route()
+            .get(filter)
+            .execute()
+            .try_collect()
+            .await
+            .context("Failed to list routes")?;
+        Ok(routes)
+    }
+
+    async fn link_names(&self) -> Result<HashMap<u32, String>> {
+        use rtnetlink::packet_route::link::LinkAttribute;
+
+        let mut names = HashMap::new();
+        let mut links = self.handle.link().get().execute();
+        while let Some(link) = links.try_next().await? {
+            for attr in &link.attributes {
+                if let LinkAttribute::IfName(name) = attr {
+                    names.insert(link.header.index, name.clone());
+                }
+            }
+        }
+        Ok(names)
+    }
+
+    async fn get_link_by_name(&self, name: &str) -> Result<u32> {
+        let mut links = self
+            .handle
+            .link()
+            .get()
+            .match_name(name.to_string())
+            .execute();
+
+        if let Some(link) = links.try_next().await? {
+            Ok(link.header.index)
+        } else {
+            anyhow::bail!("Link {} not found", name)
+        }
+    }
+}
+
+fn parse_cidr(cidr: &str) -> Result<(IpAddr, u8)> {
+    let (addr, prefix) = cidr
+        .split_once('/')
+        .ok_or_else(|| anyhow::anyhow!("Invalid CIDR format: {}", cidr))?;
+    let addr: IpAddr = addr
+        .parse()
+        .context(format!("Invalid IP address: {}", addr))?;
+    let prefix_len: u8 = prefix
+        .parse()
+        .context(format!("Invalid prefix length: {}", prefix))?;
+    Ok((addr, prefix_len))
+}
+
+fn route_message(
+    dst: IpAddr,
+    prefix_len: u8,
+    gateway: IpAddr,
+    dev_index: u32,
+) -> Result<RouteMessage> {
+    Ok(RouteMessageBuilder::<IpAddr>::new()
+        .destination_prefix(dst, prefix_len)?
+        .gateway(gateway)?
+        .output_interface(dev_index)
+        .build())
+}
+
+fn route_address(address: &RouteAddress) -> Option<IpAddr> {
+    match address {
+        RouteAddress::Inet(addr) => Some(IpAddr::V4(*addr)),
+        RouteAddress::Inet6(addr) => Some(IpAddr::V6(*addr)),
+        _ => None,
+    }
+}
+
+fn route_gateway(route: &RouteMessage) -> Option<IpAddr> {
+    route.attributes.iter().find_map(|attr| match attr {
+        RouteAttribute::Gateway(addr) => route_address(addr),
+        _ => None,
+    })
+}
+
+fn route_oif(route: &RouteMessage) -> Option<u32> {
+    route.attributes.iter().find_map(|attr| match attr {
+        RouteAttribute::Oif(index) => Some(*index),
+        _ => None,
+    })
+}
+
+/// Whether `route` is `dst/prefix_len via gateway` out of `dev_index`
+fn route_matches(
+    route: &RouteMessage,
+    dst: IpAddr,
+    prefix_len: u8,
+    gateway: IpAddr,
+    dev_index: u32,
+) -> bool {
+    let destination = route.attributes.iter().find_map(|attr| match attr {
+        RouteAttribute::Destination(addr) => route_address(addr),
+        _ => None,
+    });
+
+    route.header.destination_prefix_length == prefix_len
+        && destination == Some(dst)
+        && route_gateway(route) == Some(gateway)
+        && route_oif(route) == Some(dev_index)
+}
+
+fn route_info(route: &RouteMessage, names: &HashMap<u32, String>) -> RouteInfo {
+    let destination = route
+        .attributes
+        .iter()
+        .find_map(|attr| match attr {
+            RouteAttribute::Destination(addr) => route_address(addr),
+            _ => None,
+        })
+        .map(|addr| format!("{}/{}", addr, route.header.destination_prefix_length))
+        .unwrap_or_else(|| "default".to_string());
+
+    RouteInfo {
+        destination,
+        gateway: route_gateway(route),
+        dev: route_oif(route).and_then(|index| names.get(&index).cloned()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_destination_gateway_and_device() {
+        let dst: IpAddr = "192.168.50.0".parse().unwrap();
+        let gateway: IpAddr = "10.40.0.254".parse().unwrap();
+        let route = route_message(dst, 24, gateway, 7).unwrap();
+
+        assert!(route_matches(&route, dst, 24, gateway, 7));
+        assert!(!route_matches(&route, dst, 16, gateway, 7));
+        assert!(!route_matches(
+            &route,
+            dst,
+            24,
+            "10.40.0.253".parse().unwrap(),
+            7
+        ));
+        assert!(!route_matches(&route, dst, 24, gateway, 8));
+
+        let names = HashMap::from([(7, "br-lab".to_string())]);
+        assert_eq!(
+            route_info(&route, &names),
+            RouteInfo {
+                destination: "192.168.50.0/24".into(),
+                gateway: Some(gateway),
+                dev: Some("br-lab".into()),
+            }
+        );
+    }
+
+    #[test]
+    fn rejects_mixed_address_families() {
+        let err = route_message(
+            "fd00:50::".parse().unwrap(),
+            64,
+            "10.40.0.254".parse().unwrap(),
+            7,
+        );
+        assert!(err.is_err());
+    }
+}
@@ -1,6 +1,7 @@
 use anyhow::Result;
-use gw_core::BridgeStatus;
+use gw_core::{BridgeStatus, LinkStats};
 use rtnetlink::Handle;
+use rtnetlink::packet_route::link::{LinkAttribute, LinkMessage};
 
 pub struct StatusCollector {
     handle: Handle,
@@ -16,55 +17,81 @@ impl StatusCollector {
 
     pub async fn collect_bridge_status(&self) -> Result<Vec<BridgeStatus>> {
         use futures::stream::TryStreamExt;
-        use rtnetlink::packet_route::link::{LinkAttribute, LinkFlags};
+        use rtnetlink::packet_route::link::LinkFlags;
 
-        let mut bridges = vec![];
-        let mut links = self.handle.link().get().execute();
-
-        while let Some(link) = links.try_next().await? {
-            let name = link.attributes.iter().find_map(|attr| {
-                if let LinkAttribute::IfName(n) = attr {
-                    Some(n.clone())
-                } else {
-                    None
+        struct LinkEntry {
+            index: u32,
+            name: String,
+            up: bool,
+            is_bridge: bool,
+        }
+
+        // One enumeration serves both the bridge list and their members
+        let links: Vec<_> = self.handle.link().get().execute().try_collect().await?;
+
+        let mut entries = vec![];
+        for link in &links {
+            let mut name = None;
+            let mut has_link_info = false;
+            for attr in &link.attributes {
+                match attr {
+                    LinkAttribute::IfName(n) => name = Some(n.clone()),
+                    LinkAttribute::LinkInfo(_) => has_link_info = true,
+                    _ => {}
                 }
-            });
+            }
 
             if let Some(name) = name {
-                // Check if it's a bridge
-                let is_bridge = link
-                    .attributes
-                    .iter()
-                    .any(|attr| matches!(attr, LinkAttribute::LinkInfo(_)));
-
-                if is_bridge || name.starts_with("br-") {
-                    // Check if link is up by looking at flags
-                    let state = if link.header.flags.contains(LinkFlags::Up) {
-                        "UP"
-                    } else {
-                        "DOWN"
-                    }
-                    .to_string();
-
-                    // Get addresses for this bridge
-                    let addresses = self.get_addresses_for_link(link.header.index).await?;
-
-                    bridges.push(BridgeStatus {
-                        name,
-                        state,
-                        addresses,
-                        members: vec![], // TODO: get bridge members
-                    });
-                }
+                entries.push(LinkEntry {
+                    index: link.header.index,
+                    is_bridge: has_link_info || name.starts_with("br-"),
+                    up: link.header.flags.contains(LinkFlags::Up),
+                    name,
+                });
             }
         }
 
+        let mut bridges = vec![];
+        for entry in entries.iter().filter(|entry| entry.is_bridge) {
+            let state = if entry.up { "UP" } else { "DOWN" }.to_string();
+
+            // Get addresses for this bridge
+            let addresses = self.get_addresses_for_link(entry.index).await?;
+
+            // Ports enslaved to this bridge (VM taps, uplinks, VXLAN devices)
+            let members = crate::bridge::bridge_members(&links, entry.index);
+
+            bridges.push(BridgeStatus {
+                name: entry.name.clone(),
+                state,
+                addresses,
+                members,
+            });
+        }
+
         Ok(bridges)
     }
 
+    /// RX/TX counters of `iface`
+    pub async fn get_link_stats(&self, iface: &str) -> Result<LinkStats> {
+        use futures::stream::TryStreamExt;
+
+        let mut links = self
+            .handle
+            .link()
+            .get()
+            .match_name(iface.to_string())
+            .execute();
+
+        if let Some(link) = links.try_next().await? {
+            Ok(link_stats(&link))
+        } else {
+            anyhow::bail!("Link {} not found", iface)
+        }
+    }
+
     async fn get_addresses_for_link(&self, link_index: u32) -> Result<Vec<String>> {
         use futures::stream::TryStreamExt;
-        use rtnetlink::packet_route::address::AddressAttribute;
 
         let mut addresses = vec![];
         let mut addrs = self
@@ -75,14 +102,78 @@ impl StatusCollector {
             .execute();
 
         while let Some(addr) = addrs.try_next().await? {
-            for attr in &addr.attributes {
-                if let AddressAttribute::Address(ip) = attr {
-                    let addr_str = format!("{}/{}", (*ip), addr.header.prefix_len);
-                    addresses.push(addr_str);
-                }
+            if let Some(ip) = crate::addr::message_address(&addr) {
+                addresses.push(format!("{}/{}", ip, addr.header.prefix_len));
             }
         }
 
         Ok(addresses)
     }
 }
+
+/// Counters from `IFLA_STATS64`, falling back to the 32-bit `IFLA_STATS`
+fn link_stats(link: &LinkMessage) -> LinkStats {
+    let stats64 = link.attributes.iter().find_map(|attr| match attr {
+        LinkAttribute::Stats64(stats) => Some(LinkStats {
+            rx_bytes: stats.rx_bytes,
+            tx_bytes: stats.tx_bytes,
+            rx_packets: stats.rx_packets,
+            tx_packets: stats.tx_packets,
+            rx_errors: stats.rx_errors,
+            tx_errors: stats.tx_errors,
+        }),
+        _ => None,
+    });
+    let stats32 = || {
+        link.attributes.iter().find_map(|attr| match attr {
+            LinkAttribute::Stats(stats) => Some(LinkStats {
+                rx_bytes: stats.rx_bytes.into(),
+                tx_bytes: stats.tx_bytes.into(),
+                rx_packets: stats.rx_packets.into(),
+                tx_packets: stats.tx_packets.into(),
+                rx_errors: stats.rx_errors.into(),
+                tx_errors: stats.tx_errors.into(),
+            }),
+            _ => None,
+        })
+    };
+    stats64.or_else(stats32).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rtnetlink::packet_route::link::{Stats, Stats64};
+
+    #[test]
+    fn prefers_64_bit_counters_and_defaults_to_zero() {
+        let mut link = LinkMessage::default();
+        assert_eq!(link_stats(&link), LinkStats::default());
+
+        let mut stats = Stats::default();
+        stats.rx_bytes = 100;
+        stats.tx_errors = 2;
+        link.attributes.push(LinkAttribute::Stats(stats));
+        assert_eq!(
+            link_stats(&link),
+            LinkStats {
+                rx_bytes: 100,
+                tx_errors: 2,
+                ..Default::default()
+            }
+        );
+
+        let mut stats64 = Stats64::default();
+        stats64.rx_bytes = 5_000_000_000;
+        stats64.tx_packets = 7;
+        link.attributes.push(LinkAttribute::Stats64(stats64));
+        assert_eq!(
+            link_stats(&link),
+            LinkStats {
+                rx_bytes: 5_000_000_000,
+                tx_packets: 7,
+                ..Default::default()
+            }
+        );
+    }
+}
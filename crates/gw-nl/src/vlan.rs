@@ -7,6 +7,7 @@ pub struct VlanManager {
 
 impl VlanManager {
     pub async fn new() -> Result<Self> {
+        crate::ensure_net_admin()?;
         let (connection, handle, _) = new_connection()?;
         tokio::spawn(connection);
         Ok(Self { handle })
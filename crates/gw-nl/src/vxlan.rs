@@ -0,0 +1,163 @@
+use anyhow::{Context, Result};
+use futures::stream::StreamExt;
+use rtnetlink::packet_core::{
+    NLM_F_ACK, NLM_F_APPEND, NLM_F_CREATE, NLM_F_REQUEST, NetlinkMessage, NetlinkPayload,
+};
+use rtnetlink::packet_route::RouteNetlinkMessage;
+use rtnetlink::packet_route::neighbour::NeighbourFlags;
+use rtnetlink::{Handle, LinkUnspec, LinkVxlan, new_connection};
+use std::net::IpAddr;
+
+/// Settings for a VXLAN tunnel endpoint
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VxlanConfig {
+    pub vni: u32,
+    pub local: Option<IpAddr>,
+    pub group: Option<IpAddr>,
+    pub peers: Vec<IpAddr>,
+    pub dstport: u16,
+}
+
+pub struct VxlanManager {
+    handle: Handle,
+}
+
+impl VxlanManager {
+    pub async fn new() -> Result<Self> {
+        crate::ensure_net_admin()?;
+        let (connection, handle, _) = new_connection()?;
+        tokio::spawn(connection);
+        Ok(Self { handle })
+    }
+
+    /// Create a VXLAN interface and bring it up
+    /// Example: create_vxlan("vxlan1200", &config) with config.vni = 1200
+    pub async fn create_vxlan(&self, vxlan_name: &str, config: &VxlanConfig) -> Result<()> {
+        println!("Creating VXLAN {} (VNI: {})", vxlan_name, config.vni);
+
+        let mut link = LinkVxlan::new(vxlan_name, config.vni).port(config.dstport);
+        match config.local {
+            Some(IpAddr::V4(addr)) => link = link.local(addr),
+            Some(IpAddr::V6(addr)) => link = link.local6(addr),
+            None => {}
+        }
+        match config.group {
+            Some(IpAddr::V4(addr)) => link = link.group(addr),
+            Some(IpAddr::V6(addr)) => link = link.group6(addr),
+            None => {}
+        }
+
+        self.handle
+            .link()
+            .add(link.build())
+            .execute()
+            .await
+            .context(format!("Failed to create VXLAN {}", vxlan_name))?;
+
+        println!("Created VXLAN interface: {}", vxlan_name);
+
+        let vxlan_index = self.get_link_by_name(vxlan_name).await?;
+        for peer in &config.peers {
+            self.add_peer(vxlan_index, *peer).await.context(format!(
+                "Failed to add VXLAN peer {} to {}",
+                peer, vxlan_name
+            ))?;
+            println!("Added VXLAN peer {} to {}", peer, vxlan_name);
+        }
+
+        // Set link up
+        self.handle
+            .link()
+            .set(LinkUnspec::new_with_index(vxlan_index).up().build())
+            .execute()
+            .await
+            .context(format!("Failed to bring up VXLAN {}", vxlan_name))?;
+
+        println!("Set VXLAN {} up", vxlan_name);
+        Ok(())
+    }
+
+    /// Delete a VXLAN interface
+    pub async fn delete_vxlan(&self, vxlan_name: &str) -> Result<()> {
+        let vxlan_index = self.get_link_by_name(vxlan_name).await?;
+
+        self.handle
+            .link()
+            .del(vxlan_index)
+            .execute()
+            .await
+            .context(format!("Failed to delete VXLAN {}", vxlan_name))?;
+
+        println!("Deleted VXLAN interface: {}", vxlan_name);
+        Ok(())
+    }
+
+    /// Attach VXLAN to a bridge
+    pub async fn attach_vxlan_to_bridge(&self, vxlan_name: &str, bridge_name: &str) -> Result<()> {
+        let vxlan_index = self.get_link_by_name(vxlan_name).await?;
+        let bridge_index = self.get_link_by_name(bridge_name).await?;
+
+        self.handle
+            .link()
+            .set(
+                LinkUnspec::new_with_index(vxlan_index)
+                    .controller(bridge_index)
+                    .build(),
+            )
+            .execute()
+            .await
+            .context(format!(
+                "Failed to attach VXLAN {} to bridge {}",
+                vxlan_name, bridge_name
+            ))?;
+
+        println!("Attached VXLAN {} to bridge {}", vxlan_name, bridge_name);
+        Ok(())
+    }
+
+    /// Flood unknown-destination traffic to `peer`, like
+    /// `bridge fdb append 00:00:00:00:00:00 dev <vxlan> dst <peer>`.
+    ///
+    /// `Own` is NTF_SELF: the entry goes in the VXLAN device's own table rather
+    /// than a bridge's. rtnetlink only sends neighbour adds with NLM_F_EXCL,
+    /// which rejects a second all-zero entry, so the request is sent by hand
+    /// with NLM_F_APPEND.
+    async fn add_peer(&self, vxlan_index: u32, peer: IpAddr) -> Result<()> {
+        let mut request = self
+            .handle
+            .neighbours()
+            .add_bridge(vxlan_index, &[0; 6])
+            .flags(NeighbourFlags::Own)
+            .destination(peer);
+        let message = std::mem::take(request.message_mut());
+
+        let mut req = NetlinkMessage::from(RouteNetlinkMessage::NewNeighbour(message));
+        req.header.flags = NLM_F_REQUEST | NLM_F_ACK | NLM_F_CREATE | NLM_F_APPEND;
+
+        let mut handle = self.handle.clone();
+        let mut response = handle.request(req)?;
+        while let Some(message) = response.next().await {
+            if let NetlinkPayload::Error(err) = message.payload {
+                return Err(rtnetlink::Error::NetlinkError(err).into());
+            }
+        }
+        Ok(())
+    }
+
+    async fn get_link_by_name(&self, name: &str) -> Result<u32> {
+        use futures::stream::TryStreamExt;
+
+        let mut links = self
+            .handle
+            .link()
+            .get()
+            .match_name(name.to_string())
+            .execute();
+
+        if let Some(link) = links.try_next().await? {
+            Ok(link.header.index)
+        } else {
+            anyhow::bail!("Link {} not found", name)
+        }
+    }
+}
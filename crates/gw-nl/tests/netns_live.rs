@@ -1,4 +1,4 @@
-//! Live netlink integration tests (bridges, addresses, VLANs).
+//! Live netlink integration tests (bridges, addresses, routes, VLANs, VXLAN, veth, link stats).
 //!
 //! These mutate real host networking, so they require root (`CAP_NET_ADMIN`).
 //! They are `#[ignore]` by default; run them inside a throwaway network
@@ -12,8 +12,12 @@
 //! of real interfaces.
 
 use std::process::Command;
+use std::time::Duration;
 
-use gw_nl::{AddressManager, BridgeManager, VlanManager};
+use gw_nl::{
+    AddressManager, BridgeManager, RouteManager, StatusCollector, VlanManager, VxlanConfig,
+    VxlanManager,
+};
 
 /// Skip (rather than fail) when not run as root, so `--ignored` runs are still
 /// meaningful on unprivileged machines.
@@ -32,6 +36,15 @@ fn require_root() -> bool {
     is_root
 }
 
+fn create_dummy(name: &str) {
+    let _ = Command::new("ip").args(["link", "del", name]).status();
+    let status = Command::new("ip")
+        .args(["link", "add", name, "type", "dummy"])
+        .status()
+        .expect("run ip");
+    assert!(status.success(), "create dummy {}", name);
+}
+
 fn link_exists(name: &str) -> bool {
     Command::new("ip")
         .args(["link", "show", name])
@@ -112,6 +125,149 @@ async fn address_add_and_remove() {
         .expect("cleanup bridge");
 }
 
+#[tokio::test]
+#[ignore = "requires root + network namespace"]
+async fn ipv6_address_add_and_remove() {
+    if !require_root() {
+        return;
+    }
+    let bridge_mgr = BridgeManager::new().await.expect("bridge manager");
+    let addr_mgr = AddressManager::new().await.expect("address manager");
+    let iface = "gwt-br7";
+
+    let _ = bridge_mgr.delete_bridge(iface).await;
+    bridge_mgr
+        .create_bridge(iface)
+        .await
+        .expect("create bridge");
+
+    for cidr in ["fd00:123::1/64", "fe80::123/64"] {
+        addr_mgr
+            .add_address(iface, cidr)
+            .await
+            .expect("add address");
+        assert!(iface_has_addr(iface, cidr), "{} present", cidr);
+
+        let (addr, prefix) = cidr.split_once('/').unwrap();
+        let listed = addr_mgr.list_addresses(iface).await.expect("list");
+        assert!(listed.contains(&(addr.parse().unwrap(), prefix.parse().unwrap())));
+        addr_mgr
+            .add_address(iface, cidr)
+            .await
+            .expect("re-adding a present address is a no-op");
+
+        addr_mgr
+            .delete_address(iface, cidr)
+            .await
+            .expect("remove address");
+        assert!(!iface_has_addr(iface, cidr), "{} removed", cidr);
+    }
+
+    bridge_mgr
+        .delete_bridge(iface)
+        .await
+        .expect("cleanup bridge");
+}
+
+#[tokio::test]
+#[ignore = "requires root + network namespace"]
+async fn static_route_add_is_idempotent() {
+    if !require_root() {
+        return;
+    }
+    let bridge_mgr = BridgeManager::new().await.expect("bridge manager");
+    let addr_mgr = AddressManager::new().await.expect("address manager");
+    let route_mgr = RouteManager::new().await.expect("route manager");
+    let iface = "gwt-br6";
+    let dst = "192.0.2.0/24";
+    let via = "10.124.0.254".parse().unwrap();
+
+    let _ = bridge_mgr.delete_bridge(iface).await;
+    bridge_mgr
+        .create_bridge(iface)
+        .await
+        .expect("create bridge");
+    addr_mgr
+        .add_address(iface, "10.124.0.1/24")
+        .await
+        .expect("add address");
+
+    route_mgr
+        .add_route(dst, via, iface)
+        .await
+        .expect("add route");
+    route_mgr
+        .add_route(dst, via, iface)
+        .await
+        .expect("re-adding an existing route is a no-op");
+
+    let routes = route_mgr.list_routes().await.expect("list routes");
+    let matching: Vec<_> = routes
+        .iter()
+        .filter(|route| route.destination == dst)
+        .collect();
+    assert_eq!(matching.len(), 1, "{:?}", routes);
+    assert_eq!(matching[0].gateway, Some(via));
+    assert_eq!(matching[0].dev.as_deref(), Some(iface));
+
+    route_mgr
+        .delete_route(dst, via, iface)
+        .await
+        .expect("delete route");
+    let routes = route_mgr.list_routes().await.expect("list routes");
+    assert!(!routes.iter().any(|route| route.destination == dst));
+
+    bridge_mgr
+        .delete_bridge(iface)
+        .await
+        .expect("cleanup bridge");
+}
+
+#[tokio::test]
+#[ignore = "requires root + network namespace"]
+async fn bridge_stp_toggle() {
+    if !require_root() {
+        return;
+    }
+    let bridge_mgr = BridgeManager::new().await.expect("bridge manager");
+    let bridge = "gwt-br5";
+    // sysfs still shows the host namespace under `unshare --net`; ask ip instead
+    let stp_state = || {
+        let output = Command::new("ip")
+            .args(["-d", "link", "show", bridge])
+            .output()
+            .ok()
+            .and_then(|o| String::from_utf8(o.stdout).ok())
+            .unwrap_or_default();
+        output
+            .split_whitespace()
+            .skip_while(|token| *token != "stp_state")
+            .nth(1)
+            .unwrap_or_default()
+            .to_string()
+    };
+
+    let _ = bridge_mgr.delete_bridge(bridge).await;
+    bridge_mgr
+        .create_bridge(bridge)
+        .await
+        .expect("create bridge");
+    assert_eq!(stp_state(), "0");
+
+    bridge_mgr.set_stp(bridge, true).await.expect("enable stp");
+    assert_eq!(stp_state(), "1");
+    bridge_mgr
+        .set_stp(bridge, false)
+        .await
+        .expect("disable stp");
+    assert_eq!(stp_state(), "0");
+
+    bridge_mgr
+        .delete_bridge(bridge)
+        .await
+        .expect("cleanup bridge");
+}
+
 #[tokio::test]
 #[ignore = "requires root + network namespace"]
 async fn vlan_create_and_delete() {
@@ -146,3 +302,347 @@ async fn vlan_create_and_delete() {
         .await
         .expect("cleanup parent");
 }
+
+#[tokio::test]
+#[ignore = "requires root + network namespace"]
+async fn attach_port_as_isolated() {
+    if !require_root() {
+        return;
+    }
+    let bridge_mgr = BridgeManager::new().await.expect("bridge manager");
+    let bridge = "gwt-br2";
+    let port = "gwt-port0";
+
+    let _ = bridge_mgr.delete_bridge(bridge).await;
+
+    bridge_mgr
+        .create_bridge(bridge)
+        .await
+        .expect("create bridge");
+    // A bridge cannot be enslaved to another bridge, so use a dummy port.
+    create_dummy(port);
+    bridge_mgr
+        .attach_interface_to_bridge(port, bridge, true, None)
+        .await
+        .expect("attach isolated");
+
+    let details = Command::new("ip")
+        .args(["-d", "link", "show", port])
+        .output()
+        .ok()
+        .and_then(|o| String::from_utf8(o.stdout).ok())
+        .unwrap_or_default();
+    assert!(details.contains("isolated on"), "port should be isolated");
+
+    let _ = Command::new("ip").args(["link", "del", port]).status();
+    bridge_mgr
+        .delete_bridge(bridge)
+        .await
+        .expect("cleanup bridge");
+}
+
+#[tokio::test]
+#[ignore = "requires root + network namespace"]
+async fn attach_port_with_vlans() {
+    if !require_root() {
+        return;
+    }
+    let bridge_mgr = BridgeManager::new().await.expect("bridge manager");
+    let bridge = "gwt-br3";
+    let port = "gwt-port1";
+
+    let _ = bridge_mgr.delete_bridge(port).await;
+    let _ = bridge_mgr.delete_bridge(bridge).await;
+
+    bridge_mgr
+        .create_bridge(bridge)
+        .await
+        .expect("create bridge");
+    bridge_mgr
+        .set_vlan_filtering(bridge, true)
+        .await
+        .expect("enable vlan filtering");
+    bridge_mgr
+        .set_vlan_default_pvid(bridge, 1)
+        .await
+        .expect("set default pvid");
+    let details = Command::new("ip")
+        .args(["-d", "link", "show", bridge])
+        .output()
+        .ok()
+        .and_then(|o| String::from_utf8(o.stdout).ok())
+        .unwrap_or_default();
+    assert!(details.contains("vlan_filtering 1"), "{}", details);
+    // A bridge cannot be enslaved to another bridge, so use a dummy port.
+    create_dummy(port);
+
+    let vlans = gw_core::PortVlans {
+        pvid: Some(10),
+        vlans: vec![10, 20],
+        untagged: vec![10],
+    };
+    bridge_mgr
+        .attach_interface_to_bridge(port, bridge, false, Some(&vlans))
+        .await
+        .expect("attach with vlans");
+
+    let output = Command::new("bridge")
+        .args(["vlan", "show", "dev", port])
+        .output()
+        .ok()
+        .and_then(|o| String::from_utf8(o.stdout).ok())
+        .unwrap_or_default();
+    assert!(output.contains("10 PVID Egress Untagged"), "{}", output);
+    assert!(output.contains("20"), "{}", output);
+    assert!(
+        !output
+            .lines()
+            .any(|line| line.split_whitespace().any(|token| token == "1")),
+        "default VLAN should be removed: {}",
+        output
+    );
+
+    bridge_mgr
+        .add_bridge_vlan(port, 30, false, false)
+        .await
+        .expect("add tagged vlan");
+    let output = Command::new("bridge")
+        .args(["vlan", "show", "dev", port])
+        .output()
+        .ok()
+        .and_then(|o| String::from_utf8(o.stdout).ok())
+        .unwrap_or_default();
+    assert!(
+        output.lines().any(|line| line
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .ends_with(&["30"])),
+        "{}",
+        output
+    );
+
+    let _ = bridge_mgr.delete_bridge(port).await;
+    bridge_mgr
+        .delete_bridge(bridge)
+        .await
+        .expect("cleanup bridge");
+}
+
+#[tokio::test]
+#[ignore = "requires root + network namespace"]
+async fn vxlan_create_attach_and_delete() {
+    if !require_root() {
+        return;
+    }
+    let bridge_mgr = BridgeManager::new().await.expect("bridge manager");
+    let vxlan_mgr = VxlanManager::new().await.expect("vxlan manager");
+    let bridge = "gwt-br4";
+    let vxlan = "gwt-vx4242";
+
+    let _ = vxlan_mgr.delete_vxlan(vxlan).await;
+    let _ = bridge_mgr.delete_bridge(bridge).await;
+
+    bridge_mgr
+        .create_bridge(bridge)
+        .await
+        .expect("create bridge");
+    let config = VxlanConfig {
+        vni: 4242,
+        local: None,
+        group: None,
+        peers: vec!["192.0.2.11".parse().unwrap(), "192.0.2.12".parse().unwrap()],
+        dstport: 4789,
+    };
+    vxlan_mgr
+        .create_vxlan(vxlan, &config)
+        .await
+        .expect("create vxlan");
+    vxlan_mgr
+        .attach_vxlan_to_bridge(vxlan, bridge)
+        .await
+        .expect("attach vxlan");
+
+    let fdb = Command::new("bridge")
+        .args(["fdb", "show", "dev", vxlan])
+        .output()
+        .ok()
+        .and_then(|o| String::from_utf8(o.stdout).ok())
+        .unwrap_or_default();
+    assert!(fdb.contains("dst 192.0.2.11"), "{}", fdb);
+    assert!(fdb.contains("dst 192.0.2.12"), "{}", fdb);
+    assert_eq!(
+        bridge_mgr
+            .get_bridge_members(bridge)
+            .await
+            .expect("members"),
+        vec![vxlan.to_string()]
+    );
+
+    vxlan_mgr.delete_vxlan(vxlan).await.expect("delete vxlan");
+    assert!(!link_exists(vxlan), "vxlan link should be gone");
+
+    bridge_mgr
+        .delete_bridge(bridge)
+        .await
+        .expect("cleanup bridge");
+}
+
+#[tokio::test]
+#[ignore = "requires root + network namespace"]
+async fn ensure_bridge_heals_down_state_and_mtu() {
+    if !require_root() {
+        return;
+    }
+    let bridge_mgr = BridgeManager::new().await.expect("bridge manager");
+    let bridge = "gwt-br9";
+
+    let _ = bridge_mgr.delete_bridge(bridge).await;
+    bridge_mgr
+        .ensure_bridge(bridge, Some(1400))
+        .await
+        .expect("create bridge");
+    let info = bridge_mgr.get_bridge_info(bridge).await.expect("info");
+    assert!(info.is_up);
+    assert_eq!(info.mtu, 1400);
+
+    // Drift: someone took the bridge down and changed its MTU
+    let _ = Command::new("ip")
+        .args(["link", "set", bridge, "down", "mtu", "1300"])
+        .status();
+    bridge_mgr
+        .ensure_bridge(bridge, Some(1400))
+        .await
+        .expect("converge bridge");
+    let info = bridge_mgr.get_bridge_info(bridge).await.expect("info");
+    assert!(info.is_up);
+    assert_eq!(info.mtu, 1400);
+
+    bridge_mgr
+        .delete_bridge(bridge)
+        .await
+        .expect("cleanup bridge");
+}
+
+#[tokio::test]
+#[ignore = "requires root + network namespace"]
+async fn wait_until_up_waits_for_carrier() {
+    if !require_root() {
+        return;
+    }
+    let bridge_mgr = BridgeManager::new().await.expect("bridge manager");
+    let bridge = "gwt-br10";
+
+    let _ = bridge_mgr.delete_veth("gwt-veth2").await;
+    let _ = bridge_mgr.delete_bridge(bridge).await;
+    bridge_mgr
+        .create_bridge(bridge)
+        .await
+        .expect("create bridge");
+
+    // A veth end has no carrier while its peer is down
+    bridge_mgr
+        .create_veth_pair("gwt-veth2", "gwt-veth3")
+        .await
+        .expect("create veth pair");
+    let _ = Command::new("ip")
+        .args(["link", "set", "gwt-veth2", "up"])
+        .status();
+    let err = bridge_mgr
+        .wait_until_up("gwt-veth2", Duration::from_millis(300))
+        .await
+        .expect_err("veth without its peer up has no carrier");
+    assert!(err.to_string().contains("last flags"), "{}", err);
+    bridge_mgr
+        .delete_veth("gwt-veth2")
+        .await
+        .expect("delete veth");
+
+    bridge_mgr
+        .create_veth_on_bridge("gwt-veth2", "gwt-veth3", bridge)
+        .await
+        .expect("create veth on bridge");
+    bridge_mgr
+        .wait_until_up(bridge, Duration::from_secs(5))
+        .await
+        .expect("bridge comes up");
+
+    bridge_mgr
+        .delete_veth("gwt-veth2")
+        .await
+        .expect("delete veth");
+    bridge_mgr
+        .delete_bridge(bridge)
+        .await
+        .expect("cleanup bridge");
+}
+
+#[tokio::test]
+#[ignore = "requires root + network namespace"]
+async fn veth_pair_on_bridge_and_teardown() {
+    if !require_root() {
+        return;
+    }
+    let bridge_mgr = BridgeManager::new().await.expect("bridge manager");
+    let bridge = "gwt-br8";
+
+    let _ = bridge_mgr.delete_veth("gwt-veth0").await;
+    let _ = bridge_mgr.delete_bridge(bridge).await;
+    bridge_mgr
+        .create_bridge(bridge)
+        .await
+        .expect("create bridge");
+
+    bridge_mgr
+        .create_veth_on_bridge("gwt-veth0", "gwt-veth1", bridge)
+        .await
+        .expect("create veth on bridge");
+    assert!(link_exists("gwt-veth1"));
+    assert_eq!(
+        bridge_mgr
+            .get_bridge_members(bridge)
+            .await
+            .expect("members"),
+        vec!["gwt-veth0".to_string()]
+    );
+
+    // Deleting either end takes the pair down with it
+    bridge_mgr
+        .delete_veth("gwt-veth1")
+        .await
+        .expect("delete veth");
+    assert!(!link_exists("gwt-veth0"));
+    assert!(!link_exists("gwt-veth1"));
+
+    bridge_mgr
+        .delete_bridge(bridge)
+        .await
+        .expect("cleanup bridge");
+}
+
+#[tokio::test]
+#[ignore = "requires root + network namespace"]
+async fn loopback_link_stats_count_traffic() {
+    if !require_root() {
+        return;
+    }
+    let collector = StatusCollector::new().await.expect("status collector");
+
+    let _ = Command::new("ip")
+        .args(["link", "set", "lo", "up"])
+        .status();
+    let before = collector.get_link_stats("lo").await.expect("lo stats");
+    let socket = std::net::UdpSocket::bind("127.0.0.1:0").expect("bind");
+    socket
+        .send_to(b"gwt", socket.local_addr().unwrap())
+        .expect("send");
+    let after = collector.get_link_stats("lo").await.expect("lo stats");
+    assert!(
+        after.rx_packets > before.rx_packets,
+        "{:?} -> {:?}",
+        before,
+        after
+    );
+    assert!(after.tx_bytes > before.tx_bytes);
+
+    assert!(collector.get_link_stats("gwt-missing").await.is_err());
+}
@@ -1,32 +1,79 @@
+use crate::command::{or_timed_out, run_timed};
 use crate::diagnostics::{DiagnosticLevel, DiagnosticResult};
+use gw_core::{CommandRunner, SystemCommandRunner};
 use regex::Regex;
-use std::process::Command;
+use std::collections::BTreeSet;
+use std::process::Output;
+use std::sync::Arc;
 
 /// Bridge networking diagnostics
-pub struct BridgeDiagnostics;
+pub struct BridgeDiagnostics {
+    /// Bridges to inspect; `None` inspects every bridge on the host.
+    scope: Option<BTreeSet<String>>,
+    runner: Arc<dyn CommandRunner>,
+}
 
 impl BridgeDiagnostics {
     pub fn new() -> Self {
-        Self
+        Self {
+            scope: None,
+            runner: Arc::new(SystemCommandRunner),
+        }
+    }
+
+    /// Only inspect these bridges and the veths enslaved to them.
+    pub fn scoped(bridges: impl IntoIterator<Item = String>) -> Self {
+        Self {
+            scope: Some(bridges.into_iter().collect()),
+            ..Self::new()
+        }
+    }
+
+    /// Run commands through `runner` instead of spawning them directly.
+    pub fn with_runner(mut self, runner: Arc<dyn CommandRunner>) -> Self {
+        self.runner = runner;
+        self
+    }
+
+    async fn run(&self, program: &str, args: &[&str]) -> anyhow::Result<Output> {
+        run_timed(self.runner.as_ref(), program, args).await
+    }
+
+    fn in_scope(&self, bridge: &str) -> bool {
+        self.scope
+            .as_ref()
+            .is_none_or(|scope| scope.contains(bridge))
     }
 
     pub async fn diagnose(&self) -> anyhow::Result<Vec<DiagnosticResult>> {
         let mut results = Vec::new();
 
         // Check for bridge-utils availability
-        results.extend(self.check_bridge_tools().await?);
+        results.extend(or_timed_out(
+            "Bridge tools",
+            self.check_bridge_tools().await,
+        )?);
 
         // List all bridges
-        results.extend(self.check_bridges().await?);
+        results.extend(or_timed_out("Bridge listing", self.check_bridges().await)?);
 
         // Check GhostWarden bridges specifically
-        results.extend(self.check_ghostwarden_bridges().await?);
+        results.extend(or_timed_out(
+            "GhostWarden bridges",
+            self.check_ghostwarden_bridges().await,
+        )?);
 
         // Check bridge forwarding and STP
-        results.extend(self.check_bridge_configuration().await?);
+        results.extend(or_timed_out(
+            "Bridge netfilter",
+            self.check_bridge_configuration().await,
+        )?);
 
         // Check for common misconfigurations
-        results.extend(self.check_bridge_issues().await?);
+        results.extend(or_timed_out(
+            "Bridge issues",
+            self.check_bridge_issues().await,
+        )?);
 
         Ok(results)
     }
@@ -35,9 +82,9 @@ impl BridgeDiagnostics {
         let mut results = Vec::new();
 
         // Check if 'ip' command is available (iproute2)
-        let ip_available = Command::new("ip")
-            .arg("--version")
-            .output()
+        let ip_available = self
+            .run("ip", &["--version"])
+            .await
             .map(|o| o.status.success())
             .unwrap_or(false);
 
@@ -60,9 +107,9 @@ impl BridgeDiagnostics {
         }
 
         // Check if 'brctl' is available (optional, for compatibility)
-        let brctl_available = Command::new("brctl")
-            .arg("--version")
-            .output()
+        let brctl_available = self
+            .run("brctl", &["--version"])
+            .await
             .map(|o| o.status.success())
             .unwrap_or(false);
 
@@ -81,12 +128,7 @@ impl BridgeDiagnostics {
         let mut results = Vec::new();
 
         // List all bridge interfaces
-        let output = Command::new("ip")
-            .arg("link")
-            .arg("show")
-            .arg("type")
-            .arg("bridge")
-            .output()?;
+        let output = self.run("ip", &["link", "show", "type", "bridge"]).await?;
 
         if !output.status.success() {
             results.push(
@@ -107,7 +149,9 @@ impl BridgeDiagnostics {
         let mut bridge_names = Vec::new();
 
         for line in bridges_output.lines() {
-            if let Some(cap) = bridge_regex.captures(line) {
+            if let Some(cap) = bridge_regex.captures(line)
+                && self.in_scope(&cap[1])
+            {
                 bridge_names.push(cap[1].to_string());
             }
         }
@@ -145,11 +189,7 @@ impl BridgeDiagnostics {
         let mut results = Vec::new();
 
         // Get bridge details
-        let output = Command::new("ip")
-            .arg("link")
-            .arg("show")
-            .arg(bridge)
-            .output()?;
+        let output = self.run("ip", &["link", "show", bridge]).await?;
 
         if output.status.success() {
             let details = String::from_utf8_lossy(&output.stdout);
@@ -188,11 +228,7 @@ impl BridgeDiagnostics {
         }
 
         // Get IP addresses
-        let output = Command::new("ip")
-            .arg("addr")
-            .arg("show")
-            .arg(bridge)
-            .output()?;
+        let output = self.run("ip", &["addr", "show", bridge]).await?;
 
         if output.status.success() {
             let addr_output = String::from_utf8_lossy(&output.stdout);
@@ -226,12 +262,7 @@ impl BridgeDiagnostics {
         }
 
         // Get bridge ports/slaves
-        let output = Command::new("ip")
-            .arg("link")
-            .arg("show")
-            .arg("master")
-            .arg(bridge)
-            .output()?;
+        let output = self.run("ip", &["link", "show", "master", bridge]).await?;
 
         if output.status.success() {
             let ports_output = String::from_utf8_lossy(&output.stdout);
@@ -266,12 +297,7 @@ impl BridgeDiagnostics {
         let mut results = Vec::new();
 
         // Look for br-* bridges (GhostWarden naming convention)
-        let output = Command::new("ip")
-            .arg("link")
-            .arg("show")
-            .arg("type")
-            .arg("bridge")
-            .output()?;
+        let output = self.run("ip", &["link", "show", "type", "bridge"]).await?;
 
         if output.status.success() {
             let bridges = String::from_utf8_lossy(&output.stdout);
@@ -284,8 +310,34 @@ impl BridgeDiagnostics {
                         None
                     }
                 })
+                .filter(|name| self.in_scope(name))
                 .collect();
 
+            if let Some(scope) = &self.scope {
+                let missing: Vec<&str> = scope
+                    .iter()
+                    .map(String::as_str)
+                    .filter(|bridge| {
+                        !bridges.lines().any(|line| {
+                            line.split(':')
+                                .nth(1)
+                                .and_then(|name| name.trim().split('@').next())
+                                == Some(*bridge)
+                        })
+                    })
+                    .collect();
+                if !missing.is_empty() {
+                    results.push(
+                        DiagnosticResult::new(
+                            DiagnosticLevel::Warning,
+                            "Topology bridges missing",
+                            format!("Not present on this host: {}", missing.join(", ")),
+                        )
+                        .with_suggestion("Run 'gwarden net apply' to create network bridges"),
+                    );
+                }
+            }
+
             if gw_bridges.is_empty() {
                 results.push(
                     DiagnosticResult::new(
@@ -331,7 +383,7 @@ impl BridgeDiagnostics {
         ];
 
         for (setting, description) in br_nf_settings {
-            let output = Command::new("sysctl").arg(setting).output();
+            let output = self.run("sysctl", &[setting]).await;
 
             if let Ok(output) = output {
                 if output.status.success() {
@@ -379,12 +431,7 @@ impl BridgeDiagnostics {
         // Check for common bridge problems
 
         // 1. Check if there are bridges with duplicate subnets
-        let output = Command::new("ip")
-            .arg("addr")
-            .arg("show")
-            .arg("type")
-            .arg("bridge")
-            .output()?;
+        let output = self.run("ip", &["addr", "show", "type", "bridge"]).await?;
 
         if output.status.success() {
             let addrs = String::from_utf8_lossy(&output.stdout);
@@ -420,18 +467,13 @@ impl BridgeDiagnostics {
         }
 
         // 2. Check for bridges without proper routing
-        let output = Command::new("ip").arg("route").arg("show").output()?;
+        let output = self.run("ip", &["route", "show"]).await?;
 
         if output.status.success() {
             let routes = String::from_utf8_lossy(&output.stdout);
 
             // Get list of bridges
-            let bridge_output = Command::new("ip")
-                .arg("link")
-                .arg("show")
-                .arg("type")
-                .arg("bridge")
-                .output()?;
+            let bridge_output = self.run("ip", &["link", "show", "type", "bridge"]).await?;
 
             if bridge_output.status.success() {
                 let bridges = String::from_utf8_lossy(&bridge_output.stdout);
@@ -440,7 +482,7 @@ impl BridgeDiagnostics {
                 for cap in bridge_regex.captures_iter(&bridges) {
                     let bridge = &cap[1];
 
-                    if !routes.contains(bridge) {
+                    if self.in_scope(bridge) && !routes.contains(bridge) {
                         results.push(
                             DiagnosticResult::new(
                                 DiagnosticLevel::Warning,
@@ -458,17 +500,24 @@ impl BridgeDiagnostics {
         }
 
         // 3. Check for orphaned veth pairs
-        let output = Command::new("ip")
-            .arg("link")
-            .arg("show")
-            .arg("type")
-            .arg("veth")
-            .output()?;
+        let output = self.run("ip", &["link", "show", "type", "veth"]).await?;
 
         if output.status.success() {
             let veths = String::from_utf8_lossy(&output.stdout);
             let veth_regex = Regex::new(r"^\d+:\s+([^:@]+)").unwrap();
-            let veth_count = veth_regex.captures_iter(&veths).count();
+            let master_regex = Regex::new(r"\smaster\s+(\S+)").unwrap();
+            // Scoped runs only look at veths enslaved to an in-scope bridge.
+            let veth_lines: Vec<&str> = veths
+                .lines()
+                .filter(|line| veth_regex.is_match(line))
+                .filter(|line| {
+                    self.scope.is_none()
+                        || master_regex
+                            .captures(line)
+                            .is_some_and(|cap| self.in_scope(&cap[1]))
+                })
+                .collect();
+            let veth_count = veth_lines.len();
 
             if veth_count > 0 {
                 results.push(
@@ -482,8 +531,8 @@ impl BridgeDiagnostics {
             }
 
             // Check for DOWN veth interfaces (potential orphans)
-            let down_veths: Vec<&str> = veths
-                .lines()
+            let down_veths: Vec<&str> = veth_lines
+                .iter()
                 .filter(|line| line.contains("state DOWN"))
                 .filter_map(|line| {
                     veth_regex
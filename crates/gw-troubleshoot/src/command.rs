@@ -0,0 +1,75 @@
+use crate::diagnostics::{DiagnosticLevel, DiagnosticResult};
+use gw_core::CommandRunner;
+use std::process::Output;
+use std::time::Duration;
+
+/// How long a single diagnostic command may run before it is abandoned.
+pub const COMMAND_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A diagnostic command that did not finish within [`COMMAND_TIMEOUT`].
+#[derive(Debug, thiserror::Error)]
+#[error("`{program}` did not finish within {}s", timeout.as_secs())]
+pub struct CommandTimeout {
+    pub program: String,
+    pub timeout: Duration,
+}
+
+/// Run a diagnostic command through `runner`, abandoning it with
+/// [`CommandTimeout`] once [`COMMAND_TIMEOUT`] elapses.
+pub(crate) async fn run_timed(
+    runner: &dyn CommandRunner,
+    program: &str,
+    args: &[&str],
+) -> anyhow::Result<Output> {
+    match tokio::time::timeout(COMMAND_TIMEOUT, runner.run(program, args, None)).await {
+        Ok(output) => Ok(output?),
+        Err(_) => Err(CommandTimeout {
+            program: program.to_string(),
+            timeout: COMMAND_TIMEOUT,
+        }
+        .into()),
+    }
+}
+
+/// Turn a check that hit a [`CommandTimeout`] into a single "timed out"
+/// finding so the rest of its section still runs; other errors propagate.
+pub(crate) fn or_timed_out(
+    check: &str,
+    result: anyhow::Result<Vec<DiagnosticResult>>,
+) -> anyhow::Result<Vec<DiagnosticResult>> {
+    match result {
+        Err(err) if err.downcast_ref::<CommandTimeout>().is_some() => {
+            Ok(vec![timed_out_result(check, &err)])
+        }
+        other => other,
+    }
+}
+
+pub(crate) fn timed_out_result(check: &str, err: &anyhow::Error) -> DiagnosticResult {
+    DiagnosticResult::new(
+        DiagnosticLevel::Warning,
+        format!("{} check timed out", check),
+        err.to_string(),
+    )
+    .with_suggestion("The subsystem may be hung; re-run this section on its own once it responds")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timeouts_become_findings_and_other_errors_propagate() {
+        let timeout = CommandTimeout {
+            program: "docker".into(),
+            timeout: COMMAND_TIMEOUT,
+        };
+        let results = or_timed_out("Docker daemon", Err(timeout.into())).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].level, DiagnosticLevel::Warning);
+        assert_eq!(results[0].title, "Docker daemon check timed out");
+        assert_eq!(results[0].details, "`docker` did not finish within 10s");
+
+        assert!(or_timed_out("Docker daemon", Err(anyhow::anyhow!("boom"))).is_err());
+    }
+}
@@ -1,5 +1,6 @@
 use serde::{Deserialize, Serialize};
 use std::fmt;
+use std::time::Duration;
 
 /// Severity level for diagnostic findings
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
@@ -75,10 +76,54 @@ impl DiagnosticResult {
     }
 }
 
+/// A named group of results, with how long it took when measured
+#[derive(Debug)]
+struct Section {
+    name: String,
+    results: Vec<DiagnosticResult>,
+    elapsed: Option<Duration>,
+}
+
+/// Error and warning totals across a report; critical findings count as errors
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReportSummary {
+    pub errors: usize,
+    pub warnings: usize,
+}
+
+impl ReportSummary {
+    /// Process exit code for health checks: 2 on errors, 1 on warnings only,
+    /// 0 when clean. `strict` treats warnings as errors.
+    pub fn exit_code(&self, strict: bool) -> i32 {
+        if self.errors > 0 || (strict && self.warnings > 0) {
+            2
+        } else if self.warnings > 0 {
+            1
+        } else {
+            0
+        }
+    }
+}
+
+/// JSON shape of a report: the sections in run order, then the summary
+#[derive(Serialize)]
+struct JsonReport<'a> {
+    sections: Vec<JsonSection<'a>>,
+    summary: ReportSummary,
+}
+
+#[derive(Serialize)]
+struct JsonSection<'a> {
+    name: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    elapsed_secs: Option<f64>,
+    results: &'a [DiagnosticResult],
+}
+
 /// Complete diagnostic report
 #[derive(Debug, Default)]
 pub struct DiagnosticReport {
-    sections: Vec<(String, Vec<DiagnosticResult>)>,
+    sections: Vec<Section>,
 }
 
 impl DiagnosticReport {
@@ -87,19 +132,81 @@ impl DiagnosticReport {
     }
 
     pub fn add_section(&mut self, name: impl Into<String>, results: Vec<DiagnosticResult>) {
-        self.sections.push((name.into(), results));
+        self.sections.push(Section {
+            name: name.into(),
+            results,
+            elapsed: None,
+        });
     }
 
-    pub fn has_errors(&self) -> bool {
+    /// Add a section along with the time it took to run
+    pub fn add_timed_section(
+        &mut self,
+        name: impl Into<String>,
+        results: Vec<DiagnosticResult>,
+        elapsed: Duration,
+    ) {
+        self.sections.push(Section {
+            name: name.into(),
+            results,
+            elapsed: Some(elapsed),
+        });
+    }
+
+    /// Section names with their results, in the order they were added
+    pub fn sections(&self) -> impl Iterator<Item = (&str, &[DiagnosticResult])> {
         self.sections
             .iter()
-            .any(|(_, results)| results.iter().any(|r| r.level >= DiagnosticLevel::Error))
+            .map(|section| (section.name.as_str(), section.results.as_slice()))
+    }
+
+    /// Swap in fresh results for the section called `name`, e.g. after
+    /// re-running it to confirm a fix
+    pub fn replace_section_results(&mut self, name: &str, results: Vec<DiagnosticResult>) {
+        if let Some(section) = self.sections.iter_mut().find(|s| s.name == name) {
+            section.results = results;
+        }
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.results().any(|r| r.level >= DiagnosticLevel::Error)
     }
 
     pub fn has_warnings(&self) -> bool {
+        self.results().any(|r| r.level == DiagnosticLevel::Warning)
+    }
+
+    pub fn summary(&self) -> ReportSummary {
+        ReportSummary {
+            errors: self.count_by_level(DiagnosticLevel::Error)
+                + self.count_by_level(DiagnosticLevel::Critical),
+            warnings: self.count_by_level(DiagnosticLevel::Warning),
+        }
+    }
+
+    /// Serialize every section, including empty ones, and the summary as
+    /// pretty-printed JSON for CI pipelines and dashboards.
+    pub fn to_json(&self) -> anyhow::Result<String> {
+        let report = JsonReport {
+            sections: self
+                .sections
+                .iter()
+                .map(|section| JsonSection {
+                    name: &section.name,
+                    elapsed_secs: section.elapsed.map(|elapsed| elapsed.as_secs_f64()),
+                    results: &section.results,
+                })
+                .collect(),
+            summary: self.summary(),
+        };
+        Ok(serde_json::to_string_pretty(&report)?)
+    }
+
+    /// Every result, section by section
+    pub fn results(&self) -> impl Iterator<Item = &DiagnosticResult> {
         self.sections
             .iter()
-            .any(|(_, results)| results.iter().any(|r| r.level == DiagnosticLevel::Warning))
+            .flat_map(|section| section.results.iter())
     }
 
     pub fn display(&self) {
@@ -107,22 +214,28 @@ impl DiagnosticReport {
         println!("║            GhostWarden Troubleshooting Report                ║");
         println!("╚═══════════════════════════════════════════════════════════════╝");
 
-        for (section_name, results) in &self.sections {
-            if results.is_empty() {
+        for section in &self.sections {
+            if section.results.is_empty() {
                 continue;
             }
 
-            println!("\n━━━ {} ━━━", section_name);
+            match section.elapsed {
+                Some(elapsed) => {
+                    println!("\n━━━ {} ({:.1}s) ━━━", section.name, elapsed.as_secs_f64())
+                }
+                None => println!("\n━━━ {} ━━━", section.name),
+            }
 
-            for result in results {
+            for result in &section.results {
                 result.display();
             }
         }
 
         // Summary
-        let total_errors = self.count_by_level(DiagnosticLevel::Error)
-            + self.count_by_level(DiagnosticLevel::Critical);
-        let total_warnings = self.count_by_level(DiagnosticLevel::Warning);
+        let ReportSummary {
+            errors: total_errors,
+            warnings: total_warnings,
+        } = self.summary();
 
         println!("\n━━━ Summary ━━━");
         if total_errors > 0 {
@@ -137,10 +250,70 @@ impl DiagnosticReport {
     }
 
     fn count_by_level(&self, level: DiagnosticLevel) -> usize {
-        self.sections
-            .iter()
-            .flat_map(|(_, results)| results.iter())
-            .filter(|r| r.level == level)
-            .count()
+        self.results().filter(|r| r.level == level).count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_report_round_trips_sections_and_summary() {
+        let mut report = DiagnosticReport::new();
+        report.add_timed_section(
+            "nftables/iptables",
+            vec![
+                DiagnosticResult::new(DiagnosticLevel::Info, "nft present", "nft 1.0.9")
+                    .with_command("nft --version"),
+                DiagnosticResult::new(DiagnosticLevel::Critical, "No NAT", "masquerade missing")
+                    .with_suggestion("Run gwarden net apply"),
+            ],
+            Duration::from_millis(1500),
+        );
+        report.add_section(
+            "Bridge Configuration",
+            vec![
+                DiagnosticResult::new(DiagnosticLevel::Warning, "STP off", "br-lab"),
+                DiagnosticResult::new(DiagnosticLevel::Error, "Bridge down", "br-web"),
+            ],
+        );
+
+        let json: serde_json::Value = serde_json::from_str(&report.to_json().unwrap()).unwrap();
+
+        let summary: ReportSummary = serde_json::from_value(json["summary"].clone()).unwrap();
+        assert_eq!(
+            summary,
+            ReportSummary {
+                errors: 2,
+                warnings: 1
+            }
+        );
+
+        let sections = json["sections"].as_array().unwrap();
+        assert_eq!(sections[0]["name"], "nftables/iptables");
+        assert_eq!(sections[0]["elapsed_secs"], 1.5);
+        assert!(sections[1].get("elapsed_secs").is_none());
+
+        let results: Vec<DiagnosticResult> =
+            serde_json::from_value(sections[0]["results"].clone()).unwrap();
+        assert_eq!(results[1].level, DiagnosticLevel::Critical);
+        assert_eq!(
+            results[1].suggestion.as_deref(),
+            Some("Run gwarden net apply")
+        );
+        assert_eq!(results[0].command.as_deref(), Some("nft --version"));
+    }
+
+    #[test]
+    fn exit_code_reflects_the_worst_finding() {
+        let summary = |errors, warnings| ReportSummary { errors, warnings };
+
+        assert_eq!(summary(0, 0).exit_code(false), 0);
+        assert_eq!(summary(0, 3).exit_code(false), 1);
+        assert_eq!(summary(1, 3).exit_code(false), 2);
+
+        assert_eq!(summary(0, 0).exit_code(true), 0);
+        assert_eq!(summary(0, 3).exit_code(true), 2);
     }
 }
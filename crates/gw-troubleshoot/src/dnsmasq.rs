@@ -0,0 +1,464 @@
+use crate::command::{or_timed_out, run_timed};
+use crate::diagnostics::{DiagnosticLevel, DiagnosticResult};
+use gw_core::{CommandRunner, SystemCommandRunner};
+use std::path::{Path, PathBuf};
+use std::process::Output;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+/// Leases are handed out for 12h and renewed halfway, so a lease file nobody
+/// has written for two full lease times means dnsmasq is not serving clients.
+const LEASE_STALE_AFTER: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// DHCP/DNS diagnostics for the dnsmasq instance serving Ghostwarden networks
+pub struct DnsmasqDiagnostics {
+    runner: Arc<dyn CommandRunner>,
+    config_dir: PathBuf,
+    lease_file: PathBuf,
+}
+
+impl DnsmasqDiagnostics {
+    pub fn new() -> Self {
+        Self {
+            runner: Arc::new(SystemCommandRunner),
+            config_dir: PathBuf::from(gw_dhcpdns::DNSMASQ_CONFIG_DIR),
+            lease_file: PathBuf::from(gw_dhcpdns::DEFAULT_LEASE_FILE),
+        }
+    }
+
+    /// Run commands through `runner` instead of spawning them directly.
+    pub fn with_runner(mut self, runner: Arc<dyn CommandRunner>) -> Self {
+        self.runner = runner;
+        self
+    }
+
+    /// Read `gw-*.conf` files from `config_dir` and leases from `lease_file`
+    /// instead of the system locations.
+    pub fn with_paths(
+        mut self,
+        config_dir: impl Into<PathBuf>,
+        lease_file: impl Into<PathBuf>,
+    ) -> Self {
+        self.config_dir = config_dir.into();
+        self.lease_file = lease_file.into();
+        self
+    }
+
+    async fn run(&self, program: &str, args: &[&str]) -> anyhow::Result<Output> {
+        run_timed(self.runner.as_ref(), program, args).await
+    }
+
+    pub async fn diagnose(&self) -> anyhow::Result<Vec<DiagnosticResult>> {
+        let configs = gw_configs(&self.config_dir)?;
+        if configs.is_empty() {
+            return Ok(vec![DiagnosticResult::new(
+                DiagnosticLevel::Info,
+                "No Ghostwarden dnsmasq configs",
+                format!(
+                    "No gw-*.conf files in {}; no network has DHCP enabled",
+                    self.config_dir.display()
+                ),
+            )]);
+        }
+
+        let mut results = Vec::new();
+
+        results.extend(or_timed_out("dnsmasq service", self.check_service().await)?);
+
+        results.extend(or_timed_out(
+            "dnsmasq interfaces",
+            self.check_interfaces(&configs).await,
+        )?);
+
+        results.push(self.check_lease_file(SystemTime::now()));
+
+        Ok(results)
+    }
+
+    async fn check_service(&self) -> anyhow::Result<Vec<DiagnosticResult>> {
+        let output = self.run("systemctl", &["is-active", "dnsmasq"]).await?;
+        let state = String::from_utf8_lossy(&output.stdout).trim().to_string();
+
+        if output.status.success() {
+            return Ok(vec![DiagnosticResult::new(
+                DiagnosticLevel::Info,
+                "dnsmasq running",
+                "dnsmasq.service is active",
+            )]);
+        }
+
+        let mut results = vec![
+            DiagnosticResult::new(
+                DiagnosticLevel::Error,
+                "dnsmasq not running",
+                format!(
+                    "dnsmasq.service is {} - DHCP and DNS are down for Ghostwarden networks",
+                    if state.is_empty() {
+                        "not active"
+                    } else {
+                        &state
+                    }
+                ),
+            )
+            .with_suggestion("Check the unit's logs for config errors before starting it")
+            .with_command("sudo systemctl status dnsmasq"),
+        ];
+        results.extend(self.check_port_conflict().await?);
+        Ok(results)
+    }
+
+    /// dnsmasq cannot start while systemd-resolved's stub listener (or anything
+    /// else) holds port 53 on a wildcard or the 127.0.0.53 address.
+    async fn check_port_conflict(&self) -> anyhow::Result<Option<DiagnosticResult>> {
+        let resolved_active = self
+            .run("systemctl", &["is-active", "systemd-resolved"])
+            .await?
+            .status
+            .success();
+        if !resolved_active {
+            return Ok(None);
+        }
+
+        let output = self.run("ss", &["-H", "-ltnp"]).await?;
+        if !output.status.success() {
+            return Ok(None);
+        }
+
+        let holders: Vec<String> = port_53_listeners(&String::from_utf8_lossy(&output.stdout))
+            .into_iter()
+            .filter(|listener| listener.process.as_deref() != Some("dnsmasq"))
+            .filter(|listener| {
+                matches!(
+                    listener.address.as_str(),
+                    "0.0.0.0" | "*" | "[::]" | "127.0.0.53"
+                )
+            })
+            .map(|listener| match listener.process {
+                Some(process) => format!("{}:53 ({})", listener.address, process),
+                None => format!("{}:53", listener.address),
+            })
+            .collect();
+        if holders.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(
+            DiagnosticResult::new(
+                DiagnosticLevel::Error,
+                "Port 53 conflict with systemd-resolved",
+                format!(
+                    "systemd-resolved is active and port 53 is already taken: {}",
+                    holders.join(", ")
+                ),
+            )
+            .with_suggestion(
+                "Set DNSStubListener=no in /etc/systemd/resolved.conf, restart systemd-resolved, then start dnsmasq",
+            )
+            .with_command("sudo ss -ltnp 'sport = :53'"),
+        ))
+    }
+
+    async fn check_interfaces(&self, configs: &[PathBuf]) -> anyhow::Result<Vec<DiagnosticResult>> {
+        let mut results = Vec::new();
+
+        for config in configs {
+            let content = std::fs::read_to_string(config)?;
+            for iface in interface_bindings(&content) {
+                let exists = self
+                    .run("ip", &["link", "show", "dev", iface])
+                    .await?
+                    .status
+                    .success();
+
+                if exists {
+                    results.push(DiagnosticResult::new(
+                        DiagnosticLevel::Info,
+                        format!("dnsmasq serves {}", iface),
+                        format!("{} binds interface={}", config.display(), iface),
+                    ));
+                } else {
+                    results.push(
+                        DiagnosticResult::new(
+                            DiagnosticLevel::Warning,
+                            "dnsmasq config references missing bridge",
+                            format!(
+                                "{} binds interface={}, which does not exist",
+                                config.display(),
+                                iface
+                            ),
+                        )
+                        .with_suggestion(
+                            "Re-apply the topology, or remove configs for deleted networks with 'gwarden net clean'",
+                        ),
+                    );
+                }
+            }
+        }
+
+        Ok(results)
+    }
+
+    fn check_lease_file(&self, now: SystemTime) -> DiagnosticResult {
+        let path = self.lease_file.display();
+
+        let metadata = match std::fs::metadata(&self.lease_file) {
+            Ok(metadata) => metadata,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return DiagnosticResult::new(
+                    DiagnosticLevel::Warning,
+                    "Lease file missing",
+                    format!("{} does not exist - no lease has been handed out yet", path),
+                );
+            }
+            Err(e) => {
+                return DiagnosticResult::new(
+                    DiagnosticLevel::Error,
+                    "Lease file unreadable",
+                    format!("{}: {}", path, e),
+                );
+            }
+        };
+
+        if let Err(e) = std::fs::File::open(&self.lease_file) {
+            return DiagnosticResult::new(
+                DiagnosticLevel::Error,
+                "Lease file unreadable",
+                format!("{}: {}", path, e),
+            );
+        }
+
+        let age = metadata
+            .modified()
+            .ok()
+            .and_then(|modified| now.duration_since(modified).ok())
+            .unwrap_or_default();
+
+        if age > LEASE_STALE_AFTER {
+            DiagnosticResult::new(
+                DiagnosticLevel::Warning,
+                "Lease file is stale",
+                format!(
+                    "{} was last updated {}h ago - clients are not renewing leases",
+                    path,
+                    age.as_secs() / 3600
+                ),
+            )
+            .with_suggestion(
+                "Check that dnsmasq is bound to the bridges and DHCP traffic reaches it",
+            )
+        } else {
+            DiagnosticResult::new(
+                DiagnosticLevel::Info,
+                "Lease file up to date",
+                format!("{} was updated {}m ago", path, age.as_secs() / 60),
+            )
+        }
+    }
+}
+
+impl Default for DnsmasqDiagnostics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// `gw-*.conf` files Ghostwarden wrote to `dir`, sorted
+fn gw_configs(dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    if !dir.exists() {
+        return Ok(vec![]);
+    }
+
+    let mut configs = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        let is_gw = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.starts_with("gw-") && name.ends_with(".conf"));
+        if is_gw {
+            configs.push(path);
+        }
+    }
+
+    configs.sort();
+    Ok(configs)
+}
+
+/// A TCP socket listening on port 53
+#[derive(Debug, PartialEq, Eq)]
+struct Listener {
+    /// Local address without the `%iface` scope, e.g. `127.0.0.53` or `[::]`
+    address: String,
+    /// Owning process; only visible to root
+    process: Option<String>,
+}
+
+/// Port 53 listeners in `ss -H -ltnp` output
+fn port_53_listeners(ss_output: &str) -> Vec<Listener> {
+    ss_output
+        .lines()
+        .filter_map(|line| {
+            let local = line.split_whitespace().nth(3)?;
+            let (address, port) = local.rsplit_once(':')?;
+            if port != "53" {
+                return None;
+            }
+
+            let address = address.split('%').next().unwrap_or(address);
+            let process = line
+                .split_once("users:((\"")
+                .and_then(|(_, rest)| rest.split_once('"'))
+                .map(|(name, _)| name.to_string());
+            Some(Listener {
+                address: address.to_string(),
+                process,
+            })
+        })
+        .collect()
+}
+
+/// Interface names from `interface=` lines, which may list several
+fn interface_bindings(config: &str) -> Vec<&str> {
+    config
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("interface="))
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .filter(|iface| !iface.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gw_core::MockCommandRunner;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("gw-dnsmasq-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn parses_interface_bindings() {
+        let config = "# Ghostwarden configuration for br-lab\ninterface=br-lab\nbind-interfaces\nexcept-interface=lo\ninterface=br-a, br-b\n";
+        assert_eq!(interface_bindings(config), ["br-lab", "br-a", "br-b"]);
+    }
+
+    #[test]
+    fn parses_port_53_listeners() {
+        let ss = "LISTEN 0      4096   127.0.0.53%lo:53        0.0.0.0:*    users:((\"systemd-resolve\",pid=612,fd=14))
+LISTEN 0      32     10.33.0.1:53            0.0.0.0:*    users:((\"dnsmasq\",pid=900,fd=6))
+LISTEN 0      128    0.0.0.0:22              0.0.0.0:*    users:((\"sshd\",pid=700,fd=3))
+LISTEN 0      4096   [::]:53                 [::]:*
+";
+        assert_eq!(
+            port_53_listeners(ss),
+            [
+                Listener {
+                    address: "127.0.0.53".into(),
+                    process: Some("systemd-resolve".into()),
+                },
+                Listener {
+                    address: "10.33.0.1".into(),
+                    process: Some("dnsmasq".into()),
+                },
+                Listener {
+                    address: "[::]".into(),
+                    process: None,
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn stopped_dnsmasq_reports_resolved_holding_port_53() {
+        let runner = MockCommandRunner::new()
+            .fail("systemctl is-active dnsmasq", 3, "")
+            .ok("systemctl is-active systemd-resolved", "active\n")
+            .ok(
+                "ss -H -ltnp",
+                "LISTEN 0 4096 127.0.0.53%lo:53 0.0.0.0:* users:((\"systemd-resolve\",pid=612,fd=14))\n",
+            );
+        let diagnostics = DnsmasqDiagnostics::new().with_runner(Arc::new(runner));
+
+        let results = diagnostics.check_service().await.unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[1].level, DiagnosticLevel::Error);
+        assert_eq!(
+            results[1].details,
+            "systemd-resolved is active and port 53 is already taken: 127.0.0.53:53 (systemd-resolve)"
+        );
+    }
+
+    #[tokio::test]
+    async fn warns_about_configs_for_missing_bridges() {
+        let dir = temp_dir("configs");
+        std::fs::write(dir.join("gw-lab.conf"), "interface=br-lab\n").unwrap();
+        std::fs::write(dir.join("gw-old.conf"), "interface=br-old\n").unwrap();
+        std::fs::write(dir.join("other.conf"), "interface=eth9\n").unwrap();
+        let leases = dir.join("dnsmasq.leases");
+        std::fs::write(&leases, "").unwrap();
+
+        let runner = MockCommandRunner::new()
+            .ok("systemctl is-active dnsmasq", "active\n")
+            .ok("ip link show dev br-lab", "")
+            .fail(
+                "ip link show dev br-old",
+                1,
+                "Device \"br-old\" does not exist.",
+            );
+        let diagnostics = DnsmasqDiagnostics::new()
+            .with_runner(Arc::new(runner))
+            .with_paths(&dir, &leases);
+
+        let results = diagnostics.diagnose().await.unwrap();
+        let titles: Vec<(DiagnosticLevel, &str)> = results
+            .iter()
+            .map(|r| (r.level, r.title.as_str()))
+            .collect();
+        assert_eq!(
+            titles,
+            [
+                (DiagnosticLevel::Info, "dnsmasq running"),
+                (DiagnosticLevel::Info, "dnsmasq serves br-lab"),
+                (
+                    DiagnosticLevel::Warning,
+                    "dnsmasq config references missing bridge"
+                ),
+                (DiagnosticLevel::Info, "Lease file up to date"),
+            ]
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn reports_stopped_service_and_stale_or_missing_leases() {
+        let dir = temp_dir("leases");
+        let runner = MockCommandRunner::new()
+            .fail("systemctl is-active dnsmasq", 3, "")
+            .fail("systemctl is-active systemd-resolved", 3, "");
+        let diagnostics = DnsmasqDiagnostics::new()
+            .with_runner(Arc::new(runner))
+            .with_paths(&dir, dir.join("dnsmasq.leases"));
+
+        let service = diagnostics.check_service().await.unwrap();
+        assert_eq!(service.len(), 1);
+        assert_eq!(service[0].level, DiagnosticLevel::Error);
+
+        assert_eq!(
+            diagnostics.check_lease_file(SystemTime::now()).title,
+            "Lease file missing"
+        );
+
+        std::fs::write(dir.join("dnsmasq.leases"), "").unwrap();
+        let later = SystemTime::now() + LEASE_STALE_AFTER + Duration::from_secs(3600);
+        let stale = diagnostics.check_lease_file(later);
+        assert_eq!(stale.level, DiagnosticLevel::Warning);
+        assert_eq!(stale.title, "Lease file is stale");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
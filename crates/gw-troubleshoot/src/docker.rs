@@ -1,10 +1,13 @@
+use crate::command::{CommandTimeout, or_timed_out, run_timed, timed_out_result};
 use crate::diagnostics::{DiagnosticLevel, DiagnosticResult};
+use gw_core::{CommandRunner, SystemCommandRunner};
 use serde::Deserialize;
-use std::process::Command;
+use std::process::Output;
+use std::sync::Arc;
 
 /// Docker networking diagnostics
 pub struct DockerDiagnostics {
-    docker_available: bool,
+    runner: Arc<dyn CommandRunner>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -19,19 +22,29 @@ struct DockerNetwork {
 
 impl DockerDiagnostics {
     pub fn new() -> Self {
-        let docker_available = Command::new("docker")
-            .arg("--version")
-            .output()
-            .map(|o| o.status.success())
-            .unwrap_or(false);
+        Self {
+            runner: Arc::new(SystemCommandRunner),
+        }
+    }
+
+    /// Run commands through `runner` instead of spawning them directly.
+    pub fn with_runner(mut self, runner: Arc<dyn CommandRunner>) -> Self {
+        self.runner = runner;
+        self
+    }
 
-        Self { docker_available }
+    async fn run(&self, program: &str, args: &[&str]) -> anyhow::Result<Output> {
+        run_timed(self.runner.as_ref(), program, args).await
     }
 
     pub async fn diagnose(&self) -> anyhow::Result<Vec<DiagnosticResult>> {
         let mut results = Vec::new();
 
-        if !self.docker_available {
+        let docker_available = self
+            .run("docker", &["--version"])
+            .await
+            .is_ok_and(|output| output.status.success());
+        if !docker_available {
             results.push(DiagnosticResult::new(
                 DiagnosticLevel::Info,
                 "Docker not found",
@@ -40,20 +53,40 @@ impl DockerDiagnostics {
             return Ok(results);
         }
 
-        // Check Docker daemon status
-        results.extend(self.check_docker_daemon().await?);
+        // Check Docker daemon status; the remaining checks would hang on a
+        // wedged daemon too, so stop here if it does not answer.
+        let daemon = self.check_docker_daemon().await;
+        if let Err(err) = &daemon
+            && err.downcast_ref::<CommandTimeout>().is_some()
+        {
+            results.push(timed_out_result("Docker daemon", err));
+            return Ok(results);
+        }
+        results.extend(daemon?);
 
         // Check Docker networks
-        results.extend(self.check_docker_networks().await?);
+        results.extend(or_timed_out(
+            "Docker networks",
+            self.check_docker_networks().await,
+        )?);
 
         // Check Docker bridge configuration
-        results.extend(self.check_docker_bridge().await?);
+        results.extend(or_timed_out(
+            "Docker bridge",
+            self.check_docker_bridge().await,
+        )?);
 
         // Check for network conflicts
-        results.extend(self.check_network_conflicts().await?);
+        results.extend(or_timed_out(
+            "Docker network conflicts",
+            self.check_network_conflicts().await,
+        )?);
 
         // Check Docker iptables integration
-        results.extend(self.check_docker_iptables().await?);
+        results.extend(or_timed_out(
+            "Docker iptables",
+            self.check_docker_iptables().await,
+        )?);
 
         Ok(results)
     }
@@ -61,11 +94,9 @@ impl DockerDiagnostics {
     async fn check_docker_daemon(&self) -> anyhow::Result<Vec<DiagnosticResult>> {
         let mut results = Vec::new();
 
-        let output = Command::new("docker")
-            .arg("info")
-            .arg("--format")
-            .arg("{{json .}}")
-            .output()?;
+        let output = self
+            .run("docker", &["info", "--format", "{{json .}}"])
+            .await?;
 
         if !output.status.success() {
             results.push(
@@ -107,12 +138,9 @@ impl DockerDiagnostics {
     async fn check_docker_networks(&self) -> anyhow::Result<Vec<DiagnosticResult>> {
         let mut results = Vec::new();
 
-        let output = Command::new("docker")
-            .arg("network")
-            .arg("ls")
-            .arg("--format")
-            .arg("{{json .}}")
-            .output()?;
+        let output = self
+            .run("docker", &["network", "ls", "--format", "{{json .}}"])
+            .await?;
 
         if !output.status.success() {
             return Ok(results);
@@ -148,11 +176,7 @@ impl DockerDiagnostics {
         let mut results = Vec::new();
 
         // Check if docker0 bridge exists
-        let output = Command::new("ip")
-            .arg("link")
-            .arg("show")
-            .arg("docker0")
-            .output()?;
+        let output = self.run("ip", &["link", "show", "docker0"]).await?;
 
         if output.status.success() {
             results.push(DiagnosticResult::new(
@@ -162,11 +186,7 @@ impl DockerDiagnostics {
             ));
 
             // Get docker0 IP address
-            let output = Command::new("ip")
-                .arg("addr")
-                .arg("show")
-                .arg("docker0")
-                .output()?;
+            let output = self.run("ip", &["addr", "show", "docker0"]).await?;
 
             if output.status.success() {
                 let addr_info = String::from_utf8_lossy(&output.stdout);
@@ -220,11 +240,9 @@ impl DockerDiagnostics {
         let mut results = Vec::new();
 
         // Get Docker network subnets
-        let output = Command::new("docker")
-            .arg("network")
-            .arg("inspect")
-            .arg("bridge")
-            .output()?;
+        let output = self
+            .run("docker", &["network", "inspect", "bridge"])
+            .await?;
 
         if output.status.success() {
             let info = String::from_utf8_lossy(&output.stdout);
@@ -281,7 +299,7 @@ impl DockerDiagnostics {
         let mut results = Vec::new();
 
         // Check if iptables has DOCKER chains
-        let output = Command::new("iptables").arg("-L").arg("-n").output();
+        let output = self.run("iptables", &["-L", "-n"]).await;
 
         if let Ok(output) = output
             && output.status.success()
@@ -328,13 +346,9 @@ impl DockerDiagnostics {
         }
 
         // Check NAT rules
-        let output = Command::new("iptables")
-            .arg("-t")
-            .arg("nat")
-            .arg("-L")
-            .arg("DOCKER")
-            .arg("-n")
-            .output();
+        let output = self
+            .run("iptables", &["-t", "nat", "-L", "DOCKER", "-n"])
+            .await;
 
         if let Ok(output) = output
             && output.status.success()
@@ -367,3 +381,20 @@ impl Default for DockerDiagnostics {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gw_core::MockCommandRunner;
+
+    #[tokio::test]
+    async fn missing_docker_is_reported_without_further_checks() {
+        let runner = Arc::new(MockCommandRunner::new());
+        let diagnostics = DockerDiagnostics::new().with_runner(runner.clone());
+
+        let results = diagnostics.diagnose().await.unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].title, "Docker not found");
+        assert_eq!(runner.calls().len(), 1);
+    }
+}
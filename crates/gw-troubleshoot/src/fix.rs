@@ -0,0 +1,133 @@
+use crate::command::run_timed;
+use gw_core::CommandRunner;
+use std::fmt;
+
+/// A suggested diagnostic command that `gwarden doctor --fix` may run.
+///
+/// Only kernel module loads and sysctl writes qualify: both are idempotent and
+/// leave nothing to undo beyond what the check asked for. Package installs,
+/// link changes and anything piped through a shell are left to the operator.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SafeFix {
+    LoadModule(String),
+    SetSysctl { key: String, value: String },
+}
+
+impl SafeFix {
+    /// Recognize `[sudo] modprobe <module>` and `[sudo] sysctl -w <key>=<value>`.
+    pub fn parse(command: &str) -> Option<Self> {
+        let mut words: Vec<&str> = command.split_whitespace().collect();
+        if words.first() == Some(&"sudo") {
+            words.remove(0);
+        }
+
+        match words.as_slice() {
+            ["modprobe", module] if is_plain_word(module, &['_', '-']) => {
+                Some(Self::LoadModule(module.to_string()))
+            }
+            ["sysctl", "-w", setting] => {
+                let (key, value) = setting.split_once('=')?;
+                (is_plain_word(key, &['_', '-', '.']) && is_plain_word(value, &[])).then(|| {
+                    Self::SetSysctl {
+                        key: key.to_string(),
+                        value: value.to_string(),
+                    }
+                })
+            }
+            _ => None,
+        }
+    }
+
+    pub async fn run(&self, runner: &dyn CommandRunner) -> anyhow::Result<()> {
+        let output = match self {
+            Self::LoadModule(module) => run_timed(runner, "modprobe", &[module]).await?,
+            Self::SetSysctl { key, value } => {
+                let setting = format!("{}={}", key, value);
+                run_timed(runner, "sysctl", &["-w", &setting]).await?
+            }
+        };
+
+        if !output.status.success() {
+            anyhow::bail!(
+                "`{}` failed: {}",
+                self,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for SafeFix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::LoadModule(module) => write!(f, "modprobe {}", module),
+            Self::SetSysctl { key, value } => write!(f, "sysctl -w {}={}", key, value),
+        }
+    }
+}
+
+/// ASCII alphanumerics plus `extra`, starting with an alphanumeric so the word
+/// can never be taken for an option.
+fn is_plain_word(word: &str, extra: &[char]) -> bool {
+    word.starts_with(|c: char| c.is_ascii_alphanumeric())
+        && word
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || extra.contains(&c))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gw_core::MockCommandRunner;
+
+    #[test]
+    fn only_module_loads_and_sysctl_writes_are_safe() {
+        assert_eq!(
+            SafeFix::parse("sudo modprobe br_netfilter"),
+            Some(SafeFix::LoadModule("br_netfilter".into()))
+        );
+        assert_eq!(
+            SafeFix::parse("sysctl -w net.ipv4.ip_forward=1"),
+            Some(SafeFix::SetSysctl {
+                key: "net.ipv4.ip_forward".into(),
+                value: "1".into(),
+            })
+        );
+
+        for unsafe_command in [
+            "sudo pacman -S nftables",
+            "sudo ip link set br-lab up",
+            "sudo modprobe -r br_netfilter",
+            "sudo modprobe br_netfilter; reboot",
+            "sudo sysctl -w net.ipv4.ip_forward=$(reboot)",
+            "sudo sysctl net.ipv4.ip_forward=1",
+            "nft list ruleset | grep -A5 'chain postrouting'",
+            "sudo gwarden net clean -f ghostnet.toml --execute",
+        ] {
+            assert_eq!(SafeFix::parse(unsafe_command), None, "{}", unsafe_command);
+        }
+    }
+
+    #[tokio::test]
+    async fn runs_the_fix_and_reports_failures() {
+        let runner = MockCommandRunner::new()
+            .ok("sysctl -w net.ipv4.ip_forward=1", "")
+            .fail(
+                "modprobe nf_nat_sip",
+                1,
+                "modprobe: FATAL: Module not found",
+            );
+
+        let sysctl = SafeFix::parse("sudo sysctl -w net.ipv4.ip_forward=1").unwrap();
+        sysctl.run(&runner).await.unwrap();
+
+        let module = SafeFix::parse("sudo modprobe nf_nat_sip").unwrap();
+        let err = module.run(&runner).await.unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "`modprobe nf_nat_sip` failed: modprobe: FATAL: Module not found"
+        );
+        assert_eq!(runner.calls().len(), 2);
+    }
+}
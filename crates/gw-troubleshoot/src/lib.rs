@@ -1,18 +1,33 @@
 pub mod bridge;
+pub mod command;
 pub mod diagnostics;
+pub mod dnsmasq;
 pub mod docker;
+pub mod fix;
 pub mod nftables;
+pub mod preflight;
 
 pub use bridge::BridgeDiagnostics;
-pub use diagnostics::{DiagnosticLevel, DiagnosticReport, DiagnosticResult};
+pub use command::{COMMAND_TIMEOUT, CommandTimeout};
+pub use diagnostics::{DiagnosticLevel, DiagnosticReport, DiagnosticResult, ReportSummary};
+pub use dnsmasq::DnsmasqDiagnostics;
 pub use docker::DockerDiagnostics;
+pub use fix::SafeFix;
 pub use nftables::NftablesDiagnostics;
+pub use preflight::PreflightDiagnostics;
+
+use gw_core::{CommandRunner, SystemCommandRunner};
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 /// Main troubleshooting interface
 pub struct Troubleshooter {
     nft: NftablesDiagnostics,
     docker: DockerDiagnostics,
     bridge: BridgeDiagnostics,
+    dnsmasq: DnsmasqDiagnostics,
+    runner: Arc<dyn CommandRunner>,
 }
 
 impl Troubleshooter {
@@ -21,24 +36,56 @@ impl Troubleshooter {
             nft: NftablesDiagnostics::new(),
             docker: DockerDiagnostics::new(),
             bridge: BridgeDiagnostics::new(),
+            dnsmasq: DnsmasqDiagnostics::new(),
+            runner: Arc::new(SystemCommandRunner),
         }
     }
 
-    /// Run all diagnostics and generate comprehensive report
+    /// Run every section's commands through `runner`.
+    pub fn with_runner(mut self, runner: Arc<dyn CommandRunner>) -> Self {
+        self.nft = self.nft.with_runner(runner.clone());
+        self.docker = self.docker.with_runner(runner.clone());
+        self.bridge = self.bridge.with_runner(runner.clone());
+        self.dnsmasq = self.dnsmasq.with_runner(runner.clone());
+        self.runner = runner;
+        self
+    }
+
+    /// Whether disabled IPv6 forwarding is an error (see
+    /// [`NftablesDiagnostics::with_ipv6_required`]).
+    pub fn with_ipv6_required(mut self, required: bool) -> Self {
+        self.nft = self.nft.with_ipv6_required(required);
+        self
+    }
+
+    /// Limit bridge diagnostics to these bridges (see [`BridgeDiagnostics::scoped`]).
+    pub fn with_bridge_scope(mut self, bridges: impl IntoIterator<Item = String>) -> Self {
+        self.bridge = BridgeDiagnostics::scoped(bridges).with_runner(self.runner.clone());
+        self
+    }
+
+    /// Run all diagnostics concurrently and generate comprehensive report
     pub async fn run_all(&self) -> anyhow::Result<DiagnosticReport> {
         let mut report = DiagnosticReport::new();
 
-        // Run nftables diagnostics
-        let nft_results = self.nft.diagnose().await?;
-        report.add_section("nftables/iptables", nft_results);
+        // Each command is bounded by COMMAND_TIMEOUT, so a hung subsystem
+        // only delays its own section.
+        let (
+            (nft_results, nft_elapsed),
+            (docker_results, docker_elapsed),
+            (bridge_results, bridge_elapsed),
+            (dnsmasq_results, dnsmasq_elapsed),
+        ) = tokio::join!(
+            timed(self.nft.diagnose()),
+            timed(self.docker.diagnose()),
+            timed(self.bridge.diagnose()),
+            timed(self.dnsmasq.diagnose()),
+        );
 
-        // Run Docker diagnostics
-        let docker_results = self.docker.diagnose().await?;
-        report.add_section("Docker Networking", docker_results);
-
-        // Run bridge diagnostics
-        let bridge_results = self.bridge.diagnose().await?;
-        report.add_section("Bridge Configuration", bridge_results);
+        report.add_timed_section("nftables/iptables", nft_results?, nft_elapsed);
+        report.add_timed_section("Docker Networking", docker_results?, docker_elapsed);
+        report.add_timed_section("Bridge Configuration", bridge_results?, bridge_elapsed);
+        report.add_timed_section("DHCP/DNS", dnsmasq_results?, dnsmasq_elapsed);
 
         Ok(report)
     }
@@ -55,6 +102,16 @@ impl Troubleshooter {
     pub async fn check_bridges(&self) -> anyhow::Result<Vec<DiagnosticResult>> {
         self.bridge.diagnose().await
     }
+
+    pub async fn check_dnsmasq(&self) -> anyhow::Result<Vec<DiagnosticResult>> {
+        self.dnsmasq.diagnose().await
+    }
+}
+
+async fn timed<F: Future>(future: F) -> (F::Output, Duration) {
+    let started = Instant::now();
+    let output = future.await;
+    (output, started.elapsed())
 }
 
 impl Default for Troubleshooter {
@@ -62,3 +119,78 @@ impl Default for Troubleshooter {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gw_core::CommandFuture;
+    use std::os::unix::process::ExitStatusExt;
+    use std::process::{ExitStatus, Output};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    const COMMAND_DELAY: Duration = Duration::from_millis(25);
+
+    /// Every command fails after [`COMMAND_DELAY`], like a slow host with
+    /// nothing installed; tracks how many commands were in flight at once.
+    #[derive(Default)]
+    struct SlowRunner {
+        in_flight: AtomicUsize,
+        peak: AtomicUsize,
+    }
+
+    impl CommandRunner for SlowRunner {
+        fn run<'a>(
+            &'a self,
+            _program: &'a str,
+            _args: &'a [&'a str],
+            _stdin: Option<&'a [u8]>,
+        ) -> CommandFuture<'a> {
+            Box::pin(async move {
+                let running = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                self.peak.fetch_max(running, Ordering::SeqCst);
+                tokio::time::sleep(COMMAND_DELAY).await;
+                self.in_flight.fetch_sub(1, Ordering::SeqCst);
+                Ok(Output {
+                    status: ExitStatus::from_raw(1 << 8),
+                    stdout: vec![],
+                    stderr: b"not available".to_vec(),
+                })
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn run_all_runs_sections_concurrently_in_order() {
+        let runner = Arc::new(SlowRunner::default());
+        let troubleshooter = Troubleshooter::new().with_runner(runner.clone());
+
+        let started = Instant::now();
+        troubleshooter.check_nftables().await.unwrap();
+        troubleshooter.check_docker().await.unwrap();
+        troubleshooter.check_bridges().await.unwrap();
+        let sequential = started.elapsed();
+        assert_eq!(runner.peak.load(Ordering::SeqCst), 1);
+
+        let started = Instant::now();
+        let report = troubleshooter.run_all().await.unwrap();
+        let concurrent = started.elapsed();
+
+        assert!(runner.peak.load(Ordering::SeqCst) > 1);
+        assert!(
+            concurrent < sequential,
+            "run_all took {:?}, sequential sections {:?}",
+            concurrent,
+            sequential
+        );
+        let sections: Vec<&str> = report.sections().map(|(name, _)| name).collect();
+        assert_eq!(
+            sections,
+            [
+                "nftables/iptables",
+                "Docker Networking",
+                "Bridge Configuration",
+                "DHCP/DNS"
+            ]
+        );
+    }
+}
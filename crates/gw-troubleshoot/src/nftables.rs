@@ -1,38 +1,59 @@
+use crate::command::{or_timed_out, run_timed};
 use crate::diagnostics::{DiagnosticLevel, DiagnosticResult};
+use gw_core::{CommandRunner, SystemCommandRunner};
 use regex::Regex;
-use std::process::Command;
+use std::path::Path;
+use std::process::Output;
+use std::sync::Arc;
+
+/// Where the kernel exposes connection tracking table usage
+const CONNTRACK_SYSCTL_DIR: &str = "/proc/sys/net/netfilter";
 
 /// nftables/iptables diagnostics
 pub struct NftablesDiagnostics {
-    nft_available: bool,
-    iptables_available: bool,
+    runner: Arc<dyn CommandRunner>,
+    ipv6_required: bool,
 }
 
 impl NftablesDiagnostics {
     pub fn new() -> Self {
-        let nft_available = Command::new("nft")
-            .arg("--version")
-            .output()
-            .map(|o| o.status.success())
-            .unwrap_or(false);
-
-        let iptables_available = Command::new("iptables")
-            .arg("--version")
-            .output()
-            .map(|o| o.status.success())
-            .unwrap_or(false);
-
         Self {
-            nft_available,
-            iptables_available,
+            runner: Arc::new(SystemCommandRunner),
+            ipv6_required: true,
         }
     }
 
+    /// Whether disabled IPv6 forwarding is an error. Without a topology it is
+    /// assumed to be; pass `false` when the topology has no IPv6 networks to
+    /// report it as informational only.
+    pub fn with_ipv6_required(mut self, required: bool) -> Self {
+        self.ipv6_required = required;
+        self
+    }
+
+    /// Run commands through `runner` instead of spawning them directly.
+    pub fn with_runner(mut self, runner: Arc<dyn CommandRunner>) -> Self {
+        self.runner = runner;
+        self
+    }
+
+    async fn run(&self, program: &str, args: &[&str]) -> anyhow::Result<Output> {
+        run_timed(self.runner.as_ref(), program, args).await
+    }
+
+    async fn available(&self, program: &str) -> bool {
+        self.run(program, &["--version"])
+            .await
+            .is_ok_and(|output| output.status.success())
+    }
+
     pub async fn diagnose(&self) -> anyhow::Result<Vec<DiagnosticResult>> {
         let mut results = Vec::new();
+        let nft_available = self.available("nft").await;
+        let iptables_available = self.available("iptables").await;
 
         // Check if tools are available
-        if !self.nft_available {
+        if !nft_available {
             results.push(
                 DiagnosticResult::new(
                     DiagnosticLevel::Critical,
@@ -44,24 +65,39 @@ impl NftablesDiagnostics {
             );
         }
 
-        if self.nft_available {
+        if nft_available {
             // Check nftables ruleset
-            results.extend(self.check_nftables_ruleset().await?);
+            results.extend(or_timed_out(
+                "nftables ruleset",
+                self.check_nftables_ruleset().await,
+            )?);
 
             // Check for NAT rules
-            results.extend(self.check_nat_configuration().await?);
+            results.extend(or_timed_out(
+                "NAT configuration",
+                self.check_nat_configuration().await,
+            )?);
 
             // Check for conflicts
-            results.extend(self.check_rule_conflicts().await?);
+            results.extend(or_timed_out(
+                "Rule conflicts",
+                self.check_rule_conflicts().await,
+            )?);
         }
 
-        if self.iptables_available {
+        if iptables_available {
             // Check for iptables interference
-            results.extend(self.check_iptables_interference().await?);
+            results.extend(or_timed_out(
+                "iptables interference",
+                self.check_iptables_interference().await,
+            )?);
         }
 
         // Check kernel modules
-        results.extend(self.check_kernel_modules().await?);
+        results.extend(or_timed_out(
+            "Kernel modules",
+            self.check_kernel_modules().await,
+        )?);
 
         Ok(results)
     }
@@ -69,7 +105,7 @@ impl NftablesDiagnostics {
     async fn check_nftables_ruleset(&self) -> anyhow::Result<Vec<DiagnosticResult>> {
         let mut results = Vec::new();
 
-        let output = Command::new("nft").arg("list").arg("ruleset").output()?;
+        let output = self.run("nft", &["list", "ruleset"]).await?;
 
         if !output.status.success() {
             let stderr = String::from_utf8_lossy(&output.stderr);
@@ -126,13 +162,9 @@ impl NftablesDiagnostics {
     async fn check_nat_configuration(&self) -> anyhow::Result<Vec<DiagnosticResult>> {
         let mut results = Vec::new();
 
-        let output = Command::new("nft")
-            .arg("list")
-            .arg("chain")
-            .arg("inet")
-            .arg("gw")
-            .arg("postrouting")
-            .output();
+        let output = self
+            .run("nft", &["list", "chain", "inet", "gw", "postrouting"])
+            .await;
 
         if let Ok(output) = output
             && output.status.success()
@@ -176,7 +208,7 @@ impl NftablesDiagnostics {
         let mut results = Vec::new();
 
         // Check for duplicate chains
-        let output = Command::new("nft").arg("list").arg("ruleset").output()?;
+        let output = self.run("nft", &["list", "ruleset"]).await?;
 
         if output.status.success() {
             let ruleset = String::from_utf8_lossy(&output.stdout);
@@ -218,12 +250,7 @@ impl NftablesDiagnostics {
         let mut results = Vec::new();
 
         // Check if iptables has rules that might conflict
-        let output = Command::new("iptables")
-            .arg("-t")
-            .arg("nat")
-            .arg("-L")
-            .arg("-n")
-            .output();
+        let output = self.run("iptables", &["-t", "nat", "-L", "-n"]).await;
 
         if let Ok(output) = output
             && output.status.success()
@@ -250,7 +277,7 @@ impl NftablesDiagnostics {
         }
 
         // Check iptables filter table
-        let output = Command::new("iptables").arg("-L").arg("-n").output();
+        let output = self.run("iptables", &["-L", "-n"]).await;
 
         if let Ok(output) = output
             && output.status.success()
@@ -274,18 +301,15 @@ impl NftablesDiagnostics {
         Ok(results)
     }
 
-    async fn check_kernel_modules(&self) -> anyhow::Result<Vec<DiagnosticResult>> {
+    /// Report whether each `(module, description)` is loaded (via `lsmod`)
+    pub async fn check_modules(
+        &self,
+        modules: &[(&str, &str)],
+    ) -> anyhow::Result<Vec<DiagnosticResult>> {
         let mut results = Vec::new();
 
-        let required_modules = vec![
-            ("nf_tables", "Core nftables support"),
-            ("nf_nat", "NAT support"),
-            ("nf_conntrack", "Connection tracking"),
-            ("br_netfilter", "Bridge netfilter support"),
-        ];
-
-        for (module, description) in required_modules {
-            let output = Command::new("lsmod").output()?;
+        for &(module, description) in modules {
+            let output = self.run("lsmod", &[]).await?;
 
             if output.status.success() {
                 let modules = String::from_utf8_lossy(&output.stdout);
@@ -310,8 +334,21 @@ impl NftablesDiagnostics {
             }
         }
 
+        Ok(results)
+    }
+
+    async fn check_kernel_modules(&self) -> anyhow::Result<Vec<DiagnosticResult>> {
+        let required_modules = [
+            ("nf_tables", "Core nftables support"),
+            ("nf_nat", "NAT support"),
+            ("nf_conntrack", "Connection tracking"),
+            ("br_netfilter", "Bridge netfilter support"),
+        ];
+
+        let mut results = self.check_modules(&required_modules).await?;
+
         // Check sysctl settings for forwarding
-        let output = Command::new("sysctl").arg("net.ipv4.ip_forward").output();
+        let output = self.run("sysctl", &["net.ipv4.ip_forward"]).await;
 
         if let Ok(output) = output
             && output.status.success()
@@ -336,10 +373,13 @@ impl NftablesDiagnostics {
             }
         }
 
+        results.extend(self.check_ipv6_forwarding().await);
+        results.push(check_conntrack_usage(Path::new(CONNTRACK_SYSCTL_DIR)));
+
         // Check bridge netfilter settings
-        let output = Command::new("sysctl")
-            .arg("net.bridge.bridge-nf-call-iptables")
-            .output();
+        let output = self
+            .run("sysctl", &["net.bridge.bridge-nf-call-iptables"])
+            .await;
 
         if let Ok(output) = output
             && output.status.success()
@@ -354,6 +394,83 @@ impl NftablesDiagnostics {
 
         Ok(results)
     }
+
+    /// Dual-stack routed networks need `net.ipv6.conf.all.forwarding` as well
+    async fn check_ipv6_forwarding(&self) -> Option<DiagnosticResult> {
+        let output = self
+            .run("sysctl", &["net.ipv6.conf.all.forwarding"])
+            .await
+            .ok()
+            .filter(|output| output.status.success())?;
+
+        let value = String::from_utf8_lossy(&output.stdout);
+        if value.contains("= 1") {
+            return Some(DiagnosticResult::new(
+                DiagnosticLevel::Info,
+                "IPv6 forwarding enabled",
+                "net.ipv6.conf.all.forwarding = 1",
+            ));
+        }
+
+        Some(if self.ipv6_required {
+            DiagnosticResult::new(
+                DiagnosticLevel::Error,
+                "IPv6 forwarding disabled",
+                "net.ipv6.conf.all.forwarding = 0 - IPv6 routed networks will not forward",
+            )
+            .with_suggestion("Enable IPv6 forwarding for dual-stack routed networks")
+            .with_command("sudo sysctl -w net.ipv6.conf.all.forwarding=1")
+        } else {
+            DiagnosticResult::new(
+                DiagnosticLevel::Info,
+                "IPv6 forwarding disabled",
+                "net.ipv6.conf.all.forwarding = 0 - not needed, the topology has no IPv6 networks",
+            )
+        })
+    }
+}
+
+/// Compare `nf_conntrack_count` with `nf_conntrack_max`: a full table makes the
+/// kernel drop new connections, which under heavy NAT looks like random timeouts.
+fn check_conntrack_usage(dir: &Path) -> DiagnosticResult {
+    let read = |name: &str| -> Option<u64> {
+        std::fs::read_to_string(dir.join(name))
+            .ok()?
+            .trim()
+            .parse()
+            .ok()
+    };
+
+    let (Some(count), Some(max)) = (read("nf_conntrack_count"), read("nf_conntrack_max")) else {
+        return DiagnosticResult::new(
+            DiagnosticLevel::Info,
+            "Conntrack table not available",
+            "nf_conntrack_count/nf_conntrack_max not found - nf_conntrack is not loaded",
+        );
+    };
+
+    let usage = if max == 0 {
+        0.0
+    } else {
+        count as f64 / max as f64 * 100.0
+    };
+    let details = format!("{} of {} entries in use ({:.0}%)", count, max, usage);
+    let level = if usage > 95.0 {
+        DiagnosticLevel::Error
+    } else if usage > 80.0 {
+        DiagnosticLevel::Warning
+    } else {
+        return DiagnosticResult::new(DiagnosticLevel::Info, "Conntrack table usage", details);
+    };
+
+    DiagnosticResult::new(level, "Conntrack table nearly full", details)
+        .with_suggestion(
+            "New connections are dropped once the table is full; raise nf_conntrack_max",
+        )
+        .with_command(format!(
+            "sudo sysctl -w net.netfilter.nf_conntrack_max={}",
+            max.saturating_mul(2)
+        ))
 }
 
 impl Default for NftablesDiagnostics {
@@ -361,3 +478,83 @@ impl Default for NftablesDiagnostics {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gw_core::MockCommandRunner;
+
+    fn diagnostics(forwarding: &str, ipv6_required: bool) -> NftablesDiagnostics {
+        let runner = MockCommandRunner::new().ok(
+            "sysctl net.ipv6.conf.all.forwarding",
+            &format!("net.ipv6.conf.all.forwarding = {}\n", forwarding),
+        );
+        NftablesDiagnostics::new()
+            .with_runner(Arc::new(runner))
+            .with_ipv6_required(ipv6_required)
+    }
+
+    #[tokio::test]
+    async fn disabled_ipv6_forwarding_is_an_error_only_when_needed() {
+        let result = diagnostics("0", true)
+            .check_ipv6_forwarding()
+            .await
+            .unwrap();
+        assert_eq!(result.level, DiagnosticLevel::Error);
+        assert_eq!(
+            result.command.as_deref(),
+            Some("sudo sysctl -w net.ipv6.conf.all.forwarding=1")
+        );
+
+        let result = diagnostics("0", false)
+            .check_ipv6_forwarding()
+            .await
+            .unwrap();
+        assert_eq!(result.level, DiagnosticLevel::Info);
+        assert_eq!(result.command, None);
+
+        let result = diagnostics("1", true)
+            .check_ipv6_forwarding()
+            .await
+            .unwrap();
+        assert_eq!(result.level, DiagnosticLevel::Info);
+        assert_eq!(result.title, "IPv6 forwarding enabled");
+    }
+
+    #[test]
+    fn conntrack_usage_levels() {
+        let dir = std::env::temp_dir().join(format!("gw-conntrack-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let usage = |count: u64, max: u64| {
+            std::fs::write(dir.join("nf_conntrack_count"), format!("{}\n", count)).unwrap();
+            std::fs::write(dir.join("nf_conntrack_max"), format!("{}\n", max)).unwrap();
+            check_conntrack_usage(&dir)
+        };
+
+        assert_eq!(usage(100, 1000).level, DiagnosticLevel::Info);
+        assert_eq!(usage(800, 1000).level, DiagnosticLevel::Info);
+
+        let warning = usage(850, 1000);
+        assert_eq!(warning.level, DiagnosticLevel::Warning);
+        assert_eq!(warning.details, "850 of 1000 entries in use (85%)");
+
+        let error = usage(990, 1000);
+        assert_eq!(error.level, DiagnosticLevel::Error);
+        assert_eq!(
+            error.command.as_deref(),
+            Some("sudo sysctl -w net.netfilter.nf_conntrack_max=2000")
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+        let missing = check_conntrack_usage(&dir);
+        assert_eq!(missing.level, DiagnosticLevel::Info);
+        assert_eq!(missing.title, "Conntrack table not available");
+    }
+
+    #[tokio::test]
+    async fn unreadable_ipv6_sysctl_is_skipped() {
+        let diagnostics =
+            NftablesDiagnostics::new().with_runner(Arc::new(MockCommandRunner::new()));
+        assert!(diagnostics.check_ipv6_forwarding().await.is_none());
+    }
+}
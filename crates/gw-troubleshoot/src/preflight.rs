@@ -0,0 +1,353 @@
+use crate::command::run_timed;
+use crate::diagnostics::{DiagnosticLevel, DiagnosticResult};
+use gw_core::{CommandRunner, SystemCommandRunner};
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Binaries GhostWarden shells out to while applying or rolling back.
+const REQUIRED_BINARIES: &[(&str, &str, &str)] = &[
+    ("nft", "nftables ruleset management", "nftables"),
+    ("systemctl", "dnsmasq service control", "systemd"),
+];
+
+/// Binaries only needed for some topologies (DHCP/DNS, VM attachment).
+const OPTIONAL_BINARIES: &[(&str, &str, &str)] = &[
+    ("dnsmasq", "DHCP/DNS for routed networks", "dnsmasq"),
+    ("virsh", "libvirt VM attachment", "libvirt"),
+];
+
+const REQUIRED_MODULES: &[(&str, &str)] = &[
+    ("nf_tables", "Core nftables support"),
+    ("nf_nat", "NAT support"),
+    ("nf_conntrack", "Connection tracking"),
+    ("bridge", "Linux bridge support"),
+];
+
+/// Preflight checks that GhostWarden can operate on this host at all
+pub struct PreflightDiagnostics {
+    runner: Arc<dyn CommandRunner>,
+    state_dir: PathBuf,
+    modules_file: PathBuf,
+    search_path: Option<OsString>,
+}
+
+impl PreflightDiagnostics {
+    pub fn new(state_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            runner: Arc::new(SystemCommandRunner),
+            state_dir: state_dir.into(),
+            modules_file: PathBuf::from("/proc/modules"),
+            search_path: None,
+        }
+    }
+
+    /// Run commands through `runner` instead of spawning them directly.
+    pub fn with_runner(mut self, runner: Arc<dyn CommandRunner>) -> Self {
+        self.runner = runner;
+        self
+    }
+
+    /// Read loaded modules from `modules_file` and look binaries up in
+    /// `search_path` instead of `/proc/modules` and `$PATH`.
+    pub fn with_paths(
+        mut self,
+        modules_file: impl Into<PathBuf>,
+        search_path: impl Into<OsString>,
+    ) -> Self {
+        self.modules_file = modules_file.into();
+        self.search_path = Some(search_path.into());
+        self
+    }
+
+    /// Whether `program args` ran and exited successfully; a missing or
+    /// hung program counts as a failure.
+    async fn succeeds(&self, program: &str, args: &[&str]) -> bool {
+        run_timed(self.runner.as_ref(), program, args)
+            .await
+            .is_ok_and(|o| o.status.success())
+    }
+
+    pub async fn diagnose(&self) -> anyhow::Result<Vec<DiagnosticResult>> {
+        let mut results = vec![self.check_privileges().await];
+        results.extend(self.check_binaries());
+        results.extend(self.check_kernel_modules().await);
+        results.push(self.check_state_dir());
+        Ok(results)
+    }
+
+    async fn check_privileges(&self) -> DiagnosticResult {
+        let is_root = run_timed(self.runner.as_ref(), "id", &["-u"])
+            .await
+            .is_ok_and(|o| String::from_utf8_lossy(&o.stdout).trim() == "0");
+
+        if is_root {
+            return DiagnosticResult::new(
+                DiagnosticLevel::Info,
+                "Running as root",
+                "Netlink, nftables and dnsmasq changes are permitted",
+            );
+        }
+
+        let sudo_works = self.succeeds("sudo", &["-n", "true"]).await;
+
+        if sudo_works {
+            DiagnosticResult::new(
+                DiagnosticLevel::Error,
+                "Not running as root",
+                "Passwordless sudo is available, but gwarden must itself run as root",
+            )
+            .with_suggestion("Re-run the command with sudo")
+            .with_command("sudo gwarden <command>")
+        } else {
+            DiagnosticResult::new(
+                DiagnosticLevel::Error,
+                "Not running as root",
+                "Host network changes require root and sudo is not available without a password",
+            )
+            .with_suggestion("Run gwarden as root or from a sudo-enabled account")
+            .with_command("sudo gwarden <command>")
+        }
+    }
+
+    fn check_binaries(&self) -> Vec<DiagnosticResult> {
+        let search_path = self
+            .search_path
+            .clone()
+            .or_else(|| std::env::var_os("PATH"))
+            .unwrap_or_default();
+        let mut results = Vec::new();
+
+        for (binary, purpose, package) in REQUIRED_BINARIES {
+            results.push(binary_result(
+                &search_path,
+                binary,
+                purpose,
+                package,
+                DiagnosticLevel::Error,
+            ));
+        }
+
+        for (binary, purpose, package) in OPTIONAL_BINARIES {
+            results.push(binary_result(
+                &search_path,
+                binary,
+                purpose,
+                package,
+                DiagnosticLevel::Warning,
+            ));
+        }
+
+        results
+    }
+
+    async fn check_kernel_modules(&self) -> Vec<DiagnosticResult> {
+        let loaded = std::fs::read_to_string(&self.modules_file).unwrap_or_default();
+        let mut results = Vec::new();
+
+        for &(module, description) in REQUIRED_MODULES {
+            let is_loaded = loaded
+                .lines()
+                .any(|line| line.split_whitespace().next() == Some(module));
+
+            if is_loaded {
+                results.push(DiagnosticResult::new(
+                    DiagnosticLevel::Info,
+                    format!("Kernel module: {}", module),
+                    format!("{} is loaded", description),
+                ));
+                continue;
+            }
+
+            // `modprobe -n` resolves the module (or a built-in) without loading it.
+            if self.succeeds("modprobe", &["-n", "-q", module]).await {
+                results.push(DiagnosticResult::new(
+                    DiagnosticLevel::Info,
+                    format!("Kernel module: {}", module),
+                    format!("{} is available and will load on demand", description),
+                ));
+            } else {
+                results.push(
+                    DiagnosticResult::new(
+                        DiagnosticLevel::Error,
+                        format!("Kernel module unavailable: {}", module),
+                        format!("{} cannot be loaded on this kernel", description),
+                    )
+                    .with_suggestion(
+                        "Install the modules package for the running kernel, or reboot after a kernel upgrade",
+                    )
+                    .with_command(format!("sudo modprobe {}", module)),
+                );
+            }
+        }
+
+        results
+    }
+
+    fn check_state_dir(&self) -> DiagnosticResult {
+        match probe_writable(&self.state_dir) {
+            Ok(()) => DiagnosticResult::new(
+                DiagnosticLevel::Info,
+                "State directory writable",
+                self.state_dir.display().to_string(),
+            ),
+            Err(err) => DiagnosticResult::new(
+                DiagnosticLevel::Error,
+                "State directory not writable",
+                format!("{}: {}", self.state_dir.display(), err),
+            )
+            .with_suggestion(
+                "Fix the directory permissions or point GWARDEN_STATE_DIR at a writable path",
+            ),
+        }
+    }
+}
+
+fn binary_result(
+    search_path: &OsString,
+    binary: &str,
+    purpose: &str,
+    package: &str,
+    missing_level: DiagnosticLevel,
+) -> DiagnosticResult {
+    match find_in_path(search_path, binary) {
+        Some(path) => DiagnosticResult::new(
+            DiagnosticLevel::Info,
+            format!("Found {}", binary),
+            format!("{} ({})", path.display(), purpose),
+        ),
+        None => DiagnosticResult::new(
+            missing_level,
+            format!("{} not found", binary),
+            format!("Needed for {}", purpose),
+        )
+        .with_suggestion(format!("Install the {} package", package))
+        .with_command(format!("sudo pacman -S {}", package)),
+    }
+}
+
+fn find_in_path(search_path: &OsString, binary: &str) -> Option<PathBuf> {
+    std::env::split_paths(search_path)
+        .map(|dir| dir.join(binary))
+        .find(|candidate| candidate.is_file())
+}
+
+fn probe_writable(dir: &Path) -> std::io::Result<()> {
+    std::fs::create_dir_all(dir)?;
+    let probe = dir.join(format!(".gwarden-write-probe-{}", std::process::id()));
+    std::fs::write(&probe, b"")?;
+    std::fs::remove_file(&probe)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gw_core::MockCommandRunner;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("gw-preflight-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn levels(results: &[DiagnosticResult]) -> Vec<(DiagnosticLevel, &str)> {
+        results
+            .iter()
+            .map(|r| (r.level, r.title.as_str()))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn healthy_host_passes_every_check() {
+        let dir = temp_dir("pass");
+        let bin = dir.join("bin");
+        std::fs::create_dir_all(&bin).unwrap();
+        for binary in ["nft", "systemctl", "dnsmasq", "virsh"] {
+            std::fs::write(bin.join(binary), "").unwrap();
+        }
+        let modules = dir.join("modules");
+        std::fs::write(
+            &modules,
+            "nf_tables 1 0 - Live 0x0\nnf_nat 1 0 - Live 0x0\nbridge 1 0 - Live 0x0\n",
+        )
+        .unwrap();
+
+        let runner = Arc::new(
+            MockCommandRunner::new()
+                .ok("id -u", "0\n")
+                .ok("modprobe -n -q nf_conntrack", ""),
+        );
+        let diagnostics = PreflightDiagnostics::new(dir.join("state"))
+            .with_runner(runner.clone())
+            .with_paths(&modules, &bin);
+
+        let results = diagnostics.diagnose().await.unwrap();
+        assert!(results.iter().all(|r| r.level == DiagnosticLevel::Info));
+        assert_eq!(results[0].title, "Running as root");
+        assert_eq!(
+            results[7].details,
+            "Connection tracking is available and will load on demand"
+        );
+        assert_eq!(results.last().unwrap().title, "State directory writable");
+
+        // Loaded modules are not probed again.
+        let calls: Vec<String> = runner.calls().into_iter().map(|c| c.command).collect();
+        assert_eq!(calls, ["id -u", "modprobe -n -q nf_conntrack"]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn bare_host_reports_what_is_missing() {
+        let dir = temp_dir("fail");
+        let bin = dir.join("bin");
+        std::fs::create_dir_all(&bin).unwrap();
+        std::fs::write(bin.join("nft"), "").unwrap();
+        let modules = dir.join("modules");
+        std::fs::write(&modules, "").unwrap();
+        // A regular file where the state directory should go.
+        let state_dir = dir.join("state");
+        std::fs::write(&state_dir, "").unwrap();
+
+        let runner = MockCommandRunner::new()
+            .ok("id -u", "1000\n")
+            .ok("sudo -n true", "")
+            .ok("modprobe -n -q nf_tables", "")
+            .fail("modprobe", 1, "");
+        let diagnostics = PreflightDiagnostics::new(&state_dir)
+            .with_runner(Arc::new(runner))
+            .with_paths(&modules, &bin);
+
+        let results = diagnostics.diagnose().await.unwrap();
+        assert_eq!(
+            levels(&results),
+            [
+                (DiagnosticLevel::Error, "Not running as root"),
+                (DiagnosticLevel::Info, "Found nft"),
+                (DiagnosticLevel::Error, "systemctl not found"),
+                (DiagnosticLevel::Warning, "dnsmasq not found"),
+                (DiagnosticLevel::Warning, "virsh not found"),
+                (DiagnosticLevel::Info, "Kernel module: nf_tables"),
+                (DiagnosticLevel::Error, "Kernel module unavailable: nf_nat"),
+                (
+                    DiagnosticLevel::Error,
+                    "Kernel module unavailable: nf_conntrack"
+                ),
+                (DiagnosticLevel::Error, "Kernel module unavailable: bridge"),
+                (DiagnosticLevel::Error, "State directory not writable"),
+            ]
+        );
+        assert_eq!(
+            results[0].details,
+            "Passwordless sudo is available, but gwarden must itself run as root"
+        );
+        assert_eq!(
+            results[2].suggestion.as_deref(),
+            Some("Install the systemd package")
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -10,23 +10,106 @@ use ratatui::{
     layout::{Constraint, Direction, Layout},
     style::{Color, Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, List, ListItem, Paragraph},
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap},
 };
+use std::collections::HashMap;
 use std::io;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+/// The leases tab only shows what fits on screen; cap the per-refresh parse so
+/// polling stays cheap on gateways with very large lease files.
+const MAX_DISPLAYED_LEASES: usize = 500;
+
+/// Bridges, nftables, DHCP leases and port forwards
+const TAB_COUNT: usize = 4;
+
+const NO_TOPOLOGY: &str = "(no topology loaded)";
+
+/// How often status is re-collected when the user does not press `r`
+pub const DEFAULT_REFRESH_INTERVAL: Duration = Duration::from_secs(2);
+
+/// How long a footer notice such as "Refreshed" stays visible
+const NOTICE_DURATION: Duration = Duration::from_secs(2);
 
 pub struct TuiApp {
     selected_tab: usize,
+    /// Selection and scroll offset of each tab's list
+    list_states: [ListState; TAB_COUNT],
     status: gw_core::NetworkStatus,
+    interface_filter: gw_core::InterfaceFilter,
+    /// Transient footer message and when it was set
+    notice: Option<(String, Instant)>,
+    refresh_interval: Duration,
+    last_refresh: Option<Instant>,
+    /// Topology whose port forwards are listed, re-read on every refresh
+    topology_path: Option<PathBuf>,
+    /// (network, forward) rows, or why none could be loaded
+    forwards: std::result::Result<Vec<(String, gw_core::PortForward)>, String>,
+    /// Bridge MTUs for the details pane; empty when netlink access is denied
+    bridge_mtus: HashMap<String, u32>,
+    /// Why the last refresh could not collect a source; shown in its tab
+    bridge_error: Option<String>,
+    nft_error: Option<String>,
+    lease_error: Option<String>,
+    /// Require a second `q` before quitting
+    confirm_quit: bool,
+    /// The first `q` was pressed and the quit prompt is showing
+    quit_pending: bool,
+    /// Case-insensitive substring the current tab's rows must contain
+    filter: String,
+    /// `/` was pressed and typed keys go into `filter`
+    filter_input: bool,
 }
 
 impl TuiApp {
     pub fn new() -> Self {
         Self {
             selected_tab: 0,
+            list_states: Default::default(),
             status: gw_core::NetworkStatus::new(),
+            interface_filter: gw_core::InterfaceFilter::All,
+            notice: None,
+            refresh_interval: DEFAULT_REFRESH_INTERVAL,
+            last_refresh: None,
+            topology_path: None,
+            forwards: Err(NO_TOPOLOGY.to_string()),
+            bridge_mtus: HashMap::new(),
+            bridge_error: None,
+            nft_error: None,
+            lease_error: None,
+            confirm_quit: true,
+            quit_pending: false,
+            filter: String::new(),
+            filter_input: false,
         }
     }
 
+    /// List the port forwards of the topology at `path` on the forwards tab.
+    pub fn with_topology_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.topology_path = Some(path.into());
+        self
+    }
+
+    /// Re-collect bridges, nftables and leases every `interval` instead of
+    /// every [`DEFAULT_REFRESH_INTERVAL`].
+    pub fn with_refresh_interval(mut self, interval: Duration) -> Self {
+        self.refresh_interval = interval;
+        self
+    }
+
+    /// Quit on the first `q` instead of asking for a second one.
+    pub fn with_confirm_quit(mut self, confirm: bool) -> Self {
+        self.confirm_quit = confirm;
+        self
+    }
+
+    /// Only show bridges matched by `filter` on the bridges tab.
+    pub fn with_interface_filter(mut self, filter: gw_core::InterfaceFilter) -> Self {
+        self.interface_filter = filter;
+        self
+    }
+
     pub async fn run(&mut self) -> Result<()> {
         // Setup terminal
         enable_raw_mode()?;
@@ -59,8 +142,10 @@ impl TuiApp {
         B::Error: Send + Sync + 'static,
     {
         loop {
-            // Refresh status
-            self.refresh_status().await?;
+            // Refresh status once the interval has passed; `r` forces it sooner
+            if self.refresh_due(Instant::now()) {
+                self.refresh_status().await;
+            }
 
             // Draw UI
             terminal.draw(|f| self.ui(f))?;
@@ -69,38 +154,198 @@ impl TuiApp {
             if event::poll(std::time::Duration::from_millis(100))?
                 && let Event::Key(key) = event::read()?
             {
+                if self.filter_input {
+                    self.edit_filter(key.code);
+                    continue;
+                }
+                if self.quit_requested(key.code) {
+                    return Ok(());
+                }
                 match key.code {
-                    KeyCode::Char('q') => return Ok(()),
                     KeyCode::Char('r') => {
-                        // Refresh
+                        self.refresh_status().await;
+                        self.notice = Some(("Refreshed".to_string(), Instant::now()));
                     }
                     KeyCode::Tab => {
-                        self.selected_tab = (self.selected_tab + 1) % 3;
+                        self.set_filter(String::new());
+                        self.selected_tab = (self.selected_tab + 1) % TAB_COUNT;
+                    }
+                    KeyCode::Char('/') => {
+                        self.set_filter(String::new());
+                        self.filter_input = true;
                     }
+                    KeyCode::Esc => self.set_filter(String::new()),
+                    KeyCode::Up => self.move_selection(-1),
+                    KeyCode::Down => self.move_selection(1),
                     _ => {}
                 }
             }
         }
     }
 
-    async fn refresh_status(&mut self) -> Result<()> {
-        use gw_dhcpdns::LeaseReader;
+    /// Re-collect every source. A failing source empties its tab and shows
+    /// the error there instead of ending the session.
+    async fn refresh_status(&mut self) {
+        use gw_dhcpdns::{LeaseQuery, LeaseReader};
         use gw_nft::NftStatusCollector;
-        use gw_nl::StatusCollector;
+        use gw_nl::{BridgeManager, StatusCollector};
 
-        let bridge_collector = StatusCollector::new().await?;
-        self.status.bridges = bridge_collector.collect_bridge_status().await?;
+        let bridges = async { StatusCollector::new().await?.collect_bridge_status().await };
+        (self.status.bridges, self.bridge_error) = split_error(bridges.await);
+        self.status.retain_interfaces(&self.interface_filter);
+
+        // MTU is only shown in the details pane, so a bridge that vanished
+        // mid-refresh or missing CAP_NET_ADMIN just leaves it unknown
+        self.bridge_mtus.clear();
+        if let Ok(manager) = BridgeManager::new().await {
+            for bridge in &self.status.bridges {
+                if let Ok(info) = manager.get_bridge_info(&bridge.name).await {
+                    self.bridge_mtus.insert(bridge.name.clone(), info.mtu);
+                }
+            }
+        }
 
         let nft_collector = NftStatusCollector::new();
-        self.status.nftables = nft_collector.collect_table_status().await?;
+        (self.status.nftables, self.nft_error) =
+            split_error(nft_collector.collect_table_status().await);
 
         let lease_reader = LeaseReader::new();
-        self.status.dhcp_leases = lease_reader.read_default_leases()?;
+        (self.status.dhcp_leases, self.lease_error) = split_error(
+            lease_reader
+                .read_default_leases_with(&LeaseQuery::default().limit(MAX_DISPLAYED_LEASES)),
+        );
 
-        Ok(())
+        self.forwards = match &self.topology_path {
+            Some(path) => gw_core::Topology::from_file(path)
+                .map(|topology| forward_rows(&topology))
+                .map_err(|e| format!("(failed to load {}: {})", path.display(), e)),
+            None => Err(NO_TOPOLOGY.to_string()),
+        };
+
+        self.clamp_selections();
+        self.last_refresh = Some(Instant::now());
+    }
+
+    /// Whether `code` ends the session. With confirmation on, the first `q`
+    /// only shows the prompt and any other key dismisses it.
+    fn quit_requested(&mut self, code: KeyCode) -> bool {
+        if code != KeyCode::Char('q') {
+            self.quit_pending = false;
+            return false;
+        }
+        if !self.confirm_quit || self.quit_pending {
+            return true;
+        }
+        self.quit_pending = true;
+        false
+    }
+
+    fn refresh_due(&self, now: Instant) -> bool {
+        self.last_refresh
+            .is_none_or(|last| now.duration_since(last) >= self.refresh_interval)
+    }
+
+    /// Typing while in filter mode: `Enter` keeps the query, `Esc` drops it
+    fn edit_filter(&mut self, code: KeyCode) {
+        let mut query = self.filter.clone();
+        match code {
+            KeyCode::Enter => {
+                self.filter_input = false;
+                return;
+            }
+            KeyCode::Esc => {
+                self.filter_input = false;
+                query.clear();
+            }
+            KeyCode::Backspace => {
+                query.pop();
+            }
+            KeyCode::Char(c) => query.push(c),
+            _ => return,
+        }
+        self.set_filter(query);
+    }
+
+    /// Replace the filter and start the current tab's selection over, since
+    /// row indices change meaning
+    fn set_filter(&mut self, query: String) {
+        if query == self.filter {
+            return;
+        }
+        self.filter = query;
+        self.list_states[self.selected_tab].select(None);
+        self.clamp_selections();
     }
 
-    fn ui(&self, f: &mut Frame) {
+    /// List text of every row in `tab`, before filtering
+    fn row_texts(&self, tab: usize) -> Vec<String> {
+        match tab {
+            0 => self.status.bridges.iter().map(bridge_row).collect(),
+            1 => self.status.nftables.iter().map(nft_row).collect(),
+            2 => self.status.dhcp_leases.iter().map(lease_row).collect(),
+            3 => self.forwards.as_ref().map_or(vec![], |forwards| {
+                forwards
+                    .iter()
+                    .map(|(network, forward)| forward_row(network, forward))
+                    .collect()
+            }),
+            _ => vec![],
+        }
+    }
+
+    /// Indices of the rows `tab` lists; only the current tab is filtered
+    fn visible_rows(&self, tab: usize) -> Vec<usize> {
+        let query = self.filter.to_lowercase();
+        self.row_texts(tab)
+            .iter()
+            .enumerate()
+            .filter(|(_, text)| tab != self.selected_tab || text.to_lowercase().contains(&query))
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    fn tab_len(&self, tab: usize) -> usize {
+        self.visible_rows(tab).len()
+    }
+
+    /// "shown/total" while a filter is active, otherwise just the total
+    fn count_label(&self, shown: usize, total: usize) -> String {
+        if self.filter.is_empty() {
+            total.to_string()
+        } else {
+            format!("{}/{}", shown, total)
+        }
+    }
+
+    /// Move the current tab's selection by `delta` rows, stopping at either end
+    fn move_selection(&mut self, delta: isize) {
+        let len = self.tab_len(self.selected_tab);
+        let state = &mut self.list_states[self.selected_tab];
+        if len == 0 {
+            state.select(None);
+            return;
+        }
+
+        let current = state.selected().unwrap_or(0);
+        state.select(Some(current.saturating_add_signed(delta).min(len - 1)));
+    }
+
+    /// Keep every selection on an existing row after a refresh shrinks or
+    /// empties a list, and select the first row once one appears
+    fn clamp_selections(&mut self) {
+        for tab in 0..TAB_COUNT {
+            let len = self.tab_len(tab);
+            let state = &mut self.list_states[tab];
+            let selected = match state.selected() {
+                _ if len == 0 => None,
+                Some(index) => Some(index.min(len - 1)),
+                None => Some(0),
+            };
+            state.select(selected);
+        }
+    }
+
+    fn ui(&mut self, f: &mut Frame) {
         let chunks = Layout::default()
             .direction(Direction::Vertical)
             .constraints([
@@ -120,40 +365,85 @@ impl TuiApp {
             .block(Block::default().borders(Borders::ALL));
         f.render_widget(title, chunks[0]);
 
-        // Main content
+        // Main content: the tab's list with the selected item's details beside it
+        let content = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
+            .split(chunks[1]);
+
         match self.selected_tab {
-            0 => self.render_bridges(f, chunks[1]),
-            1 => self.render_nftables(f, chunks[1]),
-            2 => self.render_leases(f, chunks[1]),
+            0 => self.render_bridges(f, content[0]),
+            1 => self.render_nftables(f, content[0]),
+            2 => self.render_leases(f, content[0]),
+            3 => self.render_forwards(f, content[0]),
             _ => {}
         }
+        self.render_details(f, content[1]);
 
         // Footer
-        let footer_text = Line::from(vec![
+        let mut footer_spans = vec![
             Span::raw("Tab: Switch | "),
+            Span::raw("↑/↓: Select | "),
+            Span::raw("/: Filter | "),
             Span::raw("r: Refresh | "),
             Span::styled("q: Quit", Style::default().fg(Color::Red)),
-        ]);
+        ];
+        if self.quit_pending {
+            footer_spans.push(Span::styled(
+                "  Press q again to quit, any other key cancels",
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD),
+            ));
+        } else if self.filter_input {
+            footer_spans.push(Span::styled(
+                format!("  /{}_", self.filter),
+                Style::default().fg(Color::Yellow),
+            ));
+        } else if !self.filter.is_empty() {
+            footer_spans.push(Span::styled(
+                format!("  Filter: {} (Esc clears)", self.filter),
+                Style::default().fg(Color::Cyan),
+            ));
+        } else if let Some((notice, at)) = &self.notice
+            && at.elapsed() < NOTICE_DURATION
+        {
+            footer_spans.push(Span::styled(
+                format!("  {}", notice),
+                Style::default().fg(Color::Green),
+            ));
+        }
+        let footer_text = Line::from(footer_spans);
         let footer =
             Paragraph::new(footer_text).block(Block::default().borders(Borders::ALL).title("Help"));
         f.render_widget(footer, chunks[2]);
     }
 
-    fn render_bridges(&self, f: &mut Frame, area: ratatui::layout::Rect) {
-        let items: Vec<ListItem> = self
-            .status
-            .bridges
+    fn render_bridges(&mut self, f: &mut Frame, area: ratatui::layout::Rect) {
+        let rows = self.visible_rows(0);
+        let items: Vec<ListItem> = rows
             .iter()
+            .map(|&index| &self.status.bridges[index])
             .map(|b| {
-                let content = format!("{} [{}] - {}", b.name, b.state, b.addresses.join(", "));
-                ListItem::new(content)
+                ListItem::new(Line::from(vec![
+                    Span::styled(
+                        format!("{} [{}]", b.name, b.state),
+                        bridge_state_style(&b.state),
+                    ),
+                    Span::raw(format!(" - {}", b.addresses.join(", "))),
+                ]))
             })
             .collect();
+        let items = error_or(&self.bridge_error, items);
 
         let list = List::new(items)
             .block(
                 Block::default()
-                    .title(format!("Bridges ({}) [Tab 1/3]", self.status.bridges.len()))
+                    .title(format!(
+                        "Bridges ({}) [Tab 1/{}]",
+                        self.count_label(rows.len(), self.status.bridges.len()),
+                        TAB_COUNT
+                    ))
                     .borders(Borders::ALL)
                     .style(if self.selected_tab == 0 {
                         Style::default().fg(Color::Yellow)
@@ -161,31 +451,37 @@ impl TuiApp {
                         Style::default()
                     }),
             )
-            .highlight_style(Style::default().add_modifier(Modifier::BOLD));
+            .highlight_style(Style::default().add_modifier(Modifier::BOLD))
+            .highlight_symbol("> ");
 
-        f.render_widget(list, area);
+        f.render_stateful_widget(list, area, &mut self.list_states[0]);
     }
 
-    fn render_nftables(&self, f: &mut Frame, area: ratatui::layout::Rect) {
-        let items: Vec<ListItem> = self
-            .status
-            .nftables
+    fn render_nftables(&mut self, f: &mut Frame, area: ratatui::layout::Rect) {
+        let rows = self.visible_rows(1);
+        let items: Vec<ListItem> = rows
             .iter()
+            .map(|&index| &self.status.nftables[index])
             .map(|t| {
-                let content = format!(
-                    "{} ({}) - {} chains, {} rules",
-                    t.name, t.family, t.chains, t.rules
-                );
-                ListItem::new(content)
+                let content = nft_row(t);
+                // A table without rules usually means an apply went wrong
+                let style = if t.rules == 0 {
+                    Style::default().fg(Color::Yellow)
+                } else {
+                    Style::default()
+                };
+                ListItem::new(Line::styled(content, style))
             })
             .collect();
+        let items = error_or(&self.nft_error, items);
 
         let list = List::new(items)
             .block(
                 Block::default()
                     .title(format!(
-                        "nftables ({}) [Tab 2/3]",
-                        self.status.nftables.len()
+                        "nftables ({}) [Tab 2/{}]",
+                        self.count_label(rows.len(), self.status.nftables.len()),
+                        TAB_COUNT
                     ))
                     .borders(Borders::ALL)
                     .style(if self.selected_tab == 1 {
@@ -194,33 +490,36 @@ impl TuiApp {
                         Style::default()
                     }),
             )
-            .highlight_style(Style::default().add_modifier(Modifier::BOLD));
+            .highlight_style(Style::default().add_modifier(Modifier::BOLD))
+            .highlight_symbol("> ");
 
-        f.render_widget(list, area);
+        f.render_stateful_widget(list, area, &mut self.list_states[1]);
     }
 
-    fn render_leases(&self, f: &mut Frame, area: ratatui::layout::Rect) {
-        let items: Vec<ListItem> = self
-            .status
-            .dhcp_leases
+    fn render_leases(&mut self, f: &mut Frame, area: ratatui::layout::Rect) {
+        let rows = self.visible_rows(2);
+        let items: Vec<ListItem> = rows
             .iter()
+            .map(|&index| &self.status.dhcp_leases[index])
             .map(|l| {
-                let hostname = l
-                    .hostname
-                    .as_ref()
-                    .map(|h| format!(" ({})", h))
-                    .unwrap_or_default();
-                let content = format!("{}{} - {}", l.ip, hostname, l.mac);
-                ListItem::new(content)
+                let content = lease_row(l);
+                let style = if l.expires.as_deref() == Some("expired") {
+                    Style::default().fg(Color::Red).add_modifier(Modifier::DIM)
+                } else {
+                    Style::default()
+                };
+                ListItem::new(Line::styled(content, style))
             })
             .collect();
+        let items = error_or(&self.lease_error, items);
 
         let list = List::new(items)
             .block(
                 Block::default()
                     .title(format!(
-                        "DHCP Leases ({}) [Tab 3/3]",
-                        self.status.dhcp_leases.len()
+                        "DHCP Leases ({}) [Tab 3/{}]",
+                        self.count_label(rows.len(), self.status.dhcp_leases.len()),
+                        TAB_COUNT
                     ))
                     .borders(Borders::ALL)
                     .style(if self.selected_tab == 2 {
@@ -229,14 +528,424 @@ impl TuiApp {
                         Style::default()
                     }),
             )
-            .highlight_style(Style::default().add_modifier(Modifier::BOLD));
+            .highlight_style(Style::default().add_modifier(Modifier::BOLD))
+            .highlight_symbol("> ");
+
+        f.render_stateful_widget(list, area, &mut self.list_states[2]);
+    }
+
+    fn render_forwards(&mut self, f: &mut Frame, area: ratatui::layout::Rect) {
+        let rows = self.visible_rows(3);
+        let (items, count): (Vec<ListItem>, String) = match &self.forwards {
+            Ok(forwards) => (
+                rows.iter()
+                    .map(|&index| {
+                        let (network, forward) = &forwards[index];
+                        ListItem::new(forward_row(network, forward))
+                    })
+                    .collect(),
+                self.count_label(rows.len(), forwards.len()),
+            ),
+            Err(note) => (
+                vec![ListItem::new(note.clone()).style(Style::default().fg(Color::DarkGray))],
+                "0".to_string(),
+            ),
+        };
+
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .title(format!("Port Forwards ({}) [Tab 4/{}]", count, TAB_COUNT))
+                    .borders(Borders::ALL)
+                    .style(if self.selected_tab == 3 {
+                        Style::default().fg(Color::Yellow)
+                    } else {
+                        Style::default()
+                    }),
+            )
+            .highlight_style(Style::default().add_modifier(Modifier::BOLD))
+            .highlight_symbol("> ");
+
+        f.render_stateful_widget(list, area, &mut self.list_states[3]);
+    }
+
+    fn render_details(&self, f: &mut Frame, area: ratatui::layout::Rect) {
+        let lines = match self.detail_lines() {
+            Some(lines) => lines.into_iter().map(Line::from).collect(),
+            None => vec![Line::styled(
+                "(nothing selected)",
+                Style::default().fg(Color::DarkGray),
+            )],
+        };
+
+        let details = Paragraph::new(lines)
+            .wrap(Wrap { trim: false })
+            .block(Block::default().title("Details").borders(Borders::ALL));
+        f.render_widget(details, area);
+    }
+
+    /// Everything known about the current tab's selected item, one line per field
+    fn detail_lines(&self) -> Option<Vec<String>> {
+        let selected = self.list_states[self.selected_tab].selected()?;
+        let index = *self.visible_rows(self.selected_tab).get(selected)?;
+        let list = |items: &[String]| {
+            if items.is_empty() {
+                vec!["  (none)".to_string()]
+            } else {
+                items.iter().map(|item| format!("  {}", item)).collect()
+            }
+        };
+
+        let lines = match self.selected_tab {
+            0 => {
+                let bridge = self.status.bridges.get(index)?;
+                let mtu = self
+                    .bridge_mtus
+                    .get(&bridge.name)
+                    .map_or("unknown".to_string(), u32::to_string);
+                let mut lines = vec![
+                    format!("Name:  {}", bridge.name),
+                    format!("State: {}", bridge.state),
+                    format!("MTU:   {}", mtu),
+                    "Addresses:".to_string(),
+                ];
+                lines.extend(list(&bridge.addresses));
+                lines.push("Members:".to_string());
+                lines.extend(list(&bridge.members));
+                lines
+            }
+            1 => {
+                let table = self.status.nftables.get(index)?;
+                let mut lines = vec![
+                    format!("Table:  {}", table.name),
+                    format!("Family: {}", table.family),
+                    format!("Chains: {}", table.chains),
+                    format!("Rules:  {}", table.rules),
+                ];
+                if !table.quotas.is_empty() {
+                    lines.push("Quotas:".to_string());
+                    for quota in &table.quotas {
+                        lines.push(format!(
+                            "  {}: {}/{} bytes{}",
+                            quota.name,
+                            quota.used,
+                            quota.bytes,
+                            if quota.exceeded() { " (exceeded)" } else { "" }
+                        ));
+                    }
+                }
+                lines
+            }
+            2 => {
+                let lease = self.status.dhcp_leases.get(index)?;
+                vec![
+                    format!("IP:       {}", lease.ip),
+                    format!("MAC:      {}", lease.mac),
+                    format!("Hostname: {}", lease.hostname.as_deref().unwrap_or("-")),
+                    format!("Expires:  {}", lease.expires.as_deref().unwrap_or("never")),
+                ]
+            }
+            3 => {
+                let (network, forward) = self.forwards.as_ref().ok()?.get(index)?;
+                let mut lines = vec![
+                    format!("Network:  {}", network),
+                    format!("Public:   {}", forward.public),
+                    format!("Dest:     {}", forward.dst),
+                    format!("Protocol: {}", forward.protocol()),
+                ];
+                if let Some(priority) = forward.priority {
+                    lines.push(format!("Priority: {}", priority));
+                }
+                lines
+            }
+            _ => return None,
+        };
+        Some(lines)
+    }
+}
+
+fn split_error<T: Default>(result: Result<T>) -> (T, Option<String>) {
+    match result {
+        Ok(value) => (value, None),
+        Err(e) => (T::default(), Some(format!("{:#}", e))),
+    }
+}
 
-        f.render_widget(list, area);
+/// A single red row with `error` in place of the list when collection failed
+fn error_or<'a>(error: &Option<String>, items: Vec<ListItem<'a>>) -> Vec<ListItem<'a>> {
+    match error {
+        Some(error) => vec![
+            ListItem::new(format!("(failed to collect: {})", error))
+                .style(Style::default().fg(Color::Red)),
+        ],
+        None => items,
     }
 }
 
+fn bridge_row(bridge: &gw_core::BridgeStatus) -> String {
+    format!(
+        "{} [{}] - {}",
+        bridge.name,
+        bridge.state,
+        bridge.addresses.join(", ")
+    )
+}
+
+fn nft_row(table: &gw_core::NftTableStatus) -> String {
+    format!(
+        "{} ({}) - {} chains, {} rules",
+        table.name, table.family, table.chains, table.rules
+    )
+}
+
+fn lease_row(lease: &gw_core::DhcpLease) -> String {
+    let hostname = lease
+        .hostname
+        .as_ref()
+        .map(|h| format!(" ({})", h))
+        .unwrap_or_default();
+    format!("{}{} - {}", lease.ip, hostname, lease.mac)
+}
+
+fn forward_row(network: &str, forward: &gw_core::PortForward) -> String {
+    format!("{}: {} -> {}", network, forward.public, forward.dst)
+}
+
+/// Green for bridges that are up, red for DOWN or any other state
+fn bridge_state_style(state: &str) -> Style {
+    if state == "UP" {
+        Style::default().fg(Color::Green)
+    } else {
+        Style::default().fg(Color::Red)
+    }
+}
+
+/// Every routed network's forwards, ordered by network name
+fn forward_rows(topology: &gw_core::Topology) -> Vec<(String, gw_core::PortForward)> {
+    let mut networks: Vec<_> = topology.networks.iter().collect();
+    networks.sort_by_key(|(name, _)| name.as_str());
+
+    networks
+        .into_iter()
+        .filter_map(|(name, network)| match network {
+            gw_core::Network::Routed(routed) => Some((name, routed)),
+            _ => None,
+        })
+        .flat_map(|(name, routed)| {
+            routed
+                .forwards
+                .iter()
+                .map(move |forward| (name.clone(), forward.clone()))
+        })
+        .collect()
+}
+
 impl Default for TuiApp {
     fn default() -> Self {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lease(ip: &str) -> gw_core::DhcpLease {
+        gw_core::DhcpLease {
+            ip: ip.into(),
+            mac: "52:54:00:aa:bb:cc".into(),
+            hostname: None,
+            expires: None,
+        }
+    }
+
+    #[test]
+    fn forward_rows_list_routed_networks_in_order() {
+        let topology = gw_core::Topology::from_toml(
+            r#"
+version = 1
+
+[interfaces]
+
+[networks.web]
+type = "routed"
+cidr = "10.33.0.0/24"
+gw_ip = "10.33.0.1"
+forwards = [{ public = "0.0.0.0:8443/tcp", dst = "10.33.0.10:443" }]
+
+[networks.app]
+type = "routed"
+cidr = "10.34.0.0/24"
+gw_ip = "10.34.0.1"
+forwards = [{ public = ":4022/tcp", dst = "10.34.0.10:22" }]
+
+[networks.lan]
+type = "bridge"
+iface = "br-lan"
+"#,
+        )
+        .unwrap();
+
+        let rows: Vec<String> = forward_rows(&topology)
+            .into_iter()
+            .map(|(network, forward)| format!("{}: {} -> {}", network, forward.public, forward.dst))
+            .collect();
+        assert_eq!(
+            rows,
+            [
+                "app: :4022/tcp -> 10.34.0.10:22",
+                "web: 0.0.0.0:8443/tcp -> 10.33.0.10:443",
+            ]
+        );
+    }
+
+    #[test]
+    fn details_follow_the_selection() {
+        let mut app = TuiApp::new();
+        assert_eq!(app.detail_lines(), None);
+
+        app.status.bridges = vec![gw_core::BridgeStatus {
+            name: "br-lab".into(),
+            state: "UP".into(),
+            addresses: vec!["10.0.0.1/24".into(), "fd00::1/64".into()],
+            members: vec![],
+        }];
+        app.bridge_mtus.insert("br-lab".into(), 9000);
+        app.status.dhcp_leases = vec![lease("10.0.0.5"), lease("10.0.0.6")];
+        app.status.dhcp_leases[1].expires = Some("3600s".into());
+        app.clamp_selections();
+
+        assert_eq!(
+            app.detail_lines().unwrap(),
+            [
+                "Name:  br-lab",
+                "State: UP",
+                "MTU:   9000",
+                "Addresses:",
+                "  10.0.0.1/24",
+                "  fd00::1/64",
+                "Members:",
+                "  (none)",
+            ]
+        );
+
+        app.selected_tab = 2;
+        app.move_selection(1);
+        let lines = app.detail_lines().unwrap();
+        assert_eq!(lines[0], "IP:       10.0.0.6");
+        assert_eq!(lines[3], "Expires:  3600s");
+
+        app.selected_tab = 1;
+        assert_eq!(app.detail_lines(), None);
+    }
+
+    #[test]
+    fn only_up_bridges_are_green() {
+        assert_eq!(bridge_state_style("UP").fg, Some(Color::Green));
+        assert_eq!(bridge_state_style("DOWN").fg, Some(Color::Red));
+        assert_eq!(bridge_state_style("UNKNOWN").fg, Some(Color::Red));
+    }
+
+    #[test]
+    fn quitting_needs_a_second_q() {
+        let mut app = TuiApp::new();
+        assert!(!app.quit_requested(KeyCode::Char('q')));
+        assert!(app.quit_pending);
+        assert!(!app.quit_requested(KeyCode::Down));
+        assert!(!app.quit_pending);
+
+        assert!(!app.quit_requested(KeyCode::Char('q')));
+        assert!(app.quit_requested(KeyCode::Char('q')));
+
+        let mut app = TuiApp::new().with_confirm_quit(false);
+        assert!(app.quit_requested(KeyCode::Char('q')));
+    }
+
+    #[test]
+    fn filter_narrows_the_current_tab() {
+        let mut app = TuiApp::new();
+        app.selected_tab = 2;
+        app.status.dhcp_leases = vec![lease("10.0.0.5"), lease("10.0.0.6"), lease("10.0.0.7")];
+        app.status.dhcp_leases[1].hostname = Some("Printer".into());
+        app.status.bridges = vec![gw_core::BridgeStatus {
+            name: "br-lab".into(),
+            state: "UP".into(),
+            addresses: vec![],
+            members: vec![],
+        }];
+        app.clamp_selections();
+        app.move_selection(2);
+
+        app.filter_input = true;
+        for c in "PRINT".chars() {
+            app.edit_filter(KeyCode::Char(c));
+        }
+        assert_eq!(app.visible_rows(2), [1]);
+        assert_eq!(app.list_states[2].selected(), Some(0));
+        assert_eq!(app.detail_lines().unwrap()[0], "IP:       10.0.0.6");
+        // Other tabs are not filtered
+        assert_eq!(app.tab_len(0), 1);
+
+        app.edit_filter(KeyCode::Enter);
+        assert!(!app.filter_input);
+        assert_eq!(app.filter, "PRINT");
+        assert_eq!(app.count_label(1, 3), "1/3");
+
+        app.filter_input = true;
+        app.edit_filter(KeyCode::Esc);
+        assert!(!app.filter_input);
+        assert_eq!(app.visible_rows(2), [0, 1, 2]);
+    }
+
+    #[test]
+    fn failed_sources_keep_their_error() {
+        let (leases, error) = split_error::<Vec<gw_core::DhcpLease>>(Err(anyhow::anyhow!(
+            "No such file or directory"
+        )
+        .context("Failed to read leases")));
+        assert!(leases.is_empty());
+        assert_eq!(
+            error.as_deref(),
+            Some("Failed to read leases: No such file or directory")
+        );
+
+        assert_eq!(split_error(Ok(vec![lease("10.0.0.5")])).1, None);
+    }
+
+    #[test]
+    fn refresh_waits_for_the_interval() {
+        let mut app = TuiApp::new().with_refresh_interval(Duration::from_secs(5));
+        let start = Instant::now();
+        assert!(app.refresh_due(start));
+
+        app.last_refresh = Some(start);
+        assert!(!app.refresh_due(start + Duration::from_millis(100)));
+        assert!(!app.refresh_due(start + Duration::from_secs(4)));
+        assert!(app.refresh_due(start + Duration::from_secs(5)));
+    }
+
+    #[test]
+    fn selection_moves_within_bounds_and_clamps_on_refresh() {
+        let mut app = TuiApp::new();
+        app.selected_tab = 2;
+        app.status.dhcp_leases = (1..=5).map(|i| lease(&format!("10.0.0.{}", i))).collect();
+        app.clamp_selections();
+        assert_eq!(app.list_states[2].selected(), Some(0));
+        assert_eq!(app.list_states[0].selected(), None);
+
+        app.move_selection(-1);
+        assert_eq!(app.list_states[2].selected(), Some(0));
+        for _ in 0..10 {
+            app.move_selection(1);
+        }
+        assert_eq!(app.list_states[2].selected(), Some(4));
+
+        app.status.dhcp_leases.truncate(2);
+        app.clamp_selections();
+        assert_eq!(app.list_states[2].selected(), Some(1));
+
+        app.status.dhcp_leases.clear();
+        app.clamp_selections();
+        assert_eq!(app.list_states[2].selected(), None);
+    }
+}
@@ -7,10 +7,14 @@ _gwarden() {
     prev="${COMP_WORDS[COMP_CWORD-1]}"
 
     # Top-level commands
-    local commands="net vm forward policy metrics doctor tui help"
+    local commands="net vm forward policy metrics doctor self-check tui help"
 
     # If we're completing the first argument
     if [ $COMP_CWORD -eq 1 ]; then
+        if [[ "${cur}" == -* ]]; then
+            COMPREPLY=( $(compgen -W "-y --yes --help --version" -- ${cur}) )
+            return 0
+        fi
         COMPREPLY=( $(compgen -W "${commands}" -- ${cur}) )
         return 0
     fi
@@ -20,28 +24,32 @@ _gwarden() {
 
     case "${subcommand}" in
         net)
-            local net_cmds="plan apply status diff rollback state state-clear"
+            local net_cmds="plan apply status diff rollback state state-clear clean"
             if [ $COMP_CWORD -eq 2 ]; then
                 COMPREPLY=( $(compgen -W "${net_cmds}" -- ${cur}) )
             elif [[ "${prev}" == "--confirm" ]]; then
                 # Suggest rollback-window values in seconds (0 disables the wait)
                 COMPREPLY=( $(compgen -W "0 10 30 60 120" -- ${cur}) )
+            elif [[ "${prev}" == "--confirm-channel" ]]; then
+                COMPREPLY=( $(compgen -f -- ${cur}) )
+            elif [[ "${prev}" == "--interface-filter" ]]; then
+                COMPREPLY=( $(compgen -W "managed all" -- ${cur}) )
             elif [[ "${prev}" == "-f" || "${prev}" == "--file" ]]; then
-                # Complete with supported topology files for plan/apply/diff
+                # Complete with supported topology files for plan/apply/diff/status
                 COMPREPLY=( $(compgen -f -X '!*.toml' -- ${cur}) )
                 COMPREPLY+=( $(compgen -f -X '!*.yaml' -- ${cur}) )
                 COMPREPLY+=( $(compgen -f -X '!*.yml' -- ${cur}) )
             elif [[ "${cur}" == -* ]]; then
-                local opts="--file --commit --confirm --probe --probe-timeout --table --execute --json --help"
+                local opts="--file --show-merged --commit --confirm --confirm-channel --probe --probe-timeout --table --execute --json --interface-filter --all --help"
                 COMPREPLY=( $(compgen -W "${opts}" -- ${cur}) )
             fi
             ;;
         vm)
-            local vm_cmds="list attach"
+            local vm_cmds="list attach net-list"
             if [ $COMP_CWORD -eq 2 ]; then
                 COMPREPLY=( $(compgen -W "${vm_cmds}" -- ${cur}) )
             elif [[ "${cur}" == -* ]]; then
-                local opts="--vm --net --help"
+                local opts="--vm --net --tap --vlan --mtu --queues --ovs --uri --help"
                 COMPREPLY=( $(compgen -W "${opts}" -- ${cur}) )
             fi
             ;;
@@ -55,7 +63,7 @@ _gwarden() {
             fi
             ;;
         policy)
-            local pol_cmds="set list"
+            local pol_cmds="set list diff"
             if [ $COMP_CWORD -eq 2 ]; then
                 COMPREPLY=( $(compgen -W "${pol_cmds}" -- ${cur}) )
             elif [[ "${prev}" == "--profile" ]]; then
@@ -65,7 +73,7 @@ _gwarden() {
                     COMPREPLY=( $(compgen -W "${profiles}" -- ${cur}) )
                 fi
             elif [[ "${cur}" == -* ]]; then
-                local opts="--net --profile --help"
+                local opts="--net --profile --file --help"
                 COMPREPLY=( $(compgen -W "${opts}" -- ${cur}) )
             fi
             ;;
@@ -75,24 +83,37 @@ _gwarden() {
                 COMPREPLY=( $(compgen -W "${met_cmds}" -- ${cur}) )
             elif [[ "${prev}" == "--addr" ]]; then
                 COMPREPLY=( $(compgen -W ":9138 0.0.0.0:9138 127.0.0.1:9138" -- ${cur}) )
+            elif [[ "${prev}" == "-f" ]] || [[ "${prev}" == "--file" ]]; then
+                COMPREPLY=( $(compgen -f -X '!*.toml' -- ${cur}) )
+                COMPREPLY+=( $(compgen -f -X '!*.yaml' -- ${cur}) )
+                COMPREPLY+=( $(compgen -f -X '!*.yml' -- ${cur}) )
             elif [[ "${cur}" == -* ]]; then
-                local opts="--addr --help"
+                local opts="--addr --file --help"
                 COMPREPLY=( $(compgen -W "${opts}" -- ${cur}) )
             fi
             ;;
         doctor)
             local doc_cmds="nftables docker bridges all"
-            if [ $COMP_CWORD -eq 2 ]; then
-                COMPREPLY=( $(compgen -W "${doc_cmds}" -- ${cur}) )
+            if [[ "${prev}" == "--interface-filter" ]]; then
+                COMPREPLY=( $(compgen -W "managed all" -- ${cur}) )
             elif [[ "${cur}" == -* ]]; then
-                COMPREPLY=( $(compgen -W "--help" -- ${cur}) )
+                COMPREPLY=( $(compgen -W "--file --interface-filter --all --json --strict --fix --help" -- ${cur}) )
+            elif [ $COMP_CWORD -eq 2 ]; then
+                COMPREPLY=( $(compgen -W "${doc_cmds}" -- ${cur}) )
             fi
             ;;
-        tui)
+        self-check)
             if [[ "${cur}" == -* ]]; then
                 COMPREPLY=( $(compgen -W "--help" -- ${cur}) )
             fi
             ;;
+        tui)
+            if [[ "${prev}" == "--interface-filter" ]]; then
+                COMPREPLY=( $(compgen -W "managed all" -- ${cur}) )
+            elif [[ "${cur}" == -* ]]; then
+                COMPREPLY=( $(compgen -W "--file --interface-filter --all --interval --no-confirm-quit --help" -- ${cur}) )
+            fi
+            ;;
     esac
 
     return 0